@@ -0,0 +1,256 @@
+/// AppX packages, installed for users or provisioned in the image for new users, listed and
+/// removed through PowerShell. Scripts are generated and their JSON output parsed here, running
+/// them is left to [`run`].
+use glob::{MatchOptions, Pattern};
+use serde_json::Value;
+use std::process::Command;
+use tracing::debug;
+
+use crate::package::find_program;
+
+/// Lists the packages installed for any user.
+pub const LIST_INSTALLED: &str = "Get-AppxPackage -AllUsers \
+     | Select-Object Name, PackageFullName, NonRemovable | ConvertTo-Json -Compress";
+
+/// Lists the packages provisioned in the running image.
+pub const LIST_PROVISIONED: &str = "Get-AppxProvisionedPackage -Online \
+     | Select-Object DisplayName, PackageName | ConvertTo-Json -Compress";
+
+/// A package installed for at least one user.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Installed {
+    pub name: String,
+    pub full_name: String,
+    /// System packages like the Store that Windows refuses to remove.
+    pub non_removable: bool,
+}
+
+/// A package provisioned for the users created from now on.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Provisioned {
+    pub display_name: String,
+    pub package_name: String,
+}
+
+/// Quotes a string for PowerShell, inside single quotes nothing but `'` is special.
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+pub fn remove_installed(package: &Installed) -> String {
+    format!(
+        "Remove-AppxPackage -Package {} -AllUsers",
+        quote(&package.full_name)
+    )
+}
+
+pub fn remove_provisioned(package: &Provisioned) -> String {
+    format!(
+        "Remove-AppxProvisionedPackage -Online -PackageName {} | Out-Null",
+        quote(&package.package_name)
+    )
+}
+
+/// The objects of `ConvertTo-Json` output, which is empty for no object and not an array for
+/// a single one.
+fn objects(text: &str) -> Result<Vec<Value>, String> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let value = serde_json::from_str::<Value>(text.trim())
+        .map_err(|e| format!("Failed to parse the package list: {}", e))?;
+    Ok(match value {
+        Value::Array(values) => values,
+        Value::Null => Vec::new(),
+        value => vec![value],
+    })
+}
+
+fn string(object: &Value, field: &str) -> Result<String, String> {
+    object
+        .get(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("The package list has an entry without {}", field))
+}
+
+/// Parses the output of [`LIST_INSTALLED`].
+pub fn parse_installed(text: &str) -> Result<Vec<Installed>, String> {
+    objects(text)?
+        .iter()
+        .map(|object| {
+            Ok(Installed {
+                name: string(object, "Name")?,
+                full_name: string(object, "PackageFullName")?,
+                non_removable: object
+                    .get("NonRemovable")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+/// Parses the output of [`LIST_PROVISIONED`].
+pub fn parse_provisioned(text: &str) -> Result<Vec<Provisioned>, String> {
+    objects(text)?
+        .iter()
+        .map(|object| {
+            Ok(Provisioned {
+                display_name: string(object, "DisplayName")?,
+                package_name: string(object, "PackageName")?,
+            })
+        })
+        .collect()
+}
+
+/// Name patterns like `Microsoft.Bing*`, matched regardless of case.
+pub struct Patterns(Vec<Pattern>);
+
+impl Patterns {
+    pub fn parse(patterns: &[&str]) -> Result<Self, String> {
+        patterns
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern)
+                    .map_err(|e| format!("Invalid package pattern {}: {}", pattern, e))
+            })
+            .collect::<Result<Vec<Pattern>, String>>()
+            .map(Patterns)
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        self.0
+            .iter()
+            .any(|pattern| pattern.matches_with(name, options))
+    }
+}
+
+/// Runs a script with Windows PowerShell and returns its output, failing with its errors.
+pub fn run(script: &str) -> Result<String, String> {
+    let program = find_program("powershell")
+        .ok_or("PowerShell was not found, AppX packages only exist on Windows")?;
+    let script = format!(
+        "$ErrorActionPreference = 'Stop'; $ProgressPreference = 'SilentlyContinue'; {}",
+        script
+    );
+    debug!("[appx] {}", script);
+    let output = Command::new(&program)
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-ExecutionPolicy",
+            "Bypass",
+            "-Command",
+            &script,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program.display(), e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("PowerShell failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_the_scripts() {
+        assert_eq!(quote("plain"), "'plain'");
+        assert_eq!(quote("it's $(here)"), "'it''s $(here)'");
+        let installed = Installed {
+            name: "Microsoft.BingNews".to_string(),
+            full_name: "Microsoft.BingNews_4.55.62231.0_x64__8wekyb3d8bbwe".to_string(),
+            non_removable: false,
+        };
+        assert_eq!(
+            remove_installed(&installed),
+            "Remove-AppxPackage -Package 'Microsoft.BingNews_4.55.62231.0_x64__8wekyb3d8bbwe' \
+             -AllUsers"
+        );
+        let provisioned = Provisioned {
+            display_name: "Microsoft.BingNews".to_string(),
+            package_name: "Microsoft.BingNews_4.55.62231.0_neutral_~_8wekyb3d8bbwe".to_string(),
+        };
+        assert_eq!(
+            remove_provisioned(&provisioned),
+            "Remove-AppxProvisionedPackage -Online -PackageName \
+             'Microsoft.BingNews_4.55.62231.0_neutral_~_8wekyb3d8bbwe' | Out-Null"
+        );
+    }
+
+    #[test]
+    fn parses_installed_packages() {
+        let text = r#"[{"Name":"Microsoft.BingNews","PackageFullName":"Microsoft.BingNews_4.55.62231.0_x64__8wekyb3d8bbwe","NonRemovable":false},{"Name":"Microsoft.WindowsStore","PackageFullName":"Microsoft.WindowsStore_22405.1401.5.0_x64__8wekyb3d8bbwe","NonRemovable":true},{"Name":"Old.App","PackageFullName":"Old.App_1.0.0.0_x86__abc","NonRemovable":null}]
+"#;
+        assert_eq!(
+            parse_installed(text),
+            Ok(vec![
+                Installed {
+                    name: "Microsoft.BingNews".to_string(),
+                    full_name: "Microsoft.BingNews_4.55.62231.0_x64__8wekyb3d8bbwe".to_string(),
+                    non_removable: false,
+                },
+                Installed {
+                    name: "Microsoft.WindowsStore".to_string(),
+                    full_name: "Microsoft.WindowsStore_22405.1401.5.0_x64__8wekyb3d8bbwe"
+                        .to_string(),
+                    non_removable: true,
+                },
+                Installed {
+                    name: "Old.App".to_string(),
+                    full_name: "Old.App_1.0.0.0_x86__abc".to_string(),
+                    non_removable: false,
+                },
+            ])
+        );
+        // A single package isn't in an array and no package is no output at all.
+        let single = r#"{"Name":"Microsoft.BingNews","PackageFullName":"Microsoft.BingNews_1_x64__8wekyb3d8bbwe","NonRemovable":false}"#;
+        assert_eq!(parse_installed(single).unwrap().len(), 1);
+        assert_eq!(parse_installed("\r\n"), Ok(Vec::new()));
+        assert_eq!(
+            parse_installed(r#"[{"Name":"Broken"}]"#),
+            Err("The package list has an entry without PackageFullName".to_string())
+        );
+        assert!(
+            parse_installed("Get-AppxPackage : Access is denied.")
+                .unwrap_err()
+                .starts_with("Failed to parse the package list")
+        );
+    }
+
+    #[test]
+    fn parses_provisioned_packages() {
+        let text = r#"[{"DisplayName":"Microsoft.BingNews","PackageName":"Microsoft.BingNews_4.55.62231.0_neutral_~_8wekyb3d8bbwe"},{"DisplayName":"Microsoft.GetHelp","PackageName":"Microsoft.GetHelp_10.2302.10601.0_neutral_~_8wekyb3d8bbwe"}]"#;
+        let packages = parse_provisioned(text).unwrap();
+        assert_eq!(
+            packages
+                .iter()
+                .map(|p| p.display_name.as_str())
+                .collect::<Vec<&str>>(),
+            ["Microsoft.BingNews", "Microsoft.GetHelp"]
+        );
+        assert_eq!(
+            packages[1].package_name,
+            "Microsoft.GetHelp_10.2302.10601.0_neutral_~_8wekyb3d8bbwe"
+        );
+        assert_eq!(parse_provisioned("null"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn matches_patterns_regardless_of_case() {
+        let patterns = Patterns::parse(&["Microsoft.Bing*", "*Solitaire*"]).unwrap();
+        assert!(patterns.matches("Microsoft.BingNews"));
+        assert!(patterns.matches("microsoft.bingweather"));
+        assert!(patterns.matches("Microsoft.MicrosoftSolitaireCollection"));
+        assert!(!patterns.matches("Microsoft.WindowsStore"));
+        assert!(Patterns::parse(&["[broken"]).is_err());
+    }
+}
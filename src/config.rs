@@ -1,12 +1,30 @@
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub metadata: Metadata,
+    pub inputs: Option<Vec<Input>>,
+    pub secrets: Option<Vec<Secret>>,
+    pub packages: Option<Packages>,
     pub scripts: Vec<Script>,
 }
 
 impl Config {
+    /// Keeps only the scripts matching `only` (if any) and removes the ones matching `skip`.
+    pub fn filter_scripts(&mut self, only: &[String], skip: &[String]) {
+        self.scripts.retain(|script| {
+            (only.is_empty() || only.iter().any(|s| script.matches(s)))
+                && !skip.iter().any(|s| script.matches(s))
+        });
+    }
+
+    /// Keeps only the scripts that are part of the selection.
+    pub fn apply_selection(&mut self, selection: &Selection) {
+        self.scripts
+            .retain(|script| selection.scripts.contains(&script.name));
+    }
+
     pub fn generate_empty() -> Self {
         Config {
             metadata: Metadata {
@@ -16,16 +34,28 @@ impl Config {
                 trx8_version: env!("CARGO_PKG_VERSION").to_string(),
                 description: Some("This is an example configuration profile.".to_string()),
             },
+            inputs: Some(vec![Input {
+                name: "name".to_string(),
+                prompt: Some("Who should be greeted?".to_string()),
+                kind: InputType::String,
+                default: Some("World".to_string()),
+                choices: None,
+                validation: Some("[A-Za-z ]+".to_string()),
+                required: None,
+            }]),
+            secrets: None,
+            packages: None,
             scripts: vec![Script {
                 name: "An example script".to_string(),
                 condition: None,
                 description: Some("This script does nothing :)".to_string()),
+                tags: Some(vec!["example".to_string()]),
                 actions: vec![Action {
                     name: "echo".to_string(),
                     description: Some(
                         "Prints Hello, World! to the console because why not?".to_string(),
                     ),
-                    parameters: Some(vec!["Hello, World!".to_string()]),
+                    parameters: Some(vec!["Hello, ${inputs.name}!".to_string()]),
                 }],
             }],
         }
@@ -41,17 +71,105 @@ pub struct Metadata {
     pub author: Option<Vec<String>>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InputType {
+    #[default]
+    String,
+    Bool,
+    Choice,
+    /// Like `String`, but not echoed when typed.
+    Secret,
+}
+
+/// A value asked from the operator before applying, usable in parameters as `${inputs.<name>}`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Input {
+    pub name: String,
+    pub prompt: Option<String>,
+    #[serde(rename = "type", default)]
+    pub kind: InputType,
+    pub default: Option<String>,
+    /// The allowed values for `choice` inputs.
+    pub choices: Option<Vec<String>>,
+    /// A regex the whole value must match.
+    pub validation: Option<String>,
+    /// Defaults to `true`, a required input without a default must be provided.
+    pub required: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretSource {
+    Env,
+    Prompt,
+    /// A secrets file encrypted with a passphrase, managed with `trx8 secret`.
+    File,
+}
+
+/// A sensitive value usable in parameters as `${secrets.<name>}`, masked as `***` in all output.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Secret {
+    pub name: String,
+    pub source: SecretSource,
+    /// The environment variable for `env` secrets, defaults to `TRX8_SECRET_<NAME>`.
+    pub env: Option<String>,
+    /// The secrets file for `file` secrets, relative to the configuration file.
+    pub file: Option<String>,
+    /// The text shown for `prompt` secrets.
+    pub prompt: Option<String>,
+}
+
+/// Settings of the `package` action.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Packages {
+    /// The package managers to use in order of preference, the first one installed is used.
+    /// Defaults to `winget`, `scoop`, `choco` on Windows and `apt` on Linux.
+    pub providers: Option<Vec<String>>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Script {
     pub name: String,
     pub condition: Option<String>,
     pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
     pub actions: Vec<Action>,
 }
 
+impl Script {
+    /// Whether the script's name or one of its tags is `selector`.
+    pub fn matches(&self, selector: &str) -> bool {
+        self.name == selector
+            || self
+                .tags
+                .as_ref()
+                .is_some_and(|tags| tags.iter().any(|t| t == selector))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Action {
     pub name: String,
     pub description: Option<String>,
     pub parameters: Option<Vec<String>>,
 }
+
+/// A saved selection of scripts from the TUI, can be reused with `--selection`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Selection {
+    pub profile: String,
+    pub scripts: Vec<String>,
+}
+
+impl Selection {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
@@ -33,13 +33,11 @@ pub static DEFAULT_ENVS: LazyLock<Vec<(String, String)>> = LazyLock::new(|| {
             PROJECT_DIR.data_dir().to_str().unwrap().to_string(),
         ),
     ];
-    let envs = envs
-        .into_iter()
+    envs.into_iter()
         .chain(
             DEFAULT_CONST_ENVS
                 .iter()
                 .map(|(k, v)| (k.to_string(), v.to_string())),
         )
-        .collect::<Vec<(String, String)>>();
-    envs
+        .collect::<Vec<(String, String)>>()
 });
@@ -0,0 +1,277 @@
+/// Windows optional features through DISM. Arguments are generated and its output parsed here,
+/// with `/English` so the parsing doesn't depend on the language of the system.
+use std::process::Command;
+use tracing::debug;
+
+use crate::package::find_program;
+
+/// The exit code of DISM when the change needs a reboot to finish.
+const RESTART_CODE: i32 = 3010;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Enabled,
+    Disabled,
+    /// Enabled, finishing at the next boot.
+    EnablePending,
+    /// Disabled, finishing at the next boot.
+    DisablePending,
+    /// Disabled with its files removed from the image, enabling it needs a source.
+    Removed,
+}
+
+impl State {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "Enabled" => Some(State::Enabled),
+            "Disabled" => Some(State::Disabled),
+            "Enable Pending" => Some(State::EnablePending),
+            "Disable Pending" => Some(State::DisablePending),
+            "Disabled with Payload Removed" => Some(State::Removed),
+            _ => None,
+        }
+    }
+
+    pub fn enabled(self) -> bool {
+        matches!(self, State::Enabled | State::EnablePending)
+    }
+
+    pub fn pending(self) -> bool {
+        matches!(self, State::EnablePending | State::DisablePending)
+    }
+}
+
+fn args(args: &[String]) -> Vec<String> {
+    ["/English", "/Online"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain(args.iter().cloned())
+        .collect()
+}
+
+pub fn info_args(feature: &str) -> Vec<String> {
+    args(&[
+        "/Get-FeatureInfo".to_string(),
+        format!("/FeatureName:{}", feature),
+    ])
+}
+
+/// Enables the feature, with its parents when `all` and its files from `source` when given.
+pub fn enable_args(feature: &str, all: bool, source: Option<&str>) -> Vec<String> {
+    let mut enable = vec![
+        "/Enable-Feature".to_string(),
+        format!("/FeatureName:{}", feature),
+        "/NoRestart".to_string(),
+    ];
+    if all {
+        enable.push("/All".to_string());
+    }
+    if let Some(source) = source {
+        enable.push(format!("/Source:{}", source));
+        enable.push("/LimitAccess".to_string());
+    }
+    args(&enable)
+}
+
+pub fn disable_args(feature: &str) -> Vec<String> {
+    args(&[
+        "/Disable-Feature".to_string(),
+        format!("/FeatureName:{}", feature),
+        "/NoRestart".to_string(),
+    ])
+}
+
+/// The state in the output of `/Get-FeatureInfo`, from a line like `State : Enable Pending`.
+pub fn parse_state(text: &str) -> Result<State, String> {
+    let value = text
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "State")
+        .map(|(_, value)| value.trim())
+        .ok_or("DISM didn't report the state of the feature")?;
+    State::parse(value).ok_or_else(|| format!("Unknown feature state: {}", value))
+}
+
+/// Whether DISM exited with the restart code or said a restart is needed to finish.
+pub fn restart_needed(code: i32, text: &str) -> bool {
+    code == RESTART_CODE
+        || text
+            .lines()
+            .any(|line| line.trim_start().starts_with("Restart Windows to complete"))
+}
+
+/// The error DISM reported, its output ends with `Error: <code>` and the message.
+fn error(code: i32, text: &str) -> String {
+    let message = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("The DISM log file"))
+        .skip_while(|line| !line.starts_with("Error:"))
+        .collect::<Vec<&str>>()
+        .join(" ");
+    if message.is_empty() {
+        format!("DISM failed with exit code {}", code)
+    } else {
+        format!("DISM failed: {}", message)
+    }
+}
+
+/// Runs DISM, returning its output and whether a restart is needed to finish.
+pub fn run(args: &[String]) -> Result<(String, bool), String> {
+    let program = find_program("dism")
+        .ok_or("DISM was not found, optional features only exist on Windows")?;
+    debug!("[dism] {} {}", program.display(), args.join(" "));
+    let output = Command::new(&program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program.display(), e))?;
+    let text = String::from_utf8_lossy(&output.stdout).into_owned();
+    let code = output.status.code().unwrap_or(-1);
+    if !output.status.success() && code != RESTART_CODE {
+        return Err(error(code, &text));
+    }
+    let restart = restart_needed(code, &text);
+    Ok((text, restart))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INFO: &str = "
+Deployment Image Servicing and Management tool
+Version: 10.0.22621.2792
+
+Image Version: 10.0.22631.3593
+
+Feature Information:
+
+Feature Name : Microsoft-Windows-Subsystem-Linux
+Display Name : Windows Subsystem for Linux
+Description : Provides services and environments for running native user-mode Linux shells and tools on Windows.
+Restart Required : Possible
+State : Enable Pending
+
+Custom Properties:
+
+ServerComponent\\Description : Provides services and environments for running native user-mode Linux shells and tools on Windows.
+
+The operation completed successfully.
+";
+
+    #[test]
+    fn generates_the_command_lines() {
+        assert_eq!(
+            info_args("Microsoft-Hyper-V"),
+            [
+                "/English",
+                "/Online",
+                "/Get-FeatureInfo",
+                "/FeatureName:Microsoft-Hyper-V"
+            ]
+        );
+        assert_eq!(
+            enable_args("NetFx3", false, None),
+            [
+                "/English",
+                "/Online",
+                "/Enable-Feature",
+                "/FeatureName:NetFx3",
+                "/NoRestart"
+            ]
+        );
+        assert_eq!(
+            enable_args("NetFx3", true, Some(r"D:\sources\sxs")),
+            [
+                "/English",
+                "/Online",
+                "/Enable-Feature",
+                "/FeatureName:NetFx3",
+                "/NoRestart",
+                "/All",
+                r"/Source:D:\sources\sxs",
+                "/LimitAccess"
+            ]
+        );
+        assert_eq!(
+            disable_args("SMB1Protocol"),
+            [
+                "/English",
+                "/Online",
+                "/Disable-Feature",
+                "/FeatureName:SMB1Protocol",
+                "/NoRestart"
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_the_feature_state() {
+        let state = parse_state(INFO).unwrap();
+        assert_eq!(state, State::EnablePending);
+        assert!(state.enabled() && state.pending());
+
+        let info = |state: &str| INFO.replace("Enable Pending", state);
+        assert_eq!(parse_state(&info("Enabled")), Ok(State::Enabled));
+        assert_eq!(parse_state(&info("Disabled")), Ok(State::Disabled));
+        assert_eq!(
+            parse_state(&info("Disable Pending")),
+            Ok(State::DisablePending)
+        );
+        assert_eq!(
+            parse_state(&info("Disabled with Payload Removed")),
+            Ok(State::Removed)
+        );
+        assert!(!State::Removed.enabled() && !State::Removed.pending());
+        assert_eq!(
+            parse_state(&info("Staged")),
+            Err("Unknown feature state: Staged".to_string())
+        );
+        assert_eq!(
+            parse_state("Error: 0x800f080c\n\nFeature name Nope is unknown."),
+            Err("DISM didn't report the state of the feature".to_string())
+        );
+    }
+
+    #[test]
+    fn detects_needed_restarts() {
+        let enabled = "
+Enabling feature(s)
+[==========================100.0%==========================]
+The operation completed successfully.
+Restart Windows to complete this operation.
+Do you want to restart the computer now? (Y/N)
+";
+        assert!(restart_needed(0, enabled));
+        assert!(restart_needed(3010, ""));
+        assert!(!restart_needed(
+            0,
+            "Enabling feature(s)\nThe operation completed successfully.\n"
+        ));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let failed = "
+Deployment Image Servicing and Management tool
+Version: 10.0.22621.2792
+
+Image Version: 10.0.22631.3593
+
+Enabling feature(s)
+
+Error: 0x800f081f
+
+The source files could not be found.
+Use the \"Source\" option to specify the location of the files that are required to restore the feature.
+
+The DISM log file can be found at C:\\Windows\\Logs\\DISM\\dism.log
+";
+        assert_eq!(
+            error(-2146498529, failed),
+            "DISM failed: Error: 0x800f081f The source files could not be found. Use the \"Source\" \
+             option to specify the location of the files that are required to restore the feature."
+        );
+        assert_eq!(error(87, ""), "DISM failed with exit code 87");
+    }
+}
@@ -0,0 +1,254 @@
+/// Variables in a file of `NAME=value` lines: `/etc/environment`, read by `pam_env` without
+/// any expansion, or a shell script like `~/.profile` or `/etc/profile.d/*.sh` with
+/// `export NAME="value"` lines expanded by the shell.
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::Backend;
+
+pub struct EnvFile {
+    path: PathBuf,
+    /// Whether the file is sourced by a shell, anything but a file named `environment`.
+    shell: bool,
+    lines: Vec<String>,
+    dirty: bool,
+}
+
+/// A line defining a variable, `[export ]NAME=value`. Indented lines are inside shell blocks
+/// (`if ...; then`) and left alone.
+struct Definition<'a> {
+    export: bool,
+    name: &'a str,
+    value: &'a str,
+}
+
+fn parse_definition(line: &str) -> Option<Definition<'_>> {
+    let (export, rest) = match line.strip_prefix("export ") {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, line),
+    };
+    let (name, value) = rest.split_once('=')?;
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(Definition {
+        export,
+        name,
+        value: value.trim_end(),
+    })
+}
+
+/// The value without its quotes, `\` escapes are only meaningful in shell double quotes.
+fn unquote(value: &str, shell: bool) -> String {
+    if let Some(inner) = value
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        return inner.to_string();
+    }
+    let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    else {
+        return value.to_string();
+    };
+    if !shell {
+        return inner.to_string();
+    }
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next @ ('"' | '\\' | '$' | '`')) => unquoted.push(next),
+                Some(next) => unquoted.extend(['\\', next]),
+                None => unquoted.push('\\'),
+            },
+            _ => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// Double quotes the value, in shell files `$` is left alone so `$PATH` still expands.
+fn quote(value: &str, shell: bool) -> String {
+    if !shell {
+        return format!("\"{}\"", value);
+    }
+    let escaped = value
+        .chars()
+        .flat_map(|c| match c {
+            '"' | '\\' | '`' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect::<String>();
+    format!("\"{}\"", escaped)
+}
+
+impl EnvFile {
+    /// Loads the file, a missing file has no variables and is created when written.
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        let lines = match fs::read_to_string(&path) {
+            Ok(content) => content.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let shell = path.file_name().is_none_or(|name| name != "environment");
+        Ok(EnvFile {
+            path,
+            shell,
+            lines,
+            dirty: false,
+        })
+    }
+
+    /// The index of the line the variable gets its value from, the last one defining it.
+    fn position(&self, name: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .rposition(|line| parse_definition(line).is_some_and(|d| d.name == name))
+    }
+}
+
+impl Backend for EnvFile {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        Ok(self.position(name).and_then(|i| {
+            let definition = parse_definition(&self.lines[i])?;
+            Some(unquote(definition.value, self.shell))
+        }))
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let position = self.position(name);
+        // Keep `export` as it was, shell profiles often set PATH without it.
+        let export = match position.and_then(|i| parse_definition(&self.lines[i])) {
+            Some(definition) => definition.export,
+            None => self.shell,
+        };
+        let line = format!(
+            "{}{}={}",
+            if export { "export " } else { "" },
+            name,
+            quote(value, self.shell)
+        );
+        match position {
+            Some(i) => self.lines[i] = line,
+            None => self.lines.push(line),
+        }
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn remove(&mut self, name: &str) -> Result<(), String> {
+        self.lines
+            .retain(|line| parse_definition(line).is_none_or(|d| d.name != name));
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn separator(&self) -> char {
+        ':'
+    }
+
+    fn ignore_case(&self) -> bool {
+        false
+    }
+
+    /// Shell files build on the variable as the shell already has it, `/etc/environment`
+    /// replaces it so it starts from the value trx8 runs with.
+    fn inherited(&self, name: &str) -> Option<String> {
+        if self.shell {
+            Some(format!("${}", name))
+        } else {
+            std::env::var(name).ok()
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut content = self.lines.join("\n");
+        content.push('\n');
+        fs::write(&self.path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_for_pam_env_and_shells() {
+        let dir = tempfile::tempdir().unwrap();
+        let value = r#"a "b" \c $PATH"#;
+
+        let path = dir.path().join("environment");
+        let mut env = EnvFile::open(path.clone()).unwrap();
+        env.set("VALUE", value).unwrap();
+        env.flush().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "VALUE=\"a \"b\" \\c $PATH\"\n"
+        );
+        assert_eq!(
+            EnvFile::open(path).unwrap().get("VALUE").unwrap().unwrap(),
+            value
+        );
+
+        let path = dir.path().join("trx8.sh");
+        let mut env = EnvFile::open(path.clone()).unwrap();
+        env.set("VALUE", value).unwrap();
+        env.flush().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "export VALUE=\"a \\\"b\\\" \\\\c $PATH\"\n"
+        );
+        assert_eq!(
+            EnvFile::open(path).unwrap().get("VALUE").unwrap().unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn edits_the_last_definition() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".profile");
+        fs::write(
+            &path,
+            "PATH=/old\nif [ -d ~/bin ]; then\n  PATH=~/bin:$PATH\nfi\nPATH='/usr/bin'\n",
+        )
+        .unwrap();
+        let mut env = EnvFile::open(path.clone()).unwrap();
+        assert_eq!(env.get("PATH").unwrap().unwrap(), "/usr/bin");
+        assert_eq!(env.inherited("HOME").unwrap(), "$HOME");
+        env.set("PATH", "/usr/bin:/opt").unwrap();
+        env.set("EDITOR", "vi").unwrap();
+        env.flush().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "PATH=/old\nif [ -d ~/bin ]; then\n  PATH=~/bin:$PATH\nfi\nPATH=\"/usr/bin:/opt\"\nexport EDITOR=\"vi\"\n"
+        );
+        env.remove("PATH").unwrap();
+        env.flush().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "if [ -d ~/bin ]; then\n  PATH=~/bin:$PATH\nfi\nexport EDITOR=\"vi\"\n"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+/// Persistent environment variables, in the registry on Windows and in `/etc/environment` or
+/// shell profiles on Linux, with list variables like `PATH` edited entry by entry.
+mod file;
+mod registry;
+
+pub use file::EnvFile;
+pub use registry::RegistryEnvironment;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// The variables of the current user.
+    User,
+    /// The variables of every user.
+    Machine,
+}
+
+impl Scope {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "user" => Ok(Scope::User),
+            "machine" | "system" => Ok(Scope::Machine),
+            _ => Err(format!(
+                "Unknown environment scope: {} (expected user or machine)",
+                name
+            )),
+        }
+    }
+}
+
+/// Where variables are stored. Names are compared the way the backend stores them.
+pub trait Backend {
+    /// The file or registry key of the variables, for the logs.
+    fn location(&self) -> String;
+    fn get(&self, name: &str) -> Result<Option<String>, String>;
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String>;
+    /// Deletes the variable, a missing variable is not an error.
+    fn remove(&mut self, name: &str) -> Result<(), String>;
+    /// The separator of list variables like `PATH`.
+    fn separator(&self) -> char;
+    /// Whether list entries are compared ignoring case, like paths on Windows.
+    fn ignore_case(&self) -> bool;
+    /// The value list edits start from when the variable is not set here.
+    fn inherited(&self, _name: &str) -> Option<String> {
+        None
+    }
+    /// Writes pending changes and tells running programs about them.
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Where entries go in a list variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    /// After the other entries, an entry already anywhere in the list stays where it is.
+    Append,
+    /// Before the other entries, in the given order, moving them if they are elsewhere.
+    Prepend,
+}
+
+/// Whether two list entries are the same, ignoring trailing path separators.
+fn same_entry(a: &str, b: &str, ignore_case: bool) -> bool {
+    let a = a.trim().trim_end_matches(['\\', '/']);
+    let b = b.trim().trim_end_matches(['\\', '/']);
+    if ignore_case {
+        a.to_lowercase() == b.to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+/// The entries of the list, without empty ones and duplicates.
+fn entries(backend: &dyn Backend, value: &str) -> Vec<String> {
+    let mut entries: Vec<String> = Vec::new();
+    for entry in value.split(backend.separator()) {
+        if !entry.trim().is_empty()
+            && !entries
+                .iter()
+                .any(|e| same_entry(e, entry, backend.ignore_case()))
+        {
+            entries.push(entry.to_string());
+        }
+    }
+    entries
+}
+
+/// Sets the variable if it differs, returns whether it changed.
+pub fn ensure_value(backend: &mut dyn Backend, name: &str, value: &str) -> Result<bool, String> {
+    if backend.get(name)?.as_deref() == Some(value) {
+        return Ok(false);
+    }
+    backend.set(name, value)?;
+    Ok(true)
+}
+
+pub fn remove_variable(backend: &mut dyn Backend, name: &str) -> Result<bool, String> {
+    if backend.get(name)?.is_none() {
+        return Ok(false);
+    }
+    backend.remove(name)?;
+    Ok(true)
+}
+
+/// Adds the entries to the list variable, dropping duplicates and empty entries on the way.
+pub fn ensure_entries(
+    backend: &mut dyn Backend,
+    name: &str,
+    added: &[&str],
+    position: Position,
+) -> Result<bool, String> {
+    let current = backend.get(name)?;
+    let value = current
+        .clone()
+        .or_else(|| backend.inherited(name))
+        .unwrap_or_default();
+    let mut list = entries(backend, &value);
+    let ignore_case = backend.ignore_case();
+    match position {
+        Position::Append => {
+            for entry in added {
+                if !list.iter().any(|e| same_entry(e, entry, ignore_case)) {
+                    list.push(entry.to_string());
+                }
+            }
+        }
+        Position::Prepend => {
+            list.retain(|e| !added.iter().any(|entry| same_entry(e, entry, ignore_case)));
+            list.splice(0..0, added.iter().map(|entry| entry.to_string()));
+        }
+    }
+    let value = list.join(&backend.separator().to_string());
+    if current.as_deref() == Some(value.as_str()) {
+        return Ok(false);
+    }
+    backend.set(name, &value)?;
+    Ok(true)
+}
+
+/// Removes the entries from the list variable, and the variable once it is empty.
+pub fn remove_entries(
+    backend: &mut dyn Backend,
+    name: &str,
+    removed: &[&str],
+) -> Result<bool, String> {
+    let Some(current) = backend.get(name)? else {
+        return Ok(false);
+    };
+    let ignore_case = backend.ignore_case();
+    let mut list = entries(backend, &current);
+    let count = list.len();
+    list.retain(|e| {
+        !removed
+            .iter()
+            .any(|entry| same_entry(e, entry, ignore_case))
+    });
+    // Nothing to remove, duplicates are cleaned up when entries are added.
+    if list.len() == count {
+        return Ok(false);
+    }
+    if list.is_empty() {
+        backend.remove(name)?;
+        return Ok(true);
+    }
+    backend.set(name, &list.join(&backend.separator().to_string()))?;
+    Ok(true)
+}
+
+/// `~/.profile` of the user running trx8.
+#[cfg(not(target_os = "windows"))]
+fn user_profile() -> Result<std::path::PathBuf, String> {
+    directories::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().join(".profile"))
+        .ok_or_else(|| "Cannot find the home directory".to_string())
+}
+
+/// Opens the variables of the scope: the live registry on Windows, `/etc/environment` and
+/// `~/.profile` elsewhere.
+pub fn open(scope: Scope) -> Result<Box<dyn Backend>, String> {
+    #[cfg(target_os = "windows")]
+    {
+        Ok(Box::new(RegistryEnvironment::new(
+            crate::registry::open()?,
+            scope,
+            true,
+        )))
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let path = match scope {
+            Scope::User => user_profile()?,
+            Scope::Machine => "/etc/environment".into(),
+        };
+        Ok(Box::new(EnvFile::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::MemoryHive;
+
+    fn registry() -> RegistryEnvironment {
+        RegistryEnvironment::new(Box::new(MemoryHive::new()), Scope::Machine, false)
+    }
+
+    #[test]
+    fn edits_list_entries_once() {
+        let mut env = registry();
+        env.set("PATH", r"C:\Windows;;C:\Tools\;c:\windows")
+            .unwrap();
+        assert!(ensure_entries(&mut env, "PATH", &[r"C:\tools"], Position::Append).unwrap());
+        assert_eq!(env.get("PATH").unwrap().unwrap(), r"C:\Windows;C:\Tools\");
+        assert!(!ensure_entries(&mut env, "PATH", &[r"c:\TOOLS\"], Position::Append).unwrap());
+        assert!(ensure_entries(&mut env, "PATH", &[r"c:\tools"], Position::Prepend).unwrap());
+        assert_eq!(env.get("PATH").unwrap().unwrap(), r"c:\tools;C:\Windows");
+        assert!(!ensure_entries(&mut env, "PATH", &[r"c:\tools"], Position::Prepend).unwrap());
+    }
+
+    #[test]
+    fn removes_list_entries() {
+        let mut env = registry();
+        env.set("PATH", r"C:\Windows;C:\Tools;C:\Windows").unwrap();
+        assert!(!remove_entries(&mut env, "PATH", &[r"C:\Other"]).unwrap());
+        assert_eq!(
+            env.get("PATH").unwrap().unwrap(),
+            r"C:\Windows;C:\Tools;C:\Windows"
+        );
+        assert!(remove_entries(&mut env, "PATH", &[r"c:\tools\"]).unwrap());
+        assert_eq!(env.get("PATH").unwrap().unwrap(), r"C:\Windows");
+        assert!(remove_entries(&mut env, "PATH", &[r"C:\Windows"]).unwrap());
+        assert_eq!(env.get("PATH").unwrap(), None);
+        assert!(!remove_entries(&mut env, "PATH", &[r"C:\Windows"]).unwrap());
+    }
+
+    #[test]
+    fn compares_file_entries_with_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut env = EnvFile::open(dir.path().join("environment")).unwrap();
+        env.set("PATH", "/usr/bin:/opt/Tools").unwrap();
+        assert!(ensure_entries(&mut env, "PATH", &["/opt/tools"], Position::Append).unwrap());
+        assert_eq!(
+            env.get("PATH").unwrap().unwrap(),
+            "/usr/bin:/opt/Tools:/opt/tools"
+        );
+        assert!(!ensure_entries(&mut env, "PATH", &["/opt/tools/"], Position::Append).unwrap());
+    }
+}
@@ -0,0 +1,106 @@
+/// Variables as registry values, in the live registry or an offline hive. `PATH` and other
+/// values holding `%VARIABLE%` are `REG_EXPAND_SZ`.
+use super::{Backend, Scope};
+use crate::registry::{self, Key, Value};
+
+const USER_KEY: &str = "HKEY_CURRENT_USER\\Environment";
+const MACHINE_KEY: &str =
+    "HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment";
+
+pub struct RegistryEnvironment {
+    registry: Box<dyn registry::Backend>,
+    key: Key,
+    /// Whether running programs are told about changes, only for the live registry.
+    live: bool,
+}
+
+impl RegistryEnvironment {
+    pub fn new(registry: Box<dyn registry::Backend>, scope: Scope, live: bool) -> Self {
+        let key = match scope {
+            Scope::User => USER_KEY,
+            Scope::Machine => MACHINE_KEY,
+        };
+        RegistryEnvironment {
+            registry,
+            key: Key::parse(key).unwrap(),
+            live,
+        }
+    }
+}
+
+/// Sends `WM_SETTINGCHANGE` so Explorer and the programs it starts see the new variables.
+#[cfg(target_os = "windows")]
+fn broadcast() {
+    use windows::Win32::Foundation::{LPARAM, WPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        HWND_BROADCAST, SMTO_ABORTIFHUNG, SendMessageTimeoutW, WM_SETTINGCHANGE,
+    };
+    use windows::core::HSTRING;
+
+    let area = HSTRING::from("Environment");
+    unsafe {
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SETTINGCHANGE,
+            WPARAM(0),
+            LPARAM(area.as_ptr() as isize),
+            SMTO_ABORTIFHUNG,
+            5000,
+            None,
+        );
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn broadcast() {}
+
+impl Backend for RegistryEnvironment {
+    fn location(&self) -> String {
+        self.key.to_string()
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        match self.registry.get_value(&self.key, name)? {
+            Some(Value::String(value) | Value::ExpandString(value)) => Ok(Some(value)),
+            Some(other) => Err(format!(
+                "{} {} is not a string but {}",
+                self.key, name, other
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let expand = value.contains('%')
+            || matches!(
+                self.registry.get_value(&self.key, name)?,
+                Some(Value::ExpandString(_))
+            );
+        let value = if expand {
+            Value::ExpandString(value.to_string())
+        } else {
+            Value::String(value.to_string())
+        };
+        self.registry.set_value(&self.key, name, &value)
+    }
+
+    fn remove(&mut self, name: &str) -> Result<(), String> {
+        self.registry.delete_value(&self.key, name)
+    }
+
+    fn separator(&self) -> char {
+        ';'
+    }
+
+    fn ignore_case(&self) -> bool {
+        true
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.registry.flush()?;
+        if self.live {
+            broadcast();
+        }
+        Ok(())
+    }
+}
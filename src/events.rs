@@ -0,0 +1,126 @@
+/// Machine readable event stream, emitted as NDJSON (one JSON object per line) on stdout.
+///
+/// See [EVENTS](../EVENTS.md) for the schema.
+use serde::Serialize;
+use std::io::Write;
+use std::sync::OnceLock;
+
+use crate::secrets;
+
+/// Bumped whenever a field is removed or changes meaning, adding fields is not a breaking change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+type Listener = Box<dyn Fn(&Event) + Send + Sync>;
+/// An in-process consumer of the events, used by the TUI to draw the progress.
+static LISTENER: OnceLock<Listener> = OnceLock::new();
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Human readable logs on the console
+    #[default]
+    Text,
+    /// NDJSON events on stdout, logs go to the log file only
+    Json,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    RunStarted {
+        trx8_version: &'a str,
+        profile: &'a str,
+        profile_version: &'a str,
+        scripts: usize,
+    },
+    ScriptStarted {
+        index: usize,
+        total: usize,
+        name: &'a str,
+        description: Option<&'a str>,
+    },
+    ActionStarted {
+        script: usize,
+        index: usize,
+        name: &'a str,
+        description: Option<&'a str>,
+    },
+    ActionOutput {
+        action: &'a str,
+        stream: Stream,
+        text: &'a str,
+    },
+    ActionFinished {
+        script: usize,
+        index: usize,
+        name: &'a str,
+        status: crate::tools::ActionStatus,
+    },
+    RunFinished {
+        success: bool,
+        aborted: bool,
+        actions: usize,
+        failed: usize,
+        reboot_required: bool,
+    },
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    schema_version: u32,
+    timestamp: String,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+pub fn init(format: OutputFormat) {
+    ENABLED.set(format == OutputFormat::Json).ok();
+}
+
+pub fn enabled() -> bool {
+    *ENABLED.get().unwrap_or(&false)
+}
+
+pub fn set_listener(listener: impl Fn(&Event) + Send + Sync + 'static) {
+    LISTENER.set(Box::new(listener)).ok();
+}
+
+pub fn emit(event: Event) {
+    if let Some(listener) = LISTENER.get() {
+        listener(&event);
+    }
+    if !enabled() {
+        return;
+    }
+    let envelope = Envelope {
+        schema_version: SCHEMA_VERSION,
+        timestamp: chrono::Local::now().to_rfc3339(),
+        event,
+    };
+    match serde_json::to_string(&envelope) {
+        Ok(line) => {
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(stdout, "{}", secrets::redact(&line));
+            let _ = stdout.flush();
+        }
+        Err(e) => {
+            tracing::error!("Failed to serialize event: {}", e);
+        }
+    }
+}
+
+/// Shorthand for emitting the output of a subprocess or similar.
+pub fn output(action: &str, stream: Stream, text: &str) {
+    emit(Event::ActionOutput {
+        action,
+        stream,
+        text,
+    });
+}
@@ -0,0 +1,56 @@
+/// Facts about the machine trx8 runs on, available to templates as `facts`.
+use directories::BaseDirs;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Debug)]
+pub struct Facts {
+    /// `windows`, `linux`, `macos`...
+    pub os: &'static str,
+    /// `windows` or `unix`.
+    pub family: &'static str,
+    /// `x86_64`, `aarch64`...
+    pub arch: &'static str,
+    pub hostname: String,
+    pub username: String,
+    pub home_dir: Option<String>,
+    pub working_dir: Option<String>,
+    pub cpus: usize,
+    pub env: BTreeMap<String, String>,
+}
+
+fn hostname() -> String {
+    if let Ok(name) = std::env::var("COMPUTERNAME") {
+        return name;
+    }
+    std::fs::read_to_string("/etc/hostname")
+        .map(|name| name.trim().to_string())
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_default()
+}
+
+pub fn gather() -> Facts {
+    Facts {
+        os: std::env::consts::OS,
+        family: std::env::consts::FAMILY,
+        arch: std::env::consts::ARCH,
+        hostname: hostname(),
+        username: std::env::var("USERNAME")
+            .or_else(|_| std::env::var("USER"))
+            .unwrap_or_default(),
+        home_dir: BaseDirs::new().map(|dirs| dirs.home_dir().display().to_string()),
+        working_dir: std::env::current_dir()
+            .ok()
+            .map(|dir| dir.display().to_string()),
+        cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        // `vars()` panics on values that aren't valid Unicode.
+        env: std::env::vars_os()
+            .map(|(name, value)| {
+                (
+                    name.to_string_lossy().into_owned(),
+                    value.to_string_lossy().into_owned(),
+                )
+            })
+            .collect(),
+    }
+}
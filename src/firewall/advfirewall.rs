@@ -0,0 +1,154 @@
+/// The `netsh advfirewall firewall` commands of Windows Firewall rules, the fingerprint of a
+/// rule is kept at the end of its description.
+use super::{Action, Command, Direction, Protocol, Rule};
+
+const NETSH: &str = "netsh";
+const FINGERPRINT_PREFIX: &str = "trx8:";
+
+pub fn description(rule: &Rule) -> String {
+    let fingerprint = format!("{}{}", FINGERPRINT_PREFIX, rule.fingerprint());
+    match &rule.description {
+        Some(description) => format!("{} ({})", description, fingerprint),
+        None => fingerprint,
+    }
+}
+
+/// Whether the output of `show rule` or the description is that of the rule as defined now.
+pub fn is_current(text: &str, rule: &Rule) -> bool {
+    text.contains(&format!("{}{}", FINGERPRINT_PREFIX, rule.fingerprint()))
+}
+
+pub fn command(args: &[&str]) -> Command {
+    Command::new(NETSH, &[&["advfirewall", "firewall"], args].concat())
+}
+
+pub fn delete(name: &str) -> Command {
+    command(&["delete", "rule", &format!("name={}", name)])
+}
+
+pub fn add(rule: &Rule) -> Command {
+    let mut args = vec![
+        "add".to_string(),
+        "rule".to_string(),
+        format!("name={}", rule.name),
+        format!(
+            "dir={}",
+            match rule.direction {
+                Direction::In => "in",
+                Direction::Out => "out",
+            }
+        ),
+        format!(
+            "action={}",
+            match rule.action {
+                Action::Allow => "allow",
+                Action::Block => "block",
+            }
+        ),
+    ];
+    if let Some(program) = &rule.program {
+        args.push(format!("program={}", program));
+    }
+    args.push(format!("protocol={}", rule.protocol.name()));
+    if !rule.ports.is_empty() && rule.protocol != Protocol::Any {
+        let side = match rule.direction {
+            Direction::In => "localport",
+            Direction::Out => "remoteport",
+        };
+        args.push(format!("{}={}", side, rule.ports.join(",")));
+    }
+    let profiles = if rule.profiles.is_empty() {
+        "any".to_string()
+    } else {
+        rule.profiles
+            .iter()
+            .map(|profile| profile.name())
+            .collect::<Vec<&str>>()
+            .join(",")
+    };
+    args.push(format!("profile={}", profiles));
+    args.push(format!(
+        "enable={}",
+        if rule.enabled { "yes" } else { "no" }
+    ));
+    args.push(format!("description={}", description(rule)));
+    command(&args.iter().map(String::as_str).collect::<Vec<&str>>())
+}
+
+/// The commands turning a rule that `exists` (and is `current` or not) into `rule`.
+pub fn plan(rule: &Rule, exists: bool, current: bool) -> Vec<Command> {
+    match (exists, current) {
+        (true, true) => Vec::new(),
+        // `set rule` cannot clear fields like the program, so the rule is added again.
+        (true, false) => vec![delete(&rule.name), add(rule)],
+        (false, _) => vec![add(rule)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::firewall::Profile;
+
+    fn rule() -> Rule {
+        Rule {
+            name: "Web server".to_string(),
+            direction: Direction::In,
+            action: Action::Allow,
+            protocol: Protocol::Tcp,
+            ports: vec!["80".to_string(), "443".to_string()],
+            program: None,
+            profiles: Vec::new(),
+            enabled: true,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn adds_rules() {
+        let web = rule();
+        assert_eq!(
+            add(&web).to_string(),
+            format!(
+                "netsh advfirewall firewall add rule name=\"Web server\" dir=in action=allow \
+                 protocol=tcp localport=80,443 profile=any enable=yes description=trx8:{}",
+                web.fingerprint()
+            )
+        );
+
+        let telemetry = Rule {
+            name: "Block telemetry".to_string(),
+            direction: Direction::Out,
+            action: Action::Block,
+            protocol: Protocol::Any,
+            ports: Vec::new(),
+            program: Some(r"C:\Program Files\App\app.exe".to_string()),
+            profiles: vec![Profile::Private, Profile::Public],
+            enabled: false,
+            description: Some("Keep it quiet".to_string()),
+        };
+        assert_eq!(
+            add(&telemetry).to_string(),
+            format!(
+                "netsh advfirewall firewall add rule name=\"Block telemetry\" dir=out \
+                 action=block program=\"C:\\Program Files\\App\\app.exe\" protocol=any \
+                 profile=private,public enable=no description=\"Keep it quiet (trx8:{})\"",
+                telemetry.fingerprint()
+            )
+        );
+        assert!(is_current(&description(&telemetry), &telemetry));
+        assert!(!is_current(&description(&telemetry), &web));
+    }
+
+    #[test]
+    fn plans_only_what_changed() {
+        let web = rule();
+        assert_eq!(plan(&web, true, true), []);
+        assert_eq!(plan(&web, false, false), [add(&web)]);
+        assert_eq!(plan(&web, true, false), [delete("Web server"), add(&web)]);
+        assert_eq!(
+            delete("Web server").to_string(),
+            "netsh advfirewall firewall delete rule name=\"Web server\""
+        );
+    }
+}
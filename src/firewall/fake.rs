@@ -0,0 +1,74 @@
+/// Rules that only exist in a JSON file like `{"<name>": "<description>"}`, planned with the
+/// netsh commands of Windows so they can be previewed anywhere.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::{Backend, Command, Rule, advfirewall};
+
+pub struct FakeFirewall {
+    path: PathBuf,
+    rules: BTreeMap<String, String>,
+}
+
+impl FakeFirewall {
+    /// Loads the rules, a missing file has none.
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        let rules = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid fake firewall file {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        Ok(FakeFirewall { path, rules })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.rules).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+/// The value of a `key=value` argument.
+fn argument<'a>(command: &'a Command, key: &str) -> Option<&'a str> {
+    command
+        .args
+        .iter()
+        .find_map(|arg| arg.strip_prefix(key)?.strip_prefix('='))
+}
+
+impl Backend for FakeFirewall {
+    fn plan(&self, rule: &Rule) -> Result<Vec<Command>, String> {
+        let description = self.rules.get(&rule.name);
+        let current = description.is_some_and(|text| advfirewall::is_current(text, rule));
+        Ok(advfirewall::plan(rule, description.is_some(), current))
+    }
+
+    fn plan_remove(&self, name: &str) -> Result<Vec<Command>, String> {
+        Ok(match self.rules.contains_key(name) {
+            true => vec![advfirewall::delete(name)],
+            false => Vec::new(),
+        })
+    }
+
+    fn apply(&mut self, commands: &[Command]) -> Result<(), String> {
+        for command in commands {
+            let name = argument(command, "name")
+                .ok_or_else(|| format!("Unexpected command: {}", command))?
+                .to_string();
+            match command.args.get(2).map(String::as_str) {
+                Some("add") => {
+                    let description = argument(command, "description").unwrap_or_default();
+                    self.rules.insert(name, description.to_string());
+                }
+                Some("delete") => {
+                    self.rules.remove(&name);
+                }
+                _ => return Err(format!("Unexpected command: {}", command)),
+            }
+        }
+        self.save()
+    }
+}
@@ -0,0 +1,322 @@
+/// Firewall rules identified by their name, behind a [`Backend`] trait: Windows Firewall through
+/// `netsh advfirewall`, nftables on Linux, and a fake kept in a JSON file. Backends plan the
+/// exact commands bringing a rule to its definition before running them.
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::process;
+
+mod advfirewall;
+mod fake;
+#[cfg(target_os = "windows")]
+mod netsh;
+#[cfg(target_os = "linux")]
+mod nft;
+
+pub use fake::FakeFirewall;
+
+/// Uses the fake backend with this state file instead of the system firewall when set.
+pub const FAKE_ENV: &str = "TRX8_FAKE_FIREWALL";
+
+/// Only shows the commands of the firewall actions when set, as `--plan` does.
+pub const PLAN_ENV: &str = "TRX8_FIREWALL_PLAN";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+impl Direction {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "in" | "inbound" => Ok(Direction::In),
+            "out" | "outbound" => Ok(Direction::Out),
+            _ => Err(format!("Unknown direction: {} (expected in or out)", name)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Allow,
+    Block,
+}
+
+impl Action {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "allow" => Ok(Action::Allow),
+            "block" => Ok(Action::Block),
+            _ => Err(format!(
+                "Unknown firewall action: {} (expected allow or block)",
+                name
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Any,
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "any" => Ok(Protocol::Any),
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            _ => Err(format!(
+                "Unknown protocol: {} (expected any, tcp or udp)",
+                name
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Any => "any",
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+}
+
+/// The Windows Firewall profiles, a rule without profiles applies to all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    Domain,
+    Private,
+    Public,
+}
+
+impl Profile {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "domain" => Ok(Profile::Domain),
+            "private" => Ok(Profile::Private),
+            "public" => Ok(Profile::Public),
+            _ => Err(format!(
+                "Unknown firewall profile: {} (expected domain, private or public)",
+                name
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Domain => "domain",
+            Profile::Private => "private",
+            Profile::Public => "public",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub name: String,
+    pub direction: Direction,
+    pub action: Action,
+    pub protocol: Protocol,
+    /// Local ports of inbound rules and remote ports of outbound ones, `80` or `8000-8100`.
+    pub ports: Vec<String>,
+    /// Only on Windows.
+    pub program: Option<String>,
+    pub profiles: Vec<Profile>,
+    pub enabled: bool,
+    pub description: Option<String>,
+}
+
+/// Rule names end up quoted in command lines and in nftables comments, and `name=all` means
+/// every rule to netsh.
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.contains('"') || name.len() > 100 {
+        return Err(format!(
+            "Invalid rule name: {:?} (1 to 100 characters without \")",
+            name
+        ));
+    }
+    if name.trim().eq_ignore_ascii_case("all") {
+        return Err("A firewall rule cannot be named \"all\"".to_string());
+    }
+    Ok(())
+}
+
+impl Rule {
+    pub fn validate(&self) -> Result<(), String> {
+        check_name(&self.name)?;
+        if !self.ports.is_empty() && self.protocol == Protocol::Any {
+            return Err("Ports need --protocol=tcp or --protocol=udp".to_string());
+        }
+        for port in &self.ports {
+            let valid = |n: &str| n.parse::<u16>().is_ok_and(|n| n > 0);
+            let ok = match port.split_once('-') {
+                Some((first, last)) => valid(first) && valid(last),
+                None => valid(port),
+            };
+            if !ok {
+                return Err(format!("Invalid port: {}", port));
+            }
+        }
+        Ok(())
+    }
+
+    /// A hash of the definition, stored with the rule to tell whether it changed.
+    pub fn fingerprint(&self) -> String {
+        let profiles = self
+            .profiles
+            .iter()
+            .map(Profile::name)
+            .collect::<Vec<&str>>();
+        let definition = [
+            self.name.as_str(),
+            if self.direction == Direction::In {
+                "in"
+            } else {
+                "out"
+            },
+            if self.action == Action::Allow {
+                "allow"
+            } else {
+                "block"
+            },
+            self.protocol.name(),
+            &self.ports.join(","),
+            self.program.as_deref().unwrap_or_default(),
+            &profiles.join(","),
+            if self.enabled { "enabled" } else { "disabled" },
+            self.description.as_deref().unwrap_or_default(),
+        ]
+        .join("\n");
+        hex::encode(Sha256::digest(definition.as_bytes()))[..16].to_string()
+    }
+}
+
+/// A command line, shown in plans exactly as it is run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Command {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Command {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+/// Quotes an argument for the command line: `key="value"` as netsh expects it, in single quotes
+/// like a shell otherwise.
+pub fn quote(arg: &str) -> String {
+    let plain = |text: &str| {
+        !text.is_empty()
+            && text
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.,:/=@%+".contains(c))
+    };
+    if plain(arg) {
+        return arg.to_string();
+    }
+    if let Some((key, value)) = arg.split_once('=')
+        && plain(key)
+        && !value.contains('"')
+    {
+        return format!("{}=\"{}\"", key, value);
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", quote(arg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the command, returning whether it succeeded and its output.
+pub fn output(command: &Command) -> Result<(bool, String), String> {
+    let mut process = process::Command::new(&command.program);
+    #[cfg(target_os = "windows")]
+    {
+        // netsh parses its own command line, so it gets the quoting shown in plans.
+        use std::os::windows::process::CommandExt;
+        for arg in &command.args {
+            process.raw_arg(quote(arg));
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    process.args(&command.args);
+    let output = process
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", command.program, e))?;
+    let text = [
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    ]
+    .concat();
+    Ok((output.status.success(), text))
+}
+
+pub fn run(command: &Command) -> Result<String, String> {
+    match output(command)? {
+        (true, text) => Ok(text),
+        (false, text) => Err(format!("{} failed: {}", command, text.trim())),
+    }
+}
+
+/// Where rules go.
+pub trait Backend {
+    /// The commands bringing the rules named like `rule` to it, none when they already match.
+    fn plan(&self, rule: &Rule) -> Result<Vec<Command>, String>;
+    /// The commands removing the rules with this name, none when there are none.
+    fn plan_remove(&self, name: &str) -> Result<Vec<Command>, String>;
+    /// Runs the commands of a plan.
+    fn apply(&mut self, commands: &[Command]) -> Result<(), String>;
+}
+
+/// Opens the firewall of the system, or the fake one when [`FAKE_ENV`] is set.
+pub fn open() -> Result<Box<dyn Backend>, String> {
+    if let Ok(path) = std::env::var(FAKE_ENV) {
+        return Ok(Box::new(FakeFirewall::open(path.into())?));
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Ok(Box::new(netsh::Netsh))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Box::new(nft::Nft))
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        Err("Firewall rules are only available on Windows and Linux".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_arguments() {
+        assert_eq!(quote("80,443"), "80,443");
+        assert_eq!(quote("name=Web"), "name=Web");
+        assert_eq!(quote("name=Web server"), "name=\"Web server\"");
+        assert_eq!(
+            quote(r"program=C:\App\a b.exe"),
+            r#"program="C:\App\a b.exe""#
+        );
+        assert_eq!(quote("{ 80, 443 }"), "'{ 80, 443 }'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a=\"b\""), "'a=\"b\"'");
+    }
+}
@@ -0,0 +1,39 @@
+/// Windows Firewall rules through `netsh advfirewall firewall`, several rules can share a name
+/// and are then replaced or deleted together.
+use super::{Backend, Command, Rule, advfirewall, output, run};
+
+pub struct Netsh;
+
+impl Netsh {
+    /// The verbose listing of the rules with this name, `None` when there are none.
+    fn show(&self, name: &str) -> Result<Option<String>, String> {
+        let name = format!("name={}", name);
+        let command = advfirewall::command(&["show", "rule", &name, "verbose"]);
+        let (found, text) = output(&command)?;
+        Ok(found.then_some(text))
+    }
+}
+
+impl Backend for Netsh {
+    fn plan(&self, rule: &Rule) -> Result<Vec<Command>, String> {
+        let shown = self.show(&rule.name)?;
+        let current = shown
+            .as_deref()
+            .is_some_and(|text| advfirewall::is_current(text, rule));
+        Ok(advfirewall::plan(rule, shown.is_some(), current))
+    }
+
+    fn plan_remove(&self, name: &str) -> Result<Vec<Command>, String> {
+        Ok(match self.show(name)? {
+            Some(_) => vec![advfirewall::delete(name)],
+            None => Vec::new(),
+        })
+    }
+
+    fn apply(&mut self, commands: &[Command]) -> Result<(), String> {
+        for command in commands {
+            run(command)?;
+        }
+        Ok(())
+    }
+}
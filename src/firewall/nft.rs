@@ -0,0 +1,319 @@
+/// Rules in the `inet trx8` nftables table, one nftables rule per rule with a `<name>
+/// [<fingerprint>]` comment. The table is saved to `/etc/trx8/firewall.nft`, which
+/// `/etc/nftables.conf` includes so the rules are loaded again at boot. The chains of the table
+/// accept by default and a drop in any other table is final, so allow rules only matter on
+/// systems without a ruleset of their own.
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+use super::{Action, Backend, Command, Direction, Protocol, Rule, output, run};
+
+const NFT: &str = "nft";
+const FAMILY: &str = "inet";
+const TABLE: &str = "trx8";
+const SAVED: &str = "/etc/trx8/firewall.nft";
+const CONFIG: &str = "/etc/nftables.conf";
+
+pub struct Nft;
+
+/// A rule of the table made by trx8.
+struct Listed {
+    chain: String,
+    name: String,
+    fingerprint: String,
+    handle: String,
+}
+
+fn chain(direction: Direction) -> &'static str {
+    match direction {
+        Direction::In => "input",
+        Direction::Out => "output",
+    }
+}
+
+fn nft(args: &[&str]) -> Command {
+    Command::new(NFT, args)
+}
+
+/// Parses `nft --handle list table`, rules look like
+/// `tcp dport 80 accept comment "web [0123456789abcdef]" # handle 4`.
+fn parse_table(text: &str) -> Vec<Listed> {
+    let mut chain = "";
+    let mut listed = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("chain ") {
+            chain = rest.split_whitespace().next().unwrap_or_default();
+            continue;
+        }
+        let Some((rule, handle)) = line.rsplit_once(" # handle ") else {
+            continue;
+        };
+        let Some(comment) = rule
+            .split_once("comment \"")
+            .and_then(|(_, comment)| comment.strip_suffix('"'))
+        else {
+            continue;
+        };
+        let Some((name, fingerprint)) = comment
+            .rsplit_once(" [")
+            .and_then(|(name, rest)| Some((name, rest.strip_suffix(']')?)))
+        else {
+            continue;
+        };
+        listed.push(Listed {
+            chain: chain.to_string(),
+            name: name.to_string(),
+            fingerprint: fingerprint.to_string(),
+            handle: handle.trim().to_string(),
+        });
+    }
+    listed
+}
+
+fn delete_rule(listed: &Listed) -> Command {
+    nft(&[
+        "delete",
+        "rule",
+        FAMILY,
+        TABLE,
+        &listed.chain,
+        "handle",
+        &listed.handle,
+    ])
+}
+
+fn add_rule(rule: &Rule) -> Command {
+    let comment = format!("\"{} [{}]\"", rule.name, rule.fingerprint());
+    let ports = match rule.ports.as_slice() {
+        [port] => port.clone(),
+        ports => format!("{{ {} }}", ports.join(", ")),
+    };
+    let mut args = vec!["add", "rule", FAMILY, TABLE, chain(rule.direction)];
+    match (rule.protocol, rule.ports.is_empty()) {
+        (Protocol::Any, _) => {}
+        (protocol, true) => args.extend(["meta", "l4proto", protocol.name()]),
+        (protocol, false) => args.extend([protocol.name(), "dport", &ports]),
+    }
+    args.push(match rule.action {
+        Action::Allow => "accept",
+        Action::Block => "drop",
+    });
+    args.extend(["comment", &comment]);
+    nft(&args)
+}
+
+impl Nft {
+    /// The rules of the table, `None` when it doesn't exist yet.
+    fn list(&self) -> Result<Option<Vec<Listed>>, String> {
+        let command = nft(&["--handle", "list", "table", FAMILY, TABLE]);
+        match output(&command)? {
+            (true, text) => Ok(Some(parse_table(&text))),
+            (false, text) if text.contains("No such file or directory") => Ok(None),
+            (false, text) => Err(format!("{} failed: {}", command, text.trim())),
+        }
+    }
+
+    /// The tables of other rulesets, their drops win over the allow rules of this table.
+    fn other_tables(&self) -> Result<Vec<String>, String> {
+        let own = format!("table {} {}", FAMILY, TABLE);
+        Ok(run(&nft(&["list", "tables"]))?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && *line != own)
+            .map(|line| line.trim_start_matches("table ").to_string())
+            .collect())
+    }
+
+    /// Saves the table and makes sure `/etc/nftables.conf` loads it.
+    fn save(&self) -> Result<(), String> {
+        let table = run(&nft(&["list", "table", FAMILY, TABLE]))?;
+        // Declaring then deleting the table makes loading the file twice harmless.
+        let content = format!(
+            "#!/usr/sbin/nft -f\n# Written by trx8, do not edit.\n\
+             table {0} {1}\ndelete table {0} {1}\n{2}",
+            FAMILY, TABLE, table
+        );
+        let path = Path::new(SAVED);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", SAVED, e))?;
+
+        let include = format!("include \"{}\"", SAVED);
+        if let Ok(config) = fs::read_to_string(CONFIG)
+            && !config.lines().any(|line| line.trim() == include)
+        {
+            let separator = if config.ends_with('\n') || config.is_empty() {
+                ""
+            } else {
+                "\n"
+            };
+            fs::write(CONFIG, format!("{}{}{}\n", config, separator, include))
+                .map_err(|e| format!("Failed to write {}: {}", CONFIG, e))?;
+            info!("[firewall] {} now loads {}", CONFIG, SAVED);
+        }
+        Ok(())
+    }
+}
+
+impl Backend for Nft {
+    fn plan(&self, rule: &Rule) -> Result<Vec<Command>, String> {
+        if rule.program.is_some() {
+            return Err(
+                "nftables cannot match programs, --program is only for Windows".to_string(),
+            );
+        }
+        if !rule.profiles.is_empty() {
+            return Err("Firewall profiles only exist on Windows".to_string());
+        }
+        if rule.enabled && rule.action == Action::Allow {
+            let others = self.other_tables()?;
+            if !others.is_empty() {
+                warn!(
+                    "[firewall] {} may have no effect, packets dropped by {} are dropped anyway",
+                    rule.name,
+                    others.join(", ")
+                );
+            }
+        }
+        let listed = self.list()?;
+        let existing = listed
+            .iter()
+            .flatten()
+            .filter(|l| l.name == rule.name)
+            .collect::<Vec<&Listed>>();
+        let current = match existing.as_slice() {
+            [] => !rule.enabled,
+            [only] => {
+                rule.enabled
+                    && only.fingerprint == rule.fingerprint()
+                    && only.chain == chain(rule.direction)
+            }
+            _ => false,
+        };
+        if current {
+            return Ok(Vec::new());
+        }
+        let mut commands = existing
+            .into_iter()
+            .map(delete_rule)
+            .collect::<Vec<Command>>();
+        if rule.enabled {
+            if listed.is_none() {
+                commands.push(nft(&["add", "table", FAMILY, TABLE]));
+                for chain in ["input", "output"] {
+                    let definition = format!(
+                        "{{ type filter hook {} priority 0; policy accept; }}",
+                        chain
+                    );
+                    commands.push(nft(&["add", "chain", FAMILY, TABLE, chain, &definition]));
+                }
+            }
+            commands.push(add_rule(rule));
+        }
+        Ok(commands)
+    }
+
+    fn plan_remove(&self, name: &str) -> Result<Vec<Command>, String> {
+        Ok(self
+            .list()?
+            .iter()
+            .flatten()
+            .filter(|l| l.name == name)
+            .map(delete_rule)
+            .collect())
+    }
+
+    fn apply(&mut self, commands: &[Command]) -> Result<(), String> {
+        for command in commands {
+            run(command)?;
+        }
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(direction: Direction, protocol: Protocol, ports: &[&str]) -> Rule {
+        Rule {
+            name: "Web server".to_string(),
+            direction,
+            action: Action::Allow,
+            protocol,
+            ports: ports.iter().map(|port| port.to_string()).collect(),
+            program: None,
+            profiles: Vec::new(),
+            enabled: true,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn adds_rules() {
+        let web = rule(Direction::In, Protocol::Tcp, &["80", "8000-8100"]);
+        assert_eq!(
+            add_rule(&web).to_string(),
+            format!(
+                "nft add rule inet trx8 input tcp dport '{{ 80, 8000-8100 }}' accept comment \
+                 '\"Web server [{}]\"'",
+                web.fingerprint()
+            )
+        );
+        let mut dns = rule(Direction::Out, Protocol::Udp, &["53"]);
+        dns.action = Action::Block;
+        assert_eq!(
+            add_rule(&dns).args[4..9],
+            ["output", "udp", "dport", "53", "drop"]
+        );
+        assert_eq!(
+            add_rule(&rule(Direction::In, Protocol::Udp, &[])).args[5..9],
+            ["meta", "l4proto", "udp", "accept"]
+        );
+        assert_eq!(
+            add_rule(&rule(Direction::In, Protocol::Any, &[])).args[5],
+            "accept"
+        );
+    }
+
+    #[test]
+    fn parses_listed_tables() {
+        let listed = parse_table(
+            "table inet trx8 { # handle 7
+\tchain input { # handle 1
+\t\ttype filter hook input priority filter; policy accept;
+\t\ttcp dport { 80, 443 } accept comment \"Web server [0123456789abcdef]\" # handle 4
+\t\ttcp dport 22 accept # handle 5
+\t}
+\tchain output { # handle 2
+\t\tudp dport 53 drop comment \"No [DNS] [fedcba9876543210]\" # handle 6
+\t}
+}
+",
+        );
+        let summary = listed
+            .iter()
+            .map(|l| {
+                [
+                    l.chain.as_str(),
+                    l.name.as_str(),
+                    l.fingerprint.as_str(),
+                    l.handle.as_str(),
+                ]
+            })
+            .collect::<Vec<[&str; 4]>>();
+        assert_eq!(
+            summary,
+            [
+                ["input", "Web server", "0123456789abcdef", "4"],
+                ["output", "No [DNS]", "fedcba9876543210", "6"],
+            ]
+        );
+        assert_eq!(
+            delete_rule(&listed[1]).to_string(),
+            "nft delete rule inet trx8 output handle 6"
+        );
+    }
+}
@@ -0,0 +1,371 @@
+/// Profile-defined inputs, collected before execution and substituted into action parameters.
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use tracing::{info, trace, warn};
+
+use crate::config::{Config, Input, InputType};
+use crate::secrets;
+
+/// Matches `${inputs.<name>}` and `${secrets.<name>}` references in parameters.
+static REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\{(inputs|secrets)\.([A-Za-z0-9_\-]+)\}").unwrap());
+
+/// Resolved values keyed by their reference without the braces, e.g. `inputs.name`.
+pub type Values = HashMap<String, String>;
+
+/// The environment variable an input can be provided with, e.g. `TRX8_INPUT_COMPUTER_NAME`.
+pub fn env_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("TRX8_INPUT_{}", name)
+}
+
+/// Checks that the inputs are well-formed and every `${inputs.<name>}` and `${secrets.<name>}`
+/// reference is declared.
+pub fn check(config: &Config) -> Result<(), String> {
+    let inputs = config.inputs.as_deref().unwrap_or_default();
+    for input in inputs {
+        if let Some(validation) = &input.validation {
+            Regex::new(validation)
+                .map_err(|e| format!("Invalid validation regex for input {}: {}", input.name, e))?;
+        }
+        if input.kind == InputType::Choice && input.choices.as_ref().is_none_or(|c| c.is_empty()) {
+            return Err(format!(
+                "Input {} is a choice but has no choices",
+                input.name
+            ));
+        }
+    }
+    for script in &config.scripts {
+        for action in &script.actions {
+            for parameter in action.parameters.iter().flatten() {
+                for reference in REFERENCE.captures_iter(parameter) {
+                    let name = &reference[2];
+                    let declared = match &reference[1] {
+                        "inputs" => inputs.iter().any(|i| i.name == name),
+                        _ => config.secrets.iter().flatten().any(|s| s.name == name),
+                    };
+                    if !declared {
+                        return Err(format!(
+                            "Action {} in script {} references undeclared {}: {}",
+                            action.name,
+                            script.name,
+                            &reference[1].trim_end_matches('s'),
+                            name
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validates and normalizes a value for the input, e.g. `yes` becomes `true` for bool inputs.
+/// An empty value is only valid for inputs that aren't required.
+pub fn validate(input: &Input, value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        if input.required.unwrap_or(true) {
+            return Err("a value is required".to_string());
+        }
+        return Ok(String::new());
+    }
+    let value = match input.kind {
+        InputType::Bool => match value.trim().to_lowercase().as_str() {
+            "y" | "yes" | "true" | "1" => "true".to_string(),
+            "n" | "no" | "false" | "0" => "false".to_string(),
+            _ => return Err(format!("{} is not a yes/no value", value)),
+        },
+        InputType::Choice => {
+            let choices = input.choices.as_deref().unwrap_or_default();
+            // Allow picking a choice by its number as shown in the prompt.
+            match value.trim().parse::<usize>() {
+                Ok(i) if i >= 1 && i <= choices.len() && !choices.contains(&value.to_string()) => {
+                    choices[i - 1].clone()
+                }
+                _ if choices.iter().any(|c| c == value) => value.to_string(),
+                _ => return Err(format!("{} is not one of: {}", value, choices.join(", "))),
+            }
+        }
+        InputType::String | InputType::Secret => value.to_string(),
+    };
+    if let Some(validation) = &input.validation {
+        let regex = Regex::new(&format!("^(?:{})$", validation)).map_err(|e| e.to_string())?;
+        if !regex.is_match(&value) {
+            return Err(format!("value does not match {}", validation));
+        }
+    }
+    Ok(value)
+}
+
+/// Reads a line from the operator, `None` once the input has ended.
+type ReadLine<'a> = &'a mut dyn FnMut(&Input) -> Result<Option<String>, String>;
+
+/// Reads from the terminal, without echo for secrets.
+fn read_line(input: &Input) -> Result<Option<String>, String> {
+    if input.kind == InputType::Secret {
+        return rpassword::read_password()
+            .map(Some)
+            .map_err(|e| e.to_string());
+    }
+    let mut line = String::new();
+    let read = std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| e.to_string())?;
+    Ok((read > 0).then(|| line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// Asks for the input, failing if the input ended instead of looping on empty values.
+fn prompt(input: &Input, read_line: ReadLine) -> Result<String, String> {
+    let text = input.prompt.as_deref().unwrap_or(&input.name);
+    if input.kind == InputType::Choice {
+        for (i, choice) in input.choices.iter().flatten().enumerate() {
+            info!("  {}. {}", i + 1, choice);
+        }
+    }
+    match &input.default {
+        Some(default) if input.kind != InputType::Secret => warn!("{} [{}]:", text, default),
+        _ => warn!("{}:", text),
+    }
+    read_line(input)?.ok_or_else(|| {
+        format!(
+            "No value for input {}, the input ended, pass it with --input {}=<value>",
+            input.name, input.name
+        )
+    })
+}
+
+/// Collects a value for every input, from `--input name=value`, then the environment, then
+/// the operator if `interactive`, and finally the default.
+///
+/// Fails if a value is invalid or a required input has no value.
+pub fn collect(config: &Config, cli: &[String], interactive: bool) -> Result<Values, String> {
+    collect_from(config, cli, interactive.then_some(&mut read_line))
+}
+
+/// [`collect`], asking with `read_line` if it is given.
+fn collect_from(
+    config: &Config,
+    cli: &[String],
+    mut read_line: Option<ReadLine>,
+) -> Result<Values, String> {
+    let inputs = config.inputs.as_deref().unwrap_or_default();
+    let mut provided = HashMap::new();
+    for pair in cli {
+        let Some((name, value)) = pair.split_once('=') else {
+            return Err(format!("Invalid --input {}, expected name=value", pair));
+        };
+        if !inputs.iter().any(|i| i.name == name) {
+            return Err(format!("Unknown input: {}", name));
+        }
+        provided.insert(name.to_string(), value.to_string());
+    }
+    let mut values = Values::new();
+    for input in inputs {
+        let value = match provided
+            .remove(&input.name)
+            .or_else(|| std::env::var(env_name(&input.name)).ok())
+        {
+            Some(value) => validate(input, &value)
+                .map_err(|e| format!("Invalid input {}: {}", input.name, e))?,
+            None if let Some(read_line) = read_line.as_deref_mut() => loop {
+                let line = prompt(input, read_line)?;
+                let line = match (&input.default, line.is_empty()) {
+                    (Some(default), true) => default.clone(),
+                    _ => line,
+                };
+                match validate(input, &line) {
+                    Ok(value) => break value,
+                    Err(e) => warn!("Invalid value: {}", e),
+                }
+            },
+            None => match &input.default {
+                Some(default) => validate(input, default)
+                    .map_err(|e| format!("Invalid default for input {}: {}", input.name, e))?,
+                None if !input.required.unwrap_or(true) => String::new(),
+                None => {
+                    return Err(format!(
+                        "Missing required input {}, pass it with --input {}=<value> or {}",
+                        input.name,
+                        input.name,
+                        env_name(&input.name)
+                    ));
+                }
+            },
+        };
+        if input.kind == InputType::Secret {
+            secrets::register(&value);
+            trace!("Input {} set", input.name);
+        } else {
+            trace!("Input {} set to: {}", input.name, value);
+        }
+        values.insert(format!("inputs.{}", input.name), value);
+    }
+    Ok(values)
+}
+
+/// Substitutes `${inputs.<name>}` and `${secrets.<name>}` references in the parameters.
+pub fn resolve(parameters: &Option<Vec<String>>, values: &Values) -> Option<Vec<String>> {
+    parameters.as_ref().map(|params| {
+        params
+            .iter()
+            .map(|param| {
+                REFERENCE
+                    .replace_all(param, |caps: &regex::Captures| {
+                        values
+                            .get(&format!("{}.{}", &caps[1], &caps[2]))
+                            .cloned()
+                            .unwrap_or_default()
+                    })
+                    .into_owned()
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    fn config(inputs: serde_json::Value) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "Test", "version": "1.0.0", "trx8_version": "0.1.0"},
+            "inputs": inputs,
+            "scripts": []
+        }))
+        .unwrap()
+    }
+
+    fn input(value: serde_json::Value) -> Input {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Collects with the lines as the operator's answers, `None` is the end of the input.
+    fn answer(config: &Config, cli: &[&str], lines: &[&str]) -> Result<Values, String> {
+        let cli = cli.iter().map(|c| c.to_string()).collect::<Vec<String>>();
+        let mut lines = lines
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<VecDeque<String>>();
+        collect_from(config, &cli, Some(&mut |_: &Input| Ok(lines.pop_front())))
+    }
+
+    #[test]
+    fn validates_values() {
+        let flag = input(serde_json::json!({"name": "flag", "type": "bool"}));
+        assert_eq!(validate(&flag, "Yes"), Ok("true".to_string()));
+        assert_eq!(validate(&flag, "0"), Ok("false".to_string()));
+        assert_eq!(
+            validate(&flag, "maybe"),
+            Err("maybe is not a yes/no value".to_string())
+        );
+        assert_eq!(validate(&flag, ""), Err("a value is required".to_string()));
+
+        let size = input(serde_json::json!({
+            "name": "size", "type": "choice", "choices": ["small", "2", "large"]
+        }));
+        assert_eq!(validate(&size, "1"), Ok("small".to_string()));
+        assert_eq!(validate(&size, "3"), Ok("large".to_string()));
+        // A choice that is a number is taken as is.
+        assert_eq!(validate(&size, "2"), Ok("2".to_string()));
+        assert_eq!(
+            validate(&size, "huge"),
+            Err("huge is not one of: small, 2, large".to_string())
+        );
+
+        let name = input(serde_json::json!({
+            "name": "name", "validation": "[a-z]+", "required": false
+        }));
+        assert_eq!(validate(&name, "pc"), Ok("pc".to_string()));
+        assert_eq!(
+            validate(&name, "pc-01"),
+            Err("value does not match [a-z]+".to_string())
+        );
+        assert_eq!(validate(&name, ""), Ok(String::new()));
+    }
+
+    #[test]
+    fn collects_provided_values_and_defaults() {
+        let config = config(serde_json::json!([
+            {"name": "trx8_test_host", "type": "string"},
+            {"name": "trx8_test_reboot", "type": "bool", "default": "no"},
+            {"name": "trx8_test_note", "required": false}
+        ]));
+        let values = collect(&config, &["trx8_test_host=pc01".to_string()], false).unwrap();
+        assert_eq!(values["inputs.trx8_test_host"], "pc01");
+        assert_eq!(values["inputs.trx8_test_reboot"], "false");
+        assert_eq!(values["inputs.trx8_test_note"], "");
+
+        assert_eq!(
+            collect(&config, &[], false),
+            Err(
+                "Missing required input trx8_test_host, pass it with --input \
+                 trx8_test_host=<value> or TRX8_INPUT_TRX8_TEST_HOST"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            collect(&config, &["trx8_test_host=".to_string()], false),
+            Err("Invalid input trx8_test_host: a value is required".to_string())
+        );
+        assert_eq!(
+            collect(&config, &["other=1".to_string()], false),
+            Err("Unknown input: other".to_string())
+        );
+        assert_eq!(
+            collect(&config, &["trx8_test_host".to_string()], false),
+            Err("Invalid --input trx8_test_host, expected name=value".to_string())
+        );
+    }
+
+    #[test]
+    fn asks_until_the_value_is_valid() {
+        let config = config(serde_json::json!([
+            {"name": "trx8_test_user"},
+            {"name": "trx8_test_reboot", "type": "bool", "default": "yes"},
+            {"name": "trx8_test_size", "type": "choice", "choices": ["small", "large"]},
+            {"name": "trx8_test_note", "required": false}
+        ]));
+        let values = answer(&config, &[], &["", "admin", "", "medium", "2", ""]).unwrap();
+        assert_eq!(values["inputs.trx8_test_user"], "admin");
+        assert_eq!(values["inputs.trx8_test_reboot"], "true");
+        assert_eq!(values["inputs.trx8_test_size"], "large");
+        assert_eq!(values["inputs.trx8_test_note"], "");
+
+        // Provided values aren't asked for.
+        let values = answer(
+            &config,
+            &["trx8_test_user=root", "trx8_test_size=small"],
+            &["n", "note"],
+        )
+        .unwrap();
+        assert_eq!(values["inputs.trx8_test_reboot"], "false");
+        assert_eq!(values["inputs.trx8_test_note"], "note");
+    }
+
+    #[test]
+    fn fails_when_the_input_ends() {
+        let config = config(serde_json::json!([
+            {"name": "trx8_test_confirm", "type": "bool"}
+        ]));
+        assert_eq!(
+            answer(&config, &[], &["maybe"]),
+            Err(
+                "No value for input trx8_test_confirm, the input ended, pass it with --input \
+                 trx8_test_confirm=<value>"
+                    .to_string()
+            )
+        );
+        assert!(answer(&config, &[], &[]).is_err());
+    }
+}
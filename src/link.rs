@@ -0,0 +1,196 @@
+/// What the link actions need from the system: telling hard links apart, creating junctions and
+/// finding the folders shortcuts go to.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Whether both paths are the same file, like two hard links to it.
+pub fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let (a, b) = (fs::metadata(a)?, fs::metadata(b)?);
+        Ok(a.dev() == b.dev() && a.ino() == b.ino())
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::io::AsRawHandle;
+        use windows::Win32::Foundation::HANDLE;
+        use windows::Win32::Storage::FileSystem::{
+            BY_HANDLE_FILE_INFORMATION, GetFileInformationByHandle,
+        };
+
+        let id = |path: &Path| -> io::Result<(u32, u32, u32)> {
+            let file = fs::File::open(path)?;
+            let mut info = BY_HANDLE_FILE_INFORMATION::default();
+            unsafe { GetFileInformationByHandle(HANDLE(file.as_raw_handle()), &mut info) }
+                .map_err(io::Error::other)?;
+            Ok((
+                info.dwVolumeSerialNumber,
+                info.nFileIndexHigh,
+                info.nFileIndexLow,
+            ))
+        };
+        Ok(id(a)? == id(b)?)
+    }
+}
+
+/// The target of a junction without the `\\?\` or `\??\` prefix, to compare it with a path.
+#[cfg(target_os = "windows")]
+pub fn junction_target(link: &Path) -> Option<PathBuf> {
+    let target = fs::read_link(link).ok()?;
+    let target = target.to_string_lossy();
+    let target = target
+        .strip_prefix(r"\\?\")
+        .or_else(|| target.strip_prefix(r"\??\"))
+        .unwrap_or(&target);
+    Some(PathBuf::from(target))
+}
+
+/// The tag of junctions, which are mount points of directories.
+#[cfg(any(target_os = "windows", test))]
+const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+#[cfg(target_os = "windows")]
+const FSCTL_SET_REPARSE_POINT: u32 = 0x0009_00A4;
+
+/// The REPARSE_DATA_BUFFER of a junction to `target`: the NT path `\??\<target>` followed by the
+/// system and the path shown to users, both null-terminated UTF-16.
+#[cfg(any(target_os = "windows", test))]
+fn mount_point_buffer(target: &str) -> Result<Vec<u8>, String> {
+    let target = target.strip_prefix(r"\\?\").unwrap_or(target);
+    let utf16 = |text: &str| {
+        text.encode_utf16()
+            .chain([0])
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<u8>>()
+    };
+    let substitute = utf16(&format!(r"\??\{}", target));
+    let print = utf16(target);
+    // The data after the header: the offsets and lengths of the names, then the names.
+    let length = u16::try_from(8 + substitute.len() + print.len())
+        .map_err(|_| format!("Path too long for a junction: {}", target))?;
+    let mut buffer = IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes().to_vec();
+    buffer.extend_from_slice(&length.to_le_bytes());
+    buffer.extend_from_slice(&[0, 0]); // Reserved
+    // The lengths of the names are in bytes without the terminating nulls.
+    for value in [0, substitute.len() - 2, substitute.len(), print.len() - 2] {
+        buffer.extend_from_slice(&(value as u16).to_le_bytes());
+    }
+    buffer.extend(substitute);
+    buffer.extend(print);
+    Ok(buffer)
+}
+
+/// Makes `link` a junction to the directory `target`, which must be absolute. The link is
+/// created as an empty directory which is then made a mount point.
+#[cfg(target_os = "windows")]
+pub fn create_junction(target: &Path, link: &Path) -> Result<(), String> {
+    use windows::Win32::Foundation::{CloseHandle, GENERIC_WRITE};
+    use windows::Win32::Storage::FileSystem::{
+        CreateFileW, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT, FILE_SHARE_MODE,
+        OPEN_EXISTING,
+    };
+    use windows::Win32::System::IO::DeviceIoControl;
+    use windows::core::HSTRING;
+
+    let buffer = mount_point_buffer(&target.to_string_lossy())?;
+    fs::create_dir(link).map_err(|e| format!("Failed to create {}: {}", link.display(), e))?;
+    let result = unsafe {
+        CreateFileW(
+            &HSTRING::from(link),
+            GENERIC_WRITE.0,
+            FILE_SHARE_MODE(0),
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT,
+            None,
+        )
+        .and_then(|handle| {
+            let mut returned = 0;
+            let result = DeviceIoControl(
+                handle,
+                FSCTL_SET_REPARSE_POINT,
+                Some(buffer.as_ptr().cast()),
+                buffer.len() as u32,
+                None,
+                0,
+                Some(&mut returned),
+                None,
+            );
+            let _ = CloseHandle(handle);
+            result
+        })
+    };
+    result.map_err(|e| {
+        let _ = fs::remove_dir(link);
+        format!("Failed to create the junction {}: {}", link.display(), e)
+    })
+}
+
+/// A folder shortcuts are put in: `desktop`, `start_menu` (its Programs folder), `startup`, or
+/// the same for every user with `public_desktop`, `common_start_menu` and `common_startup`.
+pub fn known_folder(name: &str) -> Result<PathBuf, String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows::Win32::System::Com::CoTaskMemFree;
+        use windows::Win32::UI::Shell::*;
+
+        let id = match name {
+            "desktop" => FOLDERID_Desktop,
+            "start_menu" => FOLDERID_Programs,
+            "startup" => FOLDERID_Startup,
+            "public_desktop" => FOLDERID_PublicDesktop,
+            "common_start_menu" => FOLDERID_CommonPrograms,
+            "common_startup" => FOLDERID_CommonStartup,
+            _ => return Err(format!("Unknown folder: {}", name)),
+        };
+        unsafe {
+            let path = SHGetKnownFolderPath(&id, KF_FLAG_DEFAULT, None)
+                .map_err(|e| format!("Failed to find the {} folder: {}", name, e))?;
+            let result = path.to_string().map(PathBuf::from);
+            CoTaskMemFree(Some(path.0 as *const _));
+            result.map_err(|e| e.to_string())
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        match name {
+            "desktop" => directories::UserDirs::new()
+                .and_then(|dirs| dirs.desktop_dir().map(Path::to_path_buf))
+                .ok_or("The desktop folder was not found".to_string()),
+            "start_menu" | "startup" | "public_desktop" | "common_start_menu"
+            | "common_startup" => Err(format!("The {} folder only exists on Windows", name)),
+            _ => Err(format!("Unknown folder: {}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_the_mount_point_buffer() {
+        let utf16 = |text: &str| {
+            text.encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<u8>>()
+        };
+        let expected = [
+            vec![0x03, 0x00, 0x00, 0xA0],
+            // 8 bytes of offsets and lengths, then 13 and 9 characters of names.
+            52u16.to_le_bytes().to_vec(),
+            vec![0, 0],
+            [0u16, 24, 26, 16]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+            utf16("\\??\\C:\\Tools\0"),
+            utf16("C:\\Tools\0"),
+        ]
+        .concat();
+        assert_eq!(mount_point_buffer(r"C:\Tools"), Ok(expected.clone()));
+        assert_eq!(mount_point_buffer(r"\\?\C:\Tools"), Ok(expected));
+        assert!(mount_point_buffer(&"a".repeat(20_000)).is_err());
+    }
+}
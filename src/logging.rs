@@ -1,12 +1,16 @@
 use chrono::Local;
-use std::{fs::OpenOptions, fs::create_dir_all, path::Path};
+use std::{fs::OpenOptions, fs::create_dir_all, path::Path, sync::Arc};
 use tracing_subscriber::{
     self, EnvFilter, Registry, fmt, layer::SubscriberExt, util::SubscriberInitExt,
 };
 
-use crate::constants;
+use crate::{constants, secrets};
 
-pub fn setup(level: &str, file_name: Option<&str>) -> Result<(), ()> {
+/// Sets up logging to the console and optionally to a file in the cache directory.
+///
+/// When `console` is false, logs are only written to the file (used by `--output json`).
+/// Secrets are masked in both outputs.
+pub fn setup(level: &str, file_name: Option<&str>, console: bool) -> Result<(), ()> {
     let formatter = fmt::format()
         .with_level(true)
         .with_target(true)
@@ -19,7 +23,7 @@ pub fn setup(level: &str, file_name: Option<&str>) -> Result<(), ()> {
         .unwrap()
         .add_directive(format!("trx8={}", level.to_lowercase()).parse().unwrap());
     // This is dumb af but it works.
-    if file_name.is_some() {
+    if let Some(file_name) = file_name {
         let log_path = format!(
             "{}/log",
             constants::PROJECT_DIR.cache_dir().to_str().unwrap()
@@ -27,20 +31,32 @@ pub fn setup(level: &str, file_name: Option<&str>) -> Result<(), ()> {
         if !Path::new(log_path.as_str()).exists() {
             create_dir_all(log_path.as_str()).unwrap();
         }
-        let actual_file_name = Local::now().format(file_name.unwrap()).to_string();
-        let log_file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(format!("{}/{}", log_path.as_str(), actual_file_name))
-            .unwrap();
+        let actual_file_name = Local::now().format(file_name).to_string();
+        let log_file = Arc::new(
+            OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(format!("{}/{}", log_path.as_str(), actual_file_name))
+                .unwrap(),
+        );
         let subscriber = Registry::default()
-            .with(fmt::layer().event_format(formatter).with_ansi(true))
-            .with(fmt::layer().with_ansi(false).with_writer(log_file))
+            .with(console.then(|| {
+                fmt::layer()
+                    .event_format(formatter)
+                    .with_ansi(true)
+                    .with_writer(|| secrets::Redacting(std::io::stdout()))
+            }))
+            .with(
+                fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(move || secrets::Redacting(log_file.clone())),
+            )
             .with(filter);
         subscriber.init();
-    } else {
+    } else if console {
         let subscriber = tracing_subscriber::fmt()
             .event_format(formatter)
+            .with_writer(|| secrets::Redacting(std::io::stdout()))
             .with_env_filter(filter);
         subscriber.init();
     }
@@ -1,21 +1,44 @@
-#[allow(static_mut_refs)]  // :)
-use clap::Parser;
+#![allow(static_mut_refs)] // :)
+use clap::{Parser, Subcommand};
 use std::fs;
+use std::path::{Path, PathBuf};
 use tracing::{debug, error, info, trace, warn};
 
+mod appx;
 mod config;
 mod constants;
+mod dism;
+mod environment;
+mod events;
+mod facts;
+mod firewall;
+mod inputs;
+mod link;
 mod logging;
+mod package;
+mod process;
+mod reboot;
+mod registry;
+mod report;
+mod secrets;
+mod service;
+mod shortcut;
+mod step;
+mod task;
 mod tools;
+mod tui;
 mod utils;
 
 /// A lightweight Windows utility that applies modular configuration profiles to customize your system.
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the configuration file
-    #[arg(short, long)]
-    config: String,
+    #[arg(short, long, required = true)]
+    config: Option<String>,
 
     /// Do not ask for confirmation before applying the configuration
     #[arg(long)]
@@ -28,13 +51,98 @@ struct Args {
     /// Generate an empty configuration file at the specified path
     #[arg(long)]
     generate_config: bool,
+
+    /// Output format, `json` emits NDJSON events on stdout and writes logs to the log file only
+    #[arg(long, value_enum, default_value_t = events::OutputFormat::Text)]
+    output: events::OutputFormat,
+
+    /// Only run the scripts with this name or tag (can be repeated)
+    #[arg(long, value_name = "NAME|TAG")]
+    only: Vec<String>,
+
+    /// Do not run the scripts with this name or tag (can be repeated)
+    #[arg(long, value_name = "NAME|TAG")]
+    skip: Vec<String>,
+
+    /// List the scripts in the configuration with their tags and descriptions, then exit
+    #[arg(long)]
+    list: bool,
+
+    /// Choose the scripts to apply in an interactive terminal UI instead of the confirmation prompt
+    #[arg(long)]
+    tui: bool,
+
+    /// A selection file saved from the TUI, only the scripts in it are applied (or pre-selected with --tui)
+    #[arg(long)]
+    selection: Option<String>,
+
+    /// Provide a value for a profile input, skipping its prompt (can be repeated)
+    #[arg(long = "input", value_name = "NAME=VALUE")]
+    inputs: Vec<String>,
+
+    /// Pause before every action and ask whether to run, skip or edit it
+    #[arg(long)]
+    step: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage an encrypted secrets file, used by secrets with the `file` source
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommand,
+    },
+    /// Inspect Group Policy files
+    Policy {
+        #[command(subcommand)]
+        command: PolicyCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SecretCommand {
+    /// Add or replace a secret, the value is asked without echo
+    Set { file: String, name: String },
+    /// Remove a secret
+    Remove { file: String, name: String },
+    /// List the names of the secrets in the file
+    List { file: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum PolicyCommand {
+    /// Print the settings of a Registry.pol file
+    Dump { file: String },
+}
+
+fn run_command(command: Command) {
+    let result = match command {
+        Command::Secret { command } => match command {
+            SecretCommand::Set { file, name } => secrets::cli_set(Path::new(&file), &name),
+            SecretCommand::Remove { file, name } => secrets::cli_remove(Path::new(&file), &name),
+            SecretCommand::List { file } => secrets::cli_list(Path::new(&file)),
+        },
+        Command::Policy { command } => match command {
+            PolicyCommand::Dump { file } => registry::pol::cli_dump(Path::new(&file)),
+        },
+    };
+    if let Err(e) = result {
+        error!("{}", e);
+        std::process::exit(1);
+    }
 }
 
 #[cfg(target_os = "windows")]
 fn init() {
-    for func in vec![utils::nt::enable_privileges, utils::nt::impersonate_system, utils::nt::impersonate_ti] {
+    for func in vec![
+        utils::nt::enable_privileges,
+        utils::nt::impersonate_system,
+        utils::nt::impersonate_ti,
+    ] {
         if !func() {
-            warn!("Failed to initialize environment for Windows, some actions may not work as expected.");
+            warn!(
+                "Failed to initialize environment for Windows, some actions may not work as expected."
+            );
             break;
         }
     }
@@ -47,20 +155,28 @@ fn init() {
 
 fn main() {
     let args = Args::parse();
+    events::init(args.output);
+    let console = !events::enabled() && !args.tui;
     match args.verbose {
-        0 => logging::setup("info", Some("%Y-%m-%d_%H-%M-%S.log")).unwrap(),
-        1 => logging::setup("debug", Some("%Y-%m-%d_%H-%M-%S.log")).unwrap(),
-        2 => logging::setup("trace", Some("%Y-%m-%d_%H-%M-%S.log")).unwrap(),
-        _ => logging::setup("info", Some("%Y-%m-%d_%H-%M-%S.log")).unwrap(),
+        0 => logging::setup("info", Some("%Y-%m-%d_%H-%M-%S.log"), console).unwrap(),
+        1 => logging::setup("debug", Some("%Y-%m-%d_%H-%M-%S.log"), console).unwrap(),
+        2 => logging::setup("trace", Some("%Y-%m-%d_%H-%M-%S.log"), console).unwrap(),
+        _ => logging::setup("info", Some("%Y-%m-%d_%H-%M-%S.log"), console).unwrap(),
     }
     info!(
         "trx8 CLI v{} - {}",
         env!("CARGO_PKG_VERSION"),
         env!("CARGO_PKG_REPOSITORY")
     );
+    if let Some(command) = args.command {
+        run_command(command);
+        return;
+    }
+    let Some(config_path) = args.config.clone() else {
+        return;
+    };
     if args.generate_config {
         let empty_config = config::Config::generate_empty();
-        let config_path = args.config;
         let json_string = serde_json::to_string_pretty(&empty_config);
         match json_string {
             Ok(json) => {
@@ -73,15 +189,15 @@ fn main() {
         }
         return;
     }
-    info!("Reading configuration file at: {}", args.config);
-    let config_text = match fs::read_to_string(args.config) {
+    info!("Reading configuration file at: {}", config_path);
+    let config_text = match fs::read_to_string(&config_path) {
         Ok(content) => content,
         Err(e) => {
             error!("Failed to read configuration file: {}", e);
             return;
         }
     };
-    let config = match serde_json::from_str::<config::Config>(&config_text) {
+    let mut config = match serde_json::from_str::<config::Config>(&config_text) {
         Ok(cfg) => cfg,
         Err(e) => {
             error!("Failed to parse configuration file: {}", e);
@@ -89,6 +205,30 @@ fn main() {
         }
     };
     trace!("Configuration loaded: {:?}", config);
+    if let Err(e) = inputs::check(&config) {
+        error!("Invalid configuration file: {}", e);
+        return;
+    }
+    config.filter_scripts(&args.only, &args.skip);
+    let selection = match &args.selection {
+        Some(path) => match config::Selection::load(Path::new(path)) {
+            Ok(selection) => Some(selection),
+            Err(e) => {
+                error!("Failed to read selection file: {}", e);
+                return;
+            }
+        },
+        None => None,
+    };
+    if let Some(selection) = &selection
+        && !args.tui
+    {
+        config.apply_selection(selection);
+    }
+    if args.list {
+        list_scripts(&config);
+        return;
+    }
     info!("");
     info!("====/ CONFIGURATION INFORMATION /===");
     info!("Name: {}", config.metadata.name);
@@ -99,9 +239,49 @@ fn main() {
     if let Some(authors) = &config.metadata.author {
         info!("Author(s): {}", authors.join(", "));
     }
+    info!("Scripts to run ({}):", config.scripts.len());
+    for script in &config.scripts {
+        info!("  - {}", script.name);
+    }
     info!("====================================");
     warn!("");
-    if !args.noconfirm {
+    if !args.noconfirm && events::enabled() {
+        error!("JSON output is non-interactive, please pass --noconfirm as well.");
+        std::process::exit(2);
+    }
+    if args.step && events::enabled() {
+        error!("Step mode is interactive and can't be used with JSON output.");
+        std::process::exit(2);
+    }
+    if args.tui && (events::enabled() || args.step) {
+        error!("The TUI can't be used with JSON output or step mode.");
+        std::process::exit(2);
+    }
+    let mut input_values = inputs::Values::new();
+    if args.tui {
+        // The TUI has no prompts, so inputs have to be given on the command line or the environment.
+        match collect_values(&config, &config_path, &args.inputs, false) {
+            Ok(values) => input_values = values,
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        let selection_path = match &args.selection {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&config_path).with_extension("selection.json"),
+        };
+        match tui::choose_scripts(&config, selection.as_ref(), &selection_path) {
+            Some(scripts) => config.apply_selection(&config::Selection {
+                profile: config.metadata.name.clone(),
+                scripts,
+            }),
+            None => {
+                info!("Operation cancelled by user.");
+                return;
+            }
+        }
+    } else if !args.noconfirm {
         warn!(
             "Before applying, make sure to only use trusted configuration files as they can execute arbitrary code and potentially harm your system."
         );
@@ -115,12 +295,92 @@ fn main() {
     } else {
         warn!("No confirmation flag detected, proceeding without confirmation :)");
     }
+    if !args.tui {
+        match collect_values(&config, &config_path, &args.inputs, !args.noconfirm) {
+            Ok(values) => input_values = values,
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(2);
+            }
+        }
+    }
 
     // Initialize the environment first
     init();
     // Execute the configuration
-    // TODO: Move this to a separate function to clean up main.rs
-    for (i, script) in config.scripts.iter().enumerate() {
+    let report = apply(&config, &config_path, &input_values, args.step);
+    if args.tui {
+        tui::finish();
+    }
+    match report.save("%Y-%m-%d_%H-%M-%S.json") {
+        Ok(path) => info!("Run report written to: {}", path.display()),
+        Err(e) => error!("Failed to write run report: {}", e),
+    }
+    if report.reboot_required {
+        warn!("Reboot the system to finish applying the configuration.");
+    }
+    if report.aborted {
+        info!("Operation aborted by user.");
+    } else if report.success() {
+        info!("Configuration applied successfully.");
+    } else {
+        error!("Configuration applied with errors, please check the log above.");
+        std::process::exit(1);
+    }
+}
+
+/// Collects the inputs and secrets of the configuration, see [`inputs::collect`] and [`secrets::collect`].
+fn collect_values(
+    config: &config::Config,
+    config_path: &str,
+    cli_inputs: &[String],
+    interactive: bool,
+) -> Result<inputs::Values, String> {
+    let mut values = inputs::collect(config, cli_inputs, interactive)?;
+    let base_dir = Path::new(config_path)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    values.extend(secrets::collect(config, &base_dir, interactive)?);
+    Ok(values)
+}
+
+fn list_scripts(config: &config::Config) {
+    if config.scripts.is_empty() {
+        info!("No scripts to run.");
+    }
+    for script in &config.scripts {
+        match &script.tags {
+            Some(tags) if !tags.is_empty() => info!("{} [{}]", script.name, tags.join(", ")),
+            _ => info!("{}", script.name),
+        }
+        if let Some(description) = &script.description {
+            info!("    {}", description);
+        }
+    }
+}
+
+/// Runs every script in the configuration and returns the report of the run.
+fn apply(
+    config: &config::Config,
+    config_path: &str,
+    input_values: &inputs::Values,
+    step: bool,
+) -> report::RunReport {
+    let context = tools::Context {
+        config,
+        profile_dir: Path::new(config_path).parent().unwrap_or(Path::new(".")),
+        values: input_values,
+    };
+    let mut report = report::RunReport::new(config);
+    let mut aborted = false;
+    events::emit(events::Event::RunStarted {
+        trx8_version: env!("CARGO_PKG_VERSION"),
+        profile: &config.metadata.name,
+        profile_version: &config.metadata.version,
+        scripts: config.scripts.len(),
+    });
+    'scripts: for (i, script) in config.scripts.iter().enumerate() {
         info!(
             "Running script ({}/{}): {}",
             i + 1,
@@ -130,14 +390,77 @@ fn main() {
         if let Some(description) = &script.description {
             info!("Script description: {}", description);
         }
+        events::emit(events::Event::ScriptStarted {
+            index: i,
+            total: config.scripts.len(),
+            name: &script.name,
+            description: script.description.as_deref(),
+        });
         // TODO: Implement condition checking for scripts
-        for action in &script.actions {
+        let mut skip_script = false;
+        for (j, action) in script.actions.iter().enumerate() {
             debug!("Executing action: {}", action.name);
             if let Some(description) = &action.description {
                 trace!("Action information: {}", description);
             }
-            tools::execute_action(&action.name, &action.parameters);
+            events::emit(events::Event::ActionStarted {
+                script: i,
+                index: j,
+                name: &action.name,
+                description: action.description.as_deref(),
+            });
+            let mut parameters = inputs::resolve(&action.parameters, input_values);
+            let mut step_record = None;
+            if step && !skip_script {
+                let decision = step::ask(action, &parameters);
+                if decision.parameters.is_some() {
+                    // Edited parameters can use references too, only the report keeps them as typed.
+                    parameters = inputs::resolve(&decision.parameters, input_values);
+                }
+                match decision.choice {
+                    step::StepChoice::SkipScript => skip_script = true,
+                    step::StepChoice::Abort => aborted = true,
+                    _ => {}
+                }
+                step_record = Some(report::StepRecord {
+                    choice: decision.choice,
+                    edited_parameters: decision.parameters,
+                });
+            }
+            let status = match &step_record {
+                Some(record) if record.choice != step::StepChoice::Run => {
+                    info!("Skipping action: {}", action.name);
+                    tools::ActionStatus::Skipped
+                }
+                _ if skip_script => tools::ActionStatus::Skipped,
+                _ => tools::execute_action(&context, &action.name, &parameters),
+            };
+            events::emit(events::Event::ActionFinished {
+                script: i,
+                index: j,
+                name: &action.name,
+                status,
+            });
+            report.record(report::ActionRecord {
+                script: script.name.clone(),
+                script_index: i,
+                index: j,
+                name: action.name.clone(),
+                status,
+                step: step_record,
+            });
+            if aborted {
+                break 'scripts;
+            }
         }
     }
-    info!("Configuration applied successfully.");
+    report.finish(aborted);
+    events::emit(events::Event::RunFinished {
+        success: report.success(),
+        aborted,
+        actions: report.actions.len(),
+        failed: report.failed(),
+        reboot_required: report.reboot_required,
+    });
+    report
 }
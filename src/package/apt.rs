@@ -0,0 +1,114 @@
+/// APT on Debian and Ubuntu, through `apt-get`, `apt-cache` and `dpkg-query`.
+use std::path::{Path, PathBuf};
+
+use super::{Provider, find_program, output, run};
+
+/// Untranslated output and no prompts from package scripts.
+const ENVS: [(&str, &str); 2] = [("LC_ALL", "C"), ("DEBIAN_FRONTEND", "noninteractive")];
+
+pub struct Apt {
+    /// `apt-get`, the other programs are next to it.
+    pub path: PathBuf,
+}
+
+impl Apt {
+    fn program(&self, name: &str) -> PathBuf {
+        let sibling = self.path.with_file_name(name);
+        if sibling.is_file() {
+            return sibling;
+        }
+        find_program(name).unwrap_or(Path::new(name).to_path_buf())
+    }
+}
+
+/// The `Installed:` and `Candidate:` versions of `apt-cache policy`.
+fn parse_policy(output: &str) -> (Option<&str>, Option<&str>) {
+    let field = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(name))
+            .map(str::trim)
+            .filter(|version| *version != "(none)")
+    };
+    (field("Installed:"), field("Candidate:"))
+}
+
+impl Provider for Apt {
+    fn name(&self) -> &'static str {
+        "apt"
+    }
+
+    fn installed(&self, package: &str) -> Result<Option<String>, String> {
+        let format = "--showformat=${Status}\t${Version}";
+        // Unknown packages make dpkg-query fail.
+        let (_, stdout) = output(
+            &self.program("dpkg-query"),
+            &["--show", format, package],
+            &ENVS,
+        )?;
+        Ok(stdout.lines().find_map(|line| {
+            let (status, version) = line.split_once('\t')?;
+            status
+                .ends_with(" installed")
+                .then(|| version.trim().to_string())
+        }))
+    }
+
+    fn available(&self, package: &str) -> Result<Option<String>, String> {
+        run(&self.path, &["update", "--quiet"], &ENVS)?;
+        let stdout = run(&self.program("apt-cache"), &["policy", package], &ENVS)?;
+        Ok(match parse_policy(&stdout) {
+            (Some(installed), Some(candidate)) if installed != candidate => {
+                Some(candidate.to_string())
+            }
+            _ => None,
+        })
+    }
+
+    fn install(&self, package: &str, version: Option<&str>) -> Result<(), String> {
+        let package = match version {
+            Some(version) => format!("{}={}", package, version),
+            None => package.to_string(),
+        };
+        run(&self.path, &["install", "--yes", &package], &ENVS).map(|_| ())
+    }
+
+    fn uninstall(&self, package: &str) -> Result<(), String> {
+        run(&self.path, &["remove", "--yes", package], &ENVS).map(|_| ())
+    }
+
+    fn upgrade(&self, package: &str) -> Result<(), String> {
+        run(
+            &self.path,
+            &["install", "--only-upgrade", "--yes", package],
+            &ENVS,
+        )
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_policy() {
+        let policy = "\
+git:
+  Installed: 1:2.39.2-1.1
+  Candidate: 1:2.39.5-0+deb12u1
+  Version table:
+     1:2.39.5-0+deb12u1 500
+        500 http://deb.debian.org/debian bookworm/main amd64 Packages
+ *** 1:2.39.2-1.1 100
+        100 /var/lib/dpkg/status
+";
+        assert_eq!(
+            parse_policy(policy),
+            (Some("1:2.39.2-1.1"), Some("1:2.39.5-0+deb12u1"))
+        );
+        let policy = "vim:\n  Installed: (none)\n  Candidate: 2:9.0.1378-2\n";
+        assert_eq!(parse_policy(policy), (None, Some("2:9.0.1378-2")));
+        assert_eq!(parse_policy(""), (None, None));
+    }
+}
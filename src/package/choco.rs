@@ -0,0 +1,88 @@
+/// Chocolatey 2, whose `--limit-output` prints `name|version[|...]` lines.
+use std::path::PathBuf;
+
+use super::{Provider, run};
+
+pub struct Choco {
+    pub path: PathBuf,
+}
+
+/// The fields after the name on the line of the package.
+fn parse_limited(output: &str, package: &str) -> Option<Vec<String>> {
+    output.lines().find_map(|line| {
+        let mut fields = line.trim().split('|');
+        if !fields.next()?.eq_ignore_ascii_case(package) {
+            return None;
+        }
+        Some(fields.map(str::to_string).collect())
+    })
+}
+
+impl Provider for Choco {
+    fn name(&self) -> &'static str {
+        "choco"
+    }
+
+    fn installed(&self, package: &str) -> Result<Option<String>, String> {
+        let stdout = run(
+            &self.path,
+            &["list", package, "--exact", "--limit-output"],
+            &[],
+        )?;
+        Ok(parse_limited(&stdout, package).and_then(|fields| fields.into_iter().next()))
+    }
+
+    fn available(&self, package: &str) -> Result<Option<String>, String> {
+        // name|current|available|pinned
+        let stdout = run(&self.path, &["outdated", "--limit-output"], &[])?;
+        Ok(parse_limited(&stdout, package).and_then(|fields| fields.into_iter().nth(1)))
+    }
+
+    fn install(&self, package: &str, version: Option<&str>) -> Result<(), String> {
+        let mut args = vec!["install", package, "--yes", "--no-progress"];
+        let version = version.map(|version| format!("--version={}", version));
+        if let Some(version) = &version {
+            args.push(version);
+        }
+        run(&self.path, &args, &[]).map(|_| ())
+    }
+
+    fn uninstall(&self, package: &str) -> Result<(), String> {
+        run(&self.path, &["uninstall", package, "--yes"], &[]).map(|_| ())
+    }
+
+    fn upgrade(&self, package: &str) -> Result<(), String> {
+        run(
+            &self.path,
+            &["upgrade", package, "--yes", "--no-progress"],
+            &[],
+        )
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_limited_output() {
+        let list = "Chocolatey v2.2.2\ngit|2.44.0\ngit.install|2.44.0\n";
+        assert_eq!(parse_limited(list, "git"), Some(vec!["2.44.0".to_string()]));
+        assert_eq!(
+            parse_limited(list, "GIT.install"),
+            Some(vec!["2.44.0".to_string()])
+        );
+        assert_eq!(parse_limited(list, "vim"), None);
+
+        let outdated = "7zip|23.1.0|24.5.0|false\ngit|2.44.0|2.45.1|true\n";
+        assert_eq!(
+            parse_limited(outdated, "git"),
+            Some(vec![
+                "2.44.0".to_string(),
+                "2.45.1".to_string(),
+                "true".to_string()
+            ])
+        );
+    }
+}
@@ -0,0 +1,165 @@
+/// Package managers behind a [`Provider`] trait. Providers run the package manager found on
+/// `PATH` and parse its output, so fake executables can stand in for them.
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::debug;
+
+use crate::reboot;
+
+mod apt;
+mod choco;
+mod scoop;
+mod winget;
+
+/// Exit codes of installers that succeeded but need a reboot to finish.
+const REBOOT_CODES: [i32; 2] = [1641, 3010];
+
+/// The providers tried when the configuration doesn't list any.
+#[cfg(target_os = "windows")]
+pub const DEFAULT_PROVIDERS: &[&str] = &["winget", "scoop", "choco"];
+#[cfg(not(target_os = "windows"))]
+pub const DEFAULT_PROVIDERS: &[&str] = &["apt"];
+
+pub trait Provider {
+    fn name(&self) -> &'static str;
+    /// The installed version, `None` if the package isn't installed.
+    fn installed(&self, package: &str) -> Result<Option<String>, String>;
+    /// A newer version the installed package can be upgraded to.
+    fn available(&self, package: &str) -> Result<Option<String>, String>;
+    fn install(&self, package: &str, version: Option<&str>) -> Result<(), String>;
+    fn uninstall(&self, package: &str) -> Result<(), String>;
+    fn upgrade(&self, package: &str) -> Result<(), String>;
+}
+
+/// Finds a program on `PATH`, with the extensions of `PATHEXT` on Windows.
+pub fn find_program(name: &str) -> Option<PathBuf> {
+    let extensions = if cfg!(target_os = "windows") {
+        env::var("PATHEXT")
+            .unwrap_or(".COM;.EXE;.BAT;.CMD".to_string())
+            .split(';')
+            .map(str::to_string)
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+    env::split_paths(&env::var_os("PATH")?).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|extension| dir.join(format!("{}{}", name, extension)))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Whether the exit code is one of [`REBOOT_CODES`], requesting the reboot if so.
+fn requests_reboot(program: &Path, code: i32) -> bool {
+    if !REBOOT_CODES.contains(&code) {
+        return false;
+    }
+    reboot::request(&format!(
+        "[package] {} exited with code {}",
+        program.display(),
+        code
+    ));
+    true
+}
+
+/// Runs the program and returns whether it succeeded and its output. Progress lines redrawn with
+/// `\r` only keep their last state.
+fn output(program: &Path, args: &[&str], envs: &[(&str, &str)]) -> Result<(bool, String), String> {
+    debug!("[package] {} {}", program.display(), args.join(" "));
+    let output = Command::new(program)
+        .args(args)
+        .envs(envs.iter().copied())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program.display(), e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.rsplit('\r').next().unwrap_or_default())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let code = output.status.code().unwrap_or(-1);
+    if requests_reboot(program, code) {
+        return Ok((true, stdout));
+    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // Package managers print their errors to either stream, the end holds the reason.
+        let message = if stderr.trim().is_empty() {
+            &stdout
+        } else {
+            stderr.as_ref()
+        };
+        let tail = message.trim().lines().rev().take(5).collect::<Vec<&str>>();
+        let tail = tail.into_iter().rev().collect::<Vec<&str>>().join("\n");
+        return Ok((false, format!("exit code {}: {}", code, tail)));
+    }
+    Ok((true, stdout))
+}
+
+/// Runs the program, failing when it does.
+fn run(program: &Path, args: &[&str], envs: &[(&str, &str)]) -> Result<String, String> {
+    match output(program, args, envs)? {
+        (true, stdout) => Ok(stdout),
+        (false, error) => Err(format!(
+            "{} {} failed with {}",
+            program.file_stem().unwrap_or_default().to_string_lossy(),
+            args.first().unwrap_or(&""),
+            error
+        )),
+    }
+}
+
+/// The provider with this name if its package manager is installed.
+fn provider(name: &str) -> Result<Option<Box<dyn Provider>>, String> {
+    let program = match name {
+        "winget" => "winget",
+        "scoop" => "scoop",
+        "choco" => "choco",
+        "apt" => "apt-get",
+        _ => return Err(format!("Unknown package provider: {}", name)),
+    };
+    let Some(path) = find_program(program) else {
+        return Ok(None);
+    };
+    Ok(Some(match name {
+        "winget" => Box::new(winget::Winget { path }),
+        "scoop" => Box::new(scoop::Scoop { path }),
+        "choco" => Box::new(choco::Choco { path }),
+        _ => Box::new(apt::Apt { path }),
+    }))
+}
+
+/// The first of the providers whose package manager is installed.
+pub fn select(providers: &[&str]) -> Result<Box<dyn Provider>, String> {
+    for name in providers {
+        if let Some(provider) = provider(name)? {
+            return Ok(provider);
+        }
+        debug!("[package] {} is not installed", name);
+    }
+    Err(format!(
+        "No package manager found, tried {}",
+        providers.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reboot_exit_codes_request_a_reboot() {
+        let program = Path::new("fake-installer");
+        assert!(!requests_reboot(program, 0));
+        assert!(!requests_reboot(program, 1));
+        assert!(requests_reboot(program, 3010));
+        assert!(requests_reboot(program, 1641));
+        let reasons = reboot::reasons();
+        for code in ["3010", "1641"] {
+            let reason = format!("[package] fake-installer exited with code {}", code);
+            assert!(reasons.contains(&reason), "{:?}", reasons);
+        }
+        assert!(!reasons.iter().any(|r| r.ends_with("code 1")));
+    }
+}
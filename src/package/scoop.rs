@@ -0,0 +1,95 @@
+/// Scoop, packages are app names like `git` or `extras/vscode`.
+use std::path::PathBuf;
+
+use super::{Provider, output, run};
+
+pub struct Scoop {
+    pub path: PathBuf,
+}
+
+/// `extras/vscode` is listed as `vscode`.
+fn app_name(package: &str) -> &str {
+    package.rsplit('/').next().unwrap_or(package)
+}
+
+/// The column after the name in the row of the app, from the tables of `scoop list` (`Name
+/// Version Source ...`) and `scoop status` (`Name Installed Version Latest Version ...`).
+fn parse_table(output: &str, package: &str, column: usize) -> Option<String> {
+    let separator = output
+        .lines()
+        .position(|line| line.trim_start().starts_with("---"))?;
+    output.lines().skip(separator + 1).find_map(|line| {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        if !words.first()?.eq_ignore_ascii_case(app_name(package)) {
+            return None;
+        }
+        words.get(column).map(|word| word.to_string())
+    })
+}
+
+impl Provider for Scoop {
+    fn name(&self) -> &'static str {
+        "scoop"
+    }
+
+    fn installed(&self, package: &str) -> Result<Option<String>, String> {
+        let (_, stdout) = output(&self.path, &["list", app_name(package)], &[])?;
+        Ok(parse_table(&stdout, package, 1))
+    }
+
+    fn available(&self, package: &str) -> Result<Option<String>, String> {
+        let stdout = run(&self.path, &["status"], &[])?;
+        Ok(parse_table(&stdout, package, 2))
+    }
+
+    fn install(&self, package: &str, version: Option<&str>) -> Result<(), String> {
+        let package = match version {
+            Some(version) => format!("{}@{}", package, version),
+            None => package.to_string(),
+        };
+        run(&self.path, &["install", &package], &[]).map(|_| ())
+    }
+
+    fn uninstall(&self, package: &str) -> Result<(), String> {
+        run(&self.path, &["uninstall", app_name(package)], &[]).map(|_| ())
+    }
+
+    fn upgrade(&self, package: &str) -> Result<(), String> {
+        run(&self.path, &["update", app_name(package)], &[]).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_list_and_status_tables() {
+        let list = "\
+Installed apps:
+
+Name   Version Source Updated             Info
+----   ------- ------ -------             ----
+7zip   23.01   main   2024-01-02 10:00:00
+vscode 1.89.1  extras 2024-05-01 12:00:00
+";
+        assert_eq!(parse_table(list, "7zip", 1), Some("23.01".to_string()));
+        assert_eq!(
+            parse_table(list, "extras/vscode", 1),
+            Some("1.89.1".to_string())
+        );
+        assert_eq!(parse_table(list, "git", 1), None);
+
+        let status = "\
+Name   Installed Version Latest Version Missing Dependencies Info
+----   ----------------- -------------- -------------------- ----
+vscode 1.89.1            1.90.0
+";
+        assert_eq!(
+            parse_table(status, "extras/vscode", 2),
+            Some("1.90.0".to_string())
+        );
+        assert_eq!(parse_table(status, "7zip", 2), None);
+        assert_eq!(parse_table("Scoop is up to date.", "7zip", 2), None);
+    }
+}
@@ -0,0 +1,139 @@
+/// winget, packages are ids like `Git.Git`.
+use std::path::PathBuf;
+
+use super::{Provider, output, run};
+
+/// Sources winget prints in the last column of its tables.
+const SOURCES: [&str; 2] = ["winget", "msstore"];
+
+const NON_INTERACTIVE: [&str; 2] = ["--accept-source-agreements", "--disable-interactivity"];
+
+pub struct Winget {
+    pub path: PathBuf,
+}
+
+/// Parses the `Name Id Version [Available] Source` table of `winget list`, returning the version
+/// and the available one. The header is translated and names hold spaces, so the row is found by
+/// the id and only the columns after it are read.
+fn parse_list(output: &str, id: &str) -> Option<(String, Option<String>)> {
+    let lines = output.lines().collect::<Vec<&str>>();
+    let separator = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("---"))?;
+    let header = lines[..separator].last()?;
+    let has_available = header.split_whitespace().count() >= 5;
+    lines[separator + 1..].iter().find_map(|line| {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        let at = words.iter().position(|w| w.eq_ignore_ascii_case(id))?;
+        let (version, rest) = match &words[at + 1..] {
+            // Unknown versions are shown as `< 1.2.3`.
+            [sign @ ("<" | ">"), version, rest @ ..] => (format!("{} {}", sign, version), rest),
+            [version, rest @ ..] => (version.to_string(), rest),
+            [] => return None,
+        };
+        let available = rest
+            .first()
+            .filter(|next| has_available && !SOURCES.contains(next))
+            .map(|next| next.to_string());
+        Some((version, available))
+    })
+}
+
+impl Winget {
+    fn list(&self, package: &str) -> Result<Option<(String, Option<String>)>, String> {
+        let mut args = vec!["list", "--id", package, "--exact"];
+        args.extend(NON_INTERACTIVE);
+        // winget fails when nothing matches, the table is what tells.
+        let (_, stdout) = output(&self.path, &args, &[])?;
+        Ok(parse_list(&stdout, package))
+    }
+}
+
+impl Provider for Winget {
+    fn name(&self) -> &'static str {
+        "winget"
+    }
+
+    fn installed(&self, package: &str) -> Result<Option<String>, String> {
+        Ok(self.list(package)?.map(|(version, _)| version))
+    }
+
+    fn available(&self, package: &str) -> Result<Option<String>, String> {
+        Ok(self.list(package)?.and_then(|(_, available)| available))
+    }
+
+    fn install(&self, package: &str, version: Option<&str>) -> Result<(), String> {
+        let mut args = vec!["install", "--id", package, "--exact", "--silent"];
+        if let Some(version) = version {
+            args.extend(["--version", version]);
+        }
+        args.push("--accept-package-agreements");
+        args.extend(NON_INTERACTIVE);
+        run(&self.path, &args, &[]).map(|_| ())
+    }
+
+    fn uninstall(&self, package: &str) -> Result<(), String> {
+        let mut args = vec!["uninstall", "--id", package, "--exact", "--silent"];
+        args.extend(NON_INTERACTIVE);
+        run(&self.path, &args, &[]).map(|_| ())
+    }
+
+    fn upgrade(&self, package: &str) -> Result<(), String> {
+        let mut args = vec!["upgrade", "--id", package, "--exact", "--silent"];
+        args.push("--accept-package-agreements");
+        args.extend(NON_INTERACTIVE);
+        run(&self.path, &args, &[]).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "\
+Name                     Id             Version   Available Source
+-----------------------------------------------------------------------
+Git                      Git.Git        2.44.0    2.45.1    winget
+Microsoft Visual Studio  Code Microsoft.VisualStudioCode 1.89.1 winget
+Old Tool                 Old.Tool       < 1.2.3             winget
+";
+
+    #[test]
+    fn parses_the_list_table() {
+        assert_eq!(
+            parse_list(LIST, "Git.Git"),
+            Some(("2.44.0".to_string(), Some("2.45.1".to_string())))
+        );
+        assert_eq!(
+            parse_list(LIST, "microsoft.visualstudiocode"),
+            Some(("1.89.1".to_string(), None))
+        );
+        assert_eq!(
+            parse_list(LIST, "Old.Tool"),
+            Some(("< 1.2.3".to_string(), None))
+        );
+        assert_eq!(parse_list(LIST, "Missing.App"), None);
+    }
+
+    #[test]
+    fn parses_tables_without_available_versions() {
+        // The progress of the source update is redrawn on the line before the header.
+        let list = "\
+  ██████████████████████████████  1.00 MB / 1.00 MB
+Nom       ID        Version Source
+----------------------------------
+Git       Git.Git   2.44.0  winget
+";
+        assert_eq!(
+            parse_list(list, "Git.Git"),
+            Some(("2.44.0".to_string(), None))
+        );
+        assert_eq!(
+            parse_list(
+                "No installed package found matching input criteria.",
+                "Git.Git"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,241 @@
+/// Running processes, listed with Toolhelp32 on Windows and from `/proc` on Linux.
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "linux")]
+mod procfs;
+#[cfg(target_os = "windows")]
+mod toolhelp;
+
+#[cfg(target_os = "linux")]
+use procfs as sys;
+#[cfg(target_os = "windows")]
+use toolhelp as sys;
+
+/// How often [`wait_for`] looks at the processes again.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Debug)]
+pub struct Process {
+    pub pid: u32,
+    pub parent: u32,
+    /// The file name of the executable, e.g. `explorer.exe`.
+    pub name: String,
+    /// The full path of the executable, `None` when the process can't be queried.
+    pub path: Option<PathBuf>,
+    /// When the process was created, only comparable with the other processes. `None` when the
+    /// process can't be queried.
+    pub started: Option<u64>,
+}
+
+/// Every running process.
+pub fn list() -> Result<Vec<Process>, String> {
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    {
+        sys::list()
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        Err("Processes can only be listed on Windows and Linux".to_string())
+    }
+}
+
+/// Asks the process to exit: closes its windows on Windows, sends `SIGTERM` on Linux. `force`
+/// ends it right away instead.
+pub fn terminate(pid: u32, force: bool) -> Result<(), String> {
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    {
+        sys::terminate(pid, force)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        let _ = (pid, force);
+        Err("Processes can only be ended on Windows and Linux".to_string())
+    }
+}
+
+/// Whether a process is named `name`. On Windows case is ignored and `.exe` is optional.
+fn has_name(process: &Process, name: &str) -> bool {
+    if cfg!(target_os = "windows") {
+        let process_name = process.name.to_lowercase();
+        let name = name.to_lowercase();
+        process_name == name || process_name.strip_suffix(".exe") == Some(name.as_str())
+    } else {
+        process.name == name
+    }
+}
+
+/// Whether a process runs the executable at `path`, ignoring case on Windows.
+fn has_path(process: &Process, path: &Path) -> bool {
+    process.path.as_deref().is_some_and(|own| {
+        if cfg!(target_os = "windows") {
+            own.to_string_lossy().to_lowercase() == path.to_string_lossy().to_lowercase()
+        } else {
+            own == path
+        }
+    })
+}
+
+/// The processes of a [`list`] named `name`.
+pub fn find_by_name<'a>(processes: &'a [Process], name: &str) -> Vec<&'a Process> {
+    processes.iter().filter(|p| has_name(p, name)).collect()
+}
+
+/// The processes of a [`list`] running the executable at `path`.
+pub fn find_by_path<'a>(processes: &'a [Process], path: &Path) -> Vec<&'a Process> {
+    processes.iter().filter(|p| has_path(p, path)).collect()
+}
+
+/// Whether `process` was started by `parent`. Windows keeps the id of the parent after it exited,
+/// so a process that reused the id looks like the parent of the orphans, which are older than it.
+fn is_child(process: &Process, parent: &Process) -> bool {
+    process.parent == parent.pid
+        && process.pid != parent.pid
+        && matches!((process.started, parent.started), (Some(child), Some(parent)) if child >= parent)
+}
+
+/// The process and its descendants, children before their parents.
+pub fn tree(processes: &[Process], root: u32) -> Vec<u32> {
+    let mut tree = vec![root];
+    let mut index = 0;
+    while index < tree.len() {
+        let Some(parent) = processes.iter().find(|p| p.pid == tree[index]) else {
+            index += 1;
+            continue;
+        };
+        let children = processes
+            .iter()
+            .filter(|p| is_child(p, parent) && !tree.contains(&p.pid))
+            .map(|p| p.pid)
+            .collect::<Vec<u32>>();
+        tree.extend(children);
+        index += 1;
+    }
+    tree.reverse();
+    tree
+}
+
+/// Waits until `done` holds for the running processes, failing after `timeout` with `waiting`,
+/// which describes what was waited for.
+pub fn wait_for(
+    timeout: Duration,
+    waiting: &str,
+    mut done: impl FnMut(&[Process]) -> bool,
+) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if done(&list()?) {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "Timed out after {} seconds waiting for {}",
+                timeout.as_secs(),
+                waiting
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, parent: u32, name: &str, started: Option<u64>) -> Process {
+        Process {
+            pid,
+            parent,
+            name: name.to_string(),
+            path: Some(Path::new("/opt/app/bin").join(name)),
+            started,
+        }
+    }
+
+    #[test]
+    fn trees_only_hold_descendants() {
+        let processes = [
+            process(1, 0, "init", Some(0)),
+            process(10, 1, "app", Some(100)),
+            process(11, 10, "worker", Some(110)),
+            process(12, 11, "helper", Some(120)),
+            process(13, 10, "worker", Some(100)),
+            // Started by an earlier process 10 which exited, the id was reused since.
+            process(20, 10, "orphan", Some(50)),
+            process(21, 20, "orphan-child", Some(60)),
+            process(30, 10, "unknown", None),
+        ];
+        assert_eq!(tree(&processes, 10), [12, 13, 11, 10]);
+        assert_eq!(tree(&processes, 20), [21, 20]);
+        assert_eq!(tree(&processes, 12), [12]);
+        // A process that is gone has no known children.
+        assert_eq!(tree(&processes, 99), [99]);
+    }
+
+    #[test]
+    fn finds_processes_by_name_and_path() {
+        let processes = [
+            process(1, 0, "App.exe", Some(0)),
+            process(2, 0, "app", Some(0)),
+            process(3, 0, "app-helper", Some(0)),
+        ];
+        let pids = |found: Vec<&Process>| found.iter().map(|p| p.pid).collect::<Vec<u32>>();
+        if cfg!(target_os = "windows") {
+            assert_eq!(pids(find_by_name(&processes, "app")), [1, 2]);
+            assert_eq!(pids(find_by_name(&processes, "APP.EXE")), [1]);
+            assert_eq!(
+                pids(find_by_path(&processes, Path::new("/OPT/app/bin/app"))),
+                [2]
+            );
+        } else {
+            assert_eq!(pids(find_by_name(&processes, "app")), [2]);
+            assert_eq!(pids(find_by_name(&processes, "App")), [0u32; 0]);
+            assert_eq!(pids(find_by_name(&processes, "App.exe")), [1]);
+            assert_eq!(
+                pids(find_by_path(&processes, Path::new("/opt/app/bin/app"))),
+                [2]
+            );
+            assert_eq!(
+                pids(find_by_path(&processes, Path::new("/OPT/app/bin/app"))),
+                [0u32; 0]
+            );
+        }
+        assert!(!has_name(&processes[2], "app"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn ends_a_real_process() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::Command;
+
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        let processes = list().unwrap();
+        let sleep = processes.iter().find(|p| p.pid == pid).unwrap();
+        assert_eq!(sleep.name, "sleep");
+        assert_eq!(sleep.parent, std::process::id());
+        assert!(sleep.started.is_some());
+        assert!(
+            find_by_name(&processes, "sleep")
+                .iter()
+                .any(|p| p.pid == pid)
+        );
+        assert!(tree(&processes, std::process::id()).contains(&pid));
+
+        terminate(pid, false).unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(15));
+        wait_for(Duration::from_secs(5), "sleep to exit", |processes| {
+            !processes.iter().any(|p| p.pid == pid)
+        })
+        .unwrap();
+
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        terminate(child.id(), true).unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(9));
+        // Processes that already exited are not an error.
+        assert_eq!(terminate(child.id(), true), Ok(()));
+        assert_eq!(terminate(0, true), Err("Invalid process id: 0".to_string()));
+    }
+}
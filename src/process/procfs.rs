@@ -0,0 +1,100 @@
+/// Processes of `/proc`, ended with signals.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::Process;
+
+/// The fields of `/proc/<pid>/stat`: the name, the parent and the start time in clock ticks
+/// since boot. `None` for zombies.
+fn parse_stat(stat: &str) -> Option<(&str, u32, u64)> {
+    // `<pid> (<comm>) <state> <ppid> ...`, the name may contain spaces and parentheses.
+    let (head, rest) = stat.rsplit_once(')')?;
+    let comm = head.split_once('(')?.1;
+    let fields = rest.split_whitespace().collect::<Vec<&str>>();
+    if *fields.first()? == "Z" {
+        return None;
+    }
+    // `starttime` is the 22nd field, the state the 3rd.
+    Some((
+        comm,
+        fields.get(1)?.parse().ok()?,
+        fields.get(19)?.parse().ok()?,
+    ))
+}
+
+/// Reads `/proc/<pid>`, `None` when the process is gone or a zombie.
+fn read(pid: u32) -> Option<Process> {
+    let dir = Path::new("/proc").join(pid.to_string());
+    let stat = fs::read_to_string(dir.join("stat")).ok()?;
+    let (comm, parent, started) = parse_stat(&stat)?;
+    // The name is `comm` like for `pgrep`, which is the script rather than its interpreter,
+    // but it is cut after 15 bytes. The executable may be unreadable.
+    let path = fs::read_link(dir.join("exe")).ok();
+    let name = path
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| comm.len() == 15 && name.starts_with(comm))
+        .unwrap_or_else(|| comm.to_string());
+    Some(Process {
+        pid,
+        parent,
+        name,
+        path,
+        started: Some(started),
+    })
+}
+
+pub fn list() -> Result<Vec<Process>, String> {
+    let entries = fs::read_dir("/proc").map_err(|e| format!("Failed to read /proc: {}", e))?;
+    Ok(entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .filter_map(read)
+        .collect())
+}
+
+pub fn terminate(pid: u32, force: bool) -> Result<(), String> {
+    // 0 and negative ids would signal whole process groups.
+    let id = libc::pid_t::try_from(pid)
+        .ok()
+        .filter(|&id| id > 0)
+        .ok_or_else(|| format!("Invalid process id: {}", pid))?;
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    // SAFETY: `kill` only takes plain integers.
+    if unsafe { libc::kill(id, signal) } != 0 {
+        let error = io::Error::last_os_error();
+        // The process may have exited in the meantime.
+        if error.raw_os_error() != Some(libc::ESRCH) {
+            return Err(format!("Failed to end process {}: {}", pid, error));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stat() {
+        let stat = "4242 (my (odd) name) S 1 4242 4242 0 -1 4194560 1234 0 0 0 5 3 0 0 20 0 1 0 \
+                    987654 12345678 456 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 3 0 0 \
+                    0 0 0\n";
+        assert_eq!(parse_stat(stat), Some(("my (odd) name", 1, 987654)));
+        let zombie = stat.replace(") S ", ") Z ");
+        assert_eq!(parse_stat(&zombie), None);
+        assert_eq!(parse_stat("4242 (cut"), None);
+        assert_eq!(parse_stat("4242 (short) S 1"), None);
+    }
+
+    #[test]
+    fn reads_this_process() {
+        let own = read(std::process::id()).unwrap();
+        assert_eq!(own.pid, std::process::id());
+        assert!(own.path.is_some());
+        assert!(own.started.is_some());
+        assert!(list().unwrap().iter().any(|p| p.pid == own.parent));
+    }
+}
@@ -0,0 +1,100 @@
+/// Processes of a Toolhelp32 snapshot, closed through their windows or terminated.
+use std::mem;
+use std::path::PathBuf;
+
+use windows::Win32::Foundation::*;
+use windows::Win32::System::Diagnostics::ToolHelp::*;
+use windows::Win32::System::Threading::*;
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::core::*;
+
+use super::Process;
+
+fn utf16(text: &[u16]) -> String {
+    let end = text.iter().position(|&c| c == 0).unwrap_or(text.len());
+    String::from_utf16_lossy(&text[..end])
+}
+
+/// The full path of the executable and the creation time, which need the process to be opened.
+fn query(pid: u32) -> (Option<PathBuf>, Option<u64>) {
+    unsafe {
+        let Ok(process) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) else {
+            return (None, None);
+        };
+        let mut buffer = [0u16; 1024];
+        let mut size = buffer.len() as u32;
+        let path = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut size,
+        )
+        .ok()
+        .map(|()| PathBuf::from(String::from_utf16_lossy(&buffer[..size as usize])));
+        let (mut created, mut exited, mut kernel, mut user) = Default::default();
+        let started = GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user)
+            .ok()
+            .map(|()| ((created.dwHighDateTime as u64) << 32) | created.dwLowDateTime as u64);
+        let _ = CloseHandle(process);
+        (path, started)
+    }
+}
+
+pub fn list() -> std::result::Result<Vec<Process>, String> {
+    let mut processes = Vec::new();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
+            .map_err(|e| format!("Failed to list processes: {}", e))?;
+        let mut entry = PROCESSENTRY32W {
+            dwSize: mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut next = Process32FirstW(snapshot, &mut entry);
+        while next.is_ok() {
+            // The idle process has id 0 and no executable.
+            if entry.th32ProcessID != 0 {
+                let (path, started) = query(entry.th32ProcessID);
+                processes.push(Process {
+                    pid: entry.th32ProcessID,
+                    parent: entry.th32ParentProcessID,
+                    name: utf16(&entry.szExeFile),
+                    path,
+                    started,
+                });
+            }
+            next = Process32NextW(snapshot, &mut entry);
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    Ok(processes)
+}
+
+unsafe extern "system" fn close_window(window: HWND, pid: LPARAM) -> BOOL {
+    let mut owner = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(window, Some(&mut owner));
+        if owner == pid.0 as u32 {
+            let _ = PostMessageW(Some(window), WM_CLOSE, WPARAM(0), LPARAM(0));
+        }
+    }
+    true.into()
+}
+
+pub fn terminate(pid: u32, force: bool) -> std::result::Result<(), String> {
+    unsafe {
+        if !force {
+            // Processes without windows, like services and consoles, can only be forced.
+            let _ = EnumWindows(Some(close_window), LPARAM(pid as isize));
+            return Ok(());
+        }
+        let process = match OpenProcess(PROCESS_TERMINATE, false, pid) {
+            Ok(process) => process,
+            // The process exited in the meantime.
+            Err(e) if e.code() == ERROR_INVALID_PARAMETER.to_hresult() => return Ok(()),
+            Err(e) => return Err(format!("Failed to open process {}: {}", pid, e)),
+        };
+        let result = TerminateProcess(process, 1);
+        let _ = CloseHandle(process);
+        result.map_err(|e| format!("Failed to end process {}: {}", pid, e))
+    }
+}
@@ -0,0 +1,16 @@
+/// Whether actions asked for a reboot to finish their changes, reported once the run finishes.
+use std::sync::Mutex;
+use tracing::warn;
+
+static REASONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Records that `reason` (e.g. `Feature Microsoft-Hyper-V enabled`) needs a reboot.
+pub fn request(reason: &str) {
+    warn!("{}, a reboot is required to finish", reason);
+    REASONS.lock().unwrap().push(reason.to_string());
+}
+
+/// What asked for a reboot, in order.
+pub fn reasons() -> Vec<String> {
+    REASONS.lock().unwrap().clone()
+}
@@ -0,0 +1,251 @@
+/// The registry of the running system, through the Win32 `Reg*` functions.
+use windows::Win32::Foundation::*;
+use windows::Win32::System::Registry::*;
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+
+use super::{Backend, Key, Root, Value};
+
+/// Always the 64-bit view, so WOW64 redirection doesn't move keys around.
+const ACCESS: REG_SAM_FLAGS = REG_SAM_FLAGS(KEY_READ.0 | KEY_WRITE.0 | KEY_WOW64_64KEY.0);
+
+pub struct Live;
+
+/// An open key, closed on drop.
+struct Handle(HKEY);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = RegCloseKey(self.0);
+        }
+    }
+}
+
+fn root(root: Root) -> HKEY {
+    match root {
+        Root::Classes => HKEY_CLASSES_ROOT,
+        Root::CurrentUser => HKEY_CURRENT_USER,
+        Root::LocalMachine => HKEY_LOCAL_MACHINE,
+        Root::Users => HKEY_USERS,
+        Root::CurrentConfig => HKEY_CURRENT_CONFIG,
+    }
+}
+
+fn check(result: WIN32_ERROR, what: &str, key: &Key) -> Result<(), String> {
+    result
+        .ok()
+        .map_err(|e| format!("Failed to {} {}: {}", what, key, e.message()))
+}
+
+/// Opens the key, `None` if it doesn't exist.
+fn open(key: &Key, access: REG_SAM_FLAGS) -> Result<Option<Handle>, String> {
+    let mut handle = HKEY::default();
+    let path = HSTRING::from(key.path.as_str());
+    let result = unsafe {
+        RegOpenKeyExW(
+            root(key.root),
+            &path,
+            None,
+            access | KEY_WOW64_64KEY,
+            &mut handle,
+        )
+    };
+    if result == ERROR_FILE_NOT_FOUND {
+        return Ok(None);
+    }
+    check(result, "open", key)?;
+    Ok(Some(Handle(handle)))
+}
+
+fn create(key: &Key) -> Result<Handle, String> {
+    let mut handle = HKEY::default();
+    let path = HSTRING::from(key.path.as_str());
+    let result = unsafe {
+        RegCreateKeyExW(
+            root(key.root),
+            &path,
+            None,
+            PCWSTR::null(),
+            REG_OPTION_NON_VOLATILE,
+            ACCESS,
+            None,
+            &mut handle,
+            None,
+        )
+    };
+    check(result, "create", key)?;
+    Ok(Handle(handle))
+}
+
+/// The longest subkey name, value name and value data, in characters and bytes.
+fn info(handle: &Handle, key: &Key) -> Result<(u32, u32, u32), String> {
+    let (mut subkey, mut name, mut data) = (0, 0, 0);
+    let result = unsafe {
+        RegQueryInfoKeyW(
+            handle.0,
+            None,
+            None,
+            None,
+            None,
+            Some(&mut subkey),
+            None,
+            None,
+            Some(&mut name),
+            Some(&mut data),
+            None,
+            None,
+        )
+    };
+    check(result, "query", key)?;
+    Ok((subkey, name, data))
+}
+
+impl Backend for Live {
+    fn key_exists(&self, key: &Key) -> Result<bool, String> {
+        Ok(open(key, KEY_READ)?.is_some())
+    }
+
+    fn create_key(&mut self, key: &Key) -> Result<(), String> {
+        create(key).map(|_| ())
+    }
+
+    fn delete_key(&mut self, key: &Key) -> Result<(), String> {
+        let Some((parent, name)) = key.split() else {
+            return Err(format!("Refusing to delete the root key {}", key));
+        };
+        let Some(handle) = open(&parent, ACCESS)? else {
+            return Ok(());
+        };
+        let name = HSTRING::from(name);
+        let result = unsafe { RegDeleteTreeW(handle.0, &name) };
+        if result == ERROR_FILE_NOT_FOUND {
+            return Ok(());
+        }
+        check(result, "delete", key)
+    }
+
+    fn subkeys(&self, key: &Key) -> Result<Vec<String>, String> {
+        let Some(handle) = open(key, KEY_READ)? else {
+            return Ok(Vec::new());
+        };
+        let (longest, _, _) = info(&handle, key)?;
+        let mut buffer = vec![0u16; longest as usize + 1];
+        let mut names = Vec::new();
+        for index in 0.. {
+            let mut length = buffer.len() as u32;
+            let result = unsafe {
+                RegEnumKeyExW(
+                    handle.0,
+                    index,
+                    Some(PWSTR(buffer.as_mut_ptr())),
+                    &mut length,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+            };
+            if result == ERROR_NO_MORE_ITEMS {
+                break;
+            }
+            check(result, "enumerate", key)?;
+            names.push(String::from_utf16_lossy(&buffer[..length as usize]));
+        }
+        Ok(names)
+    }
+
+    fn values(&self, key: &Key) -> Result<Vec<(String, Value)>, String> {
+        let Some(handle) = open(key, KEY_READ)? else {
+            return Ok(Vec::new());
+        };
+        let (_, longest_name, longest_data) = info(&handle, key)?;
+        let mut name = vec![0u16; longest_name as usize + 1];
+        let mut data = vec![0u8; longest_data as usize];
+        let mut values = Vec::new();
+        for index in 0.. {
+            let mut name_length = name.len() as u32;
+            let mut data_length = data.len() as u32;
+            let mut kind = 0u32;
+            let result = unsafe {
+                RegEnumValueW(
+                    handle.0,
+                    index,
+                    Some(PWSTR(name.as_mut_ptr())),
+                    &mut name_length,
+                    None,
+                    Some(&mut kind),
+                    Some(data.as_mut_ptr()),
+                    Some(&mut data_length),
+                )
+            };
+            if result == ERROR_NO_MORE_ITEMS {
+                break;
+            }
+            check(result, "enumerate", key)?;
+            values.push((
+                String::from_utf16_lossy(&name[..name_length as usize]),
+                Value::from_raw(kind, &data[..data_length as usize]),
+            ));
+        }
+        Ok(values)
+    }
+
+    fn get_value(&self, key: &Key, name: &str) -> Result<Option<Value>, String> {
+        let Some(handle) = open(key, KEY_READ)? else {
+            return Ok(None);
+        };
+        let name = HSTRING::from(name);
+        let mut data = vec![0u8; 256];
+        loop {
+            let mut kind = REG_VALUE_TYPE::default();
+            let mut length = data.len() as u32;
+            let result = unsafe {
+                RegQueryValueExW(
+                    handle.0,
+                    &name,
+                    None,
+                    Some(&mut kind),
+                    Some(data.as_mut_ptr()),
+                    Some(&mut length),
+                )
+            };
+            match result {
+                ERROR_FILE_NOT_FOUND => return Ok(None),
+                // The value can grow between two calls, so this loops until it fits.
+                ERROR_MORE_DATA => data.resize(length as usize, 0),
+                _ => {
+                    check(result, "read", key)?;
+                    return Ok(Some(Value::from_raw(kind.0, &data[..length as usize])));
+                }
+            }
+        }
+    }
+
+    fn set_value(&mut self, key: &Key, name: &str, value: &Value) -> Result<(), String> {
+        let handle = create(key)?;
+        let name = HSTRING::from(name);
+        let data = value.to_raw();
+        let result = unsafe {
+            RegSetValueExW(
+                handle.0,
+                &name,
+                None,
+                REG_VALUE_TYPE(value.kind()),
+                Some(&data),
+            )
+        };
+        check(result, "write", key)
+    }
+
+    fn delete_value(&mut self, key: &Key, name: &str) -> Result<(), String> {
+        let Some(handle) = open(key, ACCESS)? else {
+            return Ok(());
+        };
+        let name = HSTRING::from(name);
+        let result = unsafe { RegDeleteValueW(handle.0, &name) };
+        if result == ERROR_FILE_NOT_FOUND {
+            return Ok(());
+        }
+        check(result, "delete a value of", key)
+    }
+}
@@ -0,0 +1,171 @@
+/// A registry tree kept in memory, for snapshots of keys and for hive files loaded as a whole.
+use std::collections::BTreeMap;
+
+use super::{Backend, Key, Root, Value};
+
+/// What hive files keep about a key besides its values, carried over when a hive is rewritten.
+#[derive(Clone, Debug, Default)]
+pub struct Meta {
+    /// Last write time as a FILETIME, 0 for keys created or changed since the hive was loaded.
+    pub written: u64,
+    /// The key node flags.
+    pub flags: u16,
+    /// Self-relative security descriptor, empty to inherit the one of the parent.
+    pub security: Vec<u8>,
+    pub class: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Node {
+    pub name: String,
+    pub values: Vec<(String, Value)>,
+    pub children: Vec<Node>,
+    pub meta: Meta,
+}
+
+impl Node {
+    pub fn new(name: &str) -> Self {
+        Node {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Node> {
+        self.children
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    fn child_mut(&mut self, name: &str, create: bool) -> Option<&mut Node> {
+        let index = match self
+            .children
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(name))
+        {
+            Some(i) => i,
+            None if create => {
+                self.children.push(Node::new(name));
+                self.children.len() - 1
+            }
+            None => return None,
+        };
+        Some(&mut self.children[index])
+    }
+
+    pub fn value(&self, name: &str) -> Option<&Value> {
+        self.values
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+}
+
+/// Registry roots and their keys, in memory.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryHive {
+    roots: BTreeMap<Root, Node>,
+}
+
+fn parts(key: &Key) -> impl Iterator<Item = &str> {
+    key.path.split('\\').filter(|p| !p.is_empty())
+}
+
+impl MemoryHive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn node(&self, key: &Key) -> Option<&Node> {
+        parts(key).try_fold(self.roots.get(&key.root)?, |node, part| node.child(part))
+    }
+
+    pub fn node_mut(&mut self, key: &Key, create: bool) -> Option<&mut Node> {
+        let root = self
+            .roots
+            .entry(key.root)
+            .or_insert_with(|| Node::new(key.root.name()));
+        parts(key).try_fold(root, |node, part| node.child_mut(part, create))
+    }
+
+    /// Copies a key with its values and subkeys from another backend.
+    pub fn snapshot(backend: &dyn Backend, key: &Key) -> Result<Self, String> {
+        fn copy(backend: &dyn Backend, key: &Key, hive: &mut MemoryHive) -> Result<(), String> {
+            hive.create_key(key)?;
+            for (name, value) in backend.values(key)? {
+                hive.set_value(key, &name, &value)?;
+            }
+            for name in backend.subkeys(key)? {
+                copy(backend, &key.join(&name), hive)?;
+            }
+            Ok(())
+        }
+        let mut hive = MemoryHive::new();
+        if backend.key_exists(key)? {
+            copy(backend, key, &mut hive)?;
+        }
+        Ok(hive)
+    }
+}
+
+impl Backend for MemoryHive {
+    fn key_exists(&self, key: &Key) -> Result<bool, String> {
+        Ok(key.path.is_empty() || self.node(key).is_some())
+    }
+
+    fn create_key(&mut self, key: &Key) -> Result<(), String> {
+        self.node_mut(key, true);
+        Ok(())
+    }
+
+    fn delete_key(&mut self, key: &Key) -> Result<(), String> {
+        if let Some((parent, name)) = key.split()
+            && let Some(parent) = self.node_mut(&parent, false)
+        {
+            parent
+                .children
+                .retain(|c| !c.name.eq_ignore_ascii_case(name));
+        }
+        Ok(())
+    }
+
+    fn subkeys(&self, key: &Key) -> Result<Vec<String>, String> {
+        Ok(self
+            .node(key)
+            .map(|node| node.children.iter().map(|c| c.name.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    fn values(&self, key: &Key) -> Result<Vec<(String, Value)>, String> {
+        Ok(self
+            .node(key)
+            .map(|node| node.values.clone())
+            .unwrap_or_default())
+    }
+
+    fn get_value(&self, key: &Key, name: &str) -> Result<Option<Value>, String> {
+        Ok(self.node(key).and_then(|node| node.value(name)).cloned())
+    }
+
+    fn set_value(&mut self, key: &Key, name: &str, value: &Value) -> Result<(), String> {
+        let node = self
+            .node_mut(key, true)
+            .ok_or_else(|| format!("Failed to create {}", key))?;
+        match node
+            .values
+            .iter_mut()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            Some((_, current)) => *current = value.clone(),
+            None => node.values.push((name.to_string(), value.clone())),
+        }
+        Ok(())
+    }
+
+    fn delete_value(&mut self, key: &Key, name: &str) -> Result<(), String> {
+        if let Some(node) = self.node_mut(key, false) {
+            node.values.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        }
+        Ok(())
+    }
+}
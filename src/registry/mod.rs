@@ -0,0 +1,330 @@
+/// Windows registry access behind a [`Backend`] trait, with the `.reg` file format on top.
+use std::fmt;
+
+#[cfg(target_os = "windows")]
+mod live;
+pub mod memory;
+pub mod pol;
+pub mod reg;
+pub mod regf;
+
+pub use memory::MemoryHive;
+pub use regf::HiveFile;
+
+pub const REG_NONE: u32 = 0;
+pub const REG_SZ: u32 = 1;
+pub const REG_EXPAND_SZ: u32 = 2;
+pub const REG_BINARY: u32 = 3;
+pub const REG_DWORD: u32 = 4;
+pub const REG_MULTI_SZ: u32 = 7;
+pub const REG_QWORD: u32 = 11;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Root {
+    Classes,
+    CurrentUser,
+    LocalMachine,
+    Users,
+    CurrentConfig,
+}
+
+impl Root {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Root::Classes => "HKEY_CLASSES_ROOT",
+            Root::CurrentUser => "HKEY_CURRENT_USER",
+            Root::LocalMachine => "HKEY_LOCAL_MACHINE",
+            Root::Users => "HKEY_USERS",
+            Root::CurrentConfig => "HKEY_CURRENT_CONFIG",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_uppercase().trim_end_matches(':') {
+            "HKEY_CLASSES_ROOT" | "HKCR" => Some(Root::Classes),
+            "HKEY_CURRENT_USER" | "HKCU" => Some(Root::CurrentUser),
+            "HKEY_LOCAL_MACHINE" | "HKLM" => Some(Root::LocalMachine),
+            "HKEY_USERS" | "HKU" => Some(Root::Users),
+            "HKEY_CURRENT_CONFIG" | "HKCC" => Some(Root::CurrentConfig),
+            _ => None,
+        }
+    }
+}
+
+/// A registry key, `path` is relative to the root and uses `\` without leading or trailing ones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Key {
+    pub root: Root,
+    pub path: String,
+}
+
+impl Key {
+    /// Parses `HKEY_LOCAL_MACHINE\Software\...`, short root names (`HKLM`) are accepted too.
+    pub fn parse(key: &str) -> Result<Self, String> {
+        let key = key.trim().trim_matches('\\');
+        let (root, path) = key.split_once('\\').unwrap_or((key, ""));
+        let root = Root::parse(root).ok_or_else(|| format!("Invalid registry key: {}", key))?;
+        let path = path
+            .split('\\')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<&str>>()
+            .join("\\");
+        Ok(Key { root, path })
+    }
+
+    pub fn join(&self, name: &str) -> Key {
+        Key {
+            root: self.root,
+            path: if self.path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}\\{}", self.path, name)
+            },
+        }
+    }
+
+    /// The parent key and the name of this one, `None` for a root.
+    pub fn split(&self) -> Option<(Key, &str)> {
+        if self.path.is_empty() {
+            return None;
+        }
+        let (parent, name) = self.path.rsplit_once('\\').unwrap_or(("", &self.path));
+        Some((
+            Key {
+                root: self.root,
+                path: parent.to_string(),
+            },
+            name,
+        ))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.root.name())
+        } else {
+            write!(f, "{}\\{}", self.root.name(), self.path)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    ExpandString(String),
+    Binary(Vec<u8>),
+    Dword(u32),
+    Qword(u64),
+    MultiString(Vec<String>),
+    /// Any other type (`REG_NONE`, `REG_LINK`...) or data that doesn't fit its type.
+    Other(u32, Vec<u8>),
+}
+
+fn decode_utf16(data: &[u8]) -> String {
+    let units = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&units)
+}
+
+fn encode_utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+}
+
+impl Value {
+    pub fn kind(&self) -> u32 {
+        match self {
+            Value::String(_) => REG_SZ,
+            Value::ExpandString(_) => REG_EXPAND_SZ,
+            Value::Binary(_) => REG_BINARY,
+            Value::Dword(_) => REG_DWORD,
+            Value::Qword(_) => REG_QWORD,
+            Value::MultiString(_) => REG_MULTI_SZ,
+            Value::Other(kind, _) => *kind,
+        }
+    }
+
+    /// Decodes the data as stored in the registry, strings are UTF-16LE.
+    pub fn from_raw(kind: u32, data: &[u8]) -> Self {
+        let text = || {
+            let text = decode_utf16(data);
+            text.trim_end_matches('\0').to_string()
+        };
+        match kind {
+            REG_SZ => Value::String(text()),
+            REG_EXPAND_SZ => Value::ExpandString(text()),
+            REG_BINARY => Value::Binary(data.to_vec()),
+            REG_DWORD if data.len() == 4 => {
+                Value::Dword(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+            }
+            REG_QWORD if data.len() == 8 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(data);
+                Value::Qword(u64::from_le_bytes(bytes))
+            }
+            REG_MULTI_SZ => {
+                let text = text();
+                if text.is_empty() {
+                    Value::MultiString(Vec::new())
+                } else {
+                    Value::MultiString(text.split('\0').map(str::to_string).collect())
+                }
+            }
+            _ => Value::Other(kind, data.to_vec()),
+        }
+    }
+
+    /// Encodes the data as stored in the registry, strings are NUL terminated.
+    pub fn to_raw(&self) -> Vec<u8> {
+        match self {
+            Value::String(s) | Value::ExpandString(s) => encode_utf16(&format!("{}\0", s)),
+            Value::Binary(data) | Value::Other(_, data) => data.clone(),
+            Value::Dword(n) => n.to_le_bytes().to_vec(),
+            Value::Qword(n) => n.to_le_bytes().to_vec(),
+            Value::MultiString(strings) => {
+                let mut text = strings
+                    .iter()
+                    .map(|s| format!("{}\0", s))
+                    .collect::<String>();
+                text.push('\0');
+                encode_utf16(&text)
+            }
+        }
+    }
+
+    /// Parses the data of a `registry_set` action, `data` has several items for `REG_MULTI_SZ`.
+    pub fn parse(kind: &str, data: &[&str]) -> Result<Self, String> {
+        let single = || match data {
+            [value] => Ok(value.to_string()),
+            _ => Err(format!("{} takes exactly one value", kind.to_uppercase())),
+        };
+        let number = |text: String| -> Result<u64, String> {
+            let text = text.trim();
+            match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => text.parse::<u64>(),
+            }
+            .map_err(|_| format!("Invalid number: {}", text))
+        };
+        let bytes = || {
+            let hex = data
+                .concat()
+                .chars()
+                .filter(|c| !matches!(c, ',' | ' '))
+                .collect::<String>();
+            hex::decode(&hex).map_err(|_| format!("Invalid binary data: {}", hex))
+        };
+        match kind.to_uppercase().trim_start_matches("REG_") {
+            "SZ" | "STRING" => Ok(Value::String(single()?)),
+            "EXPAND_SZ" | "EXPAND" => Ok(Value::ExpandString(single()?)),
+            "DWORD" => {
+                let n = number(single()?)?;
+                u32::try_from(n)
+                    .map(Value::Dword)
+                    .map_err(|_| format!("{} does not fit in a DWORD", n))
+            }
+            "QWORD" => Ok(Value::Qword(number(single()?)?)),
+            "BINARY" => Ok(Value::Binary(bytes()?)),
+            "NONE" => Ok(Value::Other(REG_NONE, bytes()?)),
+            "MULTI_SZ" | "MULTI" => Ok(Value::MultiString(
+                data.iter().map(|s| s.to_string()).collect(),
+            )),
+            _ => Err(format!(
+                "Unknown registry type: {} (expected REG_SZ, REG_EXPAND_SZ, REG_DWORD, REG_QWORD, \
+                 REG_BINARY, REG_MULTI_SZ or REG_NONE)",
+                kind
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "REG_SZ {:?}", s),
+            Value::ExpandString(s) => write!(f, "REG_EXPAND_SZ {:?}", s),
+            Value::Binary(data) => write!(f, "REG_BINARY {}", hex::encode(data)),
+            Value::Dword(n) => write!(f, "REG_DWORD 0x{:08x} ({})", n, n),
+            Value::Qword(n) => write!(f, "REG_QWORD 0x{:016x} ({})", n, n),
+            Value::MultiString(strings) => write!(f, "REG_MULTI_SZ {:?}", strings),
+            Value::Other(kind, data) => write!(f, "type {} {}", kind, hex::encode(data)),
+        }
+    }
+}
+
+/// Where registry operations go: the live registry, an offline hive file or memory.
+///
+/// Value names are case insensitive and `""` is the default value of a key.
+pub trait Backend {
+    fn key_exists(&self, key: &Key) -> Result<bool, String>;
+    /// Creates the key and its missing parents.
+    fn create_key(&mut self, key: &Key) -> Result<(), String>;
+    /// Deletes the key with its subkeys and values, a missing key is not an error.
+    fn delete_key(&mut self, key: &Key) -> Result<(), String>;
+    fn subkeys(&self, key: &Key) -> Result<Vec<String>, String>;
+    fn values(&self, key: &Key) -> Result<Vec<(String, Value)>, String>;
+    fn get_value(&self, key: &Key, name: &str) -> Result<Option<Value>, String>;
+    /// Sets the value, creating the key if needed.
+    fn set_value(&mut self, key: &Key, name: &str, value: &Value) -> Result<(), String>;
+    /// Deletes the value, a missing value is not an error.
+    fn delete_value(&mut self, key: &Key, name: &str) -> Result<(), String>;
+    /// Writes pending changes, for backends that don't write them right away.
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Sets the value if it differs, returns whether it changed.
+pub fn ensure_value(
+    backend: &mut dyn Backend,
+    key: &Key,
+    name: &str,
+    value: &Value,
+) -> Result<bool, String> {
+    if backend.get_value(key, name)?.as_ref() == Some(value) {
+        return Ok(false);
+    }
+    backend.set_value(key, name, value)?;
+    Ok(true)
+}
+
+pub fn remove_value(backend: &mut dyn Backend, key: &Key, name: &str) -> Result<bool, String> {
+    if backend.get_value(key, name)?.is_none() {
+        return Ok(false);
+    }
+    backend.delete_value(key, name)?;
+    Ok(true)
+}
+
+pub fn ensure_key(backend: &mut dyn Backend, key: &Key) -> Result<bool, String> {
+    if backend.key_exists(key)? {
+        return Ok(false);
+    }
+    backend.create_key(key)?;
+    Ok(true)
+}
+
+pub fn remove_key(backend: &mut dyn Backend, key: &Key) -> Result<bool, String> {
+    if key.path.is_empty() {
+        return Err(format!("Refusing to delete the root key {}", key));
+    }
+    if !backend.key_exists(key)? {
+        return Ok(false);
+    }
+    backend.delete_key(key)?;
+    Ok(true)
+}
+
+/// Opens the live registry.
+pub fn open() -> Result<Box<dyn Backend>, String> {
+    #[cfg(target_os = "windows")]
+    {
+        Ok(Box::new(live::Live))
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        Err("The live registry is only available on Windows".to_string())
+    }
+}
@@ -0,0 +1,278 @@
+/// The `Registry.pol` (PReg) format of Group Policy: a header followed by
+/// `[key;name;type;size;data]` entries, the brackets, separators and strings in UTF-16LE.
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+use super::{Value, decode_utf16, encode_utf16};
+
+const SIGNATURE: &[u8; 4] = b"PReg";
+const VERSION: u32 = 1;
+
+/// A policy setting. Names starting with `**` are instructions rather than values: `**del.<name>`
+/// deletes a value, `**delvals.` all the values of the key, `**soft.<name>` only sets a value that
+/// doesn't exist yet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Relative to `HKLM` or `HKCU`, depending on the file.
+    pub key: String,
+    pub name: String,
+    pub kind: u32,
+    /// The raw data, kept as is so untouched entries are written back unchanged.
+    pub data: Vec<u8>,
+}
+
+impl Entry {
+    pub fn new(key: &str, name: &str, value: &Value) -> Self {
+        Entry {
+            key: key.to_string(),
+            name: name.to_string(),
+            kind: value.kind(),
+            data: value.to_raw(),
+        }
+    }
+
+    pub fn value(&self) -> Value {
+        Value::from_raw(self.kind, &self.data)
+    }
+
+    /// The value name the entry is about, with the `**del.` and `**soft.` prefixes removed.
+    pub fn target(&self) -> &str {
+        for prefix in ["**del.", "**soft."] {
+            if self
+                .name
+                .get(..prefix.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+            {
+                return &self.name[prefix.len()..];
+            }
+        }
+        &self.name
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lower = self.name.to_lowercase();
+        let list = || self.value().to_string();
+        if lower.starts_with("**del.") {
+            write!(f, "{}: delete value {}", self.key, self.target())
+        } else if lower == "**delvals." {
+            write!(f, "{}: delete all values", self.key)
+        } else if lower == "**deletevalues" {
+            write!(f, "{}: delete values {}", self.key, list())
+        } else if lower == "**deletekeys" {
+            write!(f, "{}: delete subkeys {}", self.key, list())
+        } else if lower.starts_with("**soft.") {
+            write!(
+                f,
+                "{}: {} = {} (if not set)",
+                self.key,
+                self.target(),
+                self.value()
+            )
+        } else if self.name.is_empty() {
+            write!(f, "{}: (default) = {}", self.key, self.value())
+        } else {
+            write!(f, "{}: {} = {}", self.key, self.name, self.value())
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| format!("Truncated entry at offset 0x{:x}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        let at = self.pos;
+        let b = self.take(2)?;
+        if u16::from_le_bytes([b[0], b[1]]) != c as u16 {
+            return Err(format!("Expected '{}' at offset 0x{:x}", c, at));
+        }
+        Ok(())
+    }
+
+    /// A NUL terminated string.
+    fn string(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        loop {
+            let b = self.take(2)?;
+            if b == [0, 0] {
+                return Ok(decode_utf16(&self.bytes[start..self.pos - 2]));
+            }
+        }
+    }
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Vec<Entry>, String> {
+    if !bytes.starts_with(SIGNATURE) {
+        return Err("Not a Registry.pol file, the PReg signature is missing".to_string());
+    }
+    let mut reader = Reader { bytes, pos: 4 };
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(format!("Unsupported Registry.pol version {}", version));
+    }
+    let mut entries = Vec::new();
+    while reader.pos < bytes.len() {
+        reader.expect('[')?;
+        let key = reader.string()?;
+        reader.expect(';')?;
+        let name = reader.string()?;
+        reader.expect(';')?;
+        let kind = reader.u32()?;
+        reader.expect(';')?;
+        let size = reader.u32()? as usize;
+        reader.expect(';')?;
+        let data = reader.take(size)?.to_vec();
+        reader.expect(']')?;
+        entries.push(Entry {
+            key,
+            name,
+            kind,
+            data,
+        });
+    }
+    Ok(entries)
+}
+
+pub fn write(entries: &[Entry]) -> Vec<u8> {
+    let mut bytes = SIGNATURE.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    let char = |c: char| encode_utf16(&c.to_string());
+    for entry in entries {
+        bytes.extend(char('['));
+        bytes.extend(encode_utf16(&format!("{}\0", entry.key)));
+        bytes.extend(char(';'));
+        bytes.extend(encode_utf16(&format!("{}\0", entry.name)));
+        bytes.extend(char(';'));
+        bytes.extend_from_slice(&entry.kind.to_le_bytes());
+        bytes.extend(char(';'));
+        bytes.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+        bytes.extend(char(';'));
+        bytes.extend_from_slice(&entry.data);
+        bytes.extend(char(']'));
+    }
+    bytes
+}
+
+/// `trx8 policy dump <file>`
+pub fn cli_dump(path: &Path) -> Result<(), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let entries = parse(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if entries.is_empty() {
+        info!("No policies in {}", path.display());
+    }
+    for entry in entries {
+        info!("{}", entry);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{REG_DWORD, REG_SZ};
+
+    #[test]
+    fn writes_the_preg_layout() {
+        let entry = Entry::new(r"Software\Policies\Trx8", "On", &Value::Dword(1));
+        let expected = [
+            b"PReg".to_vec(),
+            vec![1, 0, 0, 0],
+            encode_utf16("[Software\\Policies\\Trx8\0;On\0;"),
+            REG_DWORD.to_le_bytes().to_vec(),
+            encode_utf16(";"),
+            4u32.to_le_bytes().to_vec(),
+            encode_utf16(";"),
+            vec![1, 0, 0, 0],
+            encode_utf16("]"),
+        ]
+        .concat();
+        assert_eq!(write(std::slice::from_ref(&entry)), expected);
+        assert_eq!(parse(&expected).unwrap(), [entry]);
+        assert_eq!(write(&[]), b"PReg\x01\0\0\0");
+    }
+
+    #[test]
+    fn round_trips_instructions() {
+        let key = r"Software\Policies\Trx8";
+        let marker = Value::String(" ".to_string());
+        let entries = vec![
+            Entry::new(key, "", &Value::String("default".to_string())),
+            Entry::new(key, "**del.Old", &marker),
+            Entry::new(key, "**delvals.", &marker),
+            Entry::new(key, "**DeleteValues", &Value::String("a;b".to_string())),
+            Entry::new(key, "**soft.Size", &Value::Qword(1 << 40)),
+            Entry::new(
+                key,
+                "List",
+                &Value::MultiString(vec!["x".to_string(), "y".to_string()]),
+            ),
+            // Unknown types and odd data are kept byte for byte.
+            Entry {
+                key: key.to_string(),
+                name: "Raw".to_string(),
+                kind: 0x1234,
+                data: vec![1, 2, 3],
+            },
+        ];
+        let bytes = write(&entries);
+        let parsed = parse(&bytes).unwrap();
+        assert_eq!(parsed, entries);
+        assert_eq!(write(&parsed), bytes);
+
+        let described = parsed.iter().map(Entry::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            described,
+            [
+                format!("{}: (default) = REG_SZ \"default\"", key),
+                format!("{}: delete value Old", key),
+                format!("{}: delete all values", key),
+                format!("{}: delete values REG_SZ \"a;b\"", key),
+                format!(
+                    "{}: Size = REG_QWORD 0x0000010000000000 (1099511627776) (if not set)",
+                    key
+                ),
+                format!("{}: List = REG_MULTI_SZ [\"x\", \"y\"]", key),
+                format!("{}: Raw = type 4660 010203", key),
+            ]
+        );
+        assert_eq!(parsed[1].target(), "Old");
+        assert_eq!(parsed[4].target(), "Size");
+        assert_eq!(parsed[4].kind, crate::registry::REG_QWORD);
+        assert_eq!(parsed[0].kind, REG_SZ);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(parse(b"Preg\x01\0\0\0").is_err());
+        assert_eq!(
+            parse(b"PReg\x02\0\0\0"),
+            Err("Unsupported Registry.pol version 2".to_string())
+        );
+        let mut bytes = write(&[Entry::new("Key", "Name", &Value::Dword(1))]);
+        bytes.truncate(bytes.len() - 4);
+        assert!(parse(&bytes).unwrap_err().starts_with("Truncated entry"));
+        let mut bytes = write(&[Entry::new("Key", "Name", &Value::Dword(1))]);
+        bytes[8] = b'(';
+        assert_eq!(parse(&bytes), Err("Expected '[' at offset 0x8".to_string()));
+    }
+}
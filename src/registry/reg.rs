@@ -0,0 +1,413 @@
+/// The `.reg` text format of regedit, both `Windows Registry Editor Version 5.00` and `REGEDIT4`.
+use super::memory::{MemoryHive, Node};
+use super::{
+    Backend, Key, REG_EXPAND_SZ, REG_MULTI_SZ, REG_SZ, Value, ensure_key, ensure_value, remove_key,
+    remove_value,
+};
+
+const HEADER_V5: &str = "Windows Registry Editor Version 5.00";
+const HEADER_V4: &str = "REGEDIT4";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
+    CreateKey(Key),
+    /// `[-HKEY_...]`
+    DeleteKey(Key),
+    SetValue(Key, String, Value),
+    /// `"name"=-`
+    DeleteValue(Key, String),
+}
+
+/// Parses a quoted string starting at `text[0] == '"'`, returns it and the rest after the quote.
+fn parse_quoted(text: &str) -> Result<(String, &str), String> {
+    let mut out = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, c)) => out.push(c),
+                None => break,
+            },
+            '"' => return Ok((out, &text[i + 1..])),
+            c => out.push(c),
+        }
+    }
+    Err("Unterminated string".to_string())
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(|b| u8::from_str_radix(b, 16).map_err(|_| format!("Invalid hex byte: {}", b)))
+        .collect()
+}
+
+/// `REGEDIT4` files store strings of hex values in the ANSI code page, read here as Latin-1.
+fn ansi_to_raw(kind: u32, data: &[u8]) -> Vec<u8> {
+    if matches!(kind, REG_SZ | REG_EXPAND_SZ | REG_MULTI_SZ) {
+        data.iter()
+            .flat_map(|&b| (b as u16).to_le_bytes())
+            .collect()
+    } else {
+        data.to_vec()
+    }
+}
+
+fn parse_data(data: &str, unicode: bool) -> Result<Option<Value>, String> {
+    let data = data.trim();
+    if data == "-" {
+        return Ok(None);
+    }
+    if data.starts_with('"') {
+        let (text, rest) = parse_quoted(data)?;
+        if !rest.trim().is_empty() {
+            return Err(format!("Unexpected text after string: {}", rest.trim()));
+        }
+        return Ok(Some(Value::String(text)));
+    }
+    if let Some(hex) = data.strip_prefix("dword:") {
+        return u32::from_str_radix(hex.trim(), 16)
+            .map(|n| Some(Value::Dword(n)))
+            .map_err(|_| format!("Invalid dword: {}", hex));
+    }
+    if let Some(hex) = data.strip_prefix("hex:") {
+        return Ok(Some(Value::Binary(parse_hex(hex)?)));
+    }
+    if let Some(rest) = data.strip_prefix("hex(")
+        && let Some((kind, hex)) = rest.split_once("):")
+    {
+        let kind = u32::from_str_radix(kind, 16).map_err(|_| format!("Invalid type: {}", kind))?;
+        let bytes = parse_hex(hex)?;
+        let raw = if unicode {
+            bytes
+        } else {
+            ansi_to_raw(kind, &bytes)
+        };
+        return Ok(Some(Value::from_raw(kind, &raw)));
+    }
+    Err(format!("Invalid value data: {}", data))
+}
+
+/// Parses the content of a `.reg` file, errors give the line number.
+pub fn parse(text: &str) -> Result<Vec<Operation>, String> {
+    let mut lines = text.lines().enumerate();
+    let unicode = loop {
+        match lines.next() {
+            Some((_, line)) if line.trim().is_empty() => continue,
+            Some((_, line)) if line.trim() == HEADER_V5 => break true,
+            Some((_, line)) if line.trim() == HEADER_V4 => break false,
+            Some((n, _)) => {
+                return Err(format!(
+                    "line {}: Expected \"{}\" or \"{}\"",
+                    n + 1,
+                    HEADER_V5,
+                    HEADER_V4
+                ));
+            }
+            None => return Err("Empty .reg file".to_string()),
+        }
+    };
+
+    let mut operations = Vec::new();
+    let mut current: Option<Key> = None;
+    while let Some((n, line)) = lines.next() {
+        let error = |e: String| format!("line {}: {}", n + 1, e);
+        let mut line = line.trim().to_string();
+        // Hex data continues on the next lines after a trailing backslash.
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, next)) => line.push_str(next.trim()),
+                None => break,
+            }
+        }
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        if let Some(inner) = line.strip_prefix('[') {
+            let inner = inner
+                .strip_suffix(']')
+                .ok_or_else(|| error("Missing ] after the key".to_string()))?;
+            if let Some(deleted) = inner.strip_prefix('-') {
+                operations.push(Operation::DeleteKey(Key::parse(deleted).map_err(error)?));
+                current = None;
+            } else {
+                let key = Key::parse(inner).map_err(error)?;
+                operations.push(Operation::CreateKey(key.clone()));
+                current = Some(key);
+            }
+            continue;
+        }
+        let key = current
+            .clone()
+            .ok_or_else(|| error("Value outside of a key".to_string()))?;
+        let (name, rest) = if let Some(rest) = line.strip_prefix('@') {
+            (String::new(), rest)
+        } else if line.starts_with('"') {
+            parse_quoted(&line).map_err(error)?
+        } else {
+            return Err(error(format!("Invalid line: {}", line)));
+        };
+        let data = rest
+            .trim_start()
+            .strip_prefix('=')
+            .ok_or_else(|| error("Missing = after the value name".to_string()))?;
+        match parse_data(data, unicode).map_err(error)? {
+            Some(value) => operations.push(Operation::SetValue(key, name, value)),
+            None => operations.push(Operation::DeleteValue(key, name)),
+        }
+    }
+    Ok(operations)
+}
+
+/// Applies the operations, returns whether anything changed.
+pub fn apply(backend: &mut dyn Backend, operations: &[Operation]) -> Result<bool, String> {
+    let mut changed = false;
+    for operation in operations {
+        changed |= match operation {
+            Operation::CreateKey(key) => ensure_key(backend, key)?,
+            Operation::DeleteKey(key) => remove_key(backend, key)?,
+            Operation::SetValue(key, name, value) => ensure_value(backend, key, name, value)?,
+            Operation::DeleteValue(key, name) => remove_value(backend, key, name)?,
+        };
+    }
+    Ok(changed)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Writes `"name"=hex(N):..` wrapped at 80 columns like regedit does.
+fn write_hex(out: &mut String, prefix: &str, data: &[u8]) {
+    let mut line = prefix.to_string();
+    for (i, byte) in data.iter().enumerate() {
+        line.push_str(&format!("{:02x}", byte));
+        if i + 1 < data.len() {
+            line.push(',');
+            if line.len() > 76 {
+                out.push_str(&line);
+                out.push_str("\\\n");
+                line = "  ".to_string();
+            }
+        }
+    }
+    out.push_str(&line);
+    out.push('\n');
+}
+
+fn write_value(out: &mut String, name: &str, value: &Value) {
+    let name = if name.is_empty() {
+        "@".to_string()
+    } else {
+        format!("\"{}\"", escape(name))
+    };
+    match value {
+        Value::String(s) if !s.contains(['\n', '\r', '\0']) => {
+            out.push_str(&format!("{}=\"{}\"\n", name, escape(s)));
+        }
+        Value::Dword(n) => out.push_str(&format!("{}=dword:{:08x}\n", name, n)),
+        Value::Binary(data) => write_hex(out, &format!("{}=hex:", name), data),
+        value => write_hex(
+            out,
+            &format!("{}=hex({:x}):", name, value.kind()),
+            &value.to_raw(),
+        ),
+    }
+}
+
+fn write_node(out: &mut String, key: &Key, node: &Node) {
+    out.push_str(&format!("[{}]\n", key));
+    for (name, value) in &node.values {
+        write_value(out, name, value);
+    }
+    out.push('\n');
+    for child in &node.children {
+        write_node(out, &key.join(&child.name), child);
+    }
+}
+
+/// Writes a key of the hive with its subkeys in the `Windows Registry Editor Version 5.00`
+/// format, with `\n` line endings.
+pub fn write(hive: &MemoryHive, key: &Key) -> String {
+    let mut out = format!("{}\n\n", HEADER_V5);
+    if let Some(node) = hive.node(key) {
+        write_node(&mut out, key, node);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::REG_QWORD;
+
+    fn key(path: &str) -> Key {
+        Key::parse(path).unwrap()
+    }
+
+    const V5: &str = r#"Windows Registry Editor Version 5.00
+
+; A comment
+[HKEY_CURRENT_USER\Software\Trx8]
+@="default"
+"Name"="C:\\Tools \"quoted\""
+"Count"=dword:0000002a
+"Data"=hex:01,02,ff
+"Path"=hex(2):25,00,53,00,79,00,73,00,74,00,65,00,6d,00,52,00,6f,00,6f,00,74,00,\
+  25,00,5c,00,61,00,00,00
+"List"=hex(7):61,00,00,00,62,00,63,00,00,00,00,00
+"Big"=hex(b):00,00,00,00,01,00,00,00
+
+[HKEY_CURRENT_USER\Software\Trx8\Child]
+"Empty"=""
+"#;
+
+    #[test]
+    fn parses_and_applies_version_5() {
+        let operations = parse(V5).unwrap();
+        let mut hive = MemoryHive::new();
+        assert!(apply(&mut hive, &operations).unwrap());
+
+        let trx8 = key(r"HKCU\Software\Trx8");
+        let get = |name: &str| hive.get_value(&trx8, name).unwrap().unwrap();
+        assert_eq!(get(""), Value::String("default".to_string()));
+        assert_eq!(
+            get("name"),
+            Value::String(r#"C:\Tools "quoted""#.to_string())
+        );
+        assert_eq!(get("Count"), Value::Dword(42));
+        assert_eq!(get("Data"), Value::Binary(vec![1, 2, 0xff]));
+        assert_eq!(
+            get("Path"),
+            Value::ExpandString(r"%SystemRoot%\a".to_string())
+        );
+        assert_eq!(
+            get("List"),
+            Value::MultiString(vec!["a".to_string(), "bc".to_string()])
+        );
+        assert_eq!(get("Big"), Value::Qword(1 << 32));
+        assert_eq!(get("Big").kind(), REG_QWORD);
+        assert_eq!(
+            hive.get_value(&trx8.join("Child"), "Empty").unwrap(),
+            Some(Value::String(String::new()))
+        );
+
+        // Importing the same file again changes nothing.
+        assert!(!apply(&mut hive, &operations).unwrap());
+    }
+
+    #[test]
+    fn export_reimports_unchanged() {
+        let mut hive = MemoryHive::new();
+        apply(&mut hive, &parse(V5).unwrap()).unwrap();
+        let exported = write(&hive, &key(r"HKCU\Software\Trx8"));
+        assert_eq!(
+            exported,
+            r#"Windows Registry Editor Version 5.00
+
+[HKEY_CURRENT_USER\Software\Trx8]
+@="default"
+"Name"="C:\\Tools \"quoted\""
+"Count"=dword:0000002a
+"Data"=hex:01,02,ff
+"Path"=hex(2):25,00,53,00,79,00,73,00,74,00,65,00,6d,00,52,00,6f,00,6f,00,74,\
+  00,25,00,5c,00,61,00,00,00
+"List"=hex(7):61,00,00,00,62,00,63,00,00,00,00,00
+"Big"=hex(b):00,00,00,00,01,00,00,00
+
+[HKEY_CURRENT_USER\Software\Trx8\Child]
+"Empty"=""
+
+"#
+        );
+
+        assert!(!apply(&mut hive, &parse(&exported).unwrap()).unwrap());
+        let mut copy = MemoryHive::new();
+        apply(&mut copy, &parse(&exported).unwrap()).unwrap();
+        assert_eq!(write(&copy, &key(r"HKCU\Software\Trx8")), exported);
+    }
+
+    #[test]
+    fn regedit4_strings_are_ansi() {
+        let text = "REGEDIT4\r\n\r\n[HKEY_LOCAL_MACHINE\\Software\\Trx8]\r\n\
+                    \"Path\"=hex(2):25,54,45,4d,50,25,e9,00\r\n\
+                    \"List\"=hex(7):61,00,62,00,00\r\n\
+                    \"Data\"=hex:61,00\r\n";
+        let operations = parse(text).unwrap();
+        let trx8 = key(r"HKLM\Software\Trx8");
+        assert_eq!(
+            operations[1],
+            Operation::SetValue(
+                trx8.clone(),
+                "Path".to_string(),
+                Value::ExpandString("%TEMP%é".to_string())
+            )
+        );
+        assert_eq!(
+            operations[2],
+            Operation::SetValue(
+                trx8.clone(),
+                "List".to_string(),
+                Value::MultiString(vec!["a".to_string(), "b".to_string()])
+            )
+        );
+        // Binary data is not text and is kept as it is.
+        assert_eq!(
+            operations[3],
+            Operation::SetValue(trx8, "Data".to_string(), Value::Binary(vec![0x61, 0]))
+        );
+    }
+
+    #[test]
+    fn deletes_keys_and_values() {
+        let mut hive = MemoryHive::new();
+        apply(&mut hive, &parse(V5).unwrap()).unwrap();
+        let text = r#"Windows Registry Editor Version 5.00
+
+[-HKEY_CURRENT_USER\Software\Trx8\Child]
+
+[HKEY_CURRENT_USER\Software\Trx8]
+"Count"=-
+@=-
+"#;
+        let operations = parse(text).unwrap();
+        let trx8 = key(r"HKCU\Software\Trx8");
+        assert_eq!(
+            operations,
+            [
+                Operation::DeleteKey(trx8.join("Child")),
+                Operation::CreateKey(trx8.clone()),
+                Operation::DeleteValue(trx8.clone(), "Count".to_string()),
+                Operation::DeleteValue(trx8.clone(), String::new()),
+            ]
+        );
+        assert!(apply(&mut hive, &operations).unwrap());
+        assert!(!hive.key_exists(&trx8.join("Child")).unwrap());
+        assert_eq!(hive.get_value(&trx8, "Count").unwrap(), None);
+        assert_eq!(hive.get_value(&trx8, "").unwrap(), None);
+        assert!(hive.get_value(&trx8, "Name").unwrap().is_some());
+
+        // Deleting what is already gone is not a change.
+        assert!(!apply(&mut hive, &operations).unwrap());
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        assert_eq!(
+            parse("REGEDIT5\n"),
+            Err(format!(
+                "line 1: Expected \"{}\" or \"{}\"",
+                HEADER_V5, HEADER_V4
+            ))
+        );
+        let text = "REGEDIT4\n\n[HKCU\\Software]\n\"a\"=dword:xyz\n";
+        assert_eq!(parse(text), Err("line 4: Invalid dword: xyz".to_string()));
+        let text = "REGEDIT4\n\"a\"=\"b\"\n";
+        assert_eq!(
+            parse(text),
+            Err("line 2: Value outside of a key".to_string())
+        );
+    }
+}
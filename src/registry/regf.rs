@@ -0,0 +1,994 @@
+/// Offline hive files in the regf format (`NTUSER.DAT`, `SOFTWARE`, `SYSTEM`...), loaded whole
+/// into a [`MemoryHive`] and written back by [`Backend::flush`].
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+use super::memory::{Meta, Node};
+use super::{Backend, Key, MemoryHive, Root, Value, decode_utf16, encode_utf16};
+
+const BASE_BLOCK_SIZE: usize = 4096;
+const BIN_HEADER_SIZE: usize = 32;
+const BIN_ALIGNMENT: usize = 4096;
+/// Values larger than this are split in `db` segments, from format 1.4 on.
+const BIG_DATA_SEGMENT: usize = 16344;
+/// Windows starts an `ri` index above this many subkeys.
+const LEAF_SIZE: usize = 1024;
+const NO_CELL: u32 = u32::MAX;
+/// Keys nested deeper than this mean a loop in a corrupted hive.
+const MAX_DEPTH: usize = 512;
+
+const KEY_COMP_NAME: u16 = 0x0020;
+const VALUE_COMP_NAME: u16 = 0x0001;
+/// Set in the data size of values of 4 bytes or less, stored in place of the data offset.
+const DATA_INLINE: u32 = 0x8000_0000;
+
+fn truncated() -> String {
+    "Truncated hive data".to_string()
+}
+
+fn bytes_at(data: &[u8], at: usize, len: usize) -> Result<&[u8], String> {
+    data.get(at..at.checked_add(len).ok_or_else(truncated)?)
+        .ok_or_else(truncated)
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16, String> {
+    let b = bytes_at(data, at, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<u32, String> {
+    let b = bytes_at(data, at, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn u64_at(data: &[u8], at: usize) -> Result<u64, String> {
+    Ok(u64::from(u32_at(data, at)?) | (u64::from(u32_at(data, at + 4)?) << 32))
+}
+
+fn put_u32(data: &mut [u8], at: usize, value: u32) {
+    data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// The current time as a FILETIME, in 100 ns intervals since 1601.
+fn filetime_now() -> u64 {
+    let since_unix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (since_unix.as_secs() + 11_644_473_600) * 10_000_000
+        + u64::from(since_unix.subsec_nanos() / 100)
+}
+
+/// XOR of the first 127 dwords of the base block, 0 and -1 are not allowed.
+fn checksum(base: &[u8]) -> u32 {
+    let sum = base[..508].chunks_exact(4).fold(0, |sum, dword| {
+        sum ^ u32::from_le_bytes([dword[0], dword[1], dword[2], dword[3]])
+    });
+    match sum {
+        0 => 1,
+        u32::MAX => u32::MAX - 1,
+        sum => sum,
+    }
+}
+
+fn decode_name(bytes: &[u8], compressed: bool) -> String {
+    if compressed {
+        bytes.iter().map(|&b| b as char).collect()
+    } else {
+        decode_utf16(bytes)
+    }
+}
+
+/// Names that fit in Latin-1 are stored with one byte per character, like Windows does.
+fn encode_name(name: &str) -> (Vec<u8>, bool) {
+    if name.chars().all(|c| (c as u32) < 0x100) {
+        (name.chars().map(|c| c as u8).collect(), true)
+    } else {
+        (encode_utf16(name), false)
+    }
+}
+
+fn upcase(name: &str) -> Vec<u16> {
+    name.encode_utf16()
+        .map(|unit| {
+            let mut upper = char::from_u32(u32::from(unit))
+                .map(|c| c.to_uppercase())
+                .into_iter()
+                .flatten();
+            match (upper.next(), upper.next()) {
+                (Some(c), None) if (c as u32) < 0x10000 => c as u16,
+                _ => unit,
+            }
+        })
+        .collect()
+}
+
+/// The hash of `lh` subkey lists.
+fn name_hash(name: &str) -> u32 {
+    upcase(name).iter().fold(0u32, |hash, &unit| {
+        hash.wrapping_mul(37).wrapping_add(u32::from(unit))
+    })
+}
+
+/// The hint of `lf` subkey lists, the first 4 characters of the name.
+fn name_hint(name: &str) -> [u8; 4] {
+    let mut hint = [0u8; 4];
+    for (slot, unit) in hint.iter_mut().zip(name.encode_utf16()) {
+        *slot = u8::try_from(unit).unwrap_or(0);
+    }
+    hint
+}
+
+fn utf16_length(text: &str) -> u32 {
+    text.encode_utf16().count() as u32 * 2
+}
+
+struct Reader<'a> {
+    /// The hive bins, cell offsets are relative to their start.
+    bins: &'a [u8],
+    minor: u32,
+}
+
+impl Reader<'_> {
+    fn cell(&self, offset: u32) -> Result<&[u8], String> {
+        let start = offset as usize;
+        let size = (u32_at(self.bins, start)? as i32).unsigned_abs() as usize;
+        if size < 4 {
+            return Err(format!("Invalid cell at 0x{:x}", offset));
+        }
+        bytes_at(self.bins, start + 4, size - 4)
+            .map_err(|_| format!("Cell at 0x{:x} is out of bounds", offset))
+    }
+
+    fn signed(&self, offset: u32, signature: &[u8; 2]) -> Result<&[u8], String> {
+        let cell = self.cell(offset)?;
+        if !cell.starts_with(signature) {
+            return Err(format!(
+                "Expected a {} cell at 0x{:x}",
+                String::from_utf8_lossy(signature),
+                offset
+            ));
+        }
+        Ok(cell)
+    }
+
+    fn key(&self, offset: u32, depth: usize) -> Result<Node, String> {
+        if depth > MAX_DEPTH {
+            return Err("Keys are nested too deep".to_string());
+        }
+        let nk = self.signed(offset, b"nk")?;
+        let flags = u16_at(nk, 2)?;
+        let name_length = u16_at(nk, 72)? as usize;
+        let name = decode_name(bytes_at(nk, 76, name_length)?, flags & KEY_COMP_NAME != 0);
+
+        let class_offset = u32_at(nk, 48)?;
+        let class_length = u16_at(nk, 74)? as usize;
+        let class = if class_offset != NO_CELL && class_length > 0 {
+            Some(decode_utf16(bytes_at(
+                self.cell(class_offset)?,
+                0,
+                class_length,
+            )?))
+        } else {
+            None
+        };
+        let security_offset = u32_at(nk, 44)?;
+        let security = if security_offset != NO_CELL {
+            let sk = self.signed(security_offset, b"sk")?;
+            bytes_at(sk, 20, u32_at(sk, 16)? as usize)?.to_vec()
+        } else {
+            Vec::new()
+        };
+
+        let value_count = u32_at(nk, 36)? as usize;
+        let mut values = Vec::new();
+        if value_count > 0 {
+            let list = self.cell(u32_at(nk, 40)?)?;
+            for i in 0..value_count {
+                values.push(self.value(u32_at(list, i * 4)?)?);
+            }
+        }
+        let mut children = Vec::new();
+        if u32_at(nk, 20)? > 0 {
+            let mut offsets = Vec::new();
+            self.subkeys(u32_at(nk, 28)?, &mut offsets, false)?;
+            for child in offsets {
+                children.push(self.key(child, depth + 1)?);
+            }
+        }
+        Ok(Node {
+            name,
+            values,
+            children,
+            meta: Meta {
+                written: u64_at(nk, 4)?,
+                flags,
+                security,
+                class,
+            },
+        })
+    }
+
+    /// Collects the key offsets of a subkey list, `ri` lists point to other lists.
+    fn subkeys(&self, offset: u32, out: &mut Vec<u32>, nested: bool) -> Result<(), String> {
+        let list = self.cell(offset)?;
+        let count = u16_at(list, 2)? as usize;
+        match bytes_at(list, 0, 2)? {
+            b"li" => {
+                for i in 0..count {
+                    out.push(u32_at(list, 4 + i * 4)?);
+                }
+            }
+            b"lf" | b"lh" => {
+                for i in 0..count {
+                    out.push(u32_at(list, 4 + i * 8)?);
+                }
+            }
+            b"ri" if !nested => {
+                for i in 0..count {
+                    self.subkeys(u32_at(list, 4 + i * 4)?, out, true)?;
+                }
+            }
+            _ => return Err(format!("Invalid subkey list at 0x{:x}", offset)),
+        }
+        Ok(())
+    }
+
+    fn value(&self, offset: u32) -> Result<(String, Value), String> {
+        let vk = self.signed(offset, b"vk")?;
+        let name_length = u16_at(vk, 2)? as usize;
+        let size = u32_at(vk, 4)?;
+        let data_offset = u32_at(vk, 8)?;
+        let kind = u32_at(vk, 12)?;
+        let flags = u16_at(vk, 16)?;
+        let name = decode_name(bytes_at(vk, 20, name_length)?, flags & VALUE_COMP_NAME != 0);
+        let data = if size & DATA_INLINE != 0 {
+            let size = ((size & !DATA_INLINE) as usize).min(4);
+            data_offset.to_le_bytes()[..size].to_vec()
+        } else if size == 0 {
+            Vec::new()
+        } else {
+            self.data(data_offset, size as usize)?
+        };
+        Ok((name, Value::from_raw(kind, &data)))
+    }
+
+    fn data(&self, offset: u32, size: usize) -> Result<Vec<u8>, String> {
+        let cell = self.cell(offset)?;
+        if self.minor >= 4 && size > BIG_DATA_SEGMENT && cell.starts_with(b"db") {
+            let count = u16_at(cell, 2)? as usize;
+            let list = self.cell(u32_at(cell, 4)?)?;
+            let mut data = Vec::new();
+            for i in 0..count {
+                let segment = self.cell(u32_at(list, i * 4)?)?;
+                let take = (size - data.len()).min(BIG_DATA_SEGMENT).min(segment.len());
+                data.extend_from_slice(&segment[..take]);
+            }
+            if data.len() < size {
+                return Err(truncated());
+            }
+            return Ok(data);
+        }
+        Ok(bytes_at(cell, 0, size)?.to_vec())
+    }
+}
+
+/// Parses a hive file, returns its base block and root key.
+fn load(file: &[u8]) -> Result<(Vec<u8>, Node), String> {
+    let base = bytes_at(file, 0, BASE_BLOCK_SIZE)?;
+    if !base.starts_with(b"regf") {
+        return Err("Not a hive file, the regf signature is missing".to_string());
+    }
+    let (major, minor) = (u32_at(base, 20)?, u32_at(base, 24)?);
+    if major != 1 || u32_at(base, 28)? != 0 {
+        return Err(format!("Unsupported hive format {}.{}", major, minor));
+    }
+    if u32_at(base, 4)? != u32_at(base, 8)? {
+        return Err(
+            "The hive was not cleanly unloaded, load it once on Windows to apply its \
+             transaction logs (.LOG1, .LOG2)"
+                .to_string(),
+        );
+    }
+    if checksum(base) != u32_at(base, 508)? {
+        return Err("Invalid base block checksum".to_string());
+    }
+    let bins = bytes_at(file, BASE_BLOCK_SIZE, u32_at(base, 40)? as usize)?;
+    let root = Reader { bins, minor }.key(u32_at(base, 36)?, 0)?;
+    Ok((base.to_vec(), root))
+}
+
+struct Writer {
+    bins: Vec<u8>,
+    /// The end of the current bin, cells never cross bins.
+    bin_end: usize,
+    minor: u32,
+    now: u64,
+    /// Security cells with their reference counts, keys with the same descriptor share one.
+    security: Vec<(u32, u32)>,
+    security_index: HashMap<Vec<u8>, usize>,
+}
+
+impl Writer {
+    fn alloc(&mut self, size: usize) -> usize {
+        let size = (size + 4).next_multiple_of(8);
+        if self.bins.len() + size > self.bin_end {
+            self.new_bin(size);
+        }
+        let offset = self.bins.len();
+        self.bins.extend_from_slice(&(-(size as i32)).to_le_bytes());
+        self.bins.resize(offset + size, 0);
+        offset
+    }
+
+    fn cell(&mut self, content: &[u8]) -> u32 {
+        let offset = self.alloc(content.len());
+        self.bins[offset + 4..offset + 4 + content.len()].copy_from_slice(content);
+        offset as u32
+    }
+
+    /// Marks the rest of the current bin as a free cell.
+    fn close_bin(&mut self) {
+        let free = self.bin_end - self.bins.len();
+        if free > 0 {
+            self.bins.extend_from_slice(&(free as i32).to_le_bytes());
+            self.bins.resize(self.bin_end, 0);
+        }
+    }
+
+    fn new_bin(&mut self, cell_size: usize) {
+        self.close_bin();
+        let start = self.bins.len();
+        let size = (cell_size + BIN_HEADER_SIZE).next_multiple_of(BIN_ALIGNMENT);
+        self.bins.extend_from_slice(b"hbin");
+        self.bins.extend_from_slice(&(start as u32).to_le_bytes());
+        self.bins.extend_from_slice(&(size as u32).to_le_bytes());
+        self.bins.resize(start + 20, 0);
+        // Only the first bin carries a timestamp.
+        let timestamp = if start == 0 { self.now } else { 0 };
+        self.bins.extend_from_slice(&timestamp.to_le_bytes());
+        self.bins.resize(start + BIN_HEADER_SIZE, 0);
+        self.bin_end = start + size;
+    }
+
+    fn security(&mut self, descriptor: &[u8]) -> u32 {
+        if let Some(&i) = self.security_index.get(descriptor) {
+            self.security[i].1 += 1;
+            return self.security[i].0;
+        }
+        let mut sk = b"sk\0\0".to_vec();
+        sk.resize(16, 0);
+        sk.extend_from_slice(&(descriptor.len() as u32).to_le_bytes());
+        sk.extend_from_slice(descriptor);
+        let offset = self.cell(&sk);
+        self.security_index
+            .insert(descriptor.to_vec(), self.security.len());
+        self.security.push((offset, 1));
+        offset
+    }
+
+    /// Links the security cells in a circular list and writes their reference counts.
+    fn link_security(&mut self) {
+        let count = self.security.len();
+        for (i, &(offset, references)) in self.security.iter().enumerate() {
+            let at = offset as usize + 4;
+            put_u32(&mut self.bins, at + 4, self.security[(i + 1) % count].0);
+            put_u32(
+                &mut self.bins,
+                at + 8,
+                self.security[(i + count - 1) % count].0,
+            );
+            put_u32(&mut self.bins, at + 12, references);
+        }
+    }
+
+    /// Writes a value cell and its data, returns its offset and the size of the data.
+    fn value(&mut self, name: &str, value: &Value) -> (u32, usize) {
+        let (name, compressed) = encode_name(name);
+        let data = value.to_raw();
+        let (size, data_offset) = if data.len() <= 4 {
+            let mut inline = [0u8; 4];
+            inline[..data.len()].copy_from_slice(&data);
+            (data.len() as u32 | DATA_INLINE, u32::from_le_bytes(inline))
+        } else if self.minor >= 4 && data.len() > BIG_DATA_SEGMENT {
+            let segments = data
+                .chunks(BIG_DATA_SEGMENT)
+                .map(|chunk| self.cell(chunk))
+                .collect::<Vec<u32>>();
+            let list = self.cell(
+                &segments
+                    .iter()
+                    .flat_map(|s| s.to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            );
+            let mut db = b"db".to_vec();
+            db.extend_from_slice(&(segments.len() as u16).to_le_bytes());
+            db.extend_from_slice(&list.to_le_bytes());
+            db.resize(12, 0);
+            (data.len() as u32, self.cell(&db))
+        } else {
+            (data.len() as u32, self.cell(&data))
+        };
+        let mut vk = b"vk".to_vec();
+        vk.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        vk.extend_from_slice(&size.to_le_bytes());
+        vk.extend_from_slice(&data_offset.to_le_bytes());
+        vk.extend_from_slice(&value.kind().to_le_bytes());
+        let flags = if compressed { VALUE_COMP_NAME } else { 0 };
+        vk.extend_from_slice(&flags.to_le_bytes());
+        vk.extend_from_slice(&[0, 0]);
+        vk.extend_from_slice(&name);
+        (self.cell(&vk), data.len())
+    }
+
+    /// Writes the subkey list of sorted keys, an `ri` index of leaves for many keys.
+    fn subkey_list(&mut self, children: &[&Node], offsets: &[u32]) -> u32 {
+        if children.is_empty() {
+            return NO_CELL;
+        }
+        let leaves = children
+            .chunks(LEAF_SIZE)
+            .zip(offsets.chunks(LEAF_SIZE))
+            .map(|(children, offsets)| {
+                let signature = match self.minor {
+                    0..=2 => b"li",
+                    3 | 4 => b"lf",
+                    _ => b"lh",
+                };
+                let mut list = signature.to_vec();
+                list.extend_from_slice(&(children.len() as u16).to_le_bytes());
+                for (child, offset) in children.iter().zip(offsets) {
+                    list.extend_from_slice(&offset.to_le_bytes());
+                    match signature {
+                        b"lh" => list.extend_from_slice(&name_hash(&child.name).to_le_bytes()),
+                        b"lf" => list.extend_from_slice(&name_hint(&child.name)),
+                        _ => {}
+                    }
+                }
+                self.cell(&list)
+            })
+            .collect::<Vec<u32>>();
+        if let [leaf] = leaves[..] {
+            return leaf;
+        }
+        let mut index = b"ri".to_vec();
+        index.extend_from_slice(&(leaves.len() as u16).to_le_bytes());
+        for leaf in leaves {
+            index.extend_from_slice(&leaf.to_le_bytes());
+        }
+        self.cell(&index)
+    }
+
+    /// Writes a key with its values and subkeys, keys without a security descriptor get the one
+    /// of their parent.
+    fn key(&mut self, node: &Node, name: &str, parent: u32, inherited: &[u8]) -> u32 {
+        let (name_bytes, compressed) = encode_name(name);
+        let offset = self.alloc(76 + name_bytes.len());
+        let descriptor = if node.meta.security.is_empty() {
+            inherited
+        } else {
+            &node.meta.security
+        };
+        let security = self.security(descriptor);
+        let class = node.meta.class.as_deref().map(encode_utf16);
+        let class_offset = class.as_ref().map_or(NO_CELL, |class| self.cell(class));
+
+        let mut value_offsets = Vec::with_capacity(node.values.len());
+        let mut max_value_name = 0;
+        let mut max_value_data = 0;
+        for (name, value) in &node.values {
+            let (offset, size) = self.value(name, value);
+            value_offsets.extend_from_slice(&offset.to_le_bytes());
+            max_value_name = max_value_name.max(utf16_length(name));
+            max_value_data = max_value_data.max(size as u32);
+        }
+        let value_list = if value_offsets.is_empty() {
+            NO_CELL
+        } else {
+            self.cell(&value_offsets)
+        };
+
+        let mut children = node.children.iter().collect::<Vec<&Node>>();
+        children.sort_by_cached_key(|child| upcase(&child.name));
+        let child_offsets = children
+            .iter()
+            .map(|child| self.key(child, &child.name, offset as u32, descriptor))
+            .collect::<Vec<u32>>();
+        let subkey_list = self.subkey_list(&children, &child_offsets);
+        let max_subkey_name = children
+            .iter()
+            .map(|child| utf16_length(&child.name))
+            .max()
+            .unwrap_or(0);
+        let max_subkey_class = children
+            .iter()
+            .filter_map(|child| child.meta.class.as_deref())
+            .map(utf16_length)
+            .max()
+            .unwrap_or(0);
+
+        let written = match node.meta.written {
+            0 => self.now,
+            written => written,
+        };
+        let flags = (node.meta.flags & !KEY_COMP_NAME) | if compressed { KEY_COMP_NAME } else { 0 };
+        let mut nk = b"nk".to_vec();
+        nk.extend_from_slice(&flags.to_le_bytes());
+        nk.extend_from_slice(&written.to_le_bytes());
+        for field in [
+            0, // access bits
+            parent,
+            children.len() as u32,
+            0, // volatile subkeys
+            subkey_list,
+            NO_CELL,
+            node.values.len() as u32,
+            value_list,
+            security,
+            class_offset,
+            max_subkey_name,
+            max_subkey_class,
+            max_value_name,
+            max_value_data,
+            0, // work var
+        ] {
+            nk.extend_from_slice(&field.to_le_bytes());
+        }
+        nk.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+        let class_length = class.as_ref().map_or(0, |class| class.len() as u16);
+        nk.extend_from_slice(&class_length.to_le_bytes());
+        nk.extend_from_slice(&name_bytes);
+        self.bins[offset + 4..offset + 4 + nk.len()].copy_from_slice(&nk);
+        offset as u32
+    }
+}
+
+/// Serializes the hive, keeping the reserved fields of the original base block.
+fn save(base: &[u8], root: &Node, root_name: &str) -> Vec<u8> {
+    let mut writer = Writer {
+        bins: Vec::new(),
+        bin_end: 0,
+        minor: u32::from_le_bytes([base[24], base[25], base[26], base[27]]),
+        now: filetime_now(),
+        security: Vec::new(),
+        security_index: HashMap::new(),
+    };
+    let root_offset = writer.key(root, root_name, NO_CELL, &[]);
+    writer.link_security();
+    writer.close_bin();
+
+    let mut base = base.to_vec();
+    // Stale transaction logs have lower sequence numbers, so Windows won't replay them.
+    let sequence = u32::from_le_bytes([base[4], base[5], base[6], base[7]]).wrapping_add(1);
+    put_u32(&mut base, 4, sequence);
+    put_u32(&mut base, 8, sequence);
+    base[12..20].copy_from_slice(&writer.now.to_le_bytes());
+    put_u32(&mut base, 36, root_offset);
+    put_u32(&mut base, 40, writer.bins.len() as u32);
+    let checksum = checksum(&base);
+    put_u32(&mut base, 508, checksum);
+    [base, writer.bins].concat()
+}
+
+/// Where Windows loads the usual hives, from their file name.
+fn default_mount(path: &Path) -> Option<Key> {
+    let name = path.file_name()?.to_string_lossy().to_uppercase();
+    let key = match name.as_str() {
+        "NTUSER.DAT" => "HKEY_CURRENT_USER",
+        "USRCLASS.DAT" => "HKEY_CURRENT_USER\\Software\\Classes",
+        "DEFAULT" => "HKEY_USERS\\.DEFAULT",
+        "SOFTWARE" | "SYSTEM" | "SAM" | "SECURITY" | "COMPONENTS" => {
+            return Some(Key::parse("HKEY_LOCAL_MACHINE").ok()?.join(&name));
+        }
+        _ => return None,
+    };
+    Key::parse(key).ok()
+}
+
+/// A hive file edited in memory, its root key stands for `mount` (`HKLM\SOFTWARE` for a
+/// `SOFTWARE` hive) so profiles use the same keys as on a live system.
+pub struct HiveFile {
+    path: PathBuf,
+    mount: Key,
+    /// `ControlSet001`... which `CurrentControlSet` stands for in a `SYSTEM` hive.
+    control_set: Option<String>,
+    hive: MemoryHive,
+    /// The base block as read.
+    base: Vec<u8>,
+    root_name: String,
+    dirty: bool,
+}
+
+impl HiveFile {
+    /// Loads the hive, `mount` defaults to where Windows loads a hive of that file name.
+    pub fn open(path: &Path, mount: Option<Key>) -> Result<Self, String> {
+        let mount = match mount.or_else(|| default_mount(path)) {
+            Some(mount) => mount,
+            None => {
+                return Err(format!(
+                    "Cannot tell where {} is loaded from its name, pass --mount=<key>",
+                    path.display()
+                ));
+            }
+        };
+        let file =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let (base, root) = load(&file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        debug!("[registry] Loaded {} as {}", path.display(), mount);
+
+        let control_set = match (mount.root, mount.path.to_uppercase().as_str()) {
+            (Root::LocalMachine, "SYSTEM") => match root
+                .child("Select")
+                .and_then(|select| select.value("Current"))
+            {
+                Some(Value::Dword(current)) => Some(format!("ControlSet{:03}", current)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let root_name = root.name.clone();
+        let mut hive = MemoryHive::new();
+        if let Some(slot) = hive.node_mut(&mount, true) {
+            *slot = Node {
+                name: slot.name.clone(),
+                ..root
+            };
+        }
+        Ok(HiveFile {
+            path: path.to_path_buf(),
+            mount,
+            control_set,
+            hive,
+            base,
+            root_name,
+            dirty: false,
+        })
+    }
+
+    fn mount_depth(&self) -> usize {
+        self.mount
+            .path
+            .split('\\')
+            .filter(|p| !p.is_empty())
+            .count()
+    }
+
+    /// The key inside the hive, keys outside of it are an error.
+    fn inner(&self, key: &Key) -> Result<Key, String> {
+        let mut parts = key
+            .path
+            .split('\\')
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        let inside = key.root == self.mount.root
+            && self
+                .mount
+                .path
+                .split('\\')
+                .filter(|p| !p.is_empty())
+                .enumerate()
+                .all(|(i, part)| parts.get(i).is_some_and(|p| p.eq_ignore_ascii_case(part)));
+        if !inside {
+            return Err(format!(
+                "{} is outside of the hive {} loaded at {}",
+                key,
+                self.path.display(),
+                self.mount
+            ));
+        }
+        let depth = self.mount_depth();
+        if let Some(control_set) = &self.control_set
+            && let Some(part) = parts.get_mut(depth)
+            && part.eq_ignore_ascii_case("CurrentControlSet")
+        {
+            *part = control_set.clone();
+        }
+        Ok(Key {
+            root: key.root,
+            path: parts.join("\\"),
+        })
+    }
+
+    /// Marks the key as written now.
+    fn touch(&mut self, key: &Key) {
+        if let Some(node) = self.hive.node_mut(key, false) {
+            node.meta.written = 0;
+        }
+        self.dirty = true;
+    }
+
+    fn touch_parent(&mut self, key: &Key) {
+        match key.split() {
+            Some((parent, _)) => self.touch(&parent),
+            None => self.dirty = true,
+        }
+    }
+}
+
+impl Backend for HiveFile {
+    fn key_exists(&self, key: &Key) -> Result<bool, String> {
+        self.hive.key_exists(&self.inner(key)?)
+    }
+
+    fn create_key(&mut self, key: &Key) -> Result<(), String> {
+        let key = self.inner(key)?;
+        self.hive.create_key(&key)?;
+        self.touch_parent(&key);
+        Ok(())
+    }
+
+    fn delete_key(&mut self, key: &Key) -> Result<(), String> {
+        let inner = self.inner(key)?;
+        if inner.path.split('\\').filter(|p| !p.is_empty()).count() <= self.mount_depth() {
+            return Err(format!(
+                "Refusing to delete the root key of the hive {}",
+                key
+            ));
+        }
+        self.hive.delete_key(&inner)?;
+        self.touch_parent(&inner);
+        Ok(())
+    }
+
+    fn subkeys(&self, key: &Key) -> Result<Vec<String>, String> {
+        self.hive.subkeys(&self.inner(key)?)
+    }
+
+    fn values(&self, key: &Key) -> Result<Vec<(String, Value)>, String> {
+        self.hive.values(&self.inner(key)?)
+    }
+
+    fn get_value(&self, key: &Key, name: &str) -> Result<Option<Value>, String> {
+        self.hive.get_value(&self.inner(key)?, name)
+    }
+
+    fn set_value(&mut self, key: &Key, name: &str, value: &Value) -> Result<(), String> {
+        let key = self.inner(key)?;
+        self.hive.set_value(&key, name, value)?;
+        self.touch(&key);
+        Ok(())
+    }
+
+    fn delete_value(&mut self, key: &Key, name: &str) -> Result<(), String> {
+        let key = self.inner(key)?;
+        self.hive.delete_value(&key, name)?;
+        self.touch(&key);
+        Ok(())
+    }
+
+    /// Rewrites the whole hive through a temporary file.
+    fn flush(&mut self) -> Result<(), String> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let root = self
+            .hive
+            .node(&self.mount)
+            .ok_or_else(|| format!("The root key of {} is gone", self.path.display()))?;
+        let bytes = save(&self.base, root, &self.root_name);
+        let mut temp = self.path.as_os_str().to_owned();
+        temp.push(".trx8-part");
+        let temp = PathBuf::from(temp);
+        fs::write(&temp, &bytes)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        self.base = bytes[..BASE_BLOCK_SIZE].to_vec();
+        self.dirty = false;
+        info!("[registry] Saved {}", self.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::REG_MULTI_SZ;
+
+    /// A small `NTUSER.DAT` written without Trx8. `Software` has a class name, `Software\Trx8`
+    /// its own security descriptor and a value of each type with `Big` split in `db` segments,
+    /// and `Software\Many` lists its five keys through an `ri` index of an `lh` and an `li` leaf.
+    const FIXTURE: &[u8] = include_bytes!("../../tests/fixtures/NTUSER.DAT");
+    /// The last write time of every key of the fixture.
+    const FIXTURE_TIME: u64 = 0x01DA_1234_5678_9ABC;
+
+    fn fixture(dir: &Path) -> PathBuf {
+        let path = dir.join("NTUSER.DAT");
+        fs::write(&path, FIXTURE).unwrap();
+        path
+    }
+
+    fn key(path: &str) -> Key {
+        Key::parse(path).unwrap()
+    }
+
+    fn meta<'a>(hive: &'a HiveFile, path: &str) -> &'a Meta {
+        &hive.hive.node(&key(path)).unwrap().meta
+    }
+
+    /// The signature of the subkey list of a key, to see how it was written.
+    fn list_signature(file: &[u8], path: &[&str]) -> [u8; 2] {
+        let reader = Reader {
+            bins: &file[BASE_BLOCK_SIZE..],
+            minor: u32_at(file, 24).unwrap(),
+        };
+        let mut offset = u32_at(file, 36).unwrap();
+        for part in path {
+            let mut children = Vec::new();
+            let list = u32_at(reader.cell(offset).unwrap(), 28).unwrap();
+            reader.subkeys(list, &mut children, false).unwrap();
+            offset = children
+                .into_iter()
+                .find(|&child| reader.key(child, 0).unwrap().name == *part)
+                .unwrap();
+        }
+        let list = u32_at(reader.cell(offset).unwrap(), 28).unwrap();
+        let signature = reader.cell(list).unwrap();
+        [signature[0], signature[1]]
+    }
+
+    #[test]
+    fn reads_the_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let hive = HiveFile::open(&fixture(dir.path()), None).unwrap();
+        let trx8 = key(r"HKCU\Software\Trx8");
+        let get = |name: &str| hive.get_value(&trx8, name).unwrap().unwrap();
+        assert_eq!(get(""), Value::String("default".to_string()));
+        assert_eq!(get("count"), Value::Dword(42));
+        assert_eq!(get("Name"), Value::String("Zoë".to_string()));
+        assert_eq!(get("Ünïcode✓"), Value::String("x".to_string()));
+        assert_eq!(
+            get("List"),
+            Value::MultiString(vec!["a".to_string(), "b".to_string()])
+        );
+        let big = (0..40000).map(|i| (i * 7 % 256) as u8).collect::<Vec<u8>>();
+        assert_eq!(get("Big"), Value::Binary(big));
+        assert_eq!(
+            hive.get_value(&key(r"HKCU\Environment"), "Path").unwrap(),
+            Some(Value::ExpandString(r"%USERPROFILE%\bin".to_string()))
+        );
+        assert_eq!(
+            hive.subkeys(&key(r"HKCU\Software\Many")).unwrap(),
+            ["Key00", "Key01", "Key02", "Key03", "Key04"]
+        );
+        assert_eq!(list_signature(FIXTURE, &["Software", "Many"]), *b"ri");
+
+        let software = meta(&hive, r"HKCU\Software");
+        assert_eq!(software.class.as_deref(), Some("Trx8Class"));
+        assert_eq!(software.written, FIXTURE_TIME);
+        assert!(!software.security.is_empty());
+        assert_ne!(
+            meta(&hive, r"HKCU\Software\Trx8").security,
+            software.security
+        );
+    }
+
+    #[test]
+    fn round_trips_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path());
+        let mut hive = HiveFile::open(&path, None).unwrap();
+        let software_security = meta(&hive, r"HKCU\Software").security.clone();
+        let trx8_security = meta(&hive, r"HKCU\Software\Trx8").security.clone();
+
+        let trx8 = key(r"HKCU\Software\Trx8");
+        let large = (0..50000).map(|i| (i % 13) as u8).collect::<Vec<u8>>();
+        hive.set_value(&trx8, "Count", &Value::Dword(7)).unwrap();
+        hive.set_value(&trx8, "Large", &Value::Binary(large.clone()))
+            .unwrap();
+        hive.delete_value(&trx8, "Name").unwrap();
+        hive.create_key(&trx8.join("Sub")).unwrap();
+        hive.delete_key(&key(r"HKCU\Software\Many\Key02")).unwrap();
+        hive.flush().unwrap();
+        assert!(!dir.path().join("NTUSER.DAT.trx8-part").exists());
+
+        let saved = fs::read(&path).unwrap();
+        assert_eq!(u32_at(&saved, 508).unwrap(), checksum(&saved));
+        // Both sequence numbers move past the ones of the original file.
+        assert_eq!(u32_at(&saved, 4).unwrap(), u32_at(FIXTURE, 4).unwrap() + 1);
+        assert_eq!(u32_at(&saved, 8).unwrap(), u32_at(&saved, 4).unwrap());
+
+        let hive = HiveFile::open(&path, None).unwrap();
+        let get = |name: &str| hive.get_value(&trx8, name).unwrap();
+        assert_eq!(get("Count"), Some(Value::Dword(7)));
+        assert_eq!(get("Large"), Some(Value::Binary(large)));
+        assert_eq!(get("Name"), None);
+        assert_eq!(get("List").map(|v| v.kind()), Some(REG_MULTI_SZ));
+        assert_eq!(get("Big").map(|v| v.to_raw().len()), Some(40000));
+        assert!(hive.key_exists(&trx8.join("Sub")).unwrap());
+        assert_eq!(
+            hive.subkeys(&key(r"HKCU\Software\Many")).unwrap(),
+            ["Key00", "Key01", "Key03", "Key04"]
+        );
+
+        // Descriptors are kept, and new keys inherit the one of their parent.
+        assert_eq!(meta(&hive, r"HKCU\Software").security, software_security);
+        assert_eq!(meta(&hive, r"HKCU\Software\Trx8").security, trx8_security);
+        assert_eq!(
+            meta(&hive, r"HKCU\Software\Trx8\Sub").security,
+            trx8_security
+        );
+        assert_eq!(
+            meta(&hive, r"HKCU\Software").class.as_deref(),
+            Some("Trx8Class")
+        );
+        // Only the changed keys get a new write time.
+        assert_eq!(meta(&hive, r"HKCU\Environment").written, FIXTURE_TIME);
+        assert_ne!(meta(&hive, r"HKCU\Software\Trx8").written, FIXTURE_TIME);
+    }
+
+    #[test]
+    fn unchanged_hives_are_not_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path());
+        let mut hive = HiveFile::open(&path, None).unwrap();
+        hive.get_value(&key(r"HKCU\Environment"), "Path").unwrap();
+        hive.flush().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), FIXTURE);
+    }
+
+    #[test]
+    fn many_subkeys_use_an_ri_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path());
+        let mut hive = HiveFile::open(&path, None).unwrap();
+        let wide = key(r"HKCU\Software\Wide");
+        for i in (0..LEAF_SIZE + 100).rev() {
+            hive.create_key(&wide.join(&format!("Key{:04}", i)))
+                .unwrap();
+        }
+        hive.flush().unwrap();
+
+        let saved = fs::read(&path).unwrap();
+        assert_eq!(list_signature(&saved, &["Software", "Wide"]), *b"ri");
+        let hive = HiveFile::open(&path, None).unwrap();
+        let subkeys = hive.subkeys(&wide).unwrap();
+        assert_eq!(subkeys.len(), LEAF_SIZE + 100);
+        // Written sorted, like Windows expects.
+        assert_eq!(subkeys[0], "Key0000");
+        assert_eq!(subkeys[LEAF_SIZE + 99], "Key1123");
+    }
+
+    #[test]
+    fn rejects_a_bad_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("NTUSER.DAT");
+        let mut file = FIXTURE.to_vec();
+        file[200] ^= 1;
+        fs::write(&path, file).unwrap();
+        let error = HiveFile::open(&path, None).err().unwrap();
+        assert!(error.ends_with("Invalid base block checksum"), "{}", error);
+    }
+
+    #[test]
+    fn refuses_a_dirty_hive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("NTUSER.DAT");
+        // Windows bumps the first sequence number before writing to the logs, and the second
+        // once the hive file itself is written.
+        let mut file = FIXTURE.to_vec();
+        let sequence = u32_at(&file, 4).unwrap();
+        put_u32(&mut file, 4, sequence + 1);
+        let sum = checksum(&file);
+        put_u32(&mut file, 508, sum);
+        fs::write(&path, file).unwrap();
+        fs::write(dir.path().join("NTUSER.DAT.LOG1"), b"DIRT").unwrap();
+        fs::write(dir.path().join("NTUSER.DAT.LOG2"), b"").unwrap();
+
+        let error = HiveFile::open(&path, None).err().unwrap();
+        assert!(error.contains("transaction logs"), "{}", error);
+    }
+
+    #[test]
+    fn keys_outside_of_the_hive_are_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut hive = HiveFile::open(&fixture(dir.path()), None).unwrap();
+        let error = hive
+            .set_value(&key(r"HKLM\Software\Trx8"), "a", &Value::Dword(1))
+            .unwrap_err();
+        assert!(error.contains("is outside of the hive"), "{}", error);
+        assert!(hive.delete_key(&key("HKCU")).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+/// A summary of a run, written as JSON to the user cache directory once the run finishes.
+use chrono::Local;
+use serde::Serialize;
+use std::fs::create_dir_all;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::constants;
+use crate::reboot;
+use crate::secrets;
+use crate::step::StepChoice;
+use crate::tools::ActionStatus;
+
+#[derive(Serialize, Debug)]
+pub struct RunReport {
+    pub trx8_version: String,
+    pub profile: String,
+    pub profile_version: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub aborted: bool,
+    /// Whether actions asked for a reboot, see [`reboot::request`].
+    pub reboot_required: bool,
+    pub reboot_reasons: Vec<String>,
+    pub actions: Vec<ActionRecord>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ActionRecord {
+    pub script: String,
+    pub script_index: usize,
+    pub index: usize,
+    pub name: String,
+    pub status: ActionStatus,
+    /// Only present when running with `--step`.
+    pub step: Option<StepRecord>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StepRecord {
+    pub choice: StepChoice,
+    /// The parameters entered by the operator, if they were edited.
+    pub edited_parameters: Option<Vec<String>>,
+}
+
+impl RunReport {
+    pub fn new(config: &Config) -> Self {
+        RunReport {
+            trx8_version: env!("CARGO_PKG_VERSION").to_string(),
+            profile: config.metadata.name.clone(),
+            profile_version: config.metadata.version.clone(),
+            started_at: Local::now().to_rfc3339(),
+            finished_at: None,
+            aborted: false,
+            reboot_required: false,
+            reboot_reasons: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, record: ActionRecord) {
+        self.actions.push(record);
+    }
+
+    pub fn failed(&self) -> usize {
+        self.actions
+            .iter()
+            .filter(|a| a.status == ActionStatus::Failed)
+            .count()
+    }
+
+    pub fn success(&self) -> bool {
+        !self.aborted && self.failed() == 0
+    }
+
+    pub fn finish(&mut self, aborted: bool) {
+        self.aborted = aborted;
+        self.reboot_reasons = reboot::reasons();
+        self.reboot_required = !self.reboot_reasons.is_empty();
+        self.finished_at = Some(Local::now().to_rfc3339());
+    }
+
+    /// Writes the report to `<cache dir>/report/<file name>`, where the file name is a `chrono` format string.
+    ///
+    /// Secrets, e.g. in edited step parameters, are masked.
+    pub fn save(&self, file_name: &str) -> std::io::Result<PathBuf> {
+        let report_dir = constants::PROJECT_DIR.cache_dir().join("report");
+        if !report_dir.exists() {
+            create_dir_all(&report_dir)?;
+        }
+        let path = report_dir.join(Local::now().format(file_name).to_string());
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, secrets::redact(&json).as_bytes())?;
+        Ok(path)
+    }
+}
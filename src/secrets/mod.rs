@@ -0,0 +1,149 @@
+/// Secret values usable in parameters as `${secrets.<name>}`, masked as `***` in all output.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, trace, warn};
+
+use crate::config::{Config, Secret, SecretSource};
+
+mod redact;
+mod store;
+
+pub use redact::{Redacting, redact, register};
+pub use store::Store;
+
+/// The environment variable the passphrase of the secrets file is read from before prompting.
+pub const PASSPHRASE_ENV: &str = "TRX8_SECRETS_PASSPHRASE";
+
+/// The default environment variable for `env` secrets, e.g. `TRX8_SECRET_API_TOKEN`.
+pub fn env_name(secret: &Secret) -> String {
+    match &secret.env {
+        Some(env) => env.clone(),
+        None => format!(
+            "TRX8_SECRET_{}",
+            secret
+                .name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                })
+                .collect::<String>()
+        ),
+    }
+}
+
+fn read_hidden(prompt: &str) -> Result<String, String> {
+    warn!("{}:", prompt);
+    rpassword::read_password().map_err(|e| e.to_string())
+}
+
+/// Reads the passphrase from the environment, or asks for it if `interactive`.
+pub fn passphrase(path: &Path, interactive: bool) -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    if !interactive {
+        return Err(format!(
+            "No passphrase for the secrets file {}, set {}",
+            path.display(),
+            PASSPHRASE_ENV
+        ));
+    }
+    read_hidden(&format!("Passphrase for {}", path.display()))
+}
+
+/// Collects every secret declared in the configuration and registers it for masking.
+///
+/// Files are resolved relative to `base_dir` (the directory of the configuration file) and
+/// each one is only opened once.
+pub fn collect(
+    config: &Config,
+    base_dir: &Path,
+    interactive: bool,
+) -> Result<HashMap<String, String>, String> {
+    let mut stores: HashMap<PathBuf, Store> = HashMap::new();
+    let mut values = HashMap::new();
+    for secret in config.secrets.iter().flatten() {
+        let value = match secret.source {
+            SecretSource::Env => std::env::var(env_name(secret)).map_err(|_| {
+                format!(
+                    "Missing secret {}, set the {} environment variable",
+                    secret.name,
+                    env_name(secret)
+                )
+            })?,
+            SecretSource::Prompt => {
+                if !interactive {
+                    return Err(format!(
+                        "Secret {} has to be entered but prompts are disabled",
+                        secret.name
+                    ));
+                }
+                read_hidden(secret.prompt.as_deref().unwrap_or(&secret.name))?
+            }
+            SecretSource::File => {
+                let Some(file) = &secret.file else {
+                    return Err(format!("Secret {} has no file", secret.name));
+                };
+                let path = base_dir.join(file);
+                if !stores.contains_key(&path) {
+                    let store = Store::open(&path, &passphrase(&path, interactive)?)
+                        .map_err(|e| format!("{}: {}", path.display(), e))?;
+                    stores.insert(path.clone(), store);
+                }
+                stores[&path]
+                    .get(&secret.name)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+        };
+        register(&value);
+        trace!("Secret {} loaded", secret.name);
+        values.insert(format!("secrets.{}", secret.name), value);
+    }
+    Ok(values)
+}
+
+fn open_or_create(path: &Path) -> Result<Store, String> {
+    if path.exists() {
+        return Store::open(path, &passphrase(path, true)?);
+    }
+    info!("Creating new secrets file at: {}", path.display());
+    let passphrase = passphrase(path, true)?;
+    if std::env::var(PASSPHRASE_ENV).is_err()
+        && read_hidden("Confirm the passphrase")? != passphrase
+    {
+        return Err("Passphrases do not match".to_string());
+    }
+    Store::create(&passphrase)
+}
+
+/// `trx8 secret set <file> <name>`, the value is read without echo.
+pub fn cli_set(path: &Path, name: &str) -> Result<(), String> {
+    let mut store = open_or_create(path)?;
+    let value = read_hidden(&format!("Value for {}", name))?;
+    store.set(name, &value)?;
+    store.save(path)?;
+    info!("Secret {} saved to {}", name, path.display());
+    Ok(())
+}
+
+/// `trx8 secret remove <file> <name>`
+pub fn cli_remove(path: &Path, name: &str) -> Result<(), String> {
+    let mut store = Store::open(path, &passphrase(path, true)?)?;
+    if !store.remove(name) {
+        return Err(format!("No secret named {}", name));
+    }
+    store.save(path)?;
+    info!("Secret {} removed from {}", name, path.display());
+    Ok(())
+}
+
+/// `trx8 secret list <file>`, only prints the names.
+pub fn cli_list(path: &Path) -> Result<(), String> {
+    let store = Store::open(path, &passphrase(path, true)?)?;
+    for name in store.names() {
+        info!("{}", name);
+    }
+    Ok(())
+}
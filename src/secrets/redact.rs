@@ -0,0 +1,57 @@
+/// Masking of secret values in everything Trx8 writes out.
+use std::borrow::Cow;
+use std::io::Write;
+use std::sync::RwLock;
+
+pub const MASK: &str = "***";
+
+static REDACTED: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Registers a value to be masked from now on, empty values are ignored.
+pub fn register(value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    let mut redacted = REDACTED.write().unwrap();
+    // Also mask the escaped form, as found in the JSON events and reports.
+    let escaped = serde_json::to_string(value).unwrap_or_default();
+    let escaped = escaped.trim_matches('"');
+    for variant in [value, escaped] {
+        if !variant.is_empty() && !redacted.iter().any(|v| v == variant) {
+            redacted.push(variant.to_string());
+        }
+    }
+    // Longer values first, so a secret containing another one is fully masked.
+    redacted.sort_by_key(|v| std::cmp::Reverse(v.len()));
+}
+
+/// Replaces every registered secret in the text with `***`.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let redacted = REDACTED.read().unwrap();
+    if !redacted.iter().any(|v| text.contains(v.as_str())) {
+        return Cow::Borrowed(text);
+    }
+    let mut text = text.to_string();
+    for value in redacted.iter() {
+        text = text.replace(value.as_str(), MASK);
+    }
+    Cow::Owned(text)
+}
+
+/// A writer that masks secrets before passing the data on, used for the log outputs.
+///
+/// `tracing-subscriber` writes each formatted event at once, so a secret is never split
+/// between two writes.
+pub struct Redacting<W: Write>(pub W);
+
+impl<W: Write> Write for Redacting<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
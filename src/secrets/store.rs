@@ -0,0 +1,124 @@
+/// A local file of secrets encrypted with a passphrase (Argon2id + ChaCha20-Poly1305).
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Encrypted with the derived key to tell a wrong passphrase apart from a corrupted entry.
+const CHECK: &[u8] = b"trx8";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Encrypted {
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StoreFile {
+    version: u32,
+    salt: String,
+    check: Encrypted,
+    entries: BTreeMap<String, Encrypted>,
+}
+
+pub struct Store {
+    file: StoreFile,
+    cipher: ChaCha20Poly1305,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+fn encrypt(cipher: &ChaCha20Poly1305, plaintext: &[u8]) -> Result<Encrypted, String> {
+    let mut nonce = [0u8; 12];
+    getrandom::getrandom(&mut nonce).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|e| format!("Failed to encrypt: {}", e))?;
+    Ok(Encrypted {
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    })
+}
+
+fn decrypt(cipher: &ChaCha20Poly1305, encrypted: &Encrypted) -> Result<Vec<u8>, String> {
+    let nonce = hex::decode(&encrypted.nonce).map_err(|e| e.to_string())?;
+    let ciphertext = hex::decode(&encrypted.ciphertext).map_err(|e| e.to_string())?;
+    if nonce.len() != 12 {
+        return Err("Invalid nonce length".to_string());
+    }
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "Failed to decrypt, wrong passphrase or corrupted file".to_string())
+}
+
+impl Store {
+    pub fn create(passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; 16];
+        getrandom::getrandom(&mut salt).map_err(|e| e.to_string())?;
+        let cipher = derive_key(passphrase, &salt)?;
+        let check = encrypt(&cipher, CHECK)?;
+        Ok(Store {
+            file: StoreFile {
+                version: 1,
+                salt: hex::encode(salt),
+                check,
+                entries: BTreeMap::new(),
+            },
+            cipher,
+        })
+    }
+
+    pub fn open(path: &Path, passphrase: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: StoreFile = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        if file.version != 1 {
+            return Err(format!(
+                "Unsupported secrets file version: {}",
+                file.version
+            ));
+        }
+        let salt = hex::decode(&file.salt).map_err(|e| e.to_string())?;
+        let cipher = derive_key(passphrase, &salt)?;
+        if decrypt(&cipher, &file.check).ok().as_deref() != Some(CHECK) {
+            return Err("Wrong passphrase for the secrets file".to_string());
+        }
+        Ok(Store { file, cipher })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.file).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.file.entries.keys()
+    }
+
+    pub fn get(&self, name: &str) -> Result<String, String> {
+        let entry = self
+            .file
+            .entries
+            .get(name)
+            .ok_or_else(|| format!("No secret named {} in the secrets file", name))?;
+        let plaintext = decrypt(&self.cipher, entry)?;
+        String::from_utf8(plaintext).map_err(|e| e.to_string())
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let entry = encrypt(&self.cipher, value.as_bytes())?;
+        self.file.entries.insert(name.to_string(), entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.file.entries.remove(name).is_some()
+    }
+}
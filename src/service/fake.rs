@@ -0,0 +1,104 @@
+/// Services that only exist in a JSON file like
+/// `{"<name>": {"state": "running", "start_type": "auto"}}`. State changes are immediate and
+/// written back right away.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::{Backend, StartType, State};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FakeService {
+    pub state: State,
+    pub start_type: StartType,
+}
+
+pub struct FakeServices {
+    path: PathBuf,
+    services: BTreeMap<String, FakeService>,
+}
+
+impl FakeServices {
+    /// Loads the services, a missing file has none.
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        let services = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid fake services file {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        Ok(FakeServices { path, services })
+    }
+
+    fn service(&self, name: &str) -> Result<&FakeService, String> {
+        self.services
+            .get(name)
+            .ok_or_else(|| format!("Service {} does not exist", name))
+    }
+
+    /// Applies `change` to the service and saves the file.
+    fn update(&mut self, name: &str, change: impl FnOnce(&mut FakeService)) -> Result<(), String> {
+        let service = self
+            .services
+            .get_mut(name)
+            .ok_or_else(|| format!("Service {} does not exist", name))?;
+        change(service);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.services).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+impl Backend for FakeServices {
+    fn exists(&self, name: &str) -> Result<bool, String> {
+        Ok(self.services.contains_key(name))
+    }
+
+    fn state(&self, name: &str) -> Result<State, String> {
+        Ok(self.service(name)?.state)
+    }
+
+    fn start_type(&self, name: &str) -> Result<StartType, String> {
+        Ok(self.service(name)?.start_type)
+    }
+
+    fn start(&mut self, name: &str) -> Result<(), String> {
+        if self.service(name)?.start_type == StartType::Disabled {
+            return Err(format!("Service {} is disabled", name));
+        }
+        // Like the Service Control Manager, a paused service is left paused.
+        self.update(name, |service| {
+            if service.state != State::Paused {
+                service.state = State::Running;
+            }
+        })
+    }
+
+    fn resume(&mut self, name: &str) -> Result<(), String> {
+        self.update(name, |service| {
+            if service.state == State::Paused {
+                service.state = State::Running;
+            }
+        })
+    }
+
+    fn stop(&mut self, name: &str) -> Result<(), String> {
+        self.update(name, |service| service.state = State::Stopped)
+    }
+
+    fn set_start_type(&mut self, name: &str, start_type: StartType) -> Result<(), String> {
+        self.update(name, |service| service.start_type = start_type)
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), String> {
+        self.service(name)?;
+        self.services.remove(name);
+        self.save()
+    }
+}
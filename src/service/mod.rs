@@ -0,0 +1,336 @@
+/// Service management behind a [`Backend`] trait: the Windows Service Control Manager, systemd
+/// on Linux, and a fake kept in a JSON file to try profiles without touching the system.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+mod fake;
+#[cfg(target_os = "windows")]
+mod scm;
+#[cfg(target_os = "linux")]
+mod systemd;
+
+pub use fake::FakeServices;
+#[cfg(target_os = "linux")]
+pub use systemd::systemctl;
+
+/// Uses the fake backend with this state file instead of the system services when set.
+pub const FAKE_ENV: &str = "TRX8_FAKE_SERVICES";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    Stopped,
+    Starting,
+    Running,
+    Stopping,
+    Paused,
+}
+
+impl State {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "stopped" | "inactive" => Ok(State::Stopped),
+            "starting" | "activating" => Ok(State::Starting),
+            "running" | "active" => Ok(State::Running),
+            "stopping" | "deactivating" => Ok(State::Stopping),
+            "paused" => Ok(State::Paused),
+            _ => Err(format!("Unknown service state: {}", name)),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            State::Stopped => "stopped",
+            State::Starting => "starting",
+            State::Running => "running",
+            State::Stopping => "stopping",
+            State::Paused => "paused",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// When a service is started. systemd only knows enabled (`Automatic`), disabled (`Manual`) and
+/// masked (`Disabled`) units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartType {
+    Boot,
+    System,
+    #[serde(rename = "auto")]
+    Automatic,
+    #[serde(rename = "delayed-auto")]
+    DelayedAutomatic,
+    Manual,
+    Disabled,
+}
+
+impl StartType {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().replace(['-', '_'], "").as_str() {
+            "boot" => Ok(StartType::Boot),
+            "system" => Ok(StartType::System),
+            "auto" | "automatic" | "enabled" => Ok(StartType::Automatic),
+            "delayedauto" | "delayedautomatic" => Ok(StartType::DelayedAutomatic),
+            "manual" | "demand" => Ok(StartType::Manual),
+            "disabled" | "masked" => Ok(StartType::Disabled),
+            _ => Err(format!("Unknown service start type: {}", name)),
+        }
+    }
+}
+
+impl fmt::Display for StartType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            StartType::Boot => "boot",
+            StartType::System => "system",
+            StartType::Automatic => "auto",
+            StartType::DelayedAutomatic => "delayed-auto",
+            StartType::Manual => "manual",
+            StartType::Disabled => "disabled",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A service manager. Every method but [`Backend::exists`] fails for a missing service, and the
+/// control methods only ask for the change, see [`wait_for`].
+pub trait Backend {
+    fn exists(&self, name: &str) -> Result<bool, String>;
+    fn state(&self, name: &str) -> Result<State, String>;
+    fn start_type(&self, name: &str) -> Result<StartType, String>;
+    fn start(&mut self, name: &str) -> Result<(), String>;
+    fn stop(&mut self, name: &str) -> Result<(), String>;
+    /// Continues a paused service, which can't be started again.
+    fn resume(&mut self, name: &str) -> Result<(), String>;
+    fn set_start_type(&mut self, name: &str, start_type: StartType) -> Result<(), String>;
+    /// The start type [`Backend::start_type`] reports once `start_type` is set, for start types
+    /// the manager has no own state for.
+    fn stored(&self, start_type: StartType) -> StartType {
+        start_type
+    }
+    /// Removes the service, which has to be stopped first.
+    fn delete(&mut self, name: &str) -> Result<(), String>;
+}
+
+/// Waits until the service is in `state`, failing after `timeout`.
+pub fn wait_for(
+    backend: &dyn Backend,
+    name: &str,
+    state: State,
+    timeout: Duration,
+) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let current = backend.state(name)?;
+        if current == state {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "{} is still {} after {} seconds, expected {}",
+                name,
+                current,
+                timeout.as_secs(),
+                state
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Starts the service unless it is running, returns whether it was started.
+pub fn ensure_running(
+    backend: &mut dyn Backend,
+    name: &str,
+    timeout: Duration,
+) -> Result<bool, String> {
+    match backend.state(name)? {
+        State::Running => return Ok(false),
+        State::Starting => {}
+        // A stop in progress has to finish before the service can be started again.
+        State::Stopping => {
+            wait_for(backend, name, State::Stopped, timeout)?;
+            backend.start(name)?;
+        }
+        State::Stopped => backend.start(name)?,
+        State::Paused => backend.resume(name)?,
+    }
+    debug!("[service] Waiting for {} to start", name);
+    wait_for(backend, name, State::Running, timeout)?;
+    Ok(true)
+}
+
+/// Stops the service unless it is stopped, returns whether it was stopped.
+pub fn ensure_stopped(
+    backend: &mut dyn Backend,
+    name: &str,
+    timeout: Duration,
+) -> Result<bool, String> {
+    match backend.state(name)? {
+        State::Stopped => return Ok(false),
+        State::Stopping => {}
+        State::Starting => {
+            wait_for(backend, name, State::Running, timeout)?;
+            backend.stop(name)?;
+        }
+        State::Running | State::Paused => backend.stop(name)?,
+    }
+    debug!("[service] Waiting for {} to stop", name);
+    wait_for(backend, name, State::Stopped, timeout)?;
+    Ok(true)
+}
+
+/// Sets the start type if it differs, returns whether it changed.
+pub fn ensure_start_type(
+    backend: &mut dyn Backend,
+    name: &str,
+    start_type: StartType,
+) -> Result<bool, String> {
+    if backend.start_type(name)? == backend.stored(start_type) {
+        return Ok(false);
+    }
+    backend.set_start_type(name, start_type)?;
+    Ok(true)
+}
+
+/// The service manager of the system, or the fake one when [`FAKE_ENV`] is set.
+pub fn open() -> Result<Box<dyn Backend>, String> {
+    if let Ok(path) = std::env::var(FAKE_ENV) {
+        return Ok(Box::new(FakeServices::open(path.into())?));
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Ok(Box::new(scm::Scm::connect()?))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Box::new(systemd::Systemd))
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        Err("Services can only be managed on Windows and Linux".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    fn fake(dir: &Path) -> FakeServices {
+        let path = dir.join("services.json");
+        fs::write(
+            &path,
+            r#"{
+                "running": {"state": "running", "start_type": "auto"},
+                "stopped": {"state": "stopped", "start_type": "manual"},
+                "paused": {"state": "paused", "start_type": "auto"},
+                "starting": {"state": "starting", "start_type": "auto"},
+                "disabled": {"state": "stopped", "start_type": "disabled"}
+            }"#,
+        )
+        .unwrap();
+        FakeServices::open(path).unwrap()
+    }
+
+    /// The services as saved, to see the changes were written.
+    fn saved(dir: &Path) -> FakeServices {
+        FakeServices::open(dir.join("services.json")).unwrap()
+    }
+
+    #[test]
+    fn starts_services() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut services = fake(dir.path());
+        assert_eq!(ensure_running(&mut services, "stopped", TIMEOUT), Ok(true));
+        assert_eq!(ensure_running(&mut services, "stopped", TIMEOUT), Ok(false));
+        assert_eq!(ensure_running(&mut services, "running", TIMEOUT), Ok(false));
+        // A paused service is resumed, starting it would leave it paused.
+        assert_eq!(ensure_running(&mut services, "paused", TIMEOUT), Ok(true));
+
+        let saved = saved(dir.path());
+        assert_eq!(saved.state("stopped"), Ok(State::Running));
+        assert_eq!(saved.state("paused"), Ok(State::Running));
+    }
+
+    #[test]
+    fn disabled_services_fail_to_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut services = fake(dir.path());
+        assert_eq!(
+            ensure_running(&mut services, "disabled", TIMEOUT),
+            Err("Service disabled is disabled".to_string())
+        );
+        assert_eq!(saved(dir.path()).state("disabled"), Ok(State::Stopped));
+        assert_eq!(
+            ensure_running(&mut services, "missing", TIMEOUT),
+            Err("Service missing does not exist".to_string())
+        );
+    }
+
+    #[test]
+    fn stops_services() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut services = fake(dir.path());
+        assert_eq!(ensure_stopped(&mut services, "running", TIMEOUT), Ok(true));
+        assert_eq!(ensure_stopped(&mut services, "running", TIMEOUT), Ok(false));
+        assert_eq!(ensure_stopped(&mut services, "paused", TIMEOUT), Ok(true));
+        assert_eq!(
+            ensure_stopped(&mut services, "disabled", TIMEOUT),
+            Ok(false)
+        );
+
+        let saved = saved(dir.path());
+        assert_eq!(saved.state("running"), Ok(State::Stopped));
+        assert_eq!(saved.state("paused"), Ok(State::Stopped));
+    }
+
+    #[test]
+    fn sets_start_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut services = fake(dir.path());
+        assert_eq!(
+            ensure_start_type(&mut services, "stopped", StartType::Automatic),
+            Ok(true)
+        );
+        assert_eq!(
+            ensure_start_type(&mut services, "stopped", StartType::Automatic),
+            Ok(false)
+        );
+        assert_eq!(
+            saved(dir.path()).start_type("stopped"),
+            Ok(StartType::Automatic)
+        );
+        // Once it is no longer disabled, the service can be started.
+        assert_eq!(
+            ensure_start_type(&mut services, "disabled", StartType::Manual),
+            Ok(true)
+        );
+        assert_eq!(ensure_running(&mut services, "disabled", TIMEOUT), Ok(true));
+    }
+
+    #[test]
+    fn waits_time_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut services = fake(dir.path());
+        assert_eq!(
+            ensure_running(&mut services, "starting", Duration::ZERO),
+            Err("starting is still starting after 0 seconds, expected running".to_string())
+        );
+        assert_eq!(
+            wait_for(&services, "running", State::Running, Duration::ZERO),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,220 @@
+/// Windows services, through the Service Control Manager.
+use std::mem;
+use windows::Win32::Foundation::*;
+use windows::Win32::System::Services::*;
+use windows::core::{HSTRING, PCWSTR};
+
+use super::{Backend, StartType, State};
+
+/// Needed to delete a service, the generic `DELETE` access right.
+const DELETE: u32 = 0x0001_0000;
+
+/// A service or manager handle, closed on drop.
+struct Handle(SC_HANDLE);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseServiceHandle(self.0);
+        }
+    }
+}
+
+pub struct Scm {
+    manager: Handle,
+}
+
+impl Scm {
+    pub fn connect() -> Result<Self, String> {
+        let manager = unsafe {
+            OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CONNECT).map_err(|e| {
+                format!(
+                    "Failed to open the Service Control Manager: {}",
+                    e.message()
+                )
+            })?
+        };
+        Ok(Scm {
+            manager: Handle(manager),
+        })
+    }
+
+    /// Opens the service, `None` if it doesn't exist.
+    fn try_open(&self, name: &str, access: u32) -> Result<Option<Handle>, String> {
+        let service_name = HSTRING::from(name);
+        match unsafe { OpenServiceW(self.manager.0, &service_name, access) } {
+            Ok(handle) => Ok(Some(Handle(handle))),
+            Err(e) if e.code() == ERROR_SERVICE_DOES_NOT_EXIST.to_hresult() => Ok(None),
+            Err(e) => Err(format!("Failed to open service {}: {}", name, e.message())),
+        }
+    }
+
+    fn open(&self, name: &str, access: u32) -> Result<Handle, String> {
+        self.try_open(name, access)?
+            .ok_or_else(|| format!("Service {} does not exist", name))
+    }
+}
+
+fn query_status(service: &Handle, name: &str) -> Result<SERVICE_STATUS_PROCESS, String> {
+    let mut status = SERVICE_STATUS_PROCESS::default();
+    let mut needed = 0u32;
+    unsafe {
+        let buffer = std::slice::from_raw_parts_mut(
+            &mut status as *mut _ as *mut u8,
+            mem::size_of::<SERVICE_STATUS_PROCESS>(),
+        );
+        QueryServiceStatusEx(service.0, SC_STATUS_PROCESS_INFO, Some(buffer), &mut needed)
+            .map_err(|e| format!("Failed to query service {}: {}", name, e.message()))?;
+    }
+    Ok(status)
+}
+
+/// The start type in the service configuration, without the delayed flag.
+fn query_start_type(service: &Handle, name: &str) -> Result<SERVICE_START_TYPE, String> {
+    let error =
+        |e: windows::core::Error| format!("Failed to query service {}: {}", name, e.message());
+    let mut needed = 0u32;
+    unsafe {
+        if let Err(e) = QueryServiceConfigW(service.0, None, 0, &mut needed)
+            && e.code() != ERROR_INSUFFICIENT_BUFFER.to_hresult()
+        {
+            return Err(error(e));
+        }
+        // u64 elements keep the structure aligned, the strings follow it in the buffer.
+        let mut buffer = vec![0u64; (needed as usize).div_ceil(8)];
+        let config = buffer.as_mut_ptr() as *mut QUERY_SERVICE_CONFIGW;
+        QueryServiceConfigW(service.0, Some(config), needed, &mut needed).map_err(error)?;
+        Ok((*config).dwStartType)
+    }
+}
+
+fn query_delayed(service: &Handle, name: &str) -> Result<bool, String> {
+    let mut info = SERVICE_DELAYED_AUTO_START_INFO::default();
+    let mut needed = 0u32;
+    unsafe {
+        let buffer = std::slice::from_raw_parts_mut(
+            &mut info as *mut _ as *mut u8,
+            mem::size_of::<SERVICE_DELAYED_AUTO_START_INFO>(),
+        );
+        QueryServiceConfig2W(
+            service.0,
+            SERVICE_CONFIG_DELAYED_AUTO_START_INFO,
+            Some(buffer),
+            &mut needed,
+        )
+        .map_err(|e| format!("Failed to query service {}: {}", name, e.message()))?;
+    }
+    Ok(info.fDelayedAutostart.as_bool())
+}
+
+impl Backend for Scm {
+    fn exists(&self, name: &str) -> Result<bool, String> {
+        Ok(self.try_open(name, SERVICE_QUERY_STATUS)?.is_some())
+    }
+
+    fn state(&self, name: &str) -> Result<State, String> {
+        let service = self.open(name, SERVICE_QUERY_STATUS)?;
+        Ok(match query_status(&service, name)?.dwCurrentState {
+            SERVICE_RUNNING => State::Running,
+            SERVICE_START_PENDING | SERVICE_CONTINUE_PENDING => State::Starting,
+            SERVICE_STOP_PENDING => State::Stopping,
+            SERVICE_PAUSED | SERVICE_PAUSE_PENDING => State::Paused,
+            _ => State::Stopped,
+        })
+    }
+
+    fn start_type(&self, name: &str) -> Result<StartType, String> {
+        let service = self.open(name, SERVICE_QUERY_CONFIG)?;
+        Ok(match query_start_type(&service, name)? {
+            SERVICE_BOOT_START => StartType::Boot,
+            SERVICE_SYSTEM_START => StartType::System,
+            SERVICE_AUTO_START if query_delayed(&service, name)? => StartType::DelayedAutomatic,
+            SERVICE_AUTO_START => StartType::Automatic,
+            SERVICE_DISABLED => StartType::Disabled,
+            _ => StartType::Manual,
+        })
+    }
+
+    fn start(&mut self, name: &str) -> Result<(), String> {
+        let service = self.open(name, SERVICE_START)?;
+        match unsafe { StartServiceW(service.0, None) } {
+            Err(e) if e.code() != ERROR_SERVICE_ALREADY_RUNNING.to_hresult() => {
+                Err(format!("Failed to start service {}: {}", name, e.message()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn stop(&mut self, name: &str) -> Result<(), String> {
+        let service = self.open(name, SERVICE_STOP)?;
+        let mut status = SERVICE_STATUS::default();
+        match unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) } {
+            Err(e) if e.code() != ERROR_SERVICE_NOT_ACTIVE.to_hresult() => {
+                Err(format!("Failed to stop service {}: {}", name, e.message()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn resume(&mut self, name: &str) -> Result<(), String> {
+        let service = self.open(name, SERVICE_PAUSE_CONTINUE)?;
+        let mut status = SERVICE_STATUS::default();
+        unsafe { ControlService(service.0, SERVICE_CONTROL_CONTINUE, &mut status) }
+            .map_err(|e| format!("Failed to resume service {}: {}", name, e.message()))
+    }
+
+    fn set_start_type(&mut self, name: &str, start_type: StartType) -> Result<(), String> {
+        let service = self.open(name, SERVICE_QUERY_CONFIG | SERVICE_CHANGE_CONFIG)?;
+        let error = |e: windows::core::Error| {
+            format!("Failed to configure service {}: {}", name, e.message())
+        };
+        let value = match start_type {
+            StartType::Boot => SERVICE_BOOT_START,
+            StartType::System => SERVICE_SYSTEM_START,
+            StartType::Automatic | StartType::DelayedAutomatic => SERVICE_AUTO_START,
+            StartType::Manual => SERVICE_DEMAND_START,
+            StartType::Disabled => SERVICE_DISABLED,
+        };
+        unsafe {
+            ChangeServiceConfigW(
+                service.0,
+                ENUM_SERVICE_TYPE(SERVICE_NO_CHANGE),
+                value,
+                SERVICE_ERROR(SERVICE_NO_CHANGE),
+                PCWSTR::null(),
+                PCWSTR::null(),
+                None,
+                PCWSTR::null(),
+                PCWSTR::null(),
+                PCWSTR::null(),
+                PCWSTR::null(),
+            )
+            .map_err(error)?;
+            // The delayed flag is kept by Windows when the start type changes, so it is always set.
+            if value == SERVICE_AUTO_START {
+                let info = SERVICE_DELAYED_AUTO_START_INFO {
+                    fDelayedAutostart: (start_type == StartType::DelayedAutomatic).into(),
+                };
+                ChangeServiceConfig2W(
+                    service.0,
+                    SERVICE_CONFIG_DELAYED_AUTO_START_INFO,
+                    Some(&info as *const _ as *const core::ffi::c_void),
+                )
+                .map_err(error)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), String> {
+        let service = self.open(name, DELETE)?;
+        match unsafe { DeleteService(service.0) } {
+            Err(e) if e.code() != ERROR_SERVICE_MARKED_FOR_DELETE.to_hresult() => Err(format!(
+                "Failed to delete service {}: {}",
+                name,
+                e.message()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+/// systemd units, through `systemctl`.
+use std::path::Path;
+use std::process::Command;
+
+use super::{Backend, StartType, State};
+
+/// Units installed by the administrator, the only ones [`Backend::delete`] removes.
+const ADMIN_UNITS: &str = "/etc/systemd/system";
+
+pub struct Systemd;
+
+/// `sshd` is `sshd.service`, other unit types have to be named in full.
+fn unit(name: &str) -> String {
+    if name.contains('.') {
+        name.to_string()
+    } else {
+        format!("{}.service", name)
+    }
+}
+
+/// Runs `systemctl`, failing with its error output.
+pub fn systemctl(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("systemctl")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run systemctl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "systemctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// A property of the unit, failing if the unit doesn't exist.
+fn property(name: &str, property: &str) -> Result<String, String> {
+    let unit = unit(name);
+    let load_state = systemctl(&["show", "--property=LoadState", "--value", &unit])?;
+    if load_state == "not-found" {
+        return Err(format!("Service {} does not exist", unit));
+    }
+    systemctl(&[
+        "show",
+        &format!("--property={}", property),
+        "--value",
+        &unit,
+    ])
+}
+
+impl Backend for Systemd {
+    fn exists(&self, name: &str) -> Result<bool, String> {
+        let load_state = systemctl(&["show", "--property=LoadState", "--value", &unit(name)])?;
+        Ok(load_state != "not-found")
+    }
+
+    fn state(&self, name: &str) -> Result<State, String> {
+        match property(name, "ActiveState")?.as_str() {
+            "active" | "reloading" | "refreshing" => Ok(State::Running),
+            "activating" => Ok(State::Starting),
+            "deactivating" => Ok(State::Stopping),
+            _ => Ok(State::Stopped),
+        }
+    }
+
+    fn start_type(&self, name: &str) -> Result<StartType, String> {
+        let state = property(name, "UnitFileState")?;
+        if state.starts_with("enabled") {
+            Ok(StartType::Automatic)
+        } else if state.starts_with("masked") {
+            Ok(StartType::Disabled)
+        } else {
+            Ok(StartType::Manual)
+        }
+    }
+
+    fn start(&mut self, name: &str) -> Result<(), String> {
+        systemctl(&["start", "--no-block", &unit(name)]).map(|_| ())
+    }
+
+    fn stop(&mut self, name: &str) -> Result<(), String> {
+        systemctl(&["stop", "--no-block", &unit(name)]).map(|_| ())
+    }
+
+    /// Units are never reported as paused, starting them is the closest there is.
+    fn resume(&mut self, name: &str) -> Result<(), String> {
+        self.start(name)
+    }
+
+    fn set_start_type(&mut self, name: &str, start_type: StartType) -> Result<(), String> {
+        let unit = unit(name);
+        let was_masked = self.start_type(name)? == StartType::Disabled;
+        if was_masked && start_type != StartType::Disabled {
+            systemctl(&["unmask", &unit])?;
+        }
+        match start_type {
+            StartType::Automatic | StartType::DelayedAutomatic => {
+                systemctl(&["enable", &unit])?;
+            }
+            StartType::Manual => {
+                systemctl(&["disable", &unit])?;
+            }
+            StartType::Disabled => {
+                systemctl(&["disable", &unit])?;
+                systemctl(&["mask", &unit])?;
+            }
+            StartType::Boot | StartType::System => {
+                return Err(format!(
+                    "The {} start type only exists for Windows drivers",
+                    start_type
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Enabled units are started at boot without a delay, `delayed-auto` is `auto`.
+    fn stored(&self, start_type: StartType) -> StartType {
+        match start_type {
+            StartType::DelayedAutomatic => StartType::Automatic,
+            _ => start_type,
+        }
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), String> {
+        let unit = unit(name);
+        let path = property(name, "FragmentPath")?;
+        if !Path::new(&path).starts_with(ADMIN_UNITS) {
+            return Err(format!(
+                "{} is installed by a package ({}), only units in {} can be deleted",
+                unit, path, ADMIN_UNITS
+            ));
+        }
+        systemctl(&["disable", &unit])?;
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", path, e))?;
+        systemctl(&["daemon-reload"]).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delayed_units_are_enabled_ones() {
+        assert_eq!(
+            Systemd.stored(StartType::DelayedAutomatic),
+            StartType::Automatic
+        );
+        assert_eq!(Systemd.stored(StartType::Disabled), StartType::Disabled);
+        assert_eq!(unit("sshd"), "sshd.service");
+        assert_eq!(unit("backup.timer"), "backup.timer");
+    }
+}
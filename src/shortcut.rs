@@ -0,0 +1,522 @@
+/// Windows shortcuts (`.lnk` files) in the Shell Link Binary File Format (MS-SHLLINK), written
+/// and read without the shell so they can be made on any system.
+///
+/// Targets like `C:\Tools\app.exe` are stored as the local base path of a LinkInfo structure,
+/// targets with environment variables or on a share in an EnvironmentVariableDataBlock which
+/// the shell expands when the shortcut is opened. Strings are always stored as UTF-16.
+const HEADER_SIZE: u32 = 0x4C;
+/// The class id of shell links, `00021401-0000-0000-C000-000000000046`.
+const LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x1;
+const HAS_LINK_INFO: u32 = 0x2;
+const HAS_NAME: u32 = 0x4;
+const HAS_RELATIVE_PATH: u32 = 0x8;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+const FORCE_NO_LINK_INFO: u32 = 0x100;
+const HAS_EXP_STRING: u32 = 0x200;
+const HAS_EXP_ICON: u32 = 0x4000;
+
+/// The size of a LinkInfo header with the offsets of the Unicode paths.
+const LINK_INFO_HEADER_SIZE: u32 = 0x24;
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+const DRIVE_FIXED: u32 = 3;
+
+const ENVIRONMENT_BLOCK_SIZE: u32 = 0x314;
+const ENVIRONMENT_SIGNATURE: u32 = 0xA000_0001;
+const ICON_ENVIRONMENT_SIGNATURE: u32 = 0xA000_0007;
+/// The size of the paths of environment blocks, in characters including the terminating null.
+const ENVIRONMENT_PATH_LENGTH: usize = 260;
+
+/// How the window of the target is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Show {
+    Normal,
+    Maximized,
+    Minimized,
+}
+
+impl Show {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "normal" => Ok(Show::Normal),
+            "maximized" => Ok(Show::Maximized),
+            "minimized" => Ok(Show::Minimized),
+            _ => Err(format!(
+                "Unknown window state: {} (expected normal, maximized or minimized)",
+                name
+            )),
+        }
+    }
+
+    /// The `SW_*` value of the header.
+    fn code(self) -> u32 {
+        match self {
+            Show::Normal => 1,
+            Show::Maximized => 3,
+            Show::Minimized => 7,
+        }
+    }
+
+    fn from_code(code: u32) -> Self {
+        match code {
+            3 => Show::Maximized,
+            7 => Show::Minimized,
+            _ => Show::Normal,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shortcut {
+    /// An absolute path like `C:\Tools\app.exe`, `\\server\share\app.exe` or
+    /// `%ProgramFiles%\App\app.exe`.
+    pub target: String,
+    pub arguments: Option<String>,
+    pub working_dir: Option<String>,
+    /// The file holding the icon, the target's own icon when `None`.
+    pub icon: Option<String>,
+    pub icon_index: i32,
+    /// The comment shown as the tooltip of the shortcut.
+    pub description: Option<String>,
+    pub show: Show,
+}
+
+impl Shortcut {
+    pub fn new(target: &str) -> Self {
+        Shortcut {
+            target: target.to_string(),
+            arguments: None,
+            working_dir: None,
+            icon: None,
+            icon_index: 0,
+            description: None,
+            show: Show::Normal,
+        }
+    }
+
+    /// The content of the `.lnk` file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let local = is_local_path(&self.target);
+        if !local && !self.target.starts_with(['%', '\\']) {
+            return Err(format!(
+                "The target of a shortcut must be an absolute Windows path: {}",
+                self.target
+            ));
+        }
+        let expand_icon = self.icon.as_deref().is_some_and(|icon| icon.contains('%'));
+        let strings = [
+            (HAS_NAME, &self.description),
+            (HAS_WORKING_DIR, &self.working_dir),
+            (HAS_ARGUMENTS, &self.arguments),
+            (HAS_ICON_LOCATION, &self.icon),
+        ];
+        let mut flags = IS_UNICODE;
+        flags |= if local {
+            HAS_LINK_INFO
+        } else {
+            HAS_EXP_STRING | FORCE_NO_LINK_INFO
+        };
+        for (flag, value) in &strings {
+            if value.is_some() {
+                flags |= flag;
+            }
+        }
+        if expand_icon {
+            flags |= HAS_EXP_ICON;
+        }
+
+        let mut out = Vec::new();
+        put_u32(&mut out, HEADER_SIZE);
+        out.extend_from_slice(&LINK_CLSID);
+        put_u32(&mut out, flags);
+        put_u32(&mut out, 0); // FileAttributes
+        out.extend_from_slice(&[0; 24]); // CreationTime, AccessTime and WriteTime
+        put_u32(&mut out, 0); // FileSize
+        out.extend_from_slice(&self.icon_index.to_le_bytes());
+        put_u32(&mut out, self.show.code());
+        out.extend_from_slice(&[0; 12]); // HotKey and the reserved fields
+
+        if local {
+            link_info(&mut out, &self.target);
+        }
+        for (_, value) in &strings {
+            if let Some(value) = value {
+                let text = value.encode_utf16().collect::<Vec<u16>>();
+                let length = u16::try_from(text.len())
+                    .map_err(|_| format!("Text too long for a shortcut: {}", value))?;
+                out.extend_from_slice(&length.to_le_bytes());
+                text.iter()
+                    .for_each(|c| out.extend_from_slice(&c.to_le_bytes()));
+            }
+        }
+        if !local {
+            environment_block(&mut out, ENVIRONMENT_SIGNATURE, &self.target)?;
+        }
+        if let Some(icon) = self.icon.as_deref().filter(|_| expand_icon) {
+            environment_block(&mut out, ICON_ENVIRONMENT_SIGNATURE, icon)?;
+        }
+        put_u32(&mut out, 0); // TerminalBlock
+        Ok(out)
+    }
+
+    /// Reads a `.lnk` file, including those made by Windows.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.u32()? != HEADER_SIZE || reader.take(16)? != LINK_CLSID {
+            return Err("Not a shortcut file".to_string());
+        }
+        let flags = reader.u32()?;
+        reader.take(32)?; // FileAttributes, the times and FileSize
+        let icon_index = reader.u32()? as i32;
+        let show = Show::from_code(reader.u32()?);
+        reader.take(12)?; // HotKey and the reserved fields
+
+        if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+            let size = reader.u16()? as usize;
+            reader.take(size)?;
+        }
+        let mut target = None;
+        if flags & HAS_LINK_INFO != 0 {
+            let start = reader.position;
+            let size = reader.u32()? as usize;
+            target = parse_link_info(reader.take(size.saturating_sub(4))?, start, bytes)?;
+        }
+
+        let mut strings = [None, None, None, None, None];
+        let string_flags = [
+            HAS_NAME,
+            HAS_RELATIVE_PATH,
+            HAS_WORKING_DIR,
+            HAS_ARGUMENTS,
+            HAS_ICON_LOCATION,
+        ];
+        for (flag, value) in string_flags.iter().zip(strings.iter_mut()) {
+            if flags & flag != 0 {
+                let length = reader.u16()? as usize;
+                *value = Some(if flags & IS_UNICODE != 0 {
+                    utf16(reader.take(length * 2)?)
+                } else {
+                    ansi(reader.take(length)?)
+                });
+            }
+        }
+        let [description, relative_path, working_dir, arguments, icon] = strings;
+
+        // Extra data blocks until the terminal block, which is smaller than 4 bytes.
+        while let Ok(size) = reader.u32() {
+            if size < 8 {
+                break;
+            }
+            let block = reader.take(size as usize - 4)?;
+            let signature = u32::from_le_bytes(block[..4].try_into().unwrap());
+            if signature == ENVIRONMENT_SIGNATURE
+                && flags & HAS_EXP_STRING != 0
+                && let Some(unicode) = block.get(4 + ENVIRONMENT_PATH_LENGTH..)
+            {
+                target = Some(utf16(unicode)).filter(|t| !t.is_empty()).or(target);
+            }
+        }
+
+        let target = target
+            .or(relative_path)
+            .ok_or("The shortcut has no target path")?;
+        Ok(Shortcut {
+            target,
+            arguments,
+            working_dir,
+            icon,
+            icon_index,
+            description,
+            show,
+        })
+    }
+}
+
+/// Whether the path is on a drive, like `C:\Tools`.
+fn is_local_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\'
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// A null-terminated string in the system code page, characters outside ASCII become `?`.
+fn to_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+        .chain([0])
+        .collect()
+}
+
+/// A null-terminated UTF-16 string.
+fn to_utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16()
+        .chain([0])
+        .flat_map(|c| c.to_le_bytes())
+        .collect()
+}
+
+/// The text up to the first null of a UTF-16 string.
+fn utf16(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&c| c != 0)
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&units)
+}
+
+fn ansi(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// A LinkInfo structure with the path of a file on a fixed drive.
+fn link_info(out: &mut Vec<u8>, path: &str) {
+    // VolumeIDSize, DriveType, DriveSerialNumber, VolumeLabelOffset and an empty label.
+    let mut volume_id = Vec::new();
+    for value in [17, DRIVE_FIXED, 0, 0x10] {
+        put_u32(&mut volume_id, value);
+    }
+    volume_id.push(0);
+    let base_path = to_ansi(path);
+    let suffix = to_ansi("");
+    let base_path_unicode = to_utf16(path);
+    let suffix_unicode = to_utf16("");
+
+    let volume_id_offset = LINK_INFO_HEADER_SIZE as usize;
+    let base_path_offset = volume_id_offset + volume_id.len();
+    let suffix_offset = base_path_offset + base_path.len();
+    let base_path_unicode_offset = suffix_offset + suffix.len();
+    let suffix_unicode_offset = base_path_unicode_offset + base_path_unicode.len();
+    let size = suffix_unicode_offset + suffix_unicode.len();
+    for value in [
+        size,
+        LINK_INFO_HEADER_SIZE as usize,
+        VOLUME_ID_AND_LOCAL_BASE_PATH as usize,
+        volume_id_offset,
+        base_path_offset,
+        0, // CommonNetworkRelativeLinkOffset
+        suffix_offset,
+        base_path_unicode_offset,
+        suffix_unicode_offset,
+    ] {
+        put_u32(out, value as u32);
+    }
+    for part in [
+        volume_id,
+        base_path,
+        suffix,
+        base_path_unicode,
+        suffix_unicode,
+    ] {
+        out.extend_from_slice(&part);
+    }
+}
+
+/// The local path of a LinkInfo structure, `None` for a path on a share. `info` is the
+/// structure after its size, which starts at `start` of `bytes`.
+fn parse_link_info(info: &[u8], start: usize, bytes: &[u8]) -> Result<Option<String>, String> {
+    let field = |index: usize| -> Result<usize, String> {
+        info.get(index * 4..index * 4 + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| "Truncated shortcut link info".to_string())
+    };
+    let end = start + 4 + info.len();
+    let at = |offset: usize| -> Result<&[u8], String> {
+        bytes
+            .get(start + offset..end)
+            .ok_or_else(|| "Invalid offset in the shortcut link info".to_string())
+    };
+    let (header_size, flags) = (field(0)?, field(1)?);
+    if flags & VOLUME_ID_AND_LOCAL_BASE_PATH as usize == 0 {
+        return Ok(None);
+    }
+    if header_size >= LINK_INFO_HEADER_SIZE as usize {
+        let base = utf16(at(field(6)?)?);
+        let suffix = utf16(at(field(7)?)?);
+        return Ok(Some(base + &suffix));
+    }
+    let base = ansi(at(field(3)?)?);
+    let suffix = ansi(at(field(5)?)?);
+    Ok(Some(base + &suffix))
+}
+
+/// An EnvironmentVariableDataBlock, or an IconEnvironmentDataBlock with the same layout.
+fn environment_block(out: &mut Vec<u8>, signature: u32, path: &str) -> Result<(), String> {
+    let ansi = to_ansi(path);
+    let unicode = to_utf16(path);
+    if ansi.len() > ENVIRONMENT_PATH_LENGTH {
+        return Err(format!("Path too long for a shortcut: {}", path));
+    }
+    put_u32(out, ENVIRONMENT_BLOCK_SIZE);
+    put_u32(out, signature);
+    out.extend_from_slice(&ansi);
+    out.resize(out.len() + ENVIRONMENT_PATH_LENGTH - ansi.len(), 0);
+    out.extend_from_slice(&unicode);
+    out.resize(out.len() + ENVIRONMENT_PATH_LENGTH * 2 - unicode.len(), 0);
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + count)
+            .ok_or("Truncated shortcut file")?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(bytes: &[u8]) -> u32 {
+        u32::from_le_bytes(bytes[20..24].try_into().unwrap())
+    }
+
+    /// The offset of the extra data block with this signature.
+    fn block(bytes: &[u8], signature: u32) -> Option<usize> {
+        bytes.windows(8).position(|w| {
+            w[..4] == ENVIRONMENT_BLOCK_SIZE.to_le_bytes() && w[4..] == signature.to_le_bytes()
+        })
+    }
+
+    #[test]
+    fn round_trips_local_targets() {
+        let shortcut = Shortcut::new(r"C:\Tools\app.exe");
+        let bytes = shortcut.to_bytes().unwrap();
+        assert_eq!(bytes[..4], HEADER_SIZE.to_le_bytes());
+        assert_eq!(bytes[4..20], LINK_CLSID);
+        assert_eq!(flags(&bytes), IS_UNICODE | HAS_LINK_INFO);
+        // The LinkInfo holds the path in the code page and in UTF-16.
+        let info = &bytes[HEADER_SIZE as usize..];
+        let size = u32::from_le_bytes(info[..4].try_into().unwrap()) as usize;
+        assert!(
+            info[..size]
+                .windows(17)
+                .any(|w| w == b"C:\\Tools\\app.exe\0")
+        );
+        assert!(block(&bytes, ENVIRONMENT_SIGNATURE).is_none());
+        assert_eq!(bytes[bytes.len() - 4..], [0; 4]);
+        assert_eq!(Shortcut::parse(&bytes), Ok(shortcut));
+    }
+
+    #[test]
+    fn round_trips_targets_with_variables() {
+        let shortcut = Shortcut::new(r"%ProgramFiles%\App\app.exe");
+        let bytes = shortcut.to_bytes().unwrap();
+        assert_eq!(
+            flags(&bytes),
+            IS_UNICODE | HAS_EXP_STRING | FORCE_NO_LINK_INFO
+        );
+        let at = block(&bytes, ENVIRONMENT_SIGNATURE).unwrap();
+        let ansi = &bytes[at + 8..at + 8 + ENVIRONMENT_PATH_LENGTH];
+        assert_eq!(super::ansi(ansi), r"%ProgramFiles%\App\app.exe");
+        let unicode =
+            &bytes[at + 8 + ENVIRONMENT_PATH_LENGTH..at + ENVIRONMENT_BLOCK_SIZE as usize];
+        assert_eq!(utf16(unicode), r"%ProgramFiles%\App\app.exe");
+        assert_eq!(Shortcut::parse(&bytes), Ok(shortcut));
+    }
+
+    #[test]
+    fn round_trips_shares() {
+        let shortcut = Shortcut::new(r"\\server\share\Setup.exe");
+        let bytes = shortcut.to_bytes().unwrap();
+        assert_eq!(flags(&bytes) & HAS_LINK_INFO, 0);
+        assert!(block(&bytes, ENVIRONMENT_SIGNATURE).is_some());
+        assert_eq!(Shortcut::parse(&bytes), Ok(shortcut));
+    }
+
+    #[test]
+    fn round_trips_all_fields() {
+        let shortcut = Shortcut {
+            target: r"C:\Program Files\App\app.exe".to_string(),
+            arguments: Some(r#"--profile "C:\Users\Zoë\app.ini""#.to_string()),
+            working_dir: Some(r"C:\Program Files\App".to_string()),
+            icon: Some(r"%SystemRoot%\System32\shell32.dll".to_string()),
+            icon_index: -3,
+            description: Some("Starts the app".to_string()),
+            show: Show::Maximized,
+        };
+        let bytes = shortcut.to_bytes().unwrap();
+        assert_eq!(
+            flags(&bytes),
+            IS_UNICODE
+                | HAS_LINK_INFO
+                | HAS_NAME
+                | HAS_WORKING_DIR
+                | HAS_ARGUMENTS
+                | HAS_ICON_LOCATION
+                | HAS_EXP_ICON
+        );
+        assert!(block(&bytes, ICON_ENVIRONMENT_SIGNATURE).is_some());
+        assert_eq!(Shortcut::parse(&bytes), Ok(shortcut.clone()));
+
+        let minimized = Shortcut {
+            icon: Some(r"C:\Icons\app.ico".to_string()),
+            show: Show::Minimized,
+            ..shortcut
+        };
+        let bytes = minimized.to_bytes().unwrap();
+        assert_eq!(flags(&bytes) & HAS_EXP_ICON, 0);
+        assert!(block(&bytes, ICON_ENVIRONMENT_SIGNATURE).is_none());
+        assert_eq!(Shortcut::parse(&bytes), Ok(minimized));
+    }
+
+    #[test]
+    fn rejects_invalid_shortcuts() {
+        assert_eq!(
+            Shortcut::new("app.exe").to_bytes(),
+            Err("The target of a shortcut must be an absolute Windows path: app.exe".to_string())
+        );
+        assert!(
+            Shortcut::new(&format!(r"%Temp%\{}", "a".repeat(300)))
+                .to_bytes()
+                .is_err()
+        );
+        assert_eq!(
+            Shortcut::parse(b"not a shortcut"),
+            Err("Not a shortcut file".to_string())
+        );
+        assert_eq!(
+            Shortcut::parse(&[0x4C, 0, 0]),
+            Err("Truncated shortcut file".to_string())
+        );
+        let mut bytes = Shortcut::new(r"C:\app.exe").to_bytes().unwrap();
+        bytes[4] = 0;
+        assert_eq!(
+            Shortcut::parse(&bytes),
+            Err("Not a shortcut file".to_string())
+        );
+        bytes[4] = LINK_CLSID[0];
+        bytes.truncate(HEADER_SIZE as usize + 8);
+        assert_eq!(
+            Shortcut::parse(&bytes),
+            Err("Truncated shortcut file".to_string())
+        );
+    }
+}
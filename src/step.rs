@@ -0,0 +1,163 @@
+/// Interactive step-through mode (`--step`), asks the operator what to do before every action.
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::config::Action;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepChoice {
+    Run,
+    Skip,
+    SkipScript,
+    Abort,
+}
+
+pub struct StepDecision {
+    pub choice: StepChoice,
+    /// The new parameters if the operator edited them.
+    pub parameters: Option<Vec<String>>,
+}
+
+/// A line of the operator, `None` once stdin is closed or can't be read.
+fn read_line() -> Option<String> {
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input) {
+        Ok(0) => None,
+        Ok(_) => Some(input.trim().to_string()),
+        Err(e) => {
+            warn!("Failed to read the choice: {}", e);
+            None
+        }
+    }
+}
+
+fn show(action: &Action, parameters: &Option<Vec<String>>) {
+    info!("");
+    info!("====/ STEP /===");
+    info!("Action: {}", action.name);
+    if let Some(description) = &action.description {
+        info!("Description: {}", description);
+    }
+    match parameters {
+        Some(params) => info!(
+            "Parameters: {}",
+            serde_json::to_string(params).unwrap_or_default()
+        ),
+        None => info!("Parameters: (none)"),
+    }
+    info!("===============");
+}
+
+/// Shows the action with its resolved parameters and waits for a choice, parameters can be edited once.
+pub fn ask(action: &Action, parameters: &Option<Vec<String>>) -> StepDecision {
+    ask_with(action, parameters, &mut read_line)
+}
+
+/// [`ask`] with the lines of the operator from `read_line`. The end of the input aborts the run,
+/// nothing is run without an answer.
+fn ask_with(
+    action: &Action,
+    parameters: &Option<Vec<String>>,
+    read_line: &mut dyn FnMut() -> Option<String>,
+) -> StepDecision {
+    let mut edited: Option<Vec<String>> = None;
+    let abort = |edited| {
+        warn!("The input ended, aborting");
+        StepDecision {
+            choice: StepChoice::Abort,
+            parameters: edited,
+        }
+    };
+    loop {
+        show(action, parameters);
+        if let Some(params) = &edited {
+            info!(
+                "Edited parameters: {}",
+                serde_json::to_string(params).unwrap_or_default()
+            );
+        }
+        if edited.is_none() {
+            warn!(
+                "[r]un, [s]kip, skip the rest of the [S]cript, [a]bort or [e]dit parameters? (r/s/S/a/e):"
+            );
+        } else {
+            warn!("[r]un, [s]kip, skip the rest of the [S]cript or [a]bort? (r/s/S/a):");
+        }
+        let Some(line) = read_line() else {
+            return abort(edited);
+        };
+        let choice = match line.as_str() {
+            "r" | "R" | "" => StepChoice::Run,
+            "s" => StepChoice::Skip,
+            "S" => StepChoice::SkipScript,
+            "a" | "A" => StepChoice::Abort,
+            "e" | "E" if edited.is_none() => {
+                warn!("Enter the new parameters as a JSON array of strings (empty to keep):");
+                let Some(input) = read_line() else {
+                    return abort(edited);
+                };
+                if input.is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Vec<String>>(&input) {
+                    Ok(params) => edited = Some(params),
+                    Err(e) => warn!("Invalid parameters, keeping the original ones: {}", e),
+                }
+                continue;
+            }
+            other => {
+                warn!("Unknown choice: {}", other);
+                continue;
+            }
+        };
+        return StepDecision {
+            choice,
+            parameters: edited,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Asks with the lines as the operator's answers, running out of them is the end of the input.
+    fn answer(lines: &[&str]) -> StepDecision {
+        let action: Action = serde_json::from_value(serde_json::json!({
+            "name": "echo", "parameters": ["hello"]
+        }))
+        .unwrap();
+        let mut lines = lines
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<VecDeque<String>>();
+        ask_with(&action, &action.parameters, &mut || lines.pop_front())
+    }
+
+    #[test]
+    fn asks_for_a_choice() {
+        assert_eq!(answer(&[""]).choice, StepChoice::Run);
+        assert_eq!(answer(&["x", "s"]).choice, StepChoice::Skip);
+        assert_eq!(answer(&["S"]).choice, StepChoice::SkipScript);
+        assert_eq!(answer(&["A"]).choice, StepChoice::Abort);
+
+        let decision = answer(&["e", "[\"${inputs.name}\"]", "e", "r"]);
+        assert_eq!(decision.choice, StepChoice::Run);
+        assert_eq!(
+            decision.parameters,
+            Some(vec!["${inputs.name}".to_string()])
+        );
+        assert_eq!(answer(&["e", "not json", "r"]).parameters, None);
+    }
+
+    #[test]
+    fn aborts_when_the_input_ends() {
+        assert_eq!(answer(&[]).choice, StepChoice::Abort);
+        assert_eq!(answer(&["x"]).choice, StepChoice::Abort);
+        let decision = answer(&["e"]);
+        assert_eq!(decision.choice, StepChoice::Abort);
+        assert_eq!(decision.parameters, None);
+    }
+}
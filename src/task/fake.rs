@@ -0,0 +1,84 @@
+/// Tasks that only exist in a JSON file like `{"<name>": {"xml": "<Task ...>", "enabled": true}}`,
+/// holding the XML the Task Scheduler would get. Changes are written back right away.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::{Backend, Task, xml};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FakeTask {
+    pub xml: String,
+    pub enabled: bool,
+}
+
+pub struct FakeTasks {
+    path: PathBuf,
+    tasks: BTreeMap<String, FakeTask>,
+}
+
+impl FakeTasks {
+    /// Loads the tasks, a missing file has none.
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        let tasks = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid fake tasks file {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        Ok(FakeTasks { path, tasks })
+    }
+
+    fn task(&self, name: &str) -> Result<&FakeTask, String> {
+        self.tasks
+            .get(name)
+            .ok_or_else(|| format!("Task {} does not exist", name))
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.tasks).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+impl Backend for FakeTasks {
+    fn exists(&self, name: &str) -> Result<bool, String> {
+        Ok(self.tasks.contains_key(name))
+    }
+
+    fn is_current(&self, task: &Task) -> Result<bool, String> {
+        Ok(self.tasks.get(&task.name).is_some_and(|registered| {
+            xml::registered_fingerprint(&registered.xml) == Some(xml::fingerprint(task).as_str())
+        }))
+    }
+
+    fn register(&mut self, task: &Task) -> Result<(), String> {
+        let registered = FakeTask {
+            xml: xml::render(task),
+            enabled: task.enabled,
+        };
+        self.tasks.insert(task.name.clone(), registered);
+        self.save()
+    }
+
+    fn enabled(&self, name: &str) -> Result<bool, String> {
+        Ok(self.task(name)?.enabled)
+    }
+
+    fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        self.task(name)?;
+        if let Some(task) = self.tasks.get_mut(name) {
+            task.enabled = enabled;
+        }
+        self.save()
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), String> {
+        self.task(name)?;
+        self.tasks.remove(name);
+        self.save()
+    }
+}
@@ -0,0 +1,162 @@
+/// Scheduled tasks behind a [`Backend`] trait: the Windows Task Scheduler (from generated task
+/// XML), systemd timers on Linux, and a fake kept in a JSON file.
+use chrono::Weekday;
+use std::fmt;
+
+mod fake;
+#[cfg(target_os = "windows")]
+mod schtasks;
+#[cfg(target_os = "linux")]
+mod systemd;
+pub mod units;
+pub mod xml;
+
+pub use fake::FakeTasks;
+
+/// Uses the fake backend with this state file instead of the system scheduler when set.
+pub const FAKE_ENV: &str = "TRX8_FAKE_TASKS";
+
+/// A time of day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Time {
+    /// `HH:MM`, 24-hour.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid time: {} (expected HH:MM)", text);
+        let (hour, minute) = text.split_once(':').ok_or_else(invalid)?;
+        let hour = hour.parse().map_err(|_| invalid())?;
+        let minute = minute.parse().map_err(|_| invalid())?;
+        if hour > 23 || minute > 59 {
+            return Err(invalid());
+        }
+        Ok(Time { hour, minute })
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    Boot,
+    /// Any user logging on, only on Windows.
+    Logon,
+    Daily(Time),
+    Weekly(Vec<Weekday>, Time),
+    /// Every that many minutes, starting at boot on Linux.
+    Every(u32),
+}
+
+impl Trigger {
+    /// `mon,fri@03:00`
+    pub fn parse_weekly(text: &str) -> Result<Self, String> {
+        let (days, time) = text
+            .split_once('@')
+            .ok_or_else(|| format!("Invalid weekly schedule: {} (expected <days>@HH:MM)", text))?;
+        let days = days
+            .split(',')
+            .map(|day| {
+                day.trim()
+                    .parse::<Weekday>()
+                    .map_err(|_| format!("Invalid day: {}", day))
+            })
+            .collect::<Result<Vec<Weekday>, String>>()?;
+        Ok(Trigger::Weekly(days, Time::parse(time)?))
+    }
+
+    /// `30m`, `2h` or `1d`.
+    pub fn parse_every(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid interval: {} (expected e.g. 30m, 2h or 1d)", text);
+        let digits = text
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let (count, unit) = text.split_at(digits);
+        let count = count.parse::<u32>().map_err(|_| invalid())?;
+        let minutes = match unit {
+            "m" => Some(count),
+            "h" => count.checked_mul(60),
+            "d" => count.checked_mul(60 * 24),
+            _ => return Err(invalid()),
+        }
+        .ok_or_else(invalid)?;
+        if minutes == 0 {
+            return Err(invalid());
+        }
+        Ok(Trigger::Every(minutes))
+    }
+}
+
+/// A task running a program on a schedule.
+#[derive(Clone, Debug)]
+pub struct Task {
+    /// On Windows a path in the Task Scheduler library (`\Folder\Name`), on Linux the unit name.
+    pub name: String,
+    pub command: String,
+    pub arguments: Vec<String>,
+    pub triggers: Vec<Trigger>,
+    pub description: Option<String>,
+    /// Runs as this user instead of `SYSTEM` or `root`.
+    pub user: Option<String>,
+    pub working_dir: Option<String>,
+    pub enabled: bool,
+}
+
+/// A task scheduler. Methods taking a name fail for a missing task, except [`Backend::exists`].
+pub trait Backend {
+    fn exists(&self, name: &str) -> Result<bool, String>;
+    /// Whether the task is registered with exactly this definition, ignoring whether it's enabled.
+    fn is_current(&self, task: &Task) -> Result<bool, String>;
+    /// Creates or replaces the task.
+    fn register(&mut self, task: &Task) -> Result<(), String>;
+    fn enabled(&self, name: &str) -> Result<bool, String>;
+    fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String>;
+    fn delete(&mut self, name: &str) -> Result<(), String>;
+}
+
+/// Enables or disables the task if needed, returns whether it changed.
+pub fn ensure_enabled(
+    backend: &mut dyn Backend,
+    name: &str,
+    enabled: bool,
+) -> Result<bool, String> {
+    if backend.enabled(name)? == enabled {
+        return Ok(false);
+    }
+    backend.set_enabled(name, enabled)?;
+    Ok(true)
+}
+
+/// Registers the task unless it is already registered as is, returns whether it changed.
+pub fn ensure_task(backend: &mut dyn Backend, task: &Task) -> Result<bool, String> {
+    if !backend.is_current(task)? {
+        backend.register(task)?;
+        return Ok(true);
+    }
+    ensure_enabled(backend, &task.name, task.enabled)
+}
+
+/// The task scheduler of the system, or the fake one when [`FAKE_ENV`] is set.
+pub fn open() -> Result<Box<dyn Backend>, String> {
+    if let Ok(path) = std::env::var(FAKE_ENV) {
+        return Ok(Box::new(FakeTasks::open(path.into())?));
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Ok(Box::new(schtasks::Schtasks))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Box::new(systemd::Systemd))
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        Err("Scheduled tasks are only available on Windows and Linux".to_string())
+    }
+}
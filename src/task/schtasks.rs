@@ -0,0 +1,102 @@
+/// Tasks in the Windows Task Scheduler, through `schtasks.exe`.
+use std::fs;
+use std::process::Command;
+
+use super::{Backend, Task, xml};
+
+pub struct Schtasks;
+
+/// Runs `schtasks`, failing with its error output.
+fn schtasks(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("schtasks")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run schtasks: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "schtasks {} failed: {}",
+            args.first().unwrap_or(&""),
+            xml::decode_output(&output.stderr).trim()
+        ));
+    }
+    Ok(xml::decode_output(&output.stdout))
+}
+
+/// The XML of the registered task.
+fn query(name: &str) -> Result<String, String> {
+    schtasks(&["/Query", "/TN", name, "/XML"])
+}
+
+/// Whether the task is enabled, from its XML: `<Enabled>` in `<Settings>` defaults to true.
+fn is_enabled(xml: &str) -> bool {
+    let Some(start) = xml.find("<Settings>") else {
+        return true;
+    };
+    let end = xml[start..]
+        .find("</Settings>")
+        .map_or(xml.len(), |i| start + i);
+    !xml[start..end].contains("<Enabled>false</Enabled>")
+}
+
+impl Schtasks {
+    fn require(&self, name: &str) -> Result<(), String> {
+        if !self.exists(name)? {
+            return Err(format!("Task {} does not exist", name));
+        }
+        Ok(())
+    }
+}
+
+impl Backend for Schtasks {
+    fn exists(&self, name: &str) -> Result<bool, String> {
+        let status = Command::new("schtasks")
+            .args(["/Query", "/TN", name])
+            .output()
+            .map_err(|e| format!("Failed to run schtasks: {}", e))?
+            .status;
+        Ok(status.success())
+    }
+
+    fn is_current(&self, task: &Task) -> Result<bool, String> {
+        if !self.exists(&task.name)? {
+            return Ok(false);
+        }
+        let registered = query(&task.name)?;
+        Ok(xml::registered_fingerprint(&registered) == Some(xml::fingerprint(task).as_str()))
+    }
+
+    fn register(&mut self, task: &Task) -> Result<(), String> {
+        // schtasks reads the file as UTF-16 like the declaration says.
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in xml::render(task).replace('\n', "\r\n").encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        let path = std::env::temp_dir().join(format!("trx8-task-{}.xml", std::process::id()));
+        fs::write(&path, bytes)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        let result = schtasks(&[
+            "/Create",
+            "/TN",
+            &task.name,
+            "/XML",
+            &path.to_string_lossy(),
+            "/F",
+        ]);
+        let _ = fs::remove_file(&path);
+        result.map(|_| ())
+    }
+
+    fn enabled(&self, name: &str) -> Result<bool, String> {
+        self.require(name)?;
+        Ok(is_enabled(&query(name)?))
+    }
+
+    fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let flag = if enabled { "/ENABLE" } else { "/DISABLE" };
+        schtasks(&["/Change", "/TN", name, flag]).map(|_| ())
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), String> {
+        schtasks(&["/Delete", "/TN", name, "/F"]).map(|_| ())
+    }
+}
@@ -0,0 +1,87 @@
+/// Tasks as systemd timers, the units are written to `/etc/systemd/system`.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Backend, Task, units};
+use crate::service::systemctl;
+
+const UNIT_DIR: &str = "/etc/systemd/system";
+
+pub struct Systemd;
+
+fn timer_unit(name: &str) -> String {
+    format!("{}.timer", units::unit_name(name))
+}
+
+fn unit_path(name: &str, kind: &str) -> PathBuf {
+    Path::new(UNIT_DIR).join(format!("{}.{}", units::unit_name(name), kind))
+}
+
+fn write_unit(path: &Path, content: &str) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+impl Systemd {
+    fn require(&self, name: &str) -> Result<(), String> {
+        if !self.exists(name)? {
+            return Err(format!("Timer {} does not exist", timer_unit(name)));
+        }
+        Ok(())
+    }
+}
+
+impl Backend for Systemd {
+    fn exists(&self, name: &str) -> Result<bool, String> {
+        let load_state =
+            systemctl(&["show", "--property=LoadState", "--value", &timer_unit(name)])?;
+        Ok(load_state != "not-found")
+    }
+
+    fn is_current(&self, task: &Task) -> Result<bool, String> {
+        let current = |kind: &str| fs::read_to_string(unit_path(&task.name, kind)).ok();
+        Ok(current("service") == Some(units::service(task))
+            && current("timer") == Some(units::timer(task)?))
+    }
+
+    fn register(&mut self, task: &Task) -> Result<(), String> {
+        write_unit(&unit_path(&task.name, "service"), &units::service(task))?;
+        write_unit(&unit_path(&task.name, "timer"), &units::timer(task)?)?;
+        systemctl(&["daemon-reload"])?;
+        self.set_enabled(&task.name, task.enabled)
+    }
+
+    fn enabled(&self, name: &str) -> Result<bool, String> {
+        self.require(name)?;
+        let state = systemctl(&[
+            "show",
+            "--property=UnitFileState",
+            "--value",
+            &timer_unit(name),
+        ])?;
+        Ok(state.starts_with("enabled"))
+    }
+
+    fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let command = if enabled { "enable" } else { "disable" };
+        systemctl(&[command, "--now", &timer_unit(name)]).map(|_| ())
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), String> {
+        let timer = unit_path(name, "timer");
+        if !timer.exists() {
+            return Err(format!(
+                "{} is installed by a package, only timers in {} can be deleted",
+                timer_unit(name),
+                UNIT_DIR
+            ));
+        }
+        systemctl(&["disable", "--now", &timer_unit(name)])?;
+        for path in [timer, unit_path(name, "service")] {
+            if path.exists() {
+                fs::remove_file(&path)
+                    .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+            }
+        }
+        systemctl(&["daemon-reload"]).map(|_| ())
+    }
+}
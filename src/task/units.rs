@@ -0,0 +1,190 @@
+/// systemd units for tasks: a oneshot `.service` running the command and a `.timer` starting it.
+use chrono::Weekday;
+use std::fmt::Write;
+
+use super::{Task, Trigger};
+
+/// `\Folder\Cleanup job` is `Folder-Cleanup-job`, unit names can't hold spaces or backslashes.
+pub fn unit_name(name: &str) -> String {
+    let name = name
+        .split(['\\', '/'])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || ":_.-@".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Quotes an argument for `ExecStart=`, `%` and `$` are taken literally.
+fn quote_argument(argument: &str) -> String {
+    let argument = argument.replace('%', "%%").replace('$', "$$");
+    if !argument.is_empty() && !argument.contains([' ', '\t', '"', '\'', '\\', ';']) {
+        return argument;
+    }
+    format!(
+        "\"{}\"",
+        argument.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// What the timer and the service start with, naming where they come from.
+fn header(task: &Task) -> String {
+    let mut unit = format!("# Generated by trx8 for the task {}\n", task.name);
+    unit.push_str("[Unit]\n");
+    let description = task.description.as_deref().unwrap_or(&task.name);
+    // Specifiers like `%n` are expanded in descriptions too.
+    let description = description.replace('\n', " ").replace('%', "%%");
+    let _ = writeln!(unit, "Description={}", description);
+    unit
+}
+
+pub fn service(task: &Task) -> String {
+    let mut unit = header(task);
+    unit.push_str("\n[Service]\nType=oneshot\n");
+    let command = std::iter::once(&task.command)
+        .chain(&task.arguments)
+        .map(|a| quote_argument(a))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let _ = writeln!(unit, "ExecStart={}", command);
+    if let Some(user) = &task.user {
+        let _ = writeln!(unit, "User={}", user);
+    }
+    if let Some(working_dir) = &task.working_dir {
+        let _ = writeln!(unit, "WorkingDirectory={}", working_dir);
+    }
+    unit
+}
+
+pub fn timer(task: &Task) -> Result<String, String> {
+    let mut unit = header(task);
+    unit.push_str("\n[Timer]\n");
+    let mut calendar = false;
+    for trigger in &task.triggers {
+        match trigger {
+            Trigger::Boot => unit.push_str("OnBootSec=1min\n"),
+            Trigger::Logon => {
+                return Err("Logon triggers are only available on Windows".to_string());
+            }
+            Trigger::Daily(time) => {
+                let _ = writeln!(unit, "OnCalendar=*-*-* {}:00", time);
+                calendar = true;
+            }
+            Trigger::Weekly(days, time) => {
+                let days = days.iter().map(Weekday::to_string).collect::<Vec<String>>();
+                let _ = writeln!(unit, "OnCalendar={} *-*-* {}:00", days.join(","), time);
+                calendar = true;
+            }
+            Trigger::Every(minutes) => {
+                let _ = writeln!(unit, "OnBootSec={}min", minutes);
+                let _ = writeln!(unit, "OnUnitActiveSec={}min", minutes);
+            }
+        }
+    }
+    // Like StartWhenAvailable on Windows: runs missed while the machine was off are caught up.
+    if calendar {
+        unit.push_str("Persistent=true\n");
+    }
+    unit.push_str("\n[Install]\nWantedBy=timers.target\n");
+    Ok(unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Time;
+
+    fn task(triggers: Vec<Trigger>) -> Task {
+        Task {
+            name: r"\trx8\Clean up".to_string(),
+            command: "/usr/local/bin/clean".to_string(),
+            arguments: Vec::new(),
+            triggers,
+            description: None,
+            user: None,
+            working_dir: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn names_units_after_the_task() {
+        assert_eq!(unit_name(r"\trx8\Clean up"), "trx8-Clean-up");
+        assert_eq!(unit_name("/backup/daily.sync"), "backup-daily.sync");
+        assert_eq!(unit_name("Zoë's job"), "Zo--s-job");
+        assert_eq!(unit_name("plain"), "plain");
+    }
+
+    #[test]
+    fn writes_the_service() {
+        let mut task = task(Vec::new());
+        task.arguments = vec![
+            "--older-than=30d".to_string(),
+            "/var/log/my app".to_string(),
+            "100%".to_string(),
+            "$HOME".to_string(),
+            r#"say "hi""#.to_string(),
+        ];
+        task.user = Some("backup".to_string());
+        task.working_dir = Some("/var/tmp".to_string());
+        task.description = Some("Removes old\nlogs, 100%".to_string());
+        assert_eq!(
+            service(&task),
+            r#"# Generated by trx8 for the task \trx8\Clean up
+[Unit]
+Description=Removes old logs, 100%%
+
+[Service]
+Type=oneshot
+ExecStart=/usr/local/bin/clean --older-than=30d "/var/log/my app" 100%% $$HOME "say \"hi\""
+User=backup
+WorkingDirectory=/var/tmp
+"#
+        );
+    }
+
+    #[test]
+    fn writes_calendar_timers() {
+        let time = Time { hour: 3, minute: 5 };
+        let task = task(vec![
+            Trigger::Daily(time),
+            Trigger::Weekly(vec![Weekday::Mon, Weekday::Sat], time),
+        ]);
+        assert_eq!(
+            timer(&task).unwrap(),
+            r#"# Generated by trx8 for the task \trx8\Clean up
+[Unit]
+Description=\trx8\Clean up
+
+[Timer]
+OnCalendar=*-*-* 03:05:00
+OnCalendar=Mon,Sat *-*-* 03:05:00
+Persistent=true
+
+[Install]
+WantedBy=timers.target
+"#
+        );
+    }
+
+    #[test]
+    fn writes_interval_and_boot_timers() {
+        let timer = timer(&task(vec![Trigger::Every(90), Trigger::Boot])).unwrap();
+        let section = &timer[timer.find("[Timer]").unwrap()..timer.find("[Install]").unwrap()];
+        assert_eq!(
+            section,
+            "[Timer]\nOnBootSec=90min\nOnUnitActiveSec=90min\nOnBootSec=1min\n\n"
+        );
+        assert_eq!(
+            super::timer(&task(vec![Trigger::Logon])),
+            Err("Logon triggers are only available on Windows".to_string())
+        );
+    }
+}
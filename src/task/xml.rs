@@ -0,0 +1,459 @@
+/// Task Scheduler XML, the format `schtasks /Create /XML` takes and `schtasks /Query /XML` prints.
+use chrono::Weekday;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+use super::{Task, Time, Trigger};
+
+/// Tasks are registered with `trx8:<fingerprint>` as their documentation, to tell whether the
+/// registered task is the one a profile describes without comparing the XML Windows rewrites.
+const FINGERPRINT_PREFIX: &str = "trx8:";
+
+/// Calendar triggers need a start, the time of day is what matters.
+const START_DATE: &str = "2000-01-01";
+
+/// The output of `schtasks`, UTF-16LE when it starts with a BOM or with ASCII characters
+/// followed by NULs, like the XML of `/Query` on some versions, UTF-8 otherwise.
+#[cfg(any(target_os = "windows", test))]
+pub fn decode_output(bytes: &[u8]) -> String {
+    let (utf16, rest) = match bytes.strip_prefix(&[0xFF, 0xFE]) {
+        Some(rest) => (true, rest),
+        None => (
+            bytes.len().is_multiple_of(2)
+                && bytes.len() >= 2
+                && bytes
+                    .chunks_exact(2)
+                    .take(4)
+                    .all(|pair| pair[0] != 0 && pair[1] == 0),
+            bytes,
+        ),
+    };
+    if !utf16 {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    let units = rest
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&units)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Quotes an argument so `CommandLineToArgvW` gives it back as is.
+pub fn quote_argument(argument: &str) -> String {
+    if !argument.is_empty() && !argument.contains([' ', '\t', '"']) {
+        return argument.to_string();
+    }
+    let mut quoted = String::from("\"");
+    // Backslashes are only special before a quote, so they are counted until the next character.
+    let mut backslashes = 0;
+    for c in argument.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+fn day_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+fn start_boundary(time: Time) -> String {
+    format!("{}T{}:00", START_DATE, time)
+}
+
+/// An ISO 8601 duration like `PT30M` or `P1D`.
+fn duration(minutes: u32) -> String {
+    if minutes.is_multiple_of(60 * 24) {
+        format!("P{}D", minutes / (60 * 24))
+    } else if minutes.is_multiple_of(60) {
+        format!("PT{}H", minutes / 60)
+    } else {
+        format!("PT{}M", minutes)
+    }
+}
+
+fn write_trigger(xml: &mut String, trigger: &Trigger) {
+    match trigger {
+        Trigger::Boot => xml.push_str("    <BootTrigger />\n"),
+        Trigger::Logon => xml.push_str("    <LogonTrigger />\n"),
+        Trigger::Daily(time) => {
+            xml.push_str("    <CalendarTrigger>\n");
+            let _ = writeln!(
+                xml,
+                "      <StartBoundary>{}</StartBoundary>",
+                start_boundary(*time)
+            );
+            xml.push_str("      <ScheduleByDay>\n");
+            xml.push_str("        <DaysInterval>1</DaysInterval>\n");
+            xml.push_str("      </ScheduleByDay>\n");
+            xml.push_str("    </CalendarTrigger>\n");
+        }
+        Trigger::Weekly(days, time) => {
+            xml.push_str("    <CalendarTrigger>\n");
+            let _ = writeln!(
+                xml,
+                "      <StartBoundary>{}</StartBoundary>",
+                start_boundary(*time)
+            );
+            xml.push_str("      <ScheduleByWeek>\n");
+            xml.push_str("        <DaysOfWeek>\n");
+            for day in days {
+                let _ = writeln!(xml, "          <{} />", day_name(*day));
+            }
+            xml.push_str("        </DaysOfWeek>\n");
+            xml.push_str("        <WeeksInterval>1</WeeksInterval>\n");
+            xml.push_str("      </ScheduleByWeek>\n");
+            xml.push_str("    </CalendarTrigger>\n");
+        }
+        Trigger::Every(minutes) => {
+            xml.push_str("    <TimeTrigger>\n");
+            xml.push_str("      <Repetition>\n");
+            let _ = writeln!(xml, "        <Interval>{}</Interval>", duration(*minutes));
+            xml.push_str("      </Repetition>\n");
+            let _ = writeln!(
+                xml,
+                "      <StartBoundary>{}</StartBoundary>",
+                start_boundary(Time { hour: 0, minute: 0 })
+            );
+            xml.push_str("    </TimeTrigger>\n");
+        }
+    }
+}
+
+fn write_task(task: &Task, fingerprint: Option<&str>) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-16\"?>\n");
+    xml.push_str(
+        "<Task version=\"1.2\" xmlns=\"http://schemas.microsoft.com/windows/2004/02/mit/task\">\n",
+    );
+    xml.push_str("  <RegistrationInfo>\n");
+    xml.push_str("    <Author>trx8</Author>\n");
+    if let Some(description) = &task.description {
+        let _ = writeln!(
+            xml,
+            "    <Description>{}</Description>",
+            escape(description)
+        );
+    }
+    if let Some(fingerprint) = fingerprint {
+        let _ = writeln!(
+            xml,
+            "    <Documentation>{}{}</Documentation>",
+            FINGERPRINT_PREFIX, fingerprint
+        );
+    }
+    xml.push_str("  </RegistrationInfo>\n");
+
+    xml.push_str("  <Triggers>\n");
+    for trigger in &task.triggers {
+        write_trigger(&mut xml, trigger);
+    }
+    xml.push_str("  </Triggers>\n");
+
+    xml.push_str("  <Principals>\n");
+    xml.push_str("    <Principal id=\"Author\">\n");
+    match &task.user {
+        Some(user) => {
+            let _ = writeln!(xml, "      <UserId>{}</UserId>", escape(user));
+            xml.push_str("      <LogonType>InteractiveToken</LogonType>\n");
+            xml.push_str("      <RunLevel>LeastPrivilege</RunLevel>\n");
+        }
+        None => {
+            // LocalSystem
+            xml.push_str("      <UserId>S-1-5-18</UserId>\n");
+            xml.push_str("      <RunLevel>HighestAvailable</RunLevel>\n");
+        }
+    }
+    xml.push_str("    </Principal>\n");
+    xml.push_str("  </Principals>\n");
+
+    xml.push_str("  <Settings>\n");
+    xml.push_str("    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>\n");
+    xml.push_str("    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>\n");
+    xml.push_str("    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>\n");
+    xml.push_str("    <StartWhenAvailable>true</StartWhenAvailable>\n");
+    xml.push_str("    <ExecutionTimeLimit>PT72H</ExecutionTimeLimit>\n");
+    let _ = writeln!(xml, "    <Enabled>{}</Enabled>", task.enabled);
+    xml.push_str("  </Settings>\n");
+
+    xml.push_str("  <Actions Context=\"Author\">\n");
+    xml.push_str("    <Exec>\n");
+    let _ = writeln!(xml, "      <Command>{}</Command>", escape(&task.command));
+    if !task.arguments.is_empty() {
+        let arguments = task
+            .arguments
+            .iter()
+            .map(|a| quote_argument(a))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let _ = writeln!(xml, "      <Arguments>{}</Arguments>", escape(&arguments));
+    }
+    if let Some(working_dir) = &task.working_dir {
+        let _ = writeln!(
+            xml,
+            "      <WorkingDirectory>{}</WorkingDirectory>",
+            escape(working_dir)
+        );
+    }
+    xml.push_str("    </Exec>\n");
+    xml.push_str("  </Actions>\n");
+    xml.push_str("</Task>\n");
+    xml
+}
+
+/// A hash of the definition of the task, the same whether the task is enabled or not.
+pub fn fingerprint(task: &Task) -> String {
+    let task = Task {
+        enabled: true,
+        ..task.clone()
+    };
+    hex::encode(Sha256::digest(write_task(&task, None).as_bytes()))
+}
+
+/// The XML of the task, with its [`fingerprint`].
+pub fn render(task: &Task) -> String {
+    write_task(task, Some(&fingerprint(task)))
+}
+
+/// The fingerprint of a task registered by trx8, from its XML.
+pub fn registered_fingerprint(xml: &str) -> Option<&str> {
+    let start = xml.find(&format!("<Documentation>{}", FINGERPRINT_PREFIX))?
+        + "<Documentation>".len()
+        + FINGERPRINT_PREFIX.len();
+    let end = start + xml[start..].find('<')?;
+    Some(&xml[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(triggers: Vec<Trigger>) -> Task {
+        Task {
+            name: r"\trx8\Clean up".to_string(),
+            command: r"C:\Tools\clean.exe".to_string(),
+            arguments: Vec::new(),
+            triggers,
+            description: None,
+            user: None,
+            working_dir: None,
+            enabled: true,
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> Time {
+        Time { hour, minute }
+    }
+
+    #[test]
+    fn decodes_schtasks_output() {
+        let utf16 = |text: &str| {
+            text.encode_utf16()
+                .flat_map(|u| u.to_le_bytes())
+                .collect::<Vec<u8>>()
+        };
+        let xml = "<?xml version=\"1.0\"?>\r\n<Description>Tâche – 日本</Description>";
+        assert_eq!(decode_output(&utf16(xml)), xml);
+        assert_eq!(
+            decode_output(&[[0xFF, 0xFE].as_slice(), &utf16(xml)].concat()),
+            xml
+        );
+        assert_eq!(decode_output("Tâche OK\r\n".as_bytes()), "Tâche OK\r\n");
+        assert_eq!(decode_output(b""), "");
+    }
+
+    #[test]
+    fn renders_a_daily_task() {
+        let mut task = task(vec![Trigger::Daily(at(3, 30))]);
+        task.description = Some("Removes <old> files & logs".to_string());
+        task.arguments = vec!["--days".to_string(), "30".to_string()];
+        task.working_dir = Some(r"C:\Tools".to_string());
+        let xml = render(&task);
+        assert_eq!(
+            xml,
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Author>trx8</Author>
+    <Description>Removes &lt;old&gt; files &amp; logs</Description>
+    <Documentation>trx8:{}</Documentation>
+  </RegistrationInfo>
+  <Triggers>
+    <CalendarTrigger>
+      <StartBoundary>2000-01-01T03:30:00</StartBoundary>
+      <ScheduleByDay>
+        <DaysInterval>1</DaysInterval>
+      </ScheduleByDay>
+    </CalendarTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <UserId>S-1-5-18</UserId>
+      <RunLevel>HighestAvailable</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <StartWhenAvailable>true</StartWhenAvailable>
+    <ExecutionTimeLimit>PT72H</ExecutionTimeLimit>
+    <Enabled>true</Enabled>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>C:\Tools\clean.exe</Command>
+      <Arguments>--days 30</Arguments>
+      <WorkingDirectory>C:\Tools</WorkingDirectory>
+    </Exec>
+  </Actions>
+</Task>
+"#,
+                fingerprint(&task)
+            )
+        );
+    }
+
+    #[test]
+    fn renders_the_other_triggers() {
+        let xml = render(&task(vec![
+            Trigger::Weekly(vec![Weekday::Mon, Weekday::Fri], at(22, 5)),
+            Trigger::Every(30),
+            Trigger::Every(120),
+            Trigger::Every(60 * 24 * 2),
+            Trigger::Boot,
+            Trigger::Logon,
+        ]));
+        let triggers = &xml[xml.find("  <Triggers>").unwrap()..xml.find("  <Principals>").unwrap()];
+        assert_eq!(
+            triggers,
+            r#"  <Triggers>
+    <CalendarTrigger>
+      <StartBoundary>2000-01-01T22:05:00</StartBoundary>
+      <ScheduleByWeek>
+        <DaysOfWeek>
+          <Monday />
+          <Friday />
+        </DaysOfWeek>
+        <WeeksInterval>1</WeeksInterval>
+      </ScheduleByWeek>
+    </CalendarTrigger>
+    <TimeTrigger>
+      <Repetition>
+        <Interval>PT30M</Interval>
+      </Repetition>
+      <StartBoundary>2000-01-01T00:00:00</StartBoundary>
+    </TimeTrigger>
+    <TimeTrigger>
+      <Repetition>
+        <Interval>PT2H</Interval>
+      </Repetition>
+      <StartBoundary>2000-01-01T00:00:00</StartBoundary>
+    </TimeTrigger>
+    <TimeTrigger>
+      <Repetition>
+        <Interval>P2D</Interval>
+      </Repetition>
+      <StartBoundary>2000-01-01T00:00:00</StartBoundary>
+    </TimeTrigger>
+    <BootTrigger />
+    <LogonTrigger />
+  </Triggers>
+"#
+        );
+    }
+
+    #[test]
+    fn runs_as_a_user() {
+        let mut task = task(vec![Trigger::Logon]);
+        task.user = Some(r"DOMAIN\Tom & Jerry".to_string());
+        let xml = render(&task);
+        assert!(xml.contains(
+            "      <UserId>DOMAIN\\Tom &amp; Jerry</UserId>\n      \
+             <LogonType>InteractiveToken</LogonType>\n      \
+             <RunLevel>LeastPrivilege</RunLevel>\n"
+        ));
+        assert!(!xml.contains("S-1-5-18"));
+    }
+
+    #[test]
+    fn quotes_and_escapes_arguments() {
+        assert_eq!(quote_argument("plain"), "plain");
+        assert_eq!(quote_argument(""), r#""""#);
+        assert_eq!(quote_argument("two words"), r#""two words""#);
+        assert_eq!(quote_argument(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_argument(r"C:\a dir\"), r#""C:\a dir\\""#);
+        assert_eq!(quote_argument(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(quote_argument(r"C:\no\spaces"), r"C:\no\spaces");
+
+        let mut task = task(vec![Trigger::Boot]);
+        task.arguments = vec!["-c".to_string(), r#"echo "a<b""#.to_string()];
+        assert!(
+            render(&task)
+                .contains("<Arguments>-c &quot;echo \\&quot;a&lt;b\\&quot;&quot;</Arguments>")
+        );
+    }
+
+    #[test]
+    fn fingerprints_the_definition() {
+        let task = task(vec![Trigger::Daily(at(3, 0))]);
+        let xml = render(&task);
+        assert_eq!(
+            registered_fingerprint(&xml),
+            Some(fingerprint(&task).as_str())
+        );
+        assert_eq!(fingerprint(&task).len(), 64);
+
+        // Enabling or disabling the task keeps its fingerprint, any other change doesn't.
+        let disabled = Task {
+            enabled: false,
+            ..task.clone()
+        };
+        assert!(render(&disabled).contains("<Enabled>false</Enabled>"));
+        assert_eq!(fingerprint(&disabled), fingerprint(&task));
+        let later = Task {
+            triggers: vec![Trigger::Daily(at(4, 0))],
+            ..task.clone()
+        };
+        assert_ne!(fingerprint(&later), fingerprint(&task));
+        let other = Task {
+            arguments: vec!["--all".to_string()],
+            ..task.clone()
+        };
+        assert_ne!(fingerprint(&other), fingerprint(&task));
+
+        assert_eq!(
+            registered_fingerprint("<Task><Documentation>Made by hand</Documentation></Task>"),
+            None
+        );
+    }
+}
@@ -0,0 +1,56 @@
+/// The `appx_remove` action, removing built-in apps for every user and from the image.
+use tracing::{info, warn};
+
+use super::params::Params;
+use super::{ActionStatus, report_change};
+use crate::appx::{self, Patterns};
+
+fn appx_remove_impl(params: &Params) -> Result<bool, String> {
+    params.require(0, "pattern")?;
+    let patterns = Patterns::parse(params.positional())?;
+    let (installed, provisioned) = match params.option("only") {
+        None => (true, true),
+        Some("installed") => (true, false),
+        Some("provisioned") => (false, true),
+        Some(other) => {
+            return Err(format!(
+                "Unknown value for --only: {} (expected installed or provisioned)",
+                other
+            ));
+        }
+    };
+
+    let mut scripts = Vec::new();
+    if installed {
+        for package in appx::parse_installed(&appx::run(appx::LIST_INSTALLED)?)? {
+            if !patterns.matches(&package.name) {
+                continue;
+            }
+            if package.non_removable {
+                warn!("[appx_remove] {} cannot be removed", package.name);
+                continue;
+            }
+            info!("[appx_remove] Removing {}", package.full_name);
+            scripts.push(appx::remove_installed(&package));
+        }
+    }
+    if provisioned {
+        for package in appx::parse_provisioned(&appx::run(appx::LIST_PROVISIONED)?)? {
+            if patterns.matches(&package.display_name) {
+                info!("[appx_remove] Deprovisioning {}", package.package_name);
+                scripts.push(appx::remove_provisioned(&package));
+            }
+        }
+    }
+    if scripts.is_empty() {
+        return Ok(false);
+    }
+    appx::run(&scripts.join("; "))?;
+    Ok(true)
+}
+
+/// `appx_remove <pattern>... [--only=installed|provisioned]`, patterns match package names
+/// like `Microsoft.BingNews` regardless of case.
+pub fn appx_remove(args: &[String]) -> ActionStatus {
+    report_change("appx_remove", appx_remove_impl(&Params::parse(args)))
+}
@@ -1,6 +1,11 @@
 /// A simple console tool that prints messages to the console.
 use tracing::info;
 
-pub fn echo(message: &str) {
+use super::ActionStatus;
+use crate::events::{self, Stream};
+
+pub fn echo(message: &str) -> ActionStatus {
     info!("[echo]: {}", message);
+    events::output("echo", Stream::Stdout, message);
+    ActionStatus::Success
 }
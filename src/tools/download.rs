@@ -0,0 +1,360 @@
+/// Downloads over HTTP(S), verified with SHA-256 and cached by hash so re-runs skip the network.
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use ureq::{Agent, Proxy};
+
+use super::fs::same_content;
+use super::params::Params;
+use super::{ActionStatus, report_change};
+use crate::constants::PROJECT_DIR;
+
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Verified downloads are kept as `<cache dir>/download/<sha256>`.
+fn cache_dir() -> PathBuf {
+    PROJECT_DIR.cache_dir().join("download")
+}
+
+/// The proxy is `--proxy`, or taken from `ALL_PROXY`, `HTTPS_PROXY` and `HTTP_PROXY`.
+fn agent(params: &Params) -> Result<Agent, String> {
+    let proxy = match params.option("proxy") {
+        Some(url) => Some(Proxy::new(url).map_err(|e| format!("Invalid proxy {}: {}", url, e))?),
+        None => Proxy::try_from_env(),
+    };
+    let config = Agent::config_builder()
+        .proxy(proxy)
+        .max_redirects(10)
+        .timeout_connect(Some(Duration::from_secs(30)))
+        .user_agent(concat!("trx8/", env!("CARGO_PKG_VERSION")))
+        .build();
+    Ok(config.into())
+}
+
+/// Downloads to `part`, resuming from where a previous attempt stopped if the server supports it.
+fn fetch(agent: &Agent, url: &str, part: &Path) -> Result<(), String> {
+    let offset = fs::metadata(part).map_or(0, |m| m.len());
+    let mut request = agent.get(url);
+    if offset > 0 {
+        request = request.header("Range", format!("bytes={}-", offset));
+    }
+    let mut response = match request.call() {
+        // The partial file is no use, start over.
+        Err(ureq::Error::StatusCode(416)) if offset > 0 => {
+            fs::remove_file(part).map_err(|e| e.to_string())?;
+            return fetch(agent, url, part);
+        }
+        result => result.map_err(|e| format!("Failed to download {}: {}", url, e))?,
+    };
+    let resumed = offset > 0 && response.status() == 206;
+    if resumed {
+        info!(
+            "[download] Resuming download of {} at {} bytes",
+            url, offset
+        );
+    }
+    if let Some(parent) = part.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(part)
+        .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+    let written = io::copy(&mut response.body_mut().as_reader(), &mut file)
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    debug!("[download] {} bytes written to {}", written, part.display());
+    Ok(())
+}
+
+/// Moves the file into place, falling back to a copy across volumes.
+fn place(src: &Path, dst: &Path, keep: bool) -> Result<(), String> {
+    if let Some(parent) = dst.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if keep || fs::rename(src, dst).is_err() {
+        fs::copy(src, dst).map_err(|e| format!("Failed to write {}: {}", dst.display(), e))?;
+        if !keep {
+            fs::remove_file(src).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// A download into an existing directory is named after the last segment of the URL.
+fn target_path(url: &str, destination: &Path) -> Result<PathBuf, String> {
+    if !destination.is_dir() {
+        return Ok(destination.to_path_buf());
+    }
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => Ok(destination.join(name)),
+        _ => Err(format!("Cannot tell the file name from {}", url)),
+    }
+}
+
+/// `cache` is the directory of verified downloads, see [`cache_dir`].
+fn download_impl(params: &Params, cache: &Path) -> Result<bool, String> {
+    let url = params.require(0, "url")?;
+    let destination = target_path(url, Path::new(params.require(1, "destination")?))?;
+    let agent = agent(params)?;
+
+    let Some(expected) = params.option("sha256").map(str::to_lowercase) else {
+        warn!(
+            "[download] No --sha256 given, {} can't be verified or cached",
+            url
+        );
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_bytes());
+        let part = cache.join(format!("{}.part", hex::encode(hasher.finalize())));
+        let _ = fs::remove_file(&part);
+        fetch(&agent, url, &part)?;
+        let same = destination.is_file()
+            && same_content(&part, &destination).map_err(|e| e.to_string())?;
+        if same {
+            let _ = fs::remove_file(&part);
+            return Ok(false);
+        }
+        place(&part, &destination, false)?;
+        return Ok(true);
+    };
+    if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid SHA-256: {}", expected));
+    }
+
+    let verified = |path: &Path| -> Result<bool, String> {
+        Ok(path.is_file() && sha256_file(path).map_err(|e| e.to_string())? == expected)
+    };
+    if verified(&destination)? {
+        return Ok(false);
+    }
+    let cached = cache.join(&expected);
+    if verified(&cached)? {
+        info!("[download] Using cached copy of {}", url);
+    } else {
+        let part = cached.with_extension("part");
+        fetch(&agent, url, &part)?;
+        let actual = sha256_file(&part).map_err(|e| e.to_string())?;
+        if actual != expected {
+            let _ = fs::remove_file(&part);
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                url, expected, actual
+            ));
+        }
+        place(&part, &cached, false)?;
+    }
+    place(&cached, &destination, true)?;
+    Ok(true)
+}
+
+/// `download <url> <destination> [--sha256=<hash>] [--proxy=<url>]`
+pub fn download(args: &[String]) -> ActionStatus {
+    report_change(
+        "download",
+        download_impl(&Params::parse(args), &cache_dir()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// A local HTTP server answering every request with `respond(path, range start)`, the
+    /// request lines and `Range` headers it got are kept in `requests`.
+    struct Server {
+        url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    fn serve(respond: impl Fn(&str, Option<usize>) -> Vec<u8> + Send + 'static) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut head = Vec::new();
+                let mut byte = [0];
+                while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                    head.push(byte[0]);
+                }
+                let head = String::from_utf8(head).unwrap().to_lowercase();
+                let target = head.split(' ').nth(1).unwrap_or_default();
+                let path = target.split('?').next().unwrap_or_default().to_string();
+                let range = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .and_then(|range| range.trim_end_matches('-').parse().ok());
+                let mut request = format!("GET {}", path);
+                if let Some(start) = range {
+                    request.push_str(&format!(" from {}", start));
+                }
+                seen.lock().unwrap().push(request);
+                let _ = stream.write_all(&respond(&path, range));
+            }
+        });
+        Server { url, requests }
+    }
+
+    fn response(status: &str, headers: &[String], body: &[u8]) -> Vec<u8> {
+        let mut out = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+        for header in headers {
+            out.push_str(&format!("{}\r\n", header));
+        }
+        out.push_str("Connection: close\r\n\r\n");
+        let mut out = out.into_bytes();
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn content() -> Vec<u8> {
+        (0..4000).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn sha256(bytes: &[u8]) -> String {
+        hex::encode(Sha256::digest(bytes))
+    }
+
+    fn download(args: &[String], cache: &Path) -> Result<bool, String> {
+        download_impl(&Params::parse(args), cache)
+    }
+
+    fn args(url: &str, destination: &Path, sha256: Option<&str>) -> Vec<String> {
+        let mut args = vec![url.to_string(), destination.display().to_string()];
+        if let Some(sha256) = sha256 {
+            args.push(format!("--sha256={}", sha256));
+        }
+        args
+    }
+
+    /// Serves `content()` at `/file`, honouring `Range` only if `ranges` is set.
+    fn file_server(ranges: bool) -> Server {
+        serve(move |path, range| match (path, range) {
+            ("/old", _) => response("302 Found", &["Location: /file".to_string()], b""),
+            ("/file", Some(start)) if ranges => {
+                let body = &content()[start..];
+                let total = content().len();
+                let header = format!("Content-Range: bytes {}-{}/{}", start, total - 1, total);
+                response("206 Partial Content", &[header], body)
+            }
+            ("/file", _) => response("200 OK", &[], &content()),
+            _ => response("404 Not Found", &[], b""),
+        })
+    }
+
+    #[test]
+    fn follows_redirects_and_caches() {
+        let server = file_server(true);
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let destination = dir.path().join("file.bin");
+        let hash = sha256(&content());
+        let args = args(&format!("{}/old", server.url), &destination, Some(&hash));
+
+        assert_eq!(download(&args, &cache), Ok(true));
+        assert_eq!(fs::read(&destination).unwrap(), content());
+        assert_eq!(fs::read(cache.join(&hash)).unwrap(), content());
+        assert_eq!(*server.requests.lock().unwrap(), ["GET /old", "GET /file"]);
+
+        assert_eq!(download(&args, &cache), Ok(false));
+        assert_eq!(server.requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn cache_hit_skips_the_network() {
+        let server = file_server(true);
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let hash = sha256(b"cached");
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join(&hash), b"cached").unwrap();
+        let destination = dir.path().join("sub").join("file.bin");
+
+        let args = args(&format!("{}/file", server.url), &destination, Some(&hash));
+        assert_eq!(download(&args, &cache), Ok(true));
+        assert_eq!(fs::read(&destination).unwrap(), b"cached");
+        // The cached copy stays for the next run.
+        assert!(cache.join(&hash).is_file());
+        assert!(server.requests.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn checksum_mismatch_removes_the_partial_file() {
+        let server = file_server(true);
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let destination = dir.path().join("file.bin");
+        let hash = sha256(b"something else");
+
+        let args = args(&format!("{}/file", server.url), &destination, Some(&hash));
+        let error = download(&args, &cache).unwrap_err();
+        assert!(error.starts_with("Checksum mismatch"), "{}", error);
+        assert!(!destination.exists());
+        assert!(!cache.join(&hash).exists());
+        assert!(!cache.join(&hash).with_extension("part").exists());
+    }
+
+    #[test]
+    fn resumes_a_partial_download() {
+        let server = file_server(true);
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let destination = dir.path().join("file.bin");
+        let hash = sha256(&content());
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join(&hash).with_extension("part"), &content()[..1500]).unwrap();
+
+        let args = args(&format!("{}/file", server.url), &destination, Some(&hash));
+        assert_eq!(download(&args, &cache), Ok(true));
+        assert_eq!(fs::read(&destination).unwrap(), content());
+        assert_eq!(*server.requests.lock().unwrap(), ["GET /file from 1500"]);
+    }
+
+    #[test]
+    fn restarts_when_the_server_ignores_ranges() {
+        let server = file_server(false);
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let destination = dir.path().join("file.bin");
+        let hash = sha256(&content());
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join(&hash).with_extension("part"), &content()[..1500]).unwrap();
+
+        let args = args(&format!("{}/file", server.url), &destination, Some(&hash));
+        assert_eq!(download(&args, &cache), Ok(true));
+        assert_eq!(fs::read(&destination).unwrap(), content());
+        assert_eq!(*server.requests.lock().unwrap(), ["GET /file from 1500"]);
+    }
+
+    #[test]
+    fn downloads_into_a_directory_without_a_checksum() {
+        let server = file_server(true);
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+
+        let args = args(&format!("{}/file?v=1", server.url), dir.path(), None);
+        assert_eq!(download(&args, &cache), Ok(true));
+        assert_eq!(fs::read(dir.path().join("file")).unwrap(), content());
+        // Without a checksum it is downloaded again, but an identical file is unchanged.
+        assert_eq!(download(&args, &cache), Ok(false));
+        assert_eq!(server.requests.lock().unwrap().len(), 2);
+    }
+}
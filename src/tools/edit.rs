@@ -0,0 +1,260 @@
+/// Text editing actions, `line_in_file` and `replace_in_file`.
+use regex::Regex;
+use std::path::Path;
+use tracing::{debug, info};
+
+use super::params::Params;
+use super::text::{Encoding, TextFile};
+use super::{ActionStatus, report_change};
+
+/// Opens the file, or returns an empty one when it is missing and `create` is set.
+///
+/// `--encoding` is only used for files that are created, existing ones keep theirs.
+fn open(path: &Path, params: &Params, create: bool) -> Result<Option<TextFile>, String> {
+    if path.exists() {
+        return TextFile::read(path).map(Some);
+    }
+    if !create {
+        return Ok(None);
+    }
+    let encoding = match params.option("encoding") {
+        Some(name) => Encoding::parse(name)?,
+        None => Encoding::Utf8,
+    };
+    Ok(Some(TextFile::new(encoding)))
+}
+
+/// Writes the file if its content differs from `original`, returns whether it was written.
+fn save(
+    path: &Path,
+    file: &TextFile,
+    original: Option<&str>,
+    params: &Params,
+) -> Result<bool, String> {
+    if original == Some(file.content.as_str()) {
+        return Ok(false);
+    }
+    if let Some(backup) = file.write(path, params.flag("backup"))? {
+        info!(
+            "Backup of {} written to {}",
+            path.display(),
+            backup.display()
+        );
+    }
+    Ok(true)
+}
+
+fn regex_option(params: &Params, name: &str) -> Result<Option<Regex>, String> {
+    params
+        .option(name)
+        .map(|r| Regex::new(r).map_err(|e| format!("Invalid regex for --{}: {}", name, e)))
+        .transpose()
+}
+
+fn line_in_file_impl(params: &Params) -> Result<bool, String> {
+    let path = Path::new(params.require(0, "path")?);
+    let line = params.require(1, "line")?;
+    let regex = regex_option(params, "regex")?;
+    let present = match params.option("state") {
+        None | Some("present") => true,
+        Some("absent") => false,
+        Some(other) => {
+            return Err(format!(
+                "Invalid state: {} (expected present or absent)",
+                other
+            ));
+        }
+    };
+    let matches = |l: &str| match &regex {
+        Some(regex) => regex.is_match(l),
+        None => l == line,
+    };
+
+    let Some(mut file) = open(path, params, present && params.flag("create"))? else {
+        if present {
+            return Err(format!(
+                "{} does not exist, pass --create to create it",
+                path.display()
+            ));
+        }
+        return Ok(false);
+    };
+    let original = path.exists().then(|| file.content.clone());
+    let mut lines = file.lines();
+
+    if !present {
+        lines.retain(|l| !matches(l));
+    } else if let Some(i) = lines.iter().rposition(|l| matches(l)) {
+        // With a regex the last matching line is replaced, otherwise the line is already there.
+        lines[i] = line.to_string();
+    } else {
+        let index = if let Some(after) = params.option("insert-after") {
+            match after {
+                "EOF" => lines.len(),
+                _ => {
+                    let after = Regex::new(after)
+                        .map_err(|e| format!("Invalid regex for --insert-after: {}", e))?;
+                    lines
+                        .iter()
+                        .rposition(|l| after.is_match(l))
+                        .map_or(lines.len(), |i| i + 1)
+                }
+            }
+        } else if let Some(before) = params.option("insert-before") {
+            match before {
+                "BOF" => 0,
+                _ => {
+                    let before = Regex::new(before)
+                        .map_err(|e| format!("Invalid regex for --insert-before: {}", e))?;
+                    lines
+                        .iter()
+                        .rposition(|l| before.is_match(l))
+                        .unwrap_or(lines.len())
+                }
+            }
+        } else {
+            lines.len()
+        };
+        debug!("[line_in_file] Inserting line at {}", index + 1);
+        lines.insert(index, line.to_string());
+    }
+    if file.content.is_empty() {
+        file.trailing_newline = true;
+    }
+    file.set_lines(&lines);
+    save(path, &file, original.as_deref(), params)
+}
+
+fn replace_in_file_impl(params: &Params) -> Result<bool, String> {
+    let path = Path::new(params.require(0, "path")?);
+    let pattern = params.require(1, "regex")?;
+    let replacement = params.require(2, "replacement")?;
+    let regex = Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+    let Some(mut file) = open(path, params, false)? else {
+        return Err(format!("{} does not exist", path.display()));
+    };
+    let original = file.content.clone();
+    let count = regex.find_iter(&original).count();
+    file.content = regex.replace_all(&original, replacement).into_owned();
+    debug!(
+        "[replace_in_file] {} match(es) in {}",
+        count,
+        path.display()
+    );
+    save(path, &file, Some(&original), params)
+}
+
+/// `line_in_file <path> <line> [--state=present|absent] [--regex=<regex>]
+/// [--insert-after=<regex>|EOF] [--insert-before=<regex>|BOF] [--create] [--backup] [--encoding=<encoding>]`
+pub fn line_in_file(args: &[String]) -> ActionStatus {
+    report_change("line_in_file", line_in_file_impl(&Params::parse(args)))
+}
+
+/// `replace_in_file <path> <regex> <replacement> [--backup]`
+pub fn replace_in_file(args: &[String]) -> ActionStatus {
+    report_change(
+        "replace_in_file",
+        replace_in_file_impl(&Params::parse(args)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::text::LineEnding;
+    use std::fs;
+
+    fn args(path: &Path, args: &[&str]) -> Vec<String> {
+        [path.to_str().unwrap()]
+            .iter()
+            .chain(args)
+            .map(|arg| arg.to_string())
+            .collect()
+    }
+
+    fn line_in_file(path: &Path, rest: &[&str]) -> Result<bool, String> {
+        line_in_file_impl(&Params::parse(&args(path, rest)))
+    }
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn edits_lines_in_the_encoding_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.ini");
+        fs::write(&path, utf16le("[Général]\r\nlangue=en\r\n[Autre]")).unwrap();
+
+        assert_eq!(
+            line_in_file(&path, &["langue=fr", "--regex=^langue="]),
+            Ok(true)
+        );
+        assert_eq!(
+            line_in_file(&path, &["thème=sombre", "--insert-after=^\\[Général\\]"]),
+            Ok(true)
+        );
+        assert_eq!(
+            line_in_file(&path, &["[Début]", "--insert-before=BOF"]),
+            Ok(true)
+        );
+        assert_eq!(line_in_file(&path, &["langue=fr"]), Ok(false));
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            utf16le("[Début]\r\n[Général]\r\nthème=sombre\r\nlangue=fr\r\n[Autre]")
+        );
+
+        assert_eq!(
+            line_in_file(&path, &["", "--regex=^\\[", "--state=absent"]),
+            Ok(true)
+        );
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            utf16le("thème=sombre\r\nlangue=fr")
+        );
+    }
+
+    #[test]
+    fn creates_missing_files_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.txt");
+        assert_eq!(
+            line_in_file(&path, &["line"]),
+            Err(format!(
+                "{} does not exist, pass --create to create it",
+                path.display()
+            ))
+        );
+        assert_eq!(line_in_file(&path, &["line", "--state=absent"]), Ok(false));
+        assert_eq!(
+            line_in_file(&path, &["é", "--create", "--encoding=utf8-bom"]),
+            Ok(true)
+        );
+        let newline = match LineEnding::native() {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        };
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            [&[0xEF, 0xBB, 0xBF], format!("é{}", newline).as_bytes()].concat()
+        );
+    }
+
+    #[test]
+    fn replaces_in_the_whole_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts.conf");
+        fs::write(&path, "host=old.example\r\nport=80\r\n").unwrap();
+        let replace = |rest: &[&str]| replace_in_file_impl(&Params::parse(&args(&path, rest)));
+        assert_eq!(replace(&["(?m)^host=.*$", "host=new.example"]), Ok(true));
+        assert_eq!(replace(&["(?m)^host=.*$", "host=new.example"]), Ok(false));
+        assert_eq!(replace(&["port=(\\d+)", "port=${1}80"]), Ok(true));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "host=new.example\r\nport=8080\r\n"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+/// Actions editing persistent environment variables, as opposed to the environment of the
+/// subprocesses started by trx8.
+use std::path::Path;
+use tracing::info;
+
+use super::params::Params;
+use super::{ActionStatus, report_change};
+use crate::environment::{self, Backend, EnvFile, Position, RegistryEnvironment, Scope};
+use crate::registry::{HiveFile, Key};
+
+/// The variables of `--scope` (`user` by default), or those of `--file` or `--hive`.
+fn open(params: &Params) -> Result<Box<dyn Backend>, String> {
+    let scope = Scope::parse(params.option("scope").unwrap_or("user"))?;
+    if let Some(path) = params.option("file") {
+        return Ok(Box::new(EnvFile::open(path.into())?));
+    }
+    if let Some(path) = params.option("hive") {
+        let mount = params.option("mount").map(Key::parse).transpose()?;
+        let hive = HiveFile::open(Path::new(path), mount)?;
+        return Ok(Box::new(RegistryEnvironment::new(
+            Box::new(hive),
+            scope,
+            false,
+        )));
+    }
+    environment::open(scope)
+}
+
+/// Runs `operation` on the variables and writes the changes out.
+fn with_environment(
+    params: &Params,
+    action: &str,
+    operation: impl FnOnce(&mut dyn Backend) -> Result<bool, String>,
+) -> Result<bool, String> {
+    let mut backend = open(params)?;
+    let changed = operation(backend.as_mut())?;
+    if changed {
+        backend.flush()?;
+        info!("[{}] Updated {}", action, backend.location());
+    }
+    Ok(changed)
+}
+
+fn env_set_impl(params: &Params) -> Result<bool, String> {
+    let name = params.require(0, "name")?;
+    let value = params.require(1, "value")?;
+    with_environment(params, "env_set", |backend| {
+        environment::ensure_value(backend, name, value)
+    })
+}
+
+fn env_add_impl(params: &Params, action: &str, position: Position) -> Result<bool, String> {
+    let name = params.require(0, "name")?;
+    params.require(1, "entry")?;
+    let entries = &params.positional()[1..];
+    with_environment(params, action, |backend| {
+        environment::ensure_entries(backend, name, entries, position)
+    })
+}
+
+fn env_remove_impl(params: &Params) -> Result<bool, String> {
+    let name = params.require(0, "name")?;
+    let entries = &params.positional()[1..];
+    with_environment(params, "env_remove", |backend| {
+        if entries.is_empty() {
+            environment::remove_variable(backend, name)
+        } else {
+            environment::remove_entries(backend, name, entries)
+        }
+    })
+}
+
+/// `env_set <name> <value> [--scope=user|machine] [--file=<path>] [--hive=<file>]`
+pub fn env_set(args: &[String]) -> ActionStatus {
+    report_change("env_set", env_set_impl(&Params::parse(args)))
+}
+
+/// `env_append <name> <entry>...`, adds the entries missing from a list variable like `PATH`.
+pub fn env_append(args: &[String]) -> ActionStatus {
+    let params = Params::parse(args);
+    report_change(
+        "env_append",
+        env_add_impl(&params, "env_append", Position::Append),
+    )
+}
+
+/// `env_prepend <name> <entry>...`, puts the entries first in a list variable like `PATH`.
+pub fn env_prepend(args: &[String]) -> ActionStatus {
+    let params = Params::parse(args);
+    report_change(
+        "env_prepend",
+        env_add_impl(&params, "env_prepend", Position::Prepend),
+    )
+}
+
+/// `env_remove <name> [<entry>...]`, removes the entries from the list variable, or the
+/// variable itself without entries.
+pub fn env_remove(args: &[String]) -> ActionStatus {
+    report_change("env_remove", env_remove_impl(&Params::parse(args)))
+}
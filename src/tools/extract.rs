@@ -0,0 +1,523 @@
+/// Native extraction of zip, tar (plain, gzip, bzip2, xz) and 7z archives.
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use super::download::sha256_file;
+use super::fs::same_content;
+use super::params::Params;
+use super::{ActionStatus, report_change};
+use crate::constants::PROJECT_DIR;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    TarXz,
+    SevenZ,
+}
+
+impl Format {
+    /// From `--format`, or the file name.
+    fn detect(archive: &Path, params: &Params) -> Result<Self, String> {
+        let name = match params.option("format") {
+            Some(format) => format!(".{}", format.to_lowercase()),
+            None => archive
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+        };
+        let formats = [
+            (".zip", Format::Zip),
+            (".tar", Format::Tar),
+            (".tar.gz", Format::TarGz),
+            (".tgz", Format::TarGz),
+            (".tar.bz2", Format::TarBz2),
+            (".tbz2", Format::TarBz2),
+            (".tar.xz", Format::TarXz),
+            (".txz", Format::TarXz),
+            (".7z", Format::SevenZ),
+        ];
+        formats
+            .into_iter()
+            .find(|(extension, _)| name.ends_with(extension))
+            .map(|(_, format)| format)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown archive format for {}, pass --format=zip|tar|tar.gz|tar.bz2|tar.xz|7z",
+                    archive.display()
+                )
+            })
+    }
+}
+
+/// An entry of an archive, `name` is the raw path stored in the archive.
+struct Entry<'r> {
+    name: String,
+    dir: bool,
+    /// Unix permissions, if the archive has them.
+    mode: Option<u32>,
+    reader: &'r mut dyn Read,
+}
+
+type Visitor<'v> = dyn FnMut(Entry) -> Result<(), String> + 'v;
+
+fn walk_tar(reader: impl Read, visit: &mut Visitor) -> Result<(), String> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let kind = entry.header().entry_type();
+        if !kind.is_file() && !kind.is_dir() {
+            warn!(
+                "[extract] Skipping {}, links and special files are not extracted",
+                name
+            );
+            continue;
+        }
+        let mode = entry.header().mode().ok();
+        visit(Entry {
+            name,
+            dir: kind.is_dir(),
+            mode,
+            reader: &mut entry,
+        })?;
+    }
+    Ok(())
+}
+
+fn walk_zip(file: fs::File, visit: &mut Visitor) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        let name = entry.name().to_string();
+        if entry.is_symlink() {
+            warn!("[extract] Skipping {}, links are not extracted", name);
+            continue;
+        }
+        let dir = entry.is_dir();
+        let mode = entry.unix_mode();
+        visit(Entry {
+            name,
+            dir,
+            mode,
+            reader: &mut entry,
+        })?;
+    }
+    Ok(())
+}
+
+fn walk_7z(archive: &Path, visit: &mut Visitor) -> Result<(), String> {
+    let mut reader = sevenz_rust::SevenZReader::open(archive, sevenz_rust::Password::empty())
+        .map_err(|e| e.to_string())?;
+    // The callback can only return the crate's errors, ours are kept aside.
+    let mut failure = None;
+    reader
+        .for_each_entries(|entry, reader| {
+            let result = visit(Entry {
+                name: entry.name().to_string(),
+                dir: entry.is_directory(),
+                mode: None,
+                reader,
+            });
+            match result {
+                Ok(()) => Ok(true),
+                Err(e) => {
+                    failure = Some(e);
+                    Ok(false)
+                }
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    failure.map_or(Ok(()), Err)
+}
+
+fn walk(archive: &Path, format: Format, visit: &mut Visitor) -> Result<(), String> {
+    let file = fs::File::open(archive)
+        .map_err(|e| format!("Failed to open {}: {}", archive.display(), e))?;
+    let file = io::BufReader::new(file);
+    match format {
+        Format::Zip => walk_zip(file.into_inner(), visit),
+        Format::Tar => walk_tar(file, visit),
+        Format::TarGz => walk_tar(flate2::read::GzDecoder::new(file), visit),
+        Format::TarBz2 => walk_tar(bzip2::read::BzDecoder::new(file), visit),
+        Format::TarXz => walk_tar(lzma_rust2::XzReader::new(file, true), visit),
+        Format::SevenZ => walk_7z(archive, visit),
+    }
+}
+
+/// The path of an entry relative to the destination, after removing `strip` leading components.
+///
+/// Absolute paths, drive letters and `..` are rejected so an archive can't write outside the
+/// destination.
+fn relative_path(name: &str, strip: usize) -> Result<Option<PathBuf>, String> {
+    let mut parts = Vec::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => {
+                return Err(format!(
+                    "Refusing to extract {}, it leaves the destination",
+                    name
+                ));
+            }
+            _ if part.contains(':') => {
+                return Err(format!(
+                    "Refusing to extract {}, it is an absolute path",
+                    name
+                ));
+            }
+            _ => parts.push(part),
+        }
+    }
+    if name.starts_with(['/', '\\']) {
+        return Err(format!(
+            "Refusing to extract {}, it is an absolute path",
+            name
+        ));
+    }
+    let path = parts.iter().skip(strip).collect::<PathBuf>();
+    // Make sure nothing like a Windows prefix slipped through.
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!(
+            "Refusing to extract {}, it leaves the destination",
+            name
+        ));
+    }
+    Ok((!path.as_os_str().is_empty()).then_some(path))
+}
+
+struct Filter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Filter {
+    fn parse(params: &Params) -> Result<Self, String> {
+        let patterns = |name: &str| {
+            params
+                .options(name)
+                .into_iter()
+                .map(|p| Pattern::new(p).map_err(|e| format!("Invalid pattern {}: {}", p, e)))
+                .collect::<Result<Vec<Pattern>, String>>()
+        };
+        Ok(Filter {
+            include: patterns("include")?,
+            exclude: patterns("exclude")?,
+        })
+    }
+
+    /// Matches the path after stripping, `*` stays within a directory and `**` crosses them.
+    fn matches(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        let any = |patterns: &[Pattern]| patterns.iter().any(|p| p.matches_with(&path, options));
+        (self.include.is_empty() || any(&self.include)) && !any(&self.exclude)
+    }
+}
+
+/// Writes the entry through a temporary file, and only replaces the target if it differs.
+fn write_entry(reader: &mut dyn Read, target: &Path, mode: Option<u32>) -> io::Result<bool> {
+    let mut temp = target.as_os_str().to_owned();
+    temp.push(".trx8-part");
+    let temp = PathBuf::from(temp);
+    io::copy(reader, &mut fs::File::create(&temp)?)?;
+    if target.is_file() && same_content(&temp, target)? {
+        fs::remove_file(&temp)?;
+        return Ok(false);
+    }
+    fs::rename(&temp, target)?;
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    Ok(true)
+}
+
+/// Markers are kept as `<cache dir>/extract/<sha256 of the destination>.json`.
+fn markers_dir() -> PathBuf {
+    PROJECT_DIR.cache_dir().join("extract")
+}
+
+/// What was extracted where, kept in the user cache directory so a re-run can skip the work.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Marker {
+    archive_sha256: String,
+    options: Vec<String>,
+    files: Vec<PathBuf>,
+}
+
+impl Marker {
+    fn path(markers: &Path, destination: &Path) -> PathBuf {
+        let destination = fs::canonicalize(destination).unwrap_or(destination.to_path_buf());
+        let mut hasher = Sha256::new();
+        hasher.update(destination.to_string_lossy().as_bytes());
+        markers.join(format!("{}.json", hex::encode(hasher.finalize())))
+    }
+
+    fn load(markers: &Path, destination: &Path) -> Option<Self> {
+        let text = fs::read_to_string(Self::path(markers, destination)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    fn save(&self, markers: &Path, destination: &Path) -> Result<(), String> {
+        let path = Self::path(markers, destination);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// `markers` is the directory of the [`Marker`]s, see [`markers_dir`].
+fn extract_impl(params: &Params, markers: &Path) -> Result<bool, String> {
+    let archive = Path::new(params.require(0, "archive")?);
+    let destination = Path::new(params.require(1, "destination")?);
+    let format = Format::detect(archive, params)?;
+    let strip = match params.option("strip-components") {
+        Some(n) => n
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid --strip-components: {}", n))?,
+        None => 0,
+    };
+    let filter = Filter::parse(params)?;
+
+    let archive_sha256 =
+        sha256_file(archive).map_err(|e| format!("Failed to read {}: {}", archive.display(), e))?;
+    let options = ["strip-components", "include", "exclude"]
+        .iter()
+        .flat_map(|name| {
+            params
+                .options(name)
+                .into_iter()
+                .map(move |v| format!("{}={}", name, v))
+        })
+        .collect::<Vec<String>>();
+    if let Some(marker) = Marker::load(markers, destination)
+        && marker.archive_sha256 == archive_sha256
+        && marker.options == options
+        && marker.files.iter().all(|f| destination.join(f).exists())
+    {
+        debug!("[extract] {} was already extracted", archive.display());
+        return Ok(false);
+    }
+
+    let mut changed = false;
+    let mut files = Vec::new();
+    walk(archive, format, &mut |entry| {
+        let Some(path) = relative_path(&entry.name, strip)? else {
+            return Ok(());
+        };
+        if !filter.matches(&path) {
+            return Ok(());
+        }
+        let target = destination.join(&path);
+        if entry.dir {
+            if !target.is_dir() {
+                fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+                changed = true;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        if write_entry(entry.reader, &target, entry.mode)
+            .map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?
+        {
+            debug!("[extract] {}", target.display());
+            changed = true;
+        }
+        files.push(path);
+        Ok(())
+    })?;
+    if files.is_empty() {
+        warn!("[extract] No files extracted from {}", archive.display());
+    } else {
+        info!(
+            "[extract] {} file(s) in {}",
+            files.len(),
+            destination.display()
+        );
+    }
+    Marker {
+        archive_sha256,
+        options,
+        files,
+    }
+    .save(markers, destination)?;
+    Ok(changed)
+}
+
+/// `extract <archive> <destination> [--strip-components=N] [--include=<glob>]... [--exclude=<glob>]...
+/// [--format=<format>]`
+pub fn extract(args: &[String]) -> ActionStatus {
+    report_change(
+        "extract",
+        extract_impl(&Params::parse(args), &markers_dir()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn extract(args: &[&str], markers: &Path) -> Result<bool, String> {
+        let args = args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+        extract_impl(&Params::parse(&args), markers)
+    }
+
+    /// A tar with `app/bin/tool`, `app/readme.txt` and a link `app/link` to `/etc/passwd`.
+    fn tar_archive(path: &Path) {
+        let mut builder = tar::Builder::new(fs::File::create(path).unwrap());
+        for (name, content) in [("app/bin/tool", "tool"), ("app/readme.txt", "readme")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, name, content.as_bytes())
+                .unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "app/link", "/etc/passwd")
+            .unwrap();
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn keeps_entries_inside_the_destination() {
+        let refused = |name: &str| relative_path(name, 0).unwrap_err();
+        assert_eq!(
+            refused("../x"),
+            "Refusing to extract ../x, it leaves the destination"
+        );
+        assert_eq!(
+            refused("a/../../x"),
+            "Refusing to extract a/../../x, it leaves the destination"
+        );
+        assert_eq!(
+            refused(r"a\..\x"),
+            r"Refusing to extract a\..\x, it leaves the destination"
+        );
+        assert_eq!(
+            refused("/abs"),
+            "Refusing to extract /abs, it is an absolute path"
+        );
+        assert_eq!(
+            refused(r"\abs"),
+            r"Refusing to extract \abs, it is an absolute path"
+        );
+        assert_eq!(
+            refused(r"C:\x"),
+            r"Refusing to extract C:\x, it is an absolute path"
+        );
+        assert_eq!(
+            refused("C:x"),
+            "Refusing to extract C:x, it is an absolute path"
+        );
+
+        let path = |name: &str, strip: usize| relative_path(name, strip).unwrap();
+        assert_eq!(path("./a//b/", 0), Some(["a", "b"].iter().collect()));
+        assert_eq!(path(r"a\b", 0), Some(["a", "b"].iter().collect()));
+        assert_eq!(
+            path("app-1.0/bin/tool", 1),
+            Some(["bin", "tool"].iter().collect())
+        );
+        assert_eq!(path("app-1.0/", 1), None);
+        assert_eq!(path("app-1.0/bin", 2), None);
+        // Stripping doesn't make `..` acceptable.
+        assert!(relative_path("../app/x", 1).is_err());
+    }
+
+    #[test]
+    fn extracts_tar_archives_without_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let markers = dir.path().join("markers");
+        let archive = dir.path().join("app.tar");
+        tar_archive(&archive);
+        let destination = dir.path().join("out");
+        let args = [archive.to_str().unwrap(), destination.to_str().unwrap()];
+        assert_eq!(extract(&args, &markers), Ok(true));
+        assert_eq!(
+            fs::read_to_string(destination.join("app/bin/tool")).unwrap(),
+            "tool"
+        );
+        assert!(destination.join("app/readme.txt").is_file());
+        assert!(fs::symlink_metadata(destination.join("app/link")).is_err());
+
+        // The marker says there is nothing to do, until a file goes missing.
+        assert_eq!(extract(&args, &markers), Ok(false));
+        assert_eq!(fs::read_dir(&markers).unwrap().count(), 1);
+        fs::remove_file(destination.join("app/readme.txt")).unwrap();
+        assert_eq!(extract(&args, &markers), Ok(true));
+        assert_eq!(extract(&args, &markers), Ok(false));
+
+        // Without the marker the files are compared, and are the same.
+        fs::remove_dir_all(&markers).unwrap();
+        assert_eq!(extract(&args, &markers), Ok(false));
+    }
+
+    #[test]
+    fn strips_and_filters_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let markers = dir.path().join("markers");
+        let archive = dir.path().join("app.tar");
+        tar_archive(&archive);
+        let destination = dir.path().join("out");
+        let args = [
+            archive.to_str().unwrap(),
+            destination.to_str().unwrap(),
+            "--strip-components=1",
+            "--include=bin/*",
+        ];
+        assert_eq!(extract(&args, &markers), Ok(true));
+        assert!(destination.join("bin/tool").is_file());
+        assert!(!destination.join("readme.txt").exists());
+        assert!(!destination.join("app").exists());
+        assert_eq!(extract(&args, &markers), Ok(false));
+    }
+
+    #[test]
+    fn refuses_zip_slip_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("evil.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_symlink("link", "/etc/passwd", options).unwrap();
+        zip.start_file("ok.txt", options).unwrap();
+        zip.write_all(b"ok").unwrap();
+        zip.start_file("../evil.txt", options).unwrap();
+        zip.write_all(b"evil").unwrap();
+        zip.finish().unwrap();
+
+        let destination = dir.path().join("out");
+        let args = [archive.to_str().unwrap(), destination.to_str().unwrap()];
+        assert_eq!(
+            extract(&args, &dir.path().join("markers")),
+            Err("Refusing to extract ../evil.txt, it leaves the destination".to_string())
+        );
+        assert!(!dir.path().join("evil.txt").exists());
+        assert!(fs::symlink_metadata(destination.join("link")).is_err());
+        assert!(destination.join("ok.txt").is_file());
+    }
+}
@@ -0,0 +1,61 @@
+/// The `windows_feature` action, enabling and disabling Windows optional features with DISM.
+use tracing::info;
+
+use super::params::Params;
+use super::{ActionStatus, report_change};
+use crate::dism;
+use crate::reboot;
+
+fn windows_feature_impl(params: &Params) -> Result<bool, String> {
+    params.require(0, "feature")?;
+    let enable = match params.option("state").unwrap_or("enabled") {
+        "enabled" | "present" => true,
+        "disabled" | "absent" => false,
+        other => {
+            return Err(format!(
+                "Unknown feature state: {} (expected enabled or disabled)",
+                other
+            ));
+        }
+    };
+
+    let mut changed = false;
+    for &feature in params.positional() {
+        let (text, _) = dism::run(&dism::info_args(feature))?;
+        let state = dism::parse_state(&text)?;
+        if state.enabled() == enable {
+            if state.pending() {
+                reboot::request(&format!(
+                    "[windows_feature] {} has a pending change",
+                    feature
+                ));
+            }
+            continue;
+        }
+        let args = if enable {
+            info!("[windows_feature] Enabling {}", feature);
+            dism::enable_args(feature, params.flag("all"), params.option("source"))
+        } else {
+            info!("[windows_feature] Disabling {}", feature);
+            dism::disable_args(feature)
+        };
+        let (_, restart) = dism::run(&args)?;
+        if restart {
+            reboot::request(&format!(
+                "[windows_feature] {} {}",
+                feature,
+                if enable { "enabled" } else { "disabled" }
+            ));
+        }
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// `windows_feature <feature>... [--state=enabled|disabled] [--all] [--source=<path>]`
+pub fn windows_feature(args: &[String]) -> ActionStatus {
+    report_change(
+        "windows_feature",
+        windows_feature_impl(&Params::parse(args)),
+    )
+}
@@ -0,0 +1,104 @@
+/// Firewall rule actions, idempotent by rule name, with a plan of the exact commands.
+use tracing::info;
+
+use super::params::Params;
+use super::{ActionStatus, report_change};
+use crate::firewall::{self, Action, Command, Direction, Profile, Protocol, Rule};
+
+fn parse_rule(params: &Params) -> Result<Rule, String> {
+    let ports = params
+        .options("port")
+        .iter()
+        .flat_map(|ports| ports.split(','))
+        .map(|port| port.trim().to_string())
+        .filter(|port| !port.is_empty())
+        .collect::<Vec<String>>();
+    let protocol = match params.option("protocol") {
+        Some(name) => Protocol::parse(name)?,
+        None if ports.is_empty() => Protocol::Any,
+        None => Protocol::Tcp,
+    };
+    let profiles = match params.option("profile") {
+        None | Some("any") => Vec::new(),
+        Some(names) => names
+            .split(',')
+            .map(|name| Profile::parse(name.trim()))
+            .collect::<Result<Vec<Profile>, String>>()?,
+    };
+    let description = params.option("description");
+    if description.is_some_and(|text| text.contains('"')) {
+        return Err("The description of a rule cannot contain \"".to_string());
+    }
+    let rule = Rule {
+        name: params.require(0, "name")?.to_string(),
+        direction: Direction::parse(params.option("direction").unwrap_or("in"))?,
+        action: Action::parse(params.option("action").unwrap_or("allow"))?,
+        protocol,
+        ports,
+        program: params.option("program").map(str::to_string),
+        profiles,
+        enabled: !params.flag("disabled"),
+        description: description.map(str::to_string),
+    };
+    rule.validate()?;
+    Ok(rule)
+}
+
+/// Logs the commands, then runs them unless only the plan is wanted.
+fn apply(
+    action: &str,
+    params: &Params,
+    backend: &mut dyn firewall::Backend,
+    commands: &[Command],
+) -> Result<bool, String> {
+    let plan = params.flag("plan") || std::env::var_os(firewall::PLAN_ENV).is_some();
+    for command in commands {
+        if plan {
+            info!("[{}] Plan: {}", action, command);
+        } else {
+            info!("[{}] {}", action, command);
+        }
+    }
+    if plan && !commands.is_empty() {
+        info!(
+            "[{}] Only planned, {} command(s) not run",
+            action,
+            commands.len()
+        );
+    }
+    if plan || commands.is_empty() {
+        return Ok(false);
+    }
+    backend.apply(commands)?;
+    Ok(true)
+}
+
+fn firewall_rule_impl(params: &Params) -> Result<bool, String> {
+    let rule = parse_rule(params)?;
+    let mut backend = firewall::open()?;
+    let commands = backend.plan(&rule)?;
+    apply("firewall_rule", params, backend.as_mut(), &commands)
+}
+
+fn firewall_remove_impl(params: &Params) -> Result<bool, String> {
+    let name = params.require(0, "name")?;
+    firewall::check_name(name)?;
+    let mut backend = firewall::open()?;
+    let commands = backend.plan_remove(name)?;
+    apply("firewall_remove", params, backend.as_mut(), &commands)
+}
+
+/// `firewall_rule <name> [--direction=in|out] [--action=allow|block] [--protocol=any|tcp|udp]
+/// [--port=<ports>] [--program=<path>] [--profile=<profiles>] [--description=<text>]
+/// [--disabled] [--plan]`
+pub fn firewall_rule(args: &[String]) -> ActionStatus {
+    report_change("firewall_rule", firewall_rule_impl(&Params::parse(args)))
+}
+
+/// `firewall_remove <name> [--plan]`, removes every rule with that name.
+pub fn firewall_remove(args: &[String]) -> ActionStatus {
+    report_change(
+        "firewall_remove",
+        firewall_remove_impl(&Params::parse(args)),
+    )
+}
@@ -0,0 +1,479 @@
+/// Native file system actions, each reports whether it changed anything so re-runs are idempotent.
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::debug;
+
+use super::params::Params;
+use super::{ActionStatus, report_change};
+
+/// When an existing destination file is replaced by `copy` and `move`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Overwrite {
+    Always,
+    Never,
+    /// Only when the content differs (the default).
+    Different,
+    /// Only when the source was modified after the destination.
+    Newer,
+}
+
+impl Overwrite {
+    fn parse(params: &Params) -> Result<Self, String> {
+        match params.option("overwrite") {
+            None | Some("different") => Ok(Overwrite::Different),
+            Some("always") => Ok(Overwrite::Always),
+            Some("never") => Ok(Overwrite::Never),
+            Some("newer") => Ok(Overwrite::Newer),
+            Some(other) => Err(format!(
+                "Invalid overwrite policy: {} (expected always, never, different or newer)",
+                other
+            )),
+        }
+    }
+}
+
+/// Compares two files by size first, then by content.
+pub fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    let mut file_a = io::BufReader::new(fs::File::open(a)?);
+    let mut file_b = io::BufReader::new(fs::File::open(b)?);
+    let mut buf_a = [0u8; 8192];
+    let mut buf_b = [0u8; 8192];
+    loop {
+        let read = file_a.read(&mut buf_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        file_b.read_exact(&mut buf_b[..read])?;
+        if buf_a[..read] != buf_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+fn should_write(src: &Path, dst: &Path, overwrite: Overwrite) -> io::Result<bool> {
+    if !dst.exists() {
+        return Ok(true);
+    }
+    match overwrite {
+        Overwrite::Always => Ok(true),
+        Overwrite::Never => Ok(false),
+        Overwrite::Different => Ok(!same_content(src, dst)?),
+        Overwrite::Newer => {
+            let modified = |p: &Path| fs::metadata(p)?.modified();
+            Ok(modified(src)? > modified(dst)?)
+        }
+    }
+}
+
+fn copy_file(src: &Path, dst: &Path, overwrite: Overwrite) -> io::Result<bool> {
+    if !should_write(src, dst, overwrite)? {
+        debug!(
+            "[copy] Skipping {}, destination is up to date",
+            dst.display()
+        );
+        return Ok(false);
+    }
+    if let Some(parent) = dst.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(src, dst)?;
+    debug!("[copy] {} -> {}", src.display(), dst.display());
+    Ok(true)
+}
+
+fn copy_dir(src: &Path, dst: &Path, overwrite: Overwrite) -> io::Result<bool> {
+    let mut changed = false;
+    if !dst.exists() {
+        fs::create_dir_all(dst)?;
+        changed = true;
+    }
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            changed |= copy_dir(&entry.path(), &target, overwrite)?;
+        } else {
+            changed |= copy_file(&entry.path(), &target, overwrite)?;
+        }
+    }
+    Ok(changed)
+}
+
+/// The absolute path with links resolved, as far as it exists.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let path = std::path::absolute(path)?;
+    let mut existing = path.as_path();
+    while !existing.exists() {
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => return Ok(path),
+        }
+    }
+    let rest = path.strip_prefix(existing).unwrap_or(Path::new(""));
+    Ok(fs::canonicalize(existing)?.join(rest))
+}
+
+/// Fails if the directory `dst` is `src` or inside it, copying would never end.
+fn check_outside(src: &Path, dst: &Path) -> Result<(), String> {
+    let resolved = |path| resolve(path).map_err(|e| format!("{}: {}", path.display(), e));
+    if resolved(dst)?.starts_with(resolved(src)?) {
+        return Err(format!(
+            "{} is inside {}, a directory can't be copied or moved into itself",
+            dst.display(),
+            src.display()
+        ));
+    }
+    Ok(())
+}
+
+/// A file copied or moved onto an existing directory goes inside it, like `cp` does.
+fn target_path(src: &Path, dst: &Path) -> PathBuf {
+    match src.file_name() {
+        Some(name) if dst.is_dir() && !src.is_dir() => dst.join(name),
+        _ => dst.to_path_buf(),
+    }
+}
+
+fn copy_impl(params: &Params) -> Result<bool, String> {
+    let src = Path::new(params.require(0, "source")?);
+    let dst = Path::new(params.require(1, "destination")?);
+    let overwrite = Overwrite::parse(params)?;
+    if src.is_dir() {
+        if !params.flag("recursive") {
+            return Err(format!(
+                "{} is a directory, pass --recursive to copy it",
+                src.display()
+            ));
+        }
+        check_outside(src, dst)?;
+        copy_dir(src, dst, overwrite).map_err(|e| e.to_string())
+    } else {
+        copy_file(src, &target_path(src, dst), overwrite).map_err(|e| e.to_string())
+    }
+}
+
+fn move_impl(params: &Params) -> Result<bool, String> {
+    let src = Path::new(params.require(0, "source")?);
+    let dst = Path::new(params.require(1, "destination")?);
+    let overwrite = Overwrite::parse(params)?;
+    let target = target_path(src, dst);
+    if !src.exists() {
+        // Already moved by a previous run.
+        if target.exists() {
+            return Ok(false);
+        }
+        return Err(format!("{} does not exist", src.display()));
+    }
+    if src.is_dir() {
+        check_outside(src, &target)?;
+    }
+    if target.exists() {
+        if src.is_dir() || target.is_dir() {
+            return Err(format!("{} already exists", target.display()));
+        }
+        if !should_write(src, &target, overwrite).map_err(|e| e.to_string())? {
+            // Leaving the source would keep the file in both places.
+            if !same_content(src, &target).map_err(|e| e.to_string())? {
+                return Err(format!(
+                    "{} already exists and differs from {}, not replaced with --overwrite={}",
+                    target.display(),
+                    src.display(),
+                    params.option("overwrite").unwrap_or("different")
+                ));
+            }
+            fs::remove_file(src)
+                .map_err(|e| format!("Failed to delete {}: {}", src.display(), e))?;
+            debug!(
+                "[move] {} is already at {}, deleted it",
+                src.display(),
+                target.display()
+            );
+            return Ok(true);
+        }
+    }
+    if let Some(parent) = target.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if fs::rename(src, &target).is_err() {
+        // Renaming fails across volumes, fall back to copying then deleting.
+        if src.is_dir() {
+            copy_dir(src, &target, Overwrite::Always).map_err(|e| e.to_string())?;
+            fs::remove_dir_all(src).map_err(|e| e.to_string())?;
+        } else {
+            fs::copy(src, &target).map_err(|e| e.to_string())?;
+            fs::remove_file(src).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(true)
+}
+
+/// Expands a glob pattern, or returns the path itself if it has no wildcards.
+fn expand(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    if !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+    glob::glob(pattern)
+        .map_err(|e| format!("Invalid pattern {}: {}", pattern, e))?
+        .map(|entry| entry.map_err(|e| e.to_string()))
+        .collect()
+}
+
+fn delete_impl(params: &Params) -> Result<bool, String> {
+    if params.positional().is_empty() {
+        return Err("Missing parameter: path".to_string());
+    }
+    let mut changed = false;
+    for pattern in params.positional() {
+        for path in expand(pattern)? {
+            // `symlink_metadata` so a link to a directory is removed, not what it points to.
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            let result = if metadata.is_symlink() {
+                // Links to directories are directories themselves on Windows.
+                fs::remove_file(&path).or_else(|_| fs::remove_dir(&path))
+            } else if metadata.is_dir() {
+                if params.flag("recursive") {
+                    fs::remove_dir_all(&path)
+                } else {
+                    fs::remove_dir(&path)
+                }
+            } else {
+                fs::remove_file(&path)
+            };
+            result.map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+            debug!("[delete] {}", path.display());
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+fn mkdir_impl(params: &Params) -> Result<bool, String> {
+    if params.positional().is_empty() {
+        return Err("Missing parameter: path".to_string());
+    }
+    let mut changed = false;
+    for path in params.positional() {
+        let path = Path::new(path);
+        if path.is_dir() {
+            continue;
+        }
+        fs::create_dir_all(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// Creates missing files, existing files only get their modification time updated, which is
+/// not reported as a change.
+fn touch_impl(params: &Params) -> Result<bool, String> {
+    if params.positional().is_empty() {
+        return Err("Missing parameter: path".to_string());
+    }
+    let mut changed = false;
+    for path in params.positional() {
+        let path = Path::new(path);
+        let existed = path.exists();
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to touch {}: {}", path.display(), e))?;
+        if existed {
+            file.set_modified(SystemTime::now())
+                .map_err(|e| format!("Failed to touch {}: {}", path.display(), e))?;
+        } else {
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+/// `copy <source> <destination> [--recursive] [--overwrite=always|never|different|newer]`
+pub fn copy(args: &[String]) -> ActionStatus {
+    report_change("copy", copy_impl(&Params::parse(args)))
+}
+
+/// `move <source> <destination> [--overwrite=always|never|different|newer]`
+pub fn r#move(args: &[String]) -> ActionStatus {
+    report_change("move", move_impl(&Params::parse(args)))
+}
+
+/// `delete <path or glob>... [--recursive]`
+pub fn delete(args: &[String]) -> ActionStatus {
+    report_change("delete", delete_impl(&Params::parse(args)))
+}
+
+/// `mkdir <path>...`, creates parent directories as needed.
+pub fn mkdir(args: &[String]) -> ActionStatus {
+    report_change("mkdir", mkdir_impl(&Params::parse(args)))
+}
+
+/// `touch <path>...`
+pub fn touch(args: &[String]) -> ActionStatus {
+    report_change("touch", touch_impl(&Params::parse(args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(action: fn(&Params) -> Result<bool, String>, args: &[&Path]) -> Result<bool, String> {
+        let args = args
+            .iter()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect::<Vec<String>>();
+        action(&Params::parse(&args))
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn copies_files_and_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("sub/b.txt"), "b").unwrap();
+        let dst = dir.path().join("dst");
+        let recursive = Path::new("--recursive");
+
+        assert!(
+            run(copy_impl, &[&src, &dst])
+                .unwrap_err()
+                .contains("--recursive")
+        );
+        assert_eq!(run(copy_impl, &[&src, &dst, recursive]), Ok(true));
+        assert_eq!(read(&dst.join("sub/b.txt")), "b");
+        assert_eq!(run(copy_impl, &[&src, &dst, recursive]), Ok(false));
+
+        // A file goes into an existing directory.
+        assert_eq!(
+            run(copy_impl, &[&src.join("a.txt"), &dst.join("sub")]),
+            Ok(true)
+        );
+        assert_eq!(read(&dst.join("sub/a.txt")), "a");
+
+        fs::write(src.join("a.txt"), "changed").unwrap();
+        let never = Path::new("--overwrite=never");
+        assert_eq!(
+            run(copy_impl, &[&src.join("a.txt"), &dst, never]),
+            Ok(false)
+        );
+        assert_eq!(read(&dst.join("a.txt")), "a");
+        assert_eq!(run(copy_impl, &[&src.join("a.txt"), &dst]), Ok(true));
+        assert_eq!(read(&dst.join("a.txt")), "changed");
+    }
+
+    #[test]
+    fn refuses_to_copy_a_directory_into_itself() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("a");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("file"), "").unwrap();
+        let recursive = Path::new("--recursive");
+        for dst in [
+            src.join("b"),
+            src.join("b/c"),
+            src.clone(),
+            dir.path().join("a/./b"),
+        ] {
+            let error = run(copy_impl, &[&src, &dst, recursive]).unwrap_err();
+            assert!(error.ends_with("a directory can't be copied or moved into itself"));
+        }
+        assert!(!src.join("b").exists());
+        assert!(run(move_impl, &[&src, &src.join("b")]).is_err());
+        assert!(src.join("file").exists());
+        // A sibling with the same prefix is fine.
+        assert_eq!(
+            run(copy_impl, &[&src, &dir.path().join("ab"), recursive]),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn moves_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dst = dir.path().join("out/b.txt");
+        fs::write(&src, "a").unwrap();
+        assert_eq!(run(move_impl, &[&src, &dst]), Ok(true));
+        assert!(!src.exists());
+        assert_eq!(read(&dst), "a");
+        // Already moved.
+        assert_eq!(run(move_impl, &[&src, &dst]), Ok(false));
+        fs::remove_file(&dst).unwrap();
+        assert_eq!(
+            run(move_impl, &[&src, &dst]),
+            Err(format!("{} does not exist", src.display()))
+        );
+    }
+
+    #[test]
+    fn moves_onto_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dst = dir.path().join("b.txt");
+
+        // The same file is already there, only the source goes away.
+        fs::write(&src, "same").unwrap();
+        fs::write(&dst, "same").unwrap();
+        assert_eq!(run(move_impl, &[&src, &dst]), Ok(true));
+        assert!(!src.exists());
+
+        // A different file is only replaced when the policy allows it.
+        fs::write(&src, "new").unwrap();
+        let never = Path::new("--overwrite=never");
+        assert_eq!(
+            run(move_impl, &[&src, &dst, never]),
+            Err(format!(
+                "{} already exists and differs from {}, not replaced with --overwrite=never",
+                dst.display(),
+                src.display()
+            ))
+        );
+        assert_eq!(read(&src), "new");
+        assert_eq!(read(&dst), "same");
+        assert_eq!(run(move_impl, &[&src, &dst]), Ok(true));
+        assert!(!src.exists());
+        assert_eq!(read(&dst), "new");
+    }
+
+    #[test]
+    fn deletes_creates_and_touches() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a/b");
+        assert_eq!(run(mkdir_impl, &[&nested]), Ok(true));
+        assert_eq!(run(mkdir_impl, &[&nested]), Ok(false));
+
+        let file = nested.join("x.log");
+        assert_eq!(run(touch_impl, &[&file]), Ok(true));
+        assert_eq!(run(touch_impl, &[&file]), Ok(false));
+        fs::write(nested.join("y.log"), "").unwrap();
+
+        assert!(run(delete_impl, &[&dir.path().join("a")]).is_err());
+        let pattern = nested.join("*.log");
+        assert_eq!(run(delete_impl, &[&pattern]), Ok(true));
+        assert_eq!(fs::read_dir(&nested).unwrap().count(), 0);
+        assert_eq!(run(delete_impl, &[&pattern]), Ok(false));
+        let recursive = Path::new("--recursive");
+        assert_eq!(
+            run(delete_impl, &[&dir.path().join("a"), recursive]),
+            Ok(true)
+        );
+        assert!(!dir.path().join("a").exists());
+    }
+}
@@ -0,0 +1,402 @@
+/// The `hosts` action, keeping entries of the hosts file in a block managed by trx8 so the
+/// entries of the user are never touched.
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use super::params::Params;
+use super::text::{Encoding, TextFile};
+use super::{ActionStatus, Context, report_change};
+
+const BEGIN: &str = "# BEGIN trx8";
+const END: &str = "# END trx8";
+const DEFAULT_ADDRESS: &str = "0.0.0.0";
+
+/// Names at the top of published hosts lists that must keep pointing to the machine itself.
+const LOCAL_NAMES: [&str; 11] = [
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "ip6-localnet",
+    "ip6-mcastprefix",
+    "ip6-allnodes",
+    "ip6-allrouters",
+    "ip6-allhosts",
+];
+
+/// `--path`, or the hosts file of the system.
+fn hosts_path(params: &Params) -> PathBuf {
+    match params.option("path") {
+        Some(path) => PathBuf::from(path),
+        None if cfg!(target_os = "windows") => {
+            let windows = std::env::var("SystemRoot").unwrap_or("C:\\Windows".to_string());
+            Path::new(&windows)
+                .join("System32")
+                .join("drivers")
+                .join("etc")
+                .join("hosts")
+        }
+        None => PathBuf::from("/etc/hosts"),
+    }
+}
+
+fn check_address(address: &str) -> Result<&str, String> {
+    address
+        .parse::<IpAddr>()
+        .map(|_| address)
+        .map_err(|_| format!("Invalid IP address: {}", address))
+}
+
+fn check_name(name: &str) -> Result<&str, String> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '#') {
+        return Err(format!("Invalid host name: {:?}", name));
+    }
+    Ok(name)
+}
+
+/// The `(address, name)` pairs of an `address name...` line, without its comment.
+fn parse_line(line: &str) -> Vec<(&str, &str)> {
+    let line = line.split('#').next().unwrap_or_default();
+    let mut words = line.split_whitespace();
+    let Some(address) = words.next() else {
+        return Vec::new();
+    };
+    words.map(|name| (address, name)).collect()
+}
+
+/// The entries of a hosts list, made of `address name...` lines or bare names. The address of
+/// the list is replaced by `address` when given, and entries for the machine itself are skipped.
+fn read_list(path: &Path, address: Option<&str>) -> Result<Vec<(String, String)>, String> {
+    let file = TextFile::read(path)?;
+    let mut entries = Vec::new();
+    for line in file.content.lines() {
+        let words = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<&str>>();
+        let (listed, names) = match words.split_first() {
+            Some((first, rest)) if first.parse::<IpAddr>().is_ok() => (*first, rest),
+            Some(_) => (DEFAULT_ADDRESS, words.as_slice()),
+            None => continue,
+        };
+        for name in names {
+            if LOCAL_NAMES.contains(&name.to_lowercase().as_str()) || name.parse::<IpAddr>().is_ok()
+            {
+                continue;
+            }
+            let name = check_name(name).map_err(|e| format!("{}: {}", path.display(), e))?;
+            entries.push((address.unwrap_or(listed).to_string(), name.to_string()));
+        }
+    }
+    Ok(entries)
+}
+
+/// The entries of the managed block, one per name, with a lookup by lowercase name.
+struct Block {
+    entries: Vec<(String, String)>,
+    index: HashMap<String, usize>,
+}
+
+impl Block {
+    fn parse(lines: &[String]) -> Self {
+        let mut block = Block {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        };
+        for line in lines {
+            for (address, name) in parse_line(line) {
+                block.set(address, name);
+            }
+        }
+        block
+    }
+
+    fn set(&mut self, address: &str, name: &str) {
+        match self.index.get(&name.to_lowercase()) {
+            Some(&i) => self.entries[i].0 = address.to_string(),
+            None => {
+                self.index.insert(name.to_lowercase(), self.entries.len());
+                self.entries.push((address.to_string(), name.to_string()));
+            }
+        }
+    }
+
+    /// Removes the names, or every entry without names.
+    fn remove(&mut self, names: &[String]) {
+        let names = names
+            .iter()
+            .map(|name| name.to_lowercase())
+            .collect::<HashSet<String>>();
+        self.entries
+            .retain(|(_, name)| !names.is_empty() && !names.contains(&name.to_lowercase()));
+        self.index = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, (_, name))| (name.to_lowercase(), i))
+            .collect();
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|(address, name)| format!("{} {}", address, name))
+            .collect()
+    }
+}
+
+/// The lines of the begin and end markers.
+fn find_block(lines: &[String], path: &Path) -> Result<Option<(usize, usize)>, String> {
+    let Some(begin) = lines.iter().position(|line| line.trim() == BEGIN) else {
+        return Ok(None);
+    };
+    match lines[begin..].iter().position(|line| line.trim() == END) {
+        Some(end) => Ok(Some((begin, begin + end))),
+        None => Err(format!(
+            "{} has a \"{}\" line without \"{}\"",
+            path.display(),
+            BEGIN,
+            END
+        )),
+    }
+}
+
+fn hosts_impl(context: &Context, params: &Params) -> Result<bool, String> {
+    let path = hosts_path(params);
+    let present = match params.option("state") {
+        None | Some("present") => true,
+        Some("absent") => false,
+        Some(other) => {
+            return Err(format!(
+                "Invalid state: {} (expected present or absent)",
+                other
+            ));
+        }
+    };
+    let address = params.option("address").map(check_address).transpose()?;
+    let mut entries = params
+        .positional()
+        .iter()
+        .map(|name| {
+            Ok((
+                address.unwrap_or(DEFAULT_ADDRESS).to_string(),
+                check_name(name)?.to_string(),
+            ))
+        })
+        .collect::<Result<Vec<(String, String)>, String>>()?;
+    for list in params.options("import") {
+        entries.extend(read_list(&context.profile_dir.join(list), address)?);
+    }
+    if present && entries.is_empty() {
+        return Err("Missing parameter: host name or --import=<file>".to_string());
+    }
+
+    let mut file = if path.exists() {
+        TextFile::read(&path)?
+    } else {
+        TextFile::new(Encoding::Utf8)
+    };
+    let original = path.exists().then(|| file.content.clone());
+    let mut lines = file.lines();
+    let block = find_block(&lines, &path)?;
+    let mut managed = Block::parse(match block {
+        Some((begin, end)) => &lines[begin + 1..end],
+        None => &[],
+    });
+
+    if present {
+        for (address, name) in &entries {
+            managed.set(address, name);
+        }
+    } else {
+        let names = entries
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>();
+        managed.remove(&names);
+    }
+    debug!(
+        "[hosts] {} entries in the managed block",
+        managed.entries.len()
+    );
+
+    let replacement = if managed.entries.is_empty() {
+        Vec::new()
+    } else {
+        [
+            vec![BEGIN.to_string()],
+            managed.lines(),
+            vec![END.to_string()],
+        ]
+        .concat()
+    };
+    if original.is_none() && replacement.is_empty() {
+        return Ok(false);
+    }
+    match block {
+        Some((begin, end)) => {
+            // The blank line added before the block goes away with it.
+            let begin = match begin.checked_sub(1) {
+                Some(blank) if replacement.is_empty() && lines[blank].trim().is_empty() => blank,
+                _ => begin,
+            };
+            lines.splice(begin..=end, replacement);
+        }
+        None if !replacement.is_empty() => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.extend(replacement);
+        }
+        None => {}
+    }
+    if file.content.is_empty() {
+        file.trailing_newline = true;
+    }
+    file.set_lines(&lines);
+    if original.as_deref() == Some(file.content.as_str()) {
+        return Ok(false);
+    }
+    if let Some(backup) = file.write(&path, params.flag("backup"))? {
+        info!(
+            "Backup of {} written to {}",
+            path.display(),
+            backup.display()
+        );
+    }
+    info!(
+        "[hosts] {} entries managed in {}",
+        managed.entries.len(),
+        path.display()
+    );
+    Ok(true)
+}
+
+/// `hosts [<name>...] [--address=<ip>] [--import=<file>] [--state=present|absent]
+/// [--path=<hosts file>] [--backup]`
+pub fn hosts(context: &Context, args: &[String]) -> ActionStatus {
+    report_change("hosts", hosts_impl(context, &Params::parse(args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::fs;
+
+    fn hosts(dir: &Path, args: &[&str]) -> Result<bool, String> {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "Test", "version": "1.0.0", "trx8_version": "0.1.0"},
+            "scripts": []
+        }))
+        .unwrap();
+        let context = Context {
+            config: &config,
+            profile_dir: dir,
+            values: &Default::default(),
+        };
+        let path = format!("--path={}", dir.join("hosts").display());
+        let args = args
+            .iter()
+            .copied()
+            .chain([path.as_str()])
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        hosts_impl(&context, &Params::parse(&args))
+    }
+
+    #[test]
+    fn reads_hosts_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list.txt");
+        fs::write(
+            &path,
+            "# Ads\n127.0.0.1 localhost\n::1 ip6-localhost\n\
+             0.0.0.0 0.0.0.0\n0.0.0.0 ads.example tracker.example # both\nbare.example\n",
+        )
+        .unwrap();
+        let entry = |address: &str, name: &str| (address.to_string(), name.to_string());
+        assert_eq!(
+            read_list(&path, None).unwrap(),
+            [
+                entry("0.0.0.0", "ads.example"),
+                entry("0.0.0.0", "tracker.example"),
+                entry("0.0.0.0", "bare.example"),
+            ]
+        );
+        assert_eq!(
+            read_list(&path, Some("127.0.0.2")).unwrap()[2],
+            entry("127.0.0.2", "bare.example")
+        );
+    }
+
+    #[test]
+    fn keeps_entries_in_a_managed_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n10.0.0.1 Server\n").unwrap();
+        fs::write(dir.path().join("list.txt"), "0.0.0.0 ads.example\n").unwrap();
+
+        assert_eq!(
+            hosts(dir.path(), &["Server", "--address=10.0.0.2"]),
+            Ok(true)
+        );
+        assert_eq!(
+            hosts(
+                dir.path(),
+                &["server", "--address=10.0.0.3", "--import=list.txt"]
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            hosts(
+                dir.path(),
+                &["server", "--address=10.0.0.3", "--import=list.txt"]
+            ),
+            Ok(false)
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "127.0.0.1 localhost\n10.0.0.1 Server\n\n\
+             # BEGIN trx8\n10.0.0.3 Server\n10.0.0.3 ads.example\n# END trx8\n"
+        );
+
+        assert_eq!(
+            hosts(dir.path(), &["ADS.example", "--state=absent"]),
+            Ok(true)
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "127.0.0.1 localhost\n10.0.0.1 Server\n\n# BEGIN trx8\n10.0.0.3 Server\n# END trx8\n"
+        );
+        assert_eq!(hosts(dir.path(), &["--state=absent"]), Ok(true));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "127.0.0.1 localhost\n10.0.0.1 Server\n"
+        );
+        assert_eq!(hosts(dir.path(), &["--state=absent"]), Ok(false));
+    }
+
+    #[test]
+    fn leaves_missing_files_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(hosts(dir.path(), &["--state=absent"]), Ok(false));
+        assert_eq!(
+            hosts(dir.path(), &["ads.example", "--state=absent"]),
+            Ok(false)
+        );
+        assert!(!dir.path().join("hosts").exists());
+
+        assert_eq!(hosts(dir.path(), &["ads.example"]), Ok(true));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("hosts")).unwrap(),
+            "# BEGIN trx8\n0.0.0.0 ads.example\n# END trx8\n"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+/// A line based INI editor, lines that are not touched are kept exactly as they were.
+pub struct Ini {
+    lines: Vec<String>,
+}
+
+/// Splits `[section] key` into the section and key, a path without a section refers to the
+/// keys before the first section and `[section]` alone to the whole section.
+pub fn parse_path(path: &str) -> (String, Option<String>) {
+    let path = path.trim();
+    if let Some(rest) = path.strip_prefix('[')
+        && let Some((section, key)) = rest.split_once(']')
+    {
+        let key = key.trim();
+        return (
+            section.trim().to_string(),
+            (!key.is_empty()).then(|| key.to_string()),
+        );
+    }
+    (String::new(), Some(path.to_string()))
+}
+
+fn section_of(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix('[')?.strip_suffix(']').map(str::trim)
+}
+
+fn key_of(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with([';', '#']) || section_of(line).is_some() {
+        return None;
+    }
+    line.split_once('=').map(|(key, _)| key.trim())
+}
+
+impl Ini {
+    pub fn parse(content: &str) -> Self {
+        Ini {
+            lines: content.lines().map(str::to_string).collect(),
+        }
+    }
+
+    /// The range of lines of a section (after its header), `""` is the keys before the first
+    /// section. Section names are case insensitive like on Windows.
+    fn section(&self, name: &str) -> Option<(usize, usize)> {
+        let start = if name.is_empty() {
+            0
+        } else {
+            self.lines
+                .iter()
+                .position(|l| section_of(l).is_some_and(|s| s.eq_ignore_ascii_case(name)))?
+                + 1
+        };
+        let end = self.lines[start..]
+            .iter()
+            .position(|l| section_of(l).is_some())
+            .map_or(self.lines.len(), |i| start + i);
+        Some((start, end))
+    }
+
+    fn find(&self, section: &str, key: &str) -> Option<usize> {
+        let (start, end) = self.section(section)?;
+        (start..end).find(|&i| key_of(&self.lines[i]).is_some_and(|k| k.eq_ignore_ascii_case(key)))
+    }
+
+    /// The value of a key, without the spaces around it.
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        let line = &self.lines[self.find(section, key)?];
+        line.split_once('=').map(|(_, value)| value.trim())
+    }
+
+    /// ` = ` if the file already uses it, `=` otherwise.
+    fn separator(&self) -> &'static str {
+        if self
+            .lines
+            .iter()
+            .any(|l| key_of(l).is_some() && l.contains(" = "))
+        {
+            " = "
+        } else {
+            "="
+        }
+    }
+
+    /// Sets a key, an existing line keeps its spacing, returns whether anything changed.
+    pub fn set(&mut self, section: &str, key: &str, value: &str) -> bool {
+        if let Some(i) = self.find(section, key) {
+            let line = &self.lines[i];
+            let (before, after) = line.split_once('=').unwrap_or((line, ""));
+            let padding = &after[..after.len() - after.trim_start().len()];
+            let updated = format!("{}={}{}", before, padding, value);
+            if *line == updated {
+                return false;
+            }
+            self.lines[i] = updated;
+            return true;
+        }
+        let entry = format!("{}{}{}", key, self.separator(), value);
+        match self.section(section) {
+            Some((start, end)) => {
+                // After the last key of the section, so trailing blank lines and comments stay.
+                let at = (start..end)
+                    .rev()
+                    .find(|&i| key_of(&self.lines[i]).is_some())
+                    .map_or(start, |i| i + 1);
+                self.lines.insert(at, entry);
+            }
+            None => {
+                if self.lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    self.lines.push(String::new());
+                }
+                self.lines.push(format!("[{}]", section));
+                self.lines.push(entry);
+            }
+        }
+        true
+    }
+
+    /// Removes a key, or the whole section if `key` is `None`.
+    pub fn delete(&mut self, section: &str, key: Option<&str>) -> bool {
+        match key {
+            Some(key) => match self.find(section, key) {
+                Some(i) => {
+                    self.lines.remove(i);
+                    true
+                }
+                None => false,
+            },
+            None => match self.section(section) {
+                Some((start, end)) if !section.is_empty() => {
+                    self.lines.drain(start - 1..end);
+                    true
+                }
+                _ => false,
+            },
+        }
+    }
+
+    pub fn content(&self) -> String {
+        self.lines.join("\n")
+    }
+}
@@ -0,0 +1,187 @@
+/// Link actions: symbolic links, hard links, junctions and Windows shortcuts.
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use super::params::Params;
+use super::{ActionStatus, report_change};
+use crate::link;
+use crate::shortcut::{Shortcut, Show};
+
+/// Makes room for a link at `path` when something else is there: fails without `--force`, and
+/// never removes a directory with content.
+fn replace(params: &Params, path: &Path) -> Result<(), String> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !params.flag("force") {
+        return Err(format!(
+            "{} already exists, pass --force to replace it",
+            path.display()
+        ));
+    }
+    let result = if metadata.is_symlink() {
+        // Links to directories are directories themselves on Windows.
+        fs::remove_file(path).or_else(|_| fs::remove_dir(path))
+    } else if metadata.is_dir() {
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    };
+    result.map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+    debug!("[link] Removed {}", path.display());
+    Ok(())
+}
+
+fn create_parent(path: &Path) -> Result<(), String> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e)),
+        _ => Ok(()),
+    }
+}
+
+fn symlink_impl(params: &Params) -> Result<bool, String> {
+    let target = Path::new(params.require(0, "target")?);
+    let path = Path::new(params.require(1, "link")?);
+    if fs::read_link(path).is_ok_and(|current| current == target) {
+        return Ok(false);
+    }
+    replace(params, path)?;
+    create_parent(path)?;
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(target, path);
+    #[cfg(target_os = "windows")]
+    let result = {
+        // A relative target is relative to the directory of the link.
+        let resolved = match path.parent() {
+            Some(parent) => parent.join(target),
+            None => target.to_path_buf(),
+        };
+        if resolved.is_dir() {
+            std::os::windows::fs::symlink_dir(target, path)
+        } else {
+            std::os::windows::fs::symlink_file(target, path)
+        }
+    };
+    result.map_err(|e| format!("Failed to create the link {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+fn hardlink_impl(params: &Params) -> Result<bool, String> {
+    let target = Path::new(params.require(0, "target")?);
+    let path = Path::new(params.require(1, "link")?);
+    if !target.is_file() {
+        return Err(format!("{} is not a file", target.display()));
+    }
+    // A symbolic link to the target is not a hard link to it.
+    let is_file = fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_file());
+    if is_file && link::same_file(target, path).map_err(|e| e.to_string())? {
+        return Ok(false);
+    }
+    replace(params, path)?;
+    create_parent(path)?;
+    fs::hard_link(target, path)
+        .map_err(|e| format!("Failed to create the link {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+#[cfg(target_os = "windows")]
+fn junction_impl(params: &Params) -> Result<bool, String> {
+    let target = std::path::absolute(params.require(0, "target")?).map_err(|e| e.to_string())?;
+    let path = Path::new(params.require(1, "link")?);
+    if !target.is_dir() {
+        return Err(format!("{} is not a directory", target.display()));
+    }
+    let same = |a: &Path, b: &Path| {
+        let normalize = |p: &Path| p.to_string_lossy().trim_end_matches('\\').to_lowercase();
+        normalize(a) == normalize(b)
+    };
+    if link::junction_target(path).is_some_and(|current| same(&current, &target)) {
+        return Ok(false);
+    }
+    replace(params, path)?;
+    create_parent(path)?;
+    link::create_junction(&target, path)?;
+    Ok(true)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn junction_impl(_params: &Params) -> Result<bool, String> {
+    Err("Junctions only exist on Windows, use symlink instead".to_string())
+}
+
+/// Where the shortcut goes: the path as given, or inside the folder of `--in`. The `.lnk`
+/// extension is added when missing.
+fn shortcut_path(params: &Params) -> Result<PathBuf, String> {
+    let name = params.require(0, "shortcut")?;
+    let mut path = match params.option("in") {
+        Some(folder) => link::known_folder(folder)?.join(name),
+        None => PathBuf::from(name),
+    };
+    if path
+        .extension()
+        .is_none_or(|extension| !extension.eq_ignore_ascii_case("lnk"))
+    {
+        path.as_mut_os_string().push(".lnk");
+    }
+    Ok(path)
+}
+
+fn shortcut_impl(params: &Params) -> Result<bool, String> {
+    let path = shortcut_path(params)?;
+    let mut shortcut = Shortcut::new(params.require(1, "target")?);
+    shortcut.arguments = params.option("args").map(str::to_string);
+    shortcut.working_dir = params.option("workdir").map(str::to_string);
+    shortcut.description = params.option("description").map(str::to_string);
+    if let Some(icon) = params.option("icon") {
+        // `<path>,<index>` like in the icon dialog of shortcuts.
+        let (file, index) = match icon.rsplit_once(',') {
+            Some((file, index)) => (
+                file,
+                index
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid icon index: {}", index))?,
+            ),
+            None => (icon, 0),
+        };
+        shortcut.icon = Some(file.to_string());
+        shortcut.icon_index = index;
+    }
+    if let Some(window) = params.option("window") {
+        shortcut.show = Show::parse(window)?;
+    }
+
+    let content = shortcut.to_bytes()?;
+    if let Ok(existing) = fs::read(&path)
+        && Shortcut::parse(&existing).is_ok_and(|existing| existing == shortcut)
+    {
+        return Ok(false);
+    }
+    create_parent(&path)?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    debug!("[shortcut] {} -> {}", path.display(), shortcut.target);
+    Ok(true)
+}
+
+/// `symlink <target> <link> [--force]`, a relative target is relative to the link.
+pub fn symlink(args: &[String]) -> ActionStatus {
+    report_change("symlink", symlink_impl(&Params::parse(args)))
+}
+
+/// `hardlink <target> <link> [--force]`
+pub fn hardlink(args: &[String]) -> ActionStatus {
+    report_change("hardlink", hardlink_impl(&Params::parse(args)))
+}
+
+/// `junction <target> <link> [--force]`, Windows only.
+pub fn junction(args: &[String]) -> ActionStatus {
+    report_change("junction", junction_impl(&Params::parse(args)))
+}
+
+/// `shortcut <path> <target> [--in=<folder>] [--args=<arguments>] [--workdir=<dir>]
+/// [--icon=<path>[,<index>]] [--description=<text>] [--window=normal|maximized|minimized]`
+pub fn shortcut(args: &[String]) -> ActionStatus {
+    report_change("shortcut", shortcut_impl(&Params::parse(args)))
+}
@@ -1,66 +1,369 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::inputs;
+
+mod appx;
 mod console;
+mod download;
+mod edit;
+mod environment;
+mod extract;
+mod feature;
+mod firewall;
+mod fs;
+mod hosts;
+mod ini;
+mod link;
+mod package;
+mod params;
+mod patch;
+mod policy;
+mod process;
+mod registry;
+mod service;
+mod structured;
 mod subprocess;
+mod task;
+mod template;
+mod text;
+mod wait;
 
-pub fn execute_action(action_name: &str, parameters: &Option<Vec<String>>) {
-    match action_name {
-        "echo" => {
-            if let Some(params) = parameters {
-                if !params.is_empty() {
-                    console::echo(&params[0]);
-                } else {
-                    console::echo("No message provided, please check your configuration.");
-                }
-            } else {
-                console::echo("No message provided, please check your configuration.");
-            }
+/// The result of a single action, reported in the event stream.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionStatus {
+    Success,
+    /// The action succeeded and changed something on the system.
+    Changed,
+    /// The action succeeded and the system was already in the wanted state.
+    Unchanged,
+    Failed,
+    /// The action was not run, e.g. skipped by the operator in step mode.
+    Skipped,
+}
+
+/// What actions know about the run besides their parameters.
+pub struct Context<'a> {
+    pub config: &'a Config,
+    /// The directory of the profile, files shipped with it are relative to this.
+    pub profile_dir: &'a Path,
+    /// The collected inputs and secrets, see [`inputs::Values`].
+    pub values: &'a inputs::Values,
+}
+
+/// Returns the parameters if there is at least one, otherwise warns and returns `None`.
+fn require_params<'a>(
+    action_name: &str,
+    parameters: &'a Option<Vec<String>>,
+) -> Option<&'a [String]> {
+    match parameters {
+        Some(params) if !params.is_empty() => Some(params),
+        _ => {
+            tracing::warn!(
+                "[{}] No parameters provided, please check your configuration.",
+                action_name
+            );
+            None
         }
-        "cmd" => {
-            if let Some(params) = parameters {
-                if !params.is_empty() {
-                    subprocess::cmd(params);
-                } else {
-                    tracing::warn!("[cmd] No command provided, please check your configuration.");
-                }
-            } else {
-                tracing::warn!("[cmd] No command provided, please check your configuration.");
-            }
+    }
+}
+
+/// Logs and maps the result of an action that reports whether it changed anything.
+fn report_change(tag: &str, result: Result<bool, String>) -> ActionStatus {
+    match result {
+        Ok(true) => {
+            tracing::info!("[{}] Changed.", tag);
+            ActionStatus::Changed
         }
-        "pwsh" => {
-            if let Some(params) = parameters {
-                if !params.is_empty() {
-                    subprocess::pwsh(params);
-                } else {
-                    tracing::warn!("[pwsh] No command provided, please check your configuration.");
-                }
-            } else {
-                tracing::warn!("[pwsh] No command provided, please check your configuration.");
-            }
+        Ok(false) => {
+            tracing::info!("[{}] Already up to date.", tag);
+            ActionStatus::Unchanged
         }
-        "run" => {
-            if let Some(params) = parameters {
-                if !params.is_empty() {
-                    subprocess::run(params);
-                } else {
-                    tracing::warn!("[run] No command provided, please check your configuration.");
-                }
-            } else {
-                tracing::warn!("[run] No command provided, please check your configuration.");
-            }
+        Err(e) => {
+            tracing::error!("[{}] {}", tag, e);
+            ActionStatus::Failed
         }
-        #[cfg(target_os = "windows")]
-        "ti_run" => {
+    }
+}
+
+/// Reports an action that waited for something, which changes nothing.
+fn report_wait(tag: &str, result: Result<(), String>) -> ActionStatus {
+    match result {
+        Ok(()) => {
+            tracing::info!("[{}] Done waiting.", tag);
+            ActionStatus::Success
+        }
+        Err(e) => {
+            tracing::error!("[{}] {}", tag, e);
+            ActionStatus::Failed
+        }
+    }
+}
+
+pub fn execute_action(
+    context: &Context,
+    action_name: &str,
+    parameters: &Option<Vec<String>>,
+) -> ActionStatus {
+    match action_name {
+        "echo" => {
             if let Some(params) = parameters {
                 if !params.is_empty() {
-                    subprocess::ti_run(params);
+                    console::echo(&params[0])
                 } else {
-                    tracing::warn!("[ti_run] No command provided, please check your configuration.");   
+                    console::echo("No message provided, please check your configuration.")
                 }
             } else {
-                tracing::warn!("[ti_run] No command provided, please check your configuration.");
+                console::echo("No message provided, please check your configuration.")
             }
         }
+        "cmd" => match require_params(action_name, parameters) {
+            Some(params) => subprocess::cmd(params),
+            None => ActionStatus::Failed,
+        },
+        "pwsh" => match require_params(action_name, parameters) {
+            Some(params) => subprocess::pwsh(params),
+            None => ActionStatus::Failed,
+        },
+        "run" => match require_params(action_name, parameters) {
+            Some(params) => subprocess::run(params),
+            None => ActionStatus::Failed,
+        },
+        #[cfg(target_os = "windows")]
+        "ti_run" => match require_params(action_name, parameters) {
+            Some(params) => subprocess::ti_run(params),
+            None => ActionStatus::Failed,
+        },
+        "copy" => match require_params(action_name, parameters) {
+            Some(params) => fs::copy(params),
+            None => ActionStatus::Failed,
+        },
+        "move" => match require_params(action_name, parameters) {
+            Some(params) => fs::r#move(params),
+            None => ActionStatus::Failed,
+        },
+        "delete" => match require_params(action_name, parameters) {
+            Some(params) => fs::delete(params),
+            None => ActionStatus::Failed,
+        },
+        "mkdir" => match require_params(action_name, parameters) {
+            Some(params) => fs::mkdir(params),
+            None => ActionStatus::Failed,
+        },
+        "touch" => match require_params(action_name, parameters) {
+            Some(params) => fs::touch(params),
+            None => ActionStatus::Failed,
+        },
+        "download" => match require_params(action_name, parameters) {
+            Some(params) => download::download(params),
+            None => ActionStatus::Failed,
+        },
+        "extract" => match require_params(action_name, parameters) {
+            Some(params) => extract::extract(params),
+            None => ActionStatus::Failed,
+        },
+        "line_in_file" => match require_params(action_name, parameters) {
+            Some(params) => edit::line_in_file(params),
+            None => ActionStatus::Failed,
+        },
+        "replace_in_file" => match require_params(action_name, parameters) {
+            Some(params) => edit::replace_in_file(params),
+            None => ActionStatus::Failed,
+        },
+        "template" => match require_params(action_name, parameters) {
+            Some(params) => template::template(context, params),
+            None => ActionStatus::Failed,
+        },
+        "set_key" => match require_params(action_name, parameters) {
+            Some(params) => structured::set_key(params),
+            None => ActionStatus::Failed,
+        },
+        "delete_key" => match require_params(action_name, parameters) {
+            Some(params) => structured::delete_key(params),
+            None => ActionStatus::Failed,
+        },
+        "merge_keys" => match require_params(action_name, parameters) {
+            Some(params) => structured::merge_keys(context, params),
+            None => ActionStatus::Failed,
+        },
+        "json_patch" => match require_params(action_name, parameters) {
+            Some(params) => structured::json_patch(context, params),
+            None => ActionStatus::Failed,
+        },
+        "registry_set" => match require_params(action_name, parameters) {
+            Some(params) => registry::registry_set(params),
+            None => ActionStatus::Failed,
+        },
+        "registry_delete" => match require_params(action_name, parameters) {
+            Some(params) => registry::registry_delete(params),
+            None => ActionStatus::Failed,
+        },
+        "registry_import" => match require_params(action_name, parameters) {
+            Some(params) => registry::registry_import(context, params),
+            None => ActionStatus::Failed,
+        },
+        "registry_export" => match require_params(action_name, parameters) {
+            Some(params) => registry::registry_export(params),
+            None => ActionStatus::Failed,
+        },
+        "policy_set" => match require_params(action_name, parameters) {
+            Some(params) => policy::policy_set(params),
+            None => ActionStatus::Failed,
+        },
+        "policy_delete" => match require_params(action_name, parameters) {
+            Some(params) => policy::policy_delete(params),
+            None => ActionStatus::Failed,
+        },
+        "service_start" => match require_params(action_name, parameters) {
+            Some(params) => service::service_start(params),
+            None => ActionStatus::Failed,
+        },
+        "service_stop" => match require_params(action_name, parameters) {
+            Some(params) => service::service_stop(params),
+            None => ActionStatus::Failed,
+        },
+        "service_restart" => match require_params(action_name, parameters) {
+            Some(params) => service::service_restart(params),
+            None => ActionStatus::Failed,
+        },
+        "service_set_start" => match require_params(action_name, parameters) {
+            Some(params) => service::service_set_start(params),
+            None => ActionStatus::Failed,
+        },
+        "service_disable" => match require_params(action_name, parameters) {
+            Some(params) => service::service_disable(params),
+            None => ActionStatus::Failed,
+        },
+        "service_delete" => match require_params(action_name, parameters) {
+            Some(params) => service::service_delete(params),
+            None => ActionStatus::Failed,
+        },
+        "service_wait" => match require_params(action_name, parameters) {
+            Some(params) => service::service_wait(params),
+            None => ActionStatus::Failed,
+        },
+        "task_create" => match require_params(action_name, parameters) {
+            Some(params) => task::task_create(params),
+            None => ActionStatus::Failed,
+        },
+        "task_enable" => match require_params(action_name, parameters) {
+            Some(params) => task::task_enable(params),
+            None => ActionStatus::Failed,
+        },
+        "task_disable" => match require_params(action_name, parameters) {
+            Some(params) => task::task_disable(params),
+            None => ActionStatus::Failed,
+        },
+        "task_delete" => match require_params(action_name, parameters) {
+            Some(params) => task::task_delete(params),
+            None => ActionStatus::Failed,
+        },
+        "package" => match require_params(action_name, parameters) {
+            Some(params) => package::package(context, params),
+            None => ActionStatus::Failed,
+        },
+        "env_set" => match require_params(action_name, parameters) {
+            Some(params) => environment::env_set(params),
+            None => ActionStatus::Failed,
+        },
+        "env_append" => match require_params(action_name, parameters) {
+            Some(params) => environment::env_append(params),
+            None => ActionStatus::Failed,
+        },
+        "env_prepend" => match require_params(action_name, parameters) {
+            Some(params) => environment::env_prepend(params),
+            None => ActionStatus::Failed,
+        },
+        "env_remove" => match require_params(action_name, parameters) {
+            Some(params) => environment::env_remove(params),
+            None => ActionStatus::Failed,
+        },
+        "hosts" => match require_params(action_name, parameters) {
+            Some(params) => hosts::hosts(context, params),
+            None => ActionStatus::Failed,
+        },
+        "firewall_rule" => match require_params(action_name, parameters) {
+            Some(params) => firewall::firewall_rule(params),
+            None => ActionStatus::Failed,
+        },
+        "firewall_remove" => match require_params(action_name, parameters) {
+            Some(params) => firewall::firewall_remove(params),
+            None => ActionStatus::Failed,
+        },
+        "appx_remove" => match require_params(action_name, parameters) {
+            Some(params) => appx::appx_remove(params),
+            None => ActionStatus::Failed,
+        },
+        "windows_feature" => match require_params(action_name, parameters) {
+            Some(params) => feature::windows_feature(params),
+            None => ActionStatus::Failed,
+        },
+        "process_kill" => match require_params(action_name, parameters) {
+            Some(params) => process::process_kill(params),
+            None => ActionStatus::Failed,
+        },
+        "process_wait_exit" => match require_params(action_name, parameters) {
+            Some(params) => process::process_wait_exit(params),
+            None => ActionStatus::Failed,
+        },
+        "process_wait_start" => match require_params(action_name, parameters) {
+            Some(params) => process::process_wait_start(params),
+            None => ActionStatus::Failed,
+        },
+        "wait_until" => match require_params(action_name, parameters) {
+            Some(params) => wait::wait_until(params),
+            None => ActionStatus::Failed,
+        },
+        "symlink" => match require_params(action_name, parameters) {
+            Some(params) => link::symlink(params),
+            None => ActionStatus::Failed,
+        },
+        "hardlink" => match require_params(action_name, parameters) {
+            Some(params) => link::hardlink(params),
+            None => ActionStatus::Failed,
+        },
+        "junction" => match require_params(action_name, parameters) {
+            Some(params) => link::junction(params),
+            None => ActionStatus::Failed,
+        },
+        "shortcut" => match require_params(action_name, parameters) {
+            Some(params) => link::shortcut(params),
+            None => ActionStatus::Failed,
+        },
         _ => {
-            tracing::warn!("Unknown action: {}", action_name);
+            // A misspelled action is a mistake in the profile, not something to skip over.
+            tracing::error!("Unknown action: {}", action_name);
+            ActionStatus::Failed
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_actions_fail() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "Test", "version": "1.0.0", "trx8_version": "0.1.0"},
+            "scripts": []
+        }))
+        .unwrap();
+        let context = Context {
+            config: &config,
+            profile_dir: Path::new("."),
+            values: &Default::default(),
+        };
+        let parameters = Some(vec!["hello".to_string()]);
+        assert_eq!(
+            execute_action(&context, "echo", &parameters),
+            ActionStatus::Success
+        );
+        assert_eq!(
+            execute_action(&context, "ehco", &parameters),
+            ActionStatus::Failed
+        );
+    }
+}
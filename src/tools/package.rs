@@ -0,0 +1,347 @@
+/// The `package` action, installing and removing software with the package manager of the system.
+use tracing::info;
+
+use super::params::Params;
+use super::{ActionStatus, Context, report_change};
+use crate::package::{self, DEFAULT_PROVIDERS, Provider};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Present,
+    Absent,
+    Latest,
+}
+
+impl State {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "present" | "installed" => Ok(State::Present),
+            "absent" | "removed" => Ok(State::Absent),
+            "latest" => Ok(State::Latest),
+            _ => Err(format!(
+                "Unknown package state: {} (expected present, absent or latest)",
+                name
+            )),
+        }
+    }
+}
+
+/// `--provider`, or the first installed of the providers of the configuration.
+fn provider(context: &Context, params: &Params) -> Result<Box<dyn Provider>, String> {
+    if let Some(name) = params.option("provider") {
+        return package::select(&[name]);
+    }
+    let configured = context
+        .config
+        .packages
+        .as_ref()
+        .and_then(|packages| packages.providers.as_ref());
+    match configured {
+        Some(names) => package::select(&names.iter().map(String::as_str).collect::<Vec<&str>>()),
+        None => package::select(DEFAULT_PROVIDERS),
+    }
+}
+
+/// Checks the package is installed after installing it, package managers don't always fail.
+fn installed_version(provider: &dyn Provider, name: &str) -> Result<String, String> {
+    provider.installed(name)?.ok_or_else(|| {
+        format!(
+            "{} is still not installed after running {}",
+            name,
+            provider.name()
+        )
+    })
+}
+
+/// Brings the package to the state of the parameters with `provider`.
+fn apply(provider: &dyn Provider, params: &Params) -> Result<bool, String> {
+    let name = params.require(0, "package")?;
+    let state = State::parse(params.option("state").unwrap_or("present"))?;
+    let version = params.option("version");
+
+    let installed = provider.installed(name)?;
+    let changed = match (state, installed) {
+        (State::Absent, None) => false,
+        (State::Absent, Some(_)) => {
+            provider.uninstall(name)?;
+            if provider.installed(name)?.is_some() {
+                return Err(format!("{} is still installed", name));
+            }
+            info!("[package] Removed {} with {}", name, provider.name());
+            return Ok(true);
+        }
+        (State::Present, Some(current)) if version.is_none_or(|v| v == current) => false,
+        (State::Latest, Some(_)) => match provider.available(name)? {
+            Some(available) => {
+                info!("[package] Upgrading {} to {}", name, available);
+                provider.upgrade(name)?;
+                true
+            }
+            None => false,
+        },
+        (_, _) => {
+            provider.install(name, version)?;
+            true
+        }
+    };
+    if state != State::Absent {
+        let version = installed_version(provider, name)?;
+        info!("[package] {} {} ({})", name, version, provider.name());
+    }
+    Ok(changed)
+}
+
+fn package_impl(context: &Context, params: &Params) -> Result<bool, String> {
+    apply(provider(context, params)?.as_ref(), params)
+}
+
+/// `package <name> [--state=present|absent|latest] [--version=<version>] [--provider=<name>]`,
+/// the provider is `winget`, `scoop`, `choco` or `apt`.
+pub fn package(context: &Context, args: &[String]) -> ActionStatus {
+    report_change("package", package_impl(context, &Params::parse(args)))
+}
+
+/// The package managers are faked with shell scripts keeping the installed and available versions
+/// of each package in files next to them.
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+
+    const WINGET: &str = r#"#!/bin/sh
+state="$(dirname "$0")/state/winget.$3"
+case "$1" in
+list)
+    if [ ! -f "$state" ]; then
+        echo "No installed package found matching input criteria."
+        exit 20
+    fi
+    echo "Name          Id     Version  Available  Source"
+    echo "-----------------------------------------------"
+    echo "Fake App 2024 $3 $(cat "$state") $(cat "$state.available" 2>/dev/null) winget"
+    exit 0
+    ;;
+install)
+    if [ "$6" = --version ]; then echo "$7" > "$state"; else echo 1.0 > "$state"; fi ;;
+uninstall) rm "$state" ;;
+upgrade) mv "$state.available" "$state" ;;
+esac
+exit "$(cat "$state.exit" 2>/dev/null || echo 0)"
+"#;
+
+    const CHOCO: &str = r#"#!/bin/sh
+dir="$(dirname "$0")/state"
+state="$dir/choco.$2"
+case "$1" in
+list)
+    echo "Chocolatey v2.2.2"
+    if [ -f "$state" ]; then echo "$2|$(cat "$state")"; fi
+    exit 0
+    ;;
+outdated)
+    for available in "$dir"/choco.*.available; do
+        [ -f "$available" ] || continue
+        name="${available#$dir/choco.}"
+        name="${name%.available}"
+        echo "$name|$(cat "$dir/choco.$name")|$(cat "$available")|false"
+    done
+    exit 0
+    ;;
+install)
+    case "$5" in
+    --version=*) echo "${5#--version=}" > "$state" ;;
+    *) echo 1.0 > "$state" ;;
+    esac
+    ;;
+uninstall) rm "$state" ;;
+upgrade) mv "$state.available" "$state" ;;
+esac
+exit "$(cat "$state.exit" 2>/dev/null || echo 0)"
+"#;
+
+    const APT_GET: &str = r#"#!/bin/sh
+dir="$(dirname "$0")/state"
+case "$1 $2" in
+"update --quiet") exit 0 ;;
+"install --only-upgrade")
+    state="$dir/apt.$4"
+    mv "$state.available" "$state"
+    ;;
+"install --yes")
+    state="$dir/apt.${3%%=*}"
+    case "$3" in
+    *=*) echo "${3#*=}" > "$state" ;;
+    *) echo 1.0 > "$state" ;;
+    esac
+    ;;
+"remove --yes")
+    state="$dir/apt.$3"
+    rm "$state"
+    ;;
+esac
+exit "$(cat "$state.exit" 2>/dev/null || echo 0)"
+"#;
+
+    const DPKG_QUERY: &str = r#"#!/bin/sh
+state="$(dirname "$0")/state/apt.$3"
+if [ ! -f "$state" ]; then
+    echo "dpkg-query: no packages found matching $3" >&2
+    exit 1
+fi
+printf 'install ok installed\t%s' "$(cat "$state")"
+"#;
+
+    const APT_CACHE: &str = r#"#!/bin/sh
+state="$(dirname "$0")/state/apt.$2"
+echo "$2:"
+echo "  Installed: $(cat "$state" 2>/dev/null || echo '(none)')"
+echo "  Candidate: $(cat "$state.available" 2>/dev/null || cat "$state" 2>/dev/null || echo 1.0)"
+"#;
+
+    /// The directory of the fake package managers, put in front of `PATH` once for all tests.
+    /// Tests use their own package names so they don't see each other's.
+    fn fakes() -> &'static Path {
+        static DIR: OnceLock<PathBuf> = OnceLock::new();
+        DIR.get_or_init(|| {
+            let dir = tempfile::tempdir().unwrap().keep();
+            fs::create_dir(dir.join("state")).unwrap();
+            for (name, script) in [
+                ("winget", WINGET),
+                ("choco", CHOCO),
+                ("apt-get", APT_GET),
+                ("dpkg-query", DPKG_QUERY),
+                ("apt-cache", APT_CACHE),
+            ] {
+                let path = dir.join(name);
+                fs::write(&path, script).unwrap();
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+            let path = std::env::var_os("PATH").unwrap_or_default();
+            let paths = std::iter::once(dir.clone()).chain(std::env::split_paths(&path));
+            let path = std::env::join_paths(paths).unwrap();
+            // SAFETY: only prepends a directory, the tests reading `PATH` meanwhile still find
+            // the same programs.
+            unsafe { std::env::set_var("PATH", path) };
+            dir
+        })
+    }
+
+    /// Sets a file of the fake state, e.g. `winget.Git.Git` for the installed version.
+    fn set(file: &str, content: &str) {
+        fs::write(fakes().join("state").join(file), content).unwrap();
+    }
+
+    fn get(file: &str) -> Option<String> {
+        fs::read_to_string(fakes().join("state").join(file))
+            .ok()
+            .map(|content| content.trim().to_string())
+    }
+
+    fn package(provider: &str, args: &[&str]) -> Result<bool, String> {
+        fakes();
+        let provider = package::select(&[provider])?;
+        let args = args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+        apply(provider.as_ref(), &Params::parse(&args))
+    }
+
+    #[test]
+    fn installs_missing_packages() {
+        assert_eq!(package("winget", &["Fake.Present"]), Ok(true));
+        assert_eq!(get("winget.Fake.Present").as_deref(), Some("1.0"));
+        assert_eq!(package("winget", &["Fake.Present"]), Ok(false));
+        assert_eq!(
+            package("winget", &["Fake.Present", "--state=installed"]),
+            Ok(false)
+        );
+
+        assert_eq!(package("apt", &["fake-present", "--version=2.0"]), Ok(true));
+        assert_eq!(get("apt.fake-present").as_deref(), Some("2.0"));
+        assert_eq!(
+            package("apt", &["fake-present", "--version=2.0"]),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn installs_the_requested_version() {
+        set("choco.fake-version", "1.0");
+        assert_eq!(package("choco", &["fake-version"]), Ok(false));
+        assert_eq!(
+            package("choco", &["fake-version", "--version=1.0"]),
+            Ok(false)
+        );
+        assert_eq!(
+            package("choco", &["fake-version", "--version=1.5"]),
+            Ok(true)
+        );
+        assert_eq!(get("choco.fake-version").as_deref(), Some("1.5"));
+    }
+
+    #[test]
+    fn removes_packages() {
+        set("choco.fake-absent", "1.0");
+        assert_eq!(
+            package("choco", &["fake-absent", "--state=absent"]),
+            Ok(true)
+        );
+        assert_eq!(get("choco.fake-absent"), None);
+        assert_eq!(
+            package("choco", &["fake-absent", "--state=absent"]),
+            Ok(false)
+        );
+
+        set("apt.fake-absent", "1.0");
+        assert_eq!(
+            package("apt", &["fake-absent", "--state=removed"]),
+            Ok(true)
+        );
+        assert_eq!(get("apt.fake-absent"), None);
+        assert_eq!(
+            package("apt", &["fake-absent", "--state=absent"]),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn upgrades_to_the_latest_version() {
+        for provider in ["winget", "choco", "apt"] {
+            let file = format!("{}.fake-latest", provider);
+            set(&file, "1.0");
+            set(&format!("{}.available", file), "1.1");
+            assert_eq!(
+                package(provider, &["fake-latest", "--state=latest"]),
+                Ok(true)
+            );
+            assert_eq!(get(&file).as_deref(), Some("1.1"), "{}", provider);
+            assert_eq!(
+                package(provider, &["fake-latest", "--state=latest"]),
+                Ok(false)
+            );
+            // `latest` installs missing packages too.
+            assert_eq!(
+                package(provider, &["fake-latest-missing", "--state=latest"]),
+                Ok(true)
+            );
+            assert!(get(&format!("{}.fake-latest-missing", provider)).is_some());
+        }
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        set("apt.fake-broken.exit", "100");
+        let error = package("apt", &["fake-broken"]).unwrap_err();
+        assert!(
+            error.starts_with("apt-get install failed with exit code 100"),
+            "{}",
+            error
+        );
+        assert_eq!(
+            package("winget", &["Fake.State", "--state=old"]),
+            Err("Unknown package state: old (expected present, absent or latest)".to_string())
+        );
+        assert!(package("brew", &["fake"]).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+/// Parsing of action parameters into positional values and `--name[=value]` options.
+use std::time::Duration;
+
+pub struct Params<'a> {
+    positional: Vec<&'a str>,
+    options: Vec<(&'a str, Option<&'a str>)>,
+}
+
+impl<'a> Params<'a> {
+    /// Everything after a lone `--` is positional, so values starting with `--` can still be passed.
+    pub fn parse(params: &'a [String]) -> Self {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut only_positional = false;
+        for param in params {
+            if only_positional {
+                positional.push(param.as_str());
+            } else if param == "--" {
+                only_positional = true;
+            } else if let Some(option) = param.strip_prefix("--") {
+                match option.split_once('=') {
+                    Some((name, value)) => options.push((name, Some(value))),
+                    None => options.push((option, None)),
+                }
+            } else {
+                positional.push(param.as_str());
+            }
+        }
+        Params {
+            positional,
+            options,
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        self.positional.get(index).copied()
+    }
+
+    /// Returns the positional parameter or an error naming it.
+    pub fn require(&self, index: usize, name: &str) -> Result<&'a str, String> {
+        self.get(index)
+            .ok_or_else(|| format!("Missing parameter: {}", name))
+    }
+
+    pub fn positional(&self) -> &[&'a str] {
+        &self.positional
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| *n == name)
+    }
+
+    /// The value of the last `--name=value` option.
+    pub fn option(&self, name: &str) -> Option<&'a str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, v)| *v)
+    }
+
+    /// The values of every `--name=value` option, for options that can be repeated.
+    pub fn options(&self, name: &str) -> Vec<&'a str> {
+        self.options
+            .iter()
+            .filter(|(n, _)| *n == name)
+            .filter_map(|(_, v)| *v)
+            .collect()
+    }
+
+    /// The `--name=<seconds>` option as a duration, `default` seconds when it is missing.
+    pub fn seconds(&self, name: &str, default: u64) -> Result<Duration, String> {
+        match self.option(name) {
+            Some(seconds) => seconds
+                .parse()
+                .map(Duration::from_secs)
+                .map_err(|_| format!("Invalid value for --{}: {}", name, seconds)),
+            None => Ok(Duration::from_secs(default)),
+        }
+    }
+}
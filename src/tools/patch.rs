@@ -0,0 +1,252 @@
+/// Editing of JSON values by path, JSON Merge Patch (RFC 7386) and JSON Patch (RFC 6902).
+use serde_json::{Map, Value};
+
+/// Splits a JSON Pointer (`/a/0/b`) or a dotted path (`a.0.b`) into its tokens.
+pub fn parse_path(path: &str) -> Vec<String> {
+    if path.is_empty() || path.starts_with('/') {
+        parse_pointer(path).unwrap_or_default()
+    } else {
+        path.split('.').map(str::to_string).collect()
+    }
+}
+
+/// Parses a JSON Pointer, `~1` and `~0` stand for `/` and `~`.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(format!("Invalid JSON Pointer: {}", pointer));
+    };
+    Ok(rest
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// The index of an array element, `-` (only if `end` is allowed) is the end of the array.
+fn index(array: &[Value], token: &str, end: bool) -> Result<usize, String> {
+    if end && token == "-" {
+        return Ok(array.len());
+    }
+    match token.parse::<usize>() {
+        Ok(i) if i < array.len() || (end && i == array.len()) => Ok(i),
+        _ => Err(format!("Invalid array index: {}", token)),
+    }
+}
+
+/// Walks down the tokens, creating missing objects along the way if `create` is set.
+fn walk_mut<'a>(
+    doc: &'a mut Value,
+    tokens: &[String],
+    create: bool,
+) -> Result<&'a mut Value, String> {
+    let mut current = doc;
+    for token in tokens {
+        current = match current {
+            Value::Object(map) => {
+                if create && !map.contains_key(token) {
+                    map.insert(token.clone(), Value::Object(Map::new()));
+                }
+                map.get_mut(token)
+                    .ok_or_else(|| format!("No such key: {}", token))?
+            }
+            Value::Array(array) => {
+                let i = index(array, token, false)?;
+                &mut array[i]
+            }
+            _ => return Err(format!("{} is not inside an object or array", token)),
+        };
+    }
+    Ok(current)
+}
+
+pub fn get<'a>(doc: &'a Value, tokens: &[String]) -> Option<&'a Value> {
+    tokens.iter().try_fold(doc, |current, token| match current {
+        Value::Object(map) => map.get(token),
+        Value::Array(array) => array.get(index(array, token, false).ok()?),
+        _ => None,
+    })
+}
+
+/// Sets the value at the path, creating missing objects. An array element is replaced, `-`
+/// appends to the array.
+pub fn set(doc: &mut Value, tokens: &[String], value: Value) -> Result<(), String> {
+    let Some((last, parents)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+    match walk_mut(doc, parents, true)? {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+        }
+        Value::Array(array) => {
+            let i = index(array, last, true)?;
+            if i == array.len() {
+                array.push(value);
+            } else {
+                array[i] = value;
+            }
+        }
+        _ => return Err(format!("{} is not inside an object or array", last)),
+    }
+    Ok(())
+}
+
+/// The `add` operation of JSON Patch, values are inserted into arrays instead of replacing.
+fn add(doc: &mut Value, tokens: &[String], value: Value) -> Result<(), String> {
+    let Some((last, parents)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+    match walk_mut(doc, parents, false)? {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+        }
+        Value::Array(array) => {
+            let i = index(array, last, true)?;
+            array.insert(i, value);
+        }
+        _ => return Err(format!("{} is not inside an object or array", last)),
+    }
+    Ok(())
+}
+
+/// Removes and returns the value at the path, keeping the order of the other keys.
+pub fn remove(doc: &mut Value, tokens: &[String]) -> Result<Value, String> {
+    let Some((last, parents)) = tokens.split_last() else {
+        return Err("Cannot remove the whole document".to_string());
+    };
+    match walk_mut(doc, parents, false)? {
+        Value::Object(map) => map
+            .shift_remove(last)
+            .ok_or_else(|| format!("No such key: {}", last)),
+        Value::Array(array) => {
+            let i = index(array, last, false)?;
+            Ok(array.remove(i))
+        }
+        _ => Err(format!("{} is not inside an object or array", last)),
+    }
+}
+
+/// Applies a JSON Merge Patch: objects are merged recursively and `null` deletes a key.
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                map.shift_remove(key);
+            } else {
+                merge(map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+fn apply_operation(doc: &mut Value, operation: &Value) -> Result<(), String> {
+    let field = |name: &str| {
+        operation
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("Missing \"{}\"", name))
+    };
+    let value = || {
+        operation
+            .get("value")
+            .cloned()
+            .ok_or_else(|| "Missing \"value\"".to_string())
+    };
+    let path = parse_pointer(field("path")?)?;
+    match field("op")? {
+        "add" => add(doc, &path, value()?),
+        "remove" => remove(doc, &path).map(|_| ()),
+        "replace" => {
+            // In place, so a replaced key keeps its position in the object.
+            let value = value()?;
+            *walk_mut(doc, &path, false)? = value;
+            Ok(())
+        }
+        "move" => {
+            let from = parse_pointer(field("from")?)?;
+            if path.len() > from.len() && path.starts_with(&from) {
+                return Err("Cannot move a value into itself".to_string());
+            }
+            let moved = remove(doc, &from)?;
+            add(doc, &path, moved)
+        }
+        "copy" => {
+            let from = parse_pointer(field("from")?)?;
+            let copied = get(doc, &from)
+                .cloned()
+                .ok_or_else(|| format!("No value at {}", field("from").unwrap_or_default()))?;
+            add(doc, &path, copied)
+        }
+        "test" => {
+            if get(doc, &path) == Some(&value()?) {
+                Ok(())
+            } else {
+                Err(format!("Test failed at {}", field("path")?))
+            }
+        }
+        other => Err(format!("Unknown operation: {}", other)),
+    }
+}
+
+/// Applies a JSON Patch, the document is left untouched if any operation fails.
+pub fn apply(doc: &mut Value, patch: &Value) -> Result<(), String> {
+    let operations = patch
+        .as_array()
+        .ok_or("A JSON Patch must be an array of operations")?;
+    let mut result = doc.clone();
+    for (i, operation) in operations.iter().enumerate() {
+        apply_operation(&mut result, operation)
+            .map_err(|e| format!("Patch operation {}: {}", i, e))?;
+    }
+    *doc = result;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn replace_keeps_the_key_order() {
+        let mut doc = json!({"a": 1, "b": {"c": 2, "d": 3}, "e": [1, 2, 3]});
+        apply(
+            &mut doc,
+            &json!([
+                {"op": "replace", "path": "/a", "value": 10},
+                {"op": "replace", "path": "/b/c", "value": 20},
+                {"op": "replace", "path": "/e/1", "value": 5}
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&doc).unwrap(),
+            r#"{"a":10,"b":{"c":20,"d":3},"e":[1,5,3]}"#
+        );
+    }
+
+    #[test]
+    fn replace_needs_an_existing_value() {
+        let mut doc = json!({"a": 1, "e": [1]});
+        for path in ["/b", "/e/1", "/e/-"] {
+            let patch = json!([{"op": "replace", "path": path, "value": 2}]);
+            assert!(apply(&mut doc, &patch).is_err(), "{}", path);
+        }
+        apply(
+            &mut doc,
+            &json!([{"op": "replace", "path": "", "value": 2}]),
+        )
+        .unwrap();
+        assert_eq!(doc, json!(2));
+    }
+}
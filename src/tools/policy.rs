@@ -0,0 +1,427 @@
+/// Local Group Policy actions, editing the `Registry.pol` files of a Group Policy object.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use super::ini::Ini;
+use super::params::Params;
+use super::text::{Encoding, LineEnding, TextFile};
+use super::{ActionStatus, report_change};
+use crate::registry::pol::{self, Entry};
+use crate::registry::{Key, Root, Value};
+
+/// The client-side extension applying `Registry.pol` files.
+const REGISTRY_EXTENSION: &str = "{35378EAC-683F-11D2-A89A-00C04FBBCFA2}";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scope {
+    Machine,
+    User,
+}
+
+impl Scope {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "machine" | "computer" | "hklm" => Ok(Scope::Machine),
+            "user" | "hkcu" => Ok(Scope::User),
+            _ => Err(format!(
+                "Unknown policy scope: {} (expected machine or user)",
+                name
+            )),
+        }
+    }
+
+    /// The folder of the scope in a Group Policy object.
+    fn folder(self) -> &'static str {
+        match self {
+            Scope::Machine => "Machine",
+            Scope::User => "User",
+        }
+    }
+
+    fn root(self) -> Root {
+        match self {
+            Scope::Machine => Root::LocalMachine,
+            Scope::User => Root::CurrentUser,
+        }
+    }
+
+    /// The `gpt.ini` key listing the extensions and the snap-in that edited the object.
+    fn extensions(self) -> (&'static str, &'static str) {
+        match self {
+            Scope::Machine => (
+                "gPCMachineExtensionNames",
+                "{D02B1F72-3407-48AE-BA88-E8213C6761F1}",
+            ),
+            Scope::User => (
+                "gPCUserExtensionNames",
+                "{D02B1F73-3407-48AE-BA88-E8213C6761F1}",
+            ),
+        }
+    }
+
+    /// The machine version is the low word of the `gpt.ini` version, the user one the high word.
+    fn version_shift(self) -> u32 {
+        match self {
+            Scope::Machine => 0,
+            Scope::User => 16,
+        }
+    }
+}
+
+/// `--file`, or the `Registry.pol` of the local Group Policy object.
+fn pol_path(scope: Scope, params: &Params) -> Result<PathBuf, String> {
+    match params.option("file") {
+        Some(file) => Ok(PathBuf::from(file)),
+        None if cfg!(target_os = "windows") => {
+            let windows = std::env::var("SystemRoot").unwrap_or("C:\\Windows".to_string());
+            Ok(Path::new(&windows)
+                .join("System32")
+                .join("GroupPolicy")
+                .join(scope.folder())
+                .join("Registry.pol"))
+        }
+        None => Err(
+            "The local Group Policy is only available on Windows, pass --file=<Registry.pol>"
+                .to_string(),
+        ),
+    }
+}
+
+/// Policy keys are relative to the root of the scope, `HKLM\` or `HKCU\` in front is dropped.
+fn policy_key(scope: Scope, key: &str) -> Result<String, String> {
+    let path = match Key::parse(key) {
+        Ok(full) if full.root == scope.root() => full.path,
+        Ok(full) => {
+            return Err(format!(
+                "{} is not a {} policy key",
+                full,
+                scope.folder().to_lowercase()
+            ));
+        }
+        Err(_) => key
+            .split('\\')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<&str>>()
+            .join("\\"),
+    };
+    if path.is_empty() {
+        return Err(format!("Invalid policy key: {}", key));
+    }
+    Ok(path)
+}
+
+/// Adds the registry extension to a `[{extension}{snap-in}...]` list, kept sorted like Windows.
+fn add_extension(names: &str, snapin: &str) -> Option<String> {
+    if names.to_uppercase().contains(REGISTRY_EXTENSION) {
+        return None;
+    }
+    let mut blocks = names
+        .split_inclusive(']')
+        .map(str::trim)
+        .filter(|block| !block.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<String>>();
+    blocks.push(format!("[{}{}]", REGISTRY_EXTENSION, snapin));
+    blocks.sort_by_key(|block| block.to_uppercase());
+    Some(blocks.concat())
+}
+
+/// Bumps the version of the Group Policy object the file is part of (`<gpo>\Machine\Registry.pol`)
+/// and lists the registry extension in its `gpt.ini`, Windows skips objects it already applied.
+fn update_gpt_ini(scope: Scope, pol: &Path) -> Result<(), String> {
+    let Some(folder) = pol.parent() else {
+        return Ok(());
+    };
+    let in_gpo = folder
+        .file_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case(scope.folder()));
+    let Some(gpo) = folder.parent().filter(|_| in_gpo) else {
+        return Ok(());
+    };
+    let path = ["gpt.ini", "GPT.INI", "Gpt.ini"]
+        .iter()
+        .map(|name| gpo.join(name))
+        .find(|path| path.is_file())
+        .unwrap_or(gpo.join("gpt.ini"));
+    let mut file = if path.is_file() {
+        TextFile::read(&path)?
+    } else {
+        let mut file = TextFile::new(Encoding::Utf8);
+        file.line_ending = LineEnding::CrLf;
+        file
+    };
+    let mut ini = Ini::parse(&file.content);
+
+    let version = ini
+        .get("General", "Version")
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(0);
+    let shift = scope.version_shift();
+    let count = ((version >> shift) & 0xFFFF) + 1;
+    let version = (version & !(0xFFFF << shift)) | ((count & 0xFFFF) << shift);
+    ini.set("General", "Version", &version.to_string());
+    let (attribute, snapin) = scope.extensions();
+    let names = ini
+        .get("General", attribute)
+        .unwrap_or_default()
+        .to_string();
+    if let Some(names) = add_extension(&names, snapin) {
+        ini.set("General", attribute, &names);
+    }
+
+    file.content = ini.content();
+    file.content.push('\n');
+    file.write(&path, false)?;
+    debug!("[policy] {} is now at version {}", path.display(), version);
+    Ok(())
+}
+
+/// Reads the policy file of the scope, applies `edit` and writes it back if the entries changed.
+fn edit(scope: Scope, params: &Params, edit: impl FnOnce(&mut Vec<Entry>)) -> Result<bool, String> {
+    let path = pol_path(scope, params)?;
+    let original = match fs::read(&path) {
+        Ok(bytes) => pol::parse(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let mut entries = original.clone();
+    edit(&mut entries);
+    if entries == original {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, pol::write(&entries))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    update_gpt_ini(scope, &path)?;
+    Ok(true)
+}
+
+/// Whether the entry sets, deletes or soft-sets the value `name` of `key`.
+fn is_about(entry: &Entry, key: &str, name: &str) -> bool {
+    entry.key.eq_ignore_ascii_case(key)
+        && !entry.name.starts_with("**delvals")
+        && entry.target().eq_ignore_ascii_case(name)
+}
+
+/// Replaces the entries about the same value, at the place of the first one.
+fn upsert(entries: &mut Vec<Entry>, entry: Entry) {
+    let name = entry.target().to_string();
+    let position = entries.iter().position(|e| is_about(e, &entry.key, &name));
+    entries.retain(|e| !is_about(e, &entry.key, &name));
+    match position {
+        Some(i) => entries.insert(i.min(entries.len()), entry),
+        None => entries.push(entry),
+    }
+}
+
+/// `@` stands for the default value of a key, like in `.reg` files.
+fn value_name(name: &str) -> &str {
+    if name == "@" { "" } else { name }
+}
+
+fn policy_set_impl(params: &Params) -> Result<bool, String> {
+    let scope = Scope::parse(params.require(0, "scope")?)?;
+    let key = policy_key(scope, params.require(1, "key")?)?;
+    let name = value_name(params.require(2, "name")?);
+    let kind = params.option("type").unwrap_or("REG_DWORD");
+    let value = Value::parse(kind, &params.positional()[3..])?;
+    let name = if params.flag("soft") {
+        format!("**soft.{}", name)
+    } else {
+        name.to_string()
+    };
+    edit(scope, params, |entries| {
+        upsert(entries, Entry::new(&key, &name, &value))
+    })
+}
+
+fn policy_delete_impl(params: &Params) -> Result<bool, String> {
+    let scope = Scope::parse(params.require(0, "scope")?)?;
+    let key = policy_key(scope, params.require(1, "key")?)?;
+    let name = params.get(2).map(value_name);
+    // Group Policy writes a single space for these.
+    let marker = Value::String(" ".to_string());
+    edit(scope, params, |entries| match name {
+        Some(name) if params.flag("enforce") => upsert(
+            entries,
+            Entry::new(&key, &format!("**del.{}", name), &marker),
+        ),
+        Some(name) => entries.retain(|e| !is_about(e, &key, name)),
+        None => {
+            entries.retain(|e| !e.key.eq_ignore_ascii_case(&key));
+            if params.flag("enforce") {
+                entries.push(Entry::new(&key, "**delvals.", &marker));
+            }
+        }
+    })
+}
+
+/// `policy_set <machine|user> <key> <name> <value>... [--type=<type>] [--soft] [--file=<path>]`,
+/// the type defaults to `REG_DWORD`.
+pub fn policy_set(args: &[String]) -> ActionStatus {
+    report_change("policy_set", policy_set_impl(&Params::parse(args)))
+}
+
+/// `policy_delete <machine|user> <key> [<name>] [--enforce] [--file=<path>]`, removes the setting
+/// so it is not configured, or with `--enforce` makes Group Policy delete the value(s).
+pub fn policy_delete(args: &[String]) -> ActionStatus {
+    report_change("policy_delete", policy_delete_impl(&Params::parse(args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = r"Software\Policies\Trx8";
+
+    fn run(action: fn(&Params) -> Result<bool, String>, args: &[&str], pol: &Path) -> bool {
+        let mut args = args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+        args.push(format!("--file={}", pol.display()));
+        action(&Params::parse(&args)).unwrap()
+    }
+
+    fn entries(pol: &Path) -> Vec<Entry> {
+        pol::parse(&fs::read(pol).unwrap()).unwrap()
+    }
+
+    fn gpt_ini(gpo: &Path) -> Ini {
+        Ini::parse(&TextFile::read(&gpo.join("gpt.ini")).unwrap().content)
+    }
+
+    #[test]
+    fn sets_and_deletes_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let pol = dir.path().join("Registry.pol");
+        let set = policy_set_impl;
+
+        assert!(run(set, &["machine", KEY, "On", "1"], &pol));
+        assert!(!run(
+            set,
+            &["machine", &format!(r"HKLM\{}", KEY), "On", "1"],
+            &pol
+        ));
+        assert!(run(set, &["machine", KEY, "Size", "5", "--soft"], &pol));
+        assert_eq!(
+            entries(&pol),
+            [
+                Entry::new(KEY, "On", &Value::Dword(1)),
+                Entry::new(KEY, "**soft.Size", &Value::Dword(5)),
+            ]
+        );
+        // A plain value replaces the soft one in place.
+        assert!(run(set, &["machine", KEY, "Size", "6"], &pol));
+        assert!(run(
+            set,
+            &["machine", KEY, "Name", "x", "--type=REG_SZ"],
+            &pol
+        ));
+        assert_eq!(
+            entries(&pol),
+            [
+                Entry::new(KEY, "On", &Value::Dword(1)),
+                Entry::new(KEY, "Size", &Value::Dword(6)),
+                Entry::new(KEY, "Name", &Value::String("x".to_string())),
+            ]
+        );
+
+        let marker = Value::String(" ".to_string());
+        assert!(run(
+            policy_delete_impl,
+            &["machine", KEY, "On", "--enforce"],
+            &pol
+        ));
+        assert!(run(policy_delete_impl, &["machine", KEY, "Size"], &pol));
+        assert!(!run(policy_delete_impl, &["machine", KEY, "Size"], &pol));
+        assert_eq!(
+            entries(&pol),
+            [
+                Entry::new(KEY, "**del.On", &marker),
+                Entry::new(KEY, "Name", &Value::String("x".to_string())),
+            ]
+        );
+        assert!(run(
+            policy_delete_impl,
+            &["machine", KEY, "--enforce"],
+            &pol
+        ));
+        assert_eq!(entries(&pol), [Entry::new(KEY, "**delvals.", &marker)]);
+
+        // Outside of a Group Policy object there is no gpt.ini to update.
+        assert!(!dir.path().join("gpt.ini").exists());
+    }
+
+    #[test]
+    fn keys_must_match_the_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = [
+            "user".to_string(),
+            format!(r"HKLM\{}", KEY),
+            "On".to_string(),
+            "1".to_string(),
+            format!("--file={}", dir.path().join("Registry.pol").display()),
+        ];
+        let error = policy_set_impl(&Params::parse(&args)).unwrap_err();
+        assert!(error.ends_with("is not a user policy key"), "{}", error);
+    }
+
+    #[test]
+    fn bumps_the_gpt_ini_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let gpo = dir.path();
+        let machine = gpo.join("Machine").join("Registry.pol");
+        let user = gpo.join("User").join("Registry.pol");
+
+        assert!(run(policy_set_impl, &["machine", KEY, "On", "1"], &machine));
+        let ini = gpt_ini(gpo);
+        assert_eq!(ini.get("General", "Version"), Some("1"));
+        assert_eq!(
+            ini.get("General", "gPCMachineExtensionNames"),
+            Some("[{35378EAC-683F-11D2-A89A-00C04FBBCFA2}{D02B1F72-3407-48AE-BA88-E8213C6761F1}]")
+        );
+        assert_eq!(ini.get("General", "gPCUserExtensionNames"), None);
+
+        // The machine version is the low word, the user version the high word.
+        assert!(run(policy_set_impl, &["user", KEY, "On", "1"], &user));
+        assert!(run(policy_set_impl, &["user", KEY, "On", "2"], &user));
+        assert!(!run(policy_set_impl, &["user", KEY, "On", "2"], &user));
+        assert_eq!(gpt_ini(gpo).get("General", "Version"), Some("131073"));
+        assert!(run(policy_set_impl, &["machine", KEY, "On", "2"], &machine));
+        assert_eq!(gpt_ini(gpo).get("General", "Version"), Some("131074"));
+    }
+
+    #[test]
+    fn keeps_other_extensions_in_gpt_ini() {
+        let dir = tempfile::tempdir().unwrap();
+        let gpo = dir.path();
+        let scripts =
+            "[{42B5FAAE-6536-11D2-AE5A-0000F87571E3}{40B6664F-4972-11D1-A7CA-0000F87571E3}]";
+        fs::write(
+            gpo.join("GPT.INI"),
+            format!(
+                "[General]\r\ngPCUserExtensionNames={}\r\nVersion=65535\r\n",
+                scripts
+            ),
+        )
+        .unwrap();
+
+        let user = gpo.join("User").join("Registry.pol");
+        assert!(run(policy_set_impl, &["user", KEY, "On", "1"], &user));
+        let file = TextFile::read(&gpo.join("GPT.INI")).unwrap();
+        assert_eq!(file.line_ending, LineEnding::CrLf);
+        let ini = Ini::parse(&file.content);
+        assert_eq!(ini.get("General", "Version"), Some("131071"));
+        assert_eq!(
+            ini.get("General", "gPCUserExtensionNames").unwrap(),
+            format!(
+                "[{}{{D02B1F73-3407-48AE-BA88-E8213C6761F1}}]{}",
+                REGISTRY_EXTENSION, scripts
+            )
+        );
+    }
+}
@@ -0,0 +1,179 @@
+/// Process actions: ending process trees and waiting for processes to exit or start.
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use super::params::Params;
+use super::{ActionStatus, report_change, report_wait};
+use crate::process::{self, Process};
+
+/// How long processes get to exit on their own before they are forced to.
+const DEFAULT_GRACE: u64 = 10;
+/// How long to wait for processes to exit or start by default.
+const DEFAULT_TIMEOUT: u64 = 60;
+/// How long forced processes get to disappear.
+const FORCE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A process given as a pid, the path of its executable or its name.
+pub(super) enum Target<'a> {
+    Pid(u32),
+    Path(&'a Path),
+    Name(&'a str),
+}
+
+impl<'a> Target<'a> {
+    pub(super) fn parse(text: &'a str) -> Self {
+        if let Ok(pid) = text.parse() {
+            Target::Pid(pid)
+        } else if text.contains(['/', '\\']) {
+            Target::Path(Path::new(text))
+        } else {
+            Target::Name(text)
+        }
+    }
+
+    fn find<'p>(&self, processes: &'p [Process]) -> Vec<&'p Process> {
+        match self {
+            Target::Pid(pid) => processes.iter().filter(|p| p.pid == *pid).collect(),
+            Target::Path(path) => process::find_by_path(processes, path),
+            Target::Name(name) => process::find_by_name(processes, name),
+        }
+    }
+}
+
+fn targets<'a>(params: &Params<'a>) -> Result<Vec<Target<'a>>, String> {
+    params.require(0, "process")?;
+    Ok(params
+        .positional()
+        .iter()
+        .map(|t| Target::parse(t))
+        .collect())
+}
+
+/// The processes matching any of the targets, except Trx8 itself.
+pub(super) fn matching<'p>(processes: &'p [Process], targets: &[Target]) -> Vec<&'p Process> {
+    let mut found: Vec<&Process> = Vec::new();
+    for process in targets.iter().flat_map(|target| target.find(processes)) {
+        if process.pid != std::process::id() && !found.iter().any(|p| p.pid == process.pid) {
+            found.push(process);
+        }
+    }
+    found
+}
+
+fn process_kill_impl(params: &Params) -> Result<bool, String> {
+    let targets = targets(params)?;
+    let grace = params.seconds("grace", DEFAULT_GRACE)?;
+    let processes = process::list()?;
+
+    let mut pids = Vec::new();
+    for root in matching(&processes, &targets) {
+        let tree = process::tree(&processes, root.pid);
+        info!(
+            "[process_kill] Ending {} ({}) and {} child process(es)",
+            root.name,
+            root.pid,
+            tree.len() - 1
+        );
+        for pid in tree {
+            if !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+    }
+    pids.retain(|&pid| pid != std::process::id());
+    if pids.is_empty() {
+        return Ok(false);
+    }
+    let gone = |processes: &[Process]| !processes.iter().any(|p| pids.contains(&p.pid));
+
+    if !params.flag("force") {
+        for &pid in &pids {
+            process::terminate(pid, false)?;
+        }
+        match process::wait_for(grace, "the processes to exit", gone) {
+            Ok(()) => return Ok(true),
+            Err(e) => debug!("[process_kill] {}, forcing them", e),
+        }
+    }
+    let running = process::list()?;
+    for &pid in &pids {
+        if running.iter().any(|p| p.pid == pid) {
+            process::terminate(pid, true)?;
+        }
+    }
+    process::wait_for(FORCE_TIMEOUT, "the forced processes to exit", gone)?;
+    Ok(true)
+}
+
+fn process_wait_exit_impl(params: &Params) -> Result<(), String> {
+    let targets = targets(params)?;
+    let timeout = params.seconds("timeout", DEFAULT_TIMEOUT)?;
+    let waiting = format!("{} to exit", params.positional().join(", "));
+    process::wait_for(timeout, &waiting, |processes| {
+        matching(processes, &targets).is_empty()
+    })
+}
+
+fn process_wait_start_impl(params: &Params) -> Result<(), String> {
+    let targets = targets(params)?;
+    let timeout = params.seconds("timeout", DEFAULT_TIMEOUT)?;
+    let waiting = format!("{} to start", params.positional().join(", "));
+    process::wait_for(timeout, &waiting, |processes| {
+        !matching(processes, &targets).is_empty()
+    })
+}
+
+/// `process_kill <process>... [--grace=<seconds>] [--force]`, processes are pids, paths or names.
+pub fn process_kill(args: &[String]) -> ActionStatus {
+    report_change("process_kill", process_kill_impl(&Params::parse(args)))
+}
+
+/// `process_wait_exit <process>... [--timeout=<seconds>]`
+pub fn process_wait_exit(args: &[String]) -> ActionStatus {
+    report_wait(
+        "process_wait_exit",
+        process_wait_exit_impl(&Params::parse(args)),
+    )
+}
+
+/// `process_wait_start <process>... [--timeout=<seconds>]`, until one of them runs.
+pub fn process_wait_start(args: &[String]) -> ActionStatus {
+    report_wait(
+        "process_wait_start",
+        process_wait_start_impl(&Params::parse(args)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_pids_paths_and_names() {
+        let process = |pid: u32, name: &str| Process {
+            pid,
+            parent: 1,
+            name: name.to_string(),
+            path: Some(Path::new("/usr/bin").join(name)),
+            started: Some(0),
+        };
+        let own = std::process::id();
+        let processes = [
+            process(100, "app"),
+            process(101, "app"),
+            process(102, "tool"),
+            process(own, "trx8"),
+        ];
+        let targets = ["app", "102", "/usr/bin/tool", "101", "trx8", "999"]
+            .iter()
+            .map(|t| Target::parse(t))
+            .collect::<Vec<Target>>();
+        let found = matching(&processes, &targets)
+            .iter()
+            .map(|p| p.pid)
+            .collect::<Vec<u32>>();
+        assert_eq!(found, [100, 101, 102]);
+        assert!(matching(&processes, &[Target::parse(&own.to_string())]).is_empty());
+    }
+}
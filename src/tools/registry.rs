@@ -0,0 +1,155 @@
+/// Registry actions, and `.reg` file import and export.
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info};
+
+use super::params::Params;
+use super::text::{Encoding, LineEnding, TextFile};
+use super::{ActionStatus, Context, report_change};
+use crate::registry::{self, Backend, HiveFile, Key, MemoryHive, Value, reg};
+
+/// `@` stands for the default value of a key, like in `.reg` files.
+fn value_name(name: &str) -> &str {
+    if name == "@" { "" } else { name }
+}
+
+/// The live registry, or the hive file given with `--hive`.
+fn open(params: &Params) -> Result<Box<dyn Backend>, String> {
+    match params.option("hive") {
+        Some(path) => {
+            let mount = params.option("mount").map(Key::parse).transpose()?;
+            Ok(Box::new(HiveFile::open(Path::new(path), mount)?))
+        }
+        None => registry::open(),
+    }
+}
+
+/// Runs `operation` on the registry and writes the changes out.
+fn with_registry(
+    params: &Params,
+    operation: impl FnOnce(&mut dyn Backend) -> Result<bool, String>,
+) -> Result<bool, String> {
+    let mut backend = open(params)?;
+    let changed = operation(backend.as_mut())?;
+    if changed {
+        backend.flush()?;
+    }
+    Ok(changed)
+}
+
+fn registry_set_impl(params: &Params) -> Result<bool, String> {
+    let key = Key::parse(params.require(0, "key")?)?;
+    let Some(name) = params.get(1) else {
+        return with_registry(params, |backend| registry::ensure_key(backend, &key));
+    };
+    let kind = params.option("type").unwrap_or("REG_SZ");
+    let value = Value::parse(kind, &params.positional()[2..])?;
+    debug!("[registry_set] {} {} = {}", key, name, value);
+    with_registry(params, |backend| {
+        registry::ensure_value(backend, &key, value_name(name), &value)
+    })
+}
+
+fn registry_delete_impl(params: &Params) -> Result<bool, String> {
+    let key = Key::parse(params.require(0, "key")?)?;
+    with_registry(params, |backend| match params.get(1) {
+        Some(name) => registry::remove_value(backend, &key, value_name(name)),
+        None => registry::remove_key(backend, &key),
+    })
+}
+
+fn registry_import_impl(context: &Context, params: &Params) -> Result<bool, String> {
+    let path = context.profile_dir.join(params.require(0, "file")?);
+    let file = TextFile::read(&path)?;
+    let operations = reg::parse(&file.content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    info!(
+        "[registry_import] {} operation(s) in {}",
+        operations.len(),
+        path.display()
+    );
+    with_registry(params, |backend| reg::apply(backend, &operations))
+}
+
+fn registry_export_impl(params: &Params) -> Result<bool, String> {
+    let key = Key::parse(params.require(0, "key")?)?;
+    let destination = Path::new(params.require(1, "file")?);
+    let backend = open(params)?;
+    if !backend.key_exists(&key)? {
+        return Err(format!("{} does not exist", key));
+    }
+    let hive = MemoryHive::snapshot(backend.as_ref(), &key)?;
+    // What regedit writes: UTF-16LE with a BOM and CRLF line endings.
+    let mut file = TextFile::new(Encoding::Utf16Le);
+    file.content = reg::write(&hive, &key);
+    file.line_ending = LineEnding::CrLf;
+    let bytes = file.encode();
+    if fs::read(destination).is_ok_and(|current| current == bytes) {
+        return Ok(false);
+    }
+    file.write(destination, false)?;
+    Ok(true)
+}
+
+/// `registry_set <key> [<name> <value>...] [--type=<type>]`, with only a key it creates the key.
+/// `REG_MULTI_SZ` takes several values, `@` is the default value of the key.
+pub fn registry_set(args: &[String]) -> ActionStatus {
+    report_change("registry_set", registry_set_impl(&Params::parse(args)))
+}
+
+/// `registry_delete <key> [<name>]`, deletes the value, or the key with its subkeys.
+pub fn registry_delete(args: &[String]) -> ActionStatus {
+    report_change(
+        "registry_delete",
+        registry_delete_impl(&Params::parse(args)),
+    )
+}
+
+/// `registry_import <file.reg>`, the file is relative to the profile.
+pub fn registry_import(context: &Context, args: &[String]) -> ActionStatus {
+    report_change(
+        "registry_import",
+        registry_import_impl(context, &Params::parse(args)),
+    )
+}
+
+/// `registry_export <key> <file.reg>`
+///
+/// Every registry action takes `--hive=<file> [--mount=<key>]` to edit an offline hive instead.
+pub fn registry_export(args: &[String]) -> ActionStatus {
+    report_change(
+        "registry_export",
+        registry_export_impl(&Params::parse(args)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_utf16_files() {
+        // What regedit writes: UTF-16LE with a BOM and CRLF line endings.
+        let text = "Windows Registry Editor Version 5.00\r\n\r\n\
+                    [HKEY_CURRENT_USER\\Software\\Trx8]\r\n\
+                    \"Name\"=\"Zoë ✓\"\r\n\
+                    \"Path\"=hex(2):25,00,54,00,45,00,4d,00,50,00,25,00,00,00\r\n";
+        let bytes = [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect::<Vec<u8>>();
+        let file = TextFile::decode(&bytes).unwrap();
+        assert_eq!(file.encoding, Encoding::Utf16Le);
+
+        let mut hive = MemoryHive::new();
+        assert!(reg::apply(&mut hive, &reg::parse(&file.content).unwrap()).unwrap());
+        let key = Key::parse(r"HKCU\Software\Trx8").unwrap();
+        assert_eq!(
+            hive.get_value(&key, "Name").unwrap(),
+            Some(Value::String("Zoë ✓".to_string()))
+        );
+        assert_eq!(
+            hive.get_value(&key, "Path").unwrap(),
+            Some(Value::ExpandString("%TEMP%".to_string()))
+        );
+    }
+}
@@ -0,0 +1,118 @@
+/// Service actions, on the Windows Service Control Manager or systemd.
+use std::time::Duration;
+use tracing::{debug, info};
+
+use super::params::Params;
+use super::{ActionStatus, report_change, report_wait};
+use crate::service::{self, Backend, StartType, State};
+
+/// How long to wait for a service to reach a state by default.
+const DEFAULT_TIMEOUT: u64 = 30;
+
+/// Runs `operation` on the service named by the first parameter.
+fn with_service<T>(
+    params: &Params,
+    operation: impl FnOnce(&mut dyn Backend, &str, Duration) -> Result<T, String>,
+) -> Result<T, String> {
+    let name = params.require(0, "service")?;
+    let timeout = params.seconds("timeout", DEFAULT_TIMEOUT)?;
+    let mut backend = service::open()?;
+    operation(backend.as_mut(), name, timeout)
+}
+
+fn service_start_impl(params: &Params) -> Result<bool, String> {
+    with_service(params, service::ensure_running)
+}
+
+fn service_stop_impl(params: &Params) -> Result<bool, String> {
+    with_service(params, service::ensure_stopped)
+}
+
+fn service_restart_impl(params: &Params) -> Result<bool, String> {
+    with_service(params, |backend, name, timeout| {
+        service::ensure_stopped(backend, name, timeout)?;
+        service::ensure_running(backend, name, timeout)
+    })
+}
+
+fn service_set_start_impl(params: &Params) -> Result<bool, String> {
+    let start_type = StartType::parse(params.require(1, "start type")?)?;
+    with_service(params, |backend, name, _| {
+        service::ensure_start_type(backend, name, start_type)
+    })
+}
+
+fn service_disable_impl(params: &Params) -> Result<bool, String> {
+    with_service(params, |backend, name, timeout| {
+        let disabled = service::ensure_start_type(backend, name, StartType::Disabled)?;
+        let stopped = service::ensure_stopped(backend, name, timeout)?;
+        Ok(disabled || stopped)
+    })
+}
+
+fn service_delete_impl(params: &Params) -> Result<bool, String> {
+    with_service(params, |backend, name, timeout| {
+        if !backend.exists(name)? {
+            debug!("[service_delete] {} does not exist", name);
+            return Ok(false);
+        }
+        service::ensure_stopped(backend, name, timeout)?;
+        backend.delete(name)?;
+        Ok(true)
+    })
+}
+
+fn service_wait_impl(params: &Params) -> Result<(), String> {
+    let state = State::parse(params.require(1, "state")?)?;
+    with_service(params, |backend, name, timeout| {
+        service::wait_for(backend, name, state, timeout)?;
+        info!("[service_wait] {} is {}", name, state);
+        Ok(())
+    })
+}
+
+/// `service_start <name> [--timeout=<seconds>]`, starts the service and waits until it runs.
+pub fn service_start(args: &[String]) -> ActionStatus {
+    report_change("service_start", service_start_impl(&Params::parse(args)))
+}
+
+/// `service_stop <name> [--timeout=<seconds>]`
+pub fn service_stop(args: &[String]) -> ActionStatus {
+    report_change("service_stop", service_stop_impl(&Params::parse(args)))
+}
+
+/// `service_restart <name> [--timeout=<seconds>]`, always changes: a stopped service is started.
+pub fn service_restart(args: &[String]) -> ActionStatus {
+    report_change(
+        "service_restart",
+        service_restart_impl(&Params::parse(args)),
+    )
+}
+
+/// `service_set_start <name> <boot|system|auto|delayed-auto|manual|disabled>`
+pub fn service_set_start(args: &[String]) -> ActionStatus {
+    report_change(
+        "service_set_start",
+        service_set_start_impl(&Params::parse(args)),
+    )
+}
+
+/// `service_disable <name> [--timeout=<seconds>]`, sets the start type to disabled and stops it.
+pub fn service_disable(args: &[String]) -> ActionStatus {
+    report_change(
+        "service_disable",
+        service_disable_impl(&Params::parse(args)),
+    )
+}
+
+/// `service_delete <name> [--timeout=<seconds>]`, stops and deletes the service if it exists.
+pub fn service_delete(args: &[String]) -> ActionStatus {
+    report_change("service_delete", service_delete_impl(&Params::parse(args)))
+}
+
+/// `service_wait <name> <state> [--timeout=<seconds>]`, fails if the state isn't reached in time.
+///
+/// Every service action uses the fake services of the `TRX8_FAKE_SERVICES` file when it is set.
+pub fn service_wait(args: &[String]) -> ActionStatus {
+    report_wait("service_wait", service_wait_impl(&Params::parse(args)))
+}
@@ -0,0 +1,417 @@
+/// Actions editing JSON, YAML and INI files by key, keeping unrelated content where possible.
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+use super::ini::{self, Ini};
+use super::params::Params;
+use super::patch;
+use super::text::{Encoding, TextFile};
+use super::{ActionStatus, Context, report_change};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// JSON, comments and trailing commas (JSONC) are accepted.
+    Json,
+    Yaml,
+    Ini,
+}
+
+impl Format {
+    /// From `--format`, or the file extension.
+    fn detect(path: &Path, params: &Params) -> Result<Self, String> {
+        let name = match params.option("format") {
+            Some(format) => format.to_lowercase(),
+            None => path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+        };
+        match name.as_str() {
+            "json" | "jsonc" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "ini" | "inf" | "cfg" | "conf" => Ok(Format::Ini),
+            _ => Err(format!(
+                "Unknown format for {}, pass --format=json|yaml|ini",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// The parsed content of a file.
+enum Data {
+    Tree(Value),
+    Ini(Ini),
+}
+
+/// The index of the first character from `i` on that is neither whitespace nor in a comment.
+fn skip_comments(chars: &[char], mut i: usize) -> usize {
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+        } else if chars[i] == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+/// Blanks out `//` and `/* */` comments and drops trailing commas, returns whether there were
+/// comments.
+fn strip_jsonc(text: &str) -> (String, bool) {
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut out = String::with_capacity(text.len());
+    let mut comments = false;
+    let mut in_string = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            out.push(c);
+            if c == '\\' && i + 1 < chars.len() {
+                out.push(chars[i + 1]);
+                i += 1;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+            out.push(c);
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            comments = true;
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            comments = true;
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+            continue;
+        } else if c == ',' {
+            // Comments may come between the comma and the end, like in VS Code settings.
+            let next = chars.get(skip_comments(&chars, i + 1));
+            if !matches!(next, Some('}') | Some(']')) {
+                out.push(c);
+            }
+        } else {
+            out.push(c);
+        }
+        i += 1;
+    }
+    (out, comments)
+}
+
+/// The indentation of the first indented line, two spaces if there is none.
+fn indentation(text: &str) -> String {
+    text.lines()
+        .map(|l| &l[..l.len() - l.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+        .to_string()
+}
+
+fn parse(format: Format, content: &str) -> Result<Data, String> {
+    let empty = content.trim().is_empty();
+    match format {
+        Format::Json if empty => Ok(Data::Tree(Value::Object(Default::default()))),
+        Format::Json => serde_json::from_str(&strip_jsonc(content).0)
+            .map(Data::Tree)
+            .map_err(|e| format!("Invalid JSON: {}", e)),
+        Format::Yaml if empty => Ok(Data::Tree(Value::Object(Default::default()))),
+        Format::Yaml => serde_yaml::from_str(content)
+            .map(Data::Tree)
+            .map_err(|e| format!("Invalid YAML: {}", e)),
+        Format::Ini => Ok(Data::Ini(Ini::parse(content))),
+    }
+}
+
+/// Serializes the data like the original content: same indentation, compact JSON stays compact.
+fn serialize(format: Format, data: &Data, original: &str) -> Result<String, String> {
+    match data {
+        Data::Tree(value) if format == Format::Json => {
+            if !original.trim().is_empty() && !original.trim().contains('\n') {
+                return serde_json::to_string(value).map_err(|e| e.to_string());
+            }
+            let indent = indentation(original);
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut bytes = Vec::new();
+            let mut serializer = serde_json::Serializer::with_formatter(&mut bytes, formatter);
+            serde::Serialize::serialize(value, &mut serializer).map_err(|e| e.to_string())?;
+            String::from_utf8(bytes).map_err(|e| e.to_string())
+        }
+        Data::Tree(value) => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        Data::Ini(ini) => Ok(ini.content()),
+    }
+}
+
+fn has_comments(format: Format, content: &str) -> bool {
+    match format {
+        Format::Json => strip_jsonc(content).1,
+        Format::Yaml => content.lines().any(|l| l.trim_start().starts_with('#')),
+        Format::Ini => false,
+    }
+}
+
+/// Reads the file, applies `edit` and writes it back if the data changed.
+///
+/// A missing file is created (with `--encoding`) if `create` is set, otherwise it's an error.
+fn edit(
+    path: &Path,
+    params: &Params,
+    create: bool,
+    edit: impl FnOnce(&mut Data) -> Result<(), String>,
+) -> Result<bool, String> {
+    let format = Format::detect(path, params)?;
+    let mut file = if path.exists() {
+        TextFile::read(path)?
+    } else if create {
+        let encoding = match params.option("encoding") {
+            Some(name) => Encoding::parse(name)?,
+            None => Encoding::Utf8,
+        };
+        TextFile::new(encoding)
+    } else {
+        return Err(format!(
+            "{} does not exist, pass --create to create it",
+            path.display()
+        ));
+    };
+    let original = file.content.clone();
+    let mut data = parse(format, &original)?;
+    edit(&mut data)?;
+    let unchanged = match (&data, parse(format, &original)?) {
+        (Data::Tree(value), Data::Tree(before)) => *value == before,
+        (Data::Ini(ini), Data::Ini(before)) => ini.content() == before.content(),
+        _ => false,
+    };
+    if unchanged && path.exists() {
+        return Ok(false);
+    }
+    if has_comments(format, &original) {
+        warn!(
+            "Comments in {} are not preserved when it is rewritten",
+            path.display()
+        );
+    }
+    file.content = serialize(format, &data, &original)?
+        .trim_end_matches('\n')
+        .to_string();
+    if file.trailing_newline {
+        file.content.push('\n');
+    }
+    if let Some(backup) = file.write(path, params.flag("backup"))? {
+        info!(
+            "Backup of {} written to {}",
+            path.display(),
+            backup.display()
+        );
+    }
+    Ok(true)
+}
+
+/// Values are parsed as JSON (`true`, `42`, `{"a": 1}`...), anything else is a string.
+fn parse_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+}
+
+/// Inline JSON if it starts with `{` or `[`, otherwise a JSON or YAML file next to the profile.
+fn load_document(context: &Context, source: &str) -> Result<Value, String> {
+    let trimmed = source.trim_start();
+    if trimmed.starts_with(['{', '[']) {
+        return serde_json::from_str(trimmed).map_err(|e| format!("Invalid JSON: {}", e));
+    }
+    let path = context.profile_dir.join(source);
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let format = Format::detect(&path, &Params::parse(&[]))?;
+    match parse(format, &text)? {
+        Data::Tree(value) => Ok(value),
+        Data::Ini(_) => Err(format!("{} is not a JSON or YAML file", path.display())),
+    }
+}
+
+fn ini_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn set_key_impl(params: &Params) -> Result<bool, String> {
+    let path = Path::new(params.require(0, "file")?);
+    let key = params.require(1, "key")?;
+    let value = params.require(2, "value")?;
+    edit(path, params, params.flag("create"), |data| match data {
+        Data::Tree(tree) => patch::set(tree, &patch::parse_path(key), parse_value(value)),
+        Data::Ini(ini) => match ini::parse_path(key) {
+            (section, Some(key)) => {
+                ini.set(&section, &key, value);
+                Ok(())
+            }
+            (_, None) => Err(format!("Missing key in {}", key)),
+        },
+    })
+}
+
+fn delete_key_impl(params: &Params) -> Result<bool, String> {
+    let path = Path::new(params.require(0, "file")?);
+    let key = params.require(1, "key")?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    edit(path, params, false, |data| {
+        match data {
+            Data::Tree(tree) => {
+                let tokens = patch::parse_path(key);
+                if patch::get(tree, &tokens).is_some() {
+                    patch::remove(tree, &tokens)?;
+                }
+            }
+            Data::Ini(ini) => {
+                let (section, key) = ini::parse_path(key);
+                ini.delete(&section, key.as_deref());
+            }
+        }
+        Ok(())
+    })
+}
+
+fn merge_keys_impl(context: &Context, params: &Params) -> Result<bool, String> {
+    let path = Path::new(params.require(0, "file")?);
+    let document = load_document(context, params.require(1, "document")?)?;
+    edit(path, params, params.flag("create"), |data| {
+        match data {
+            Data::Tree(tree) => patch::merge(tree, &document),
+            Data::Ini(ini) => {
+                let Value::Object(entries) = &document else {
+                    return Err(
+                        "The document merged into an INI file must be an object".to_string()
+                    );
+                };
+                // Objects are sections, other values are keys before the first section.
+                for (name, value) in entries {
+                    match value {
+                        Value::Object(keys) => {
+                            for (key, value) in keys {
+                                if value.is_null() {
+                                    ini.delete(name, Some(key));
+                                } else {
+                                    ini.set(name, key, &ini_value(value));
+                                }
+                            }
+                        }
+                        Value::Null => {
+                            ini.delete("", Some(name));
+                        }
+                        value => {
+                            ini.set("", name, &ini_value(value));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+fn json_patch_impl(context: &Context, params: &Params) -> Result<bool, String> {
+    let path = Path::new(params.require(0, "file")?);
+    let document = load_document(context, params.require(1, "patch")?)?;
+    edit(path, params, false, |data| match data {
+        Data::Tree(tree) => patch::apply(tree, &document),
+        Data::Ini(_) => Err("JSON Patch is not supported for INI files".to_string()),
+    })
+}
+
+/// `set_key <file> <key> <value> [--create] [--format=json|yaml|ini] [--backup]`
+pub fn set_key(args: &[String]) -> ActionStatus {
+    report_change("set_key", set_key_impl(&Params::parse(args)))
+}
+
+/// `delete_key <file> <key> [--format=json|yaml|ini] [--backup]`
+pub fn delete_key(args: &[String]) -> ActionStatus {
+    report_change("delete_key", delete_key_impl(&Params::parse(args)))
+}
+
+/// `merge_keys <file> <json or file> [--create] [--format=json|yaml|ini] [--backup]`
+pub fn merge_keys(context: &Context, args: &[String]) -> ActionStatus {
+    report_change("merge_keys", merge_keys_impl(context, &Params::parse(args)))
+}
+
+/// `json_patch <file> <json or file> [--format=json|yaml] [--backup]`
+pub fn json_patch(context: &Context, args: &[String]) -> ActionStatus {
+    report_change("json_patch", json_patch_impl(context, &Params::parse(args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_key(path: &Path, key: &str, value: &str) -> Result<bool, String> {
+        let args = [path.to_str().unwrap(), key, value].map(str::to_string);
+        set_key_impl(&Params::parse(&args))
+    }
+
+    #[test]
+    fn strips_jsonc() {
+        let text = "{\n  \"a\": 1, // note\n  \"b\": [1, 2, /* last */],\n  \"c\": \"// kept, \\\" }\",\n}";
+        let (stripped, comments) = strip_jsonc(text);
+        assert!(comments);
+        assert_eq!(
+            serde_json::from_str::<Value>(&stripped).unwrap(),
+            serde_json::json!({"a": 1, "b": [1, 2], "c": "// kept, \" }"})
+        );
+        assert_eq!(strip_jsonc("[1, 2]"), ("[1, 2]".to_string(), false));
+    }
+
+    #[test]
+    fn edits_vs_code_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(
+            &path,
+            "{\n    \"editor.fontSize\": 14, // bigger\n    \"files.eol\": \"\\n\",\n}\n",
+        )
+        .unwrap();
+        assert_eq!(set_key(&path, "/editor.fontSize", "14"), Ok(false));
+        assert_eq!(set_key(&path, "/editor.tabSize", "2"), Ok(true));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\n    \"editor.fontSize\": 14,\n    \"files.eol\": \"\\n\",\n    \"editor.tabSize\": 2\n}\n"
+        );
+    }
+
+    #[test]
+    fn unchanged_ini_files_are_not_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.ini");
+        let content = "; settings\n[main]\nname = app\n\n[paths]\nlogs = C:\\Logs\n\n";
+        fs::write(&path, content).unwrap();
+        assert_eq!(set_key(&path, "[main] name", "app"), Ok(false));
+        assert_eq!(set_key(&path, "[paths] logs", r"C:\Logs"), Ok(false));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        assert_eq!(set_key(&path, "[main] name", "other"), Ok(true));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "; settings\n[main]\nname = other\n\n[paths]\nlogs = C:\\Logs\n"
+        );
+        assert_eq!(set_key(&path, "[main] name", "other"), Ok(false));
+    }
+}
@@ -1,13 +1,17 @@
 /// A simple subprocess tool that executes external commands.
-use std::process::Command;
+use std::process::{Command, Output};
 
-use crate::{constants::DEFAULT_ENVS, utils};
+use super::ActionStatus;
+use crate::constants::DEFAULT_ENVS;
+use crate::events::{self, Stream};
+#[cfg(target_os = "windows")]
+use crate::utils;
 
 fn get_cwd(args: &[String]) -> String {
     // Do not get the first argument.
     for arg in args[1..].iter() {
-        if arg.starts_with("--trx8-subprocess-cwd=") {
-            return arg["--trx8-subprocess-cwd=".len()..].to_string();
+        if let Some(cwd) = arg.strip_prefix("--trx8-subprocess-cwd=") {
+            return cwd.to_string();
         }
     }
     std::env::current_dir()
@@ -19,17 +23,35 @@ fn get_cwd(args: &[String]) -> String {
 fn get_extra_envs(args: &[String]) -> Vec<(String, String)> {
     let mut envs = DEFAULT_ENVS.clone();
     for arg in args {
-        if arg.starts_with("--trx8-subprocess-env=") {
-            let env_pair = &arg["--trx8-subprocess-env=".len()..];
-            if let Some((key, value)) = env_pair.split_once('=') {
-                envs.push((key.to_string(), value.to_string()));
-            }
+        if let Some(env_pair) = arg.strip_prefix("--trx8-subprocess-env=")
+            && let Some((key, value)) = env_pair.split_once('=')
+        {
+            envs.push((key.to_string(), value.to_string()));
         }
     }
     envs
 }
 
-pub fn cmd(args: &[String]) {
+/// Logs the output of a finished subprocess and forwards it to the event stream.
+fn handle_output(action: &str, tag: &str, output: &Output) -> ActionStatus {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stdout.is_empty() {
+        events::output(action, Stream::Stdout, &stdout);
+    }
+    if !stderr.is_empty() {
+        events::output(action, Stream::Stderr, &stderr);
+    }
+    if output.status.success() {
+        tracing::info!("[{}]: {}", tag, stdout);
+        ActionStatus::Success
+    } else {
+        tracing::error!("[{}] Command failed: {}", tag, stderr);
+        ActionStatus::Failed
+    }
+}
+
+pub fn cmd(args: &[String]) -> ActionStatus {
     let cwd = get_cwd(args);
     let command = &args[0];
     let output = match Command::new("cmd")
@@ -41,23 +63,18 @@ pub fn cmd(args: &[String]) {
         Ok(output) => output,
         Err(e) => {
             tracing::error!("[cmd] Failed to execute command: {}", e);
-            return;
+            return ActionStatus::Failed;
         }
     };
 
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        tracing::info!("[shell]: {}", stdout);
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        tracing::error!("[shell] Command failed: {}", stderr);
-    }
+    handle_output("cmd", "shell", &output)
 }
 
-pub fn run(args: &[String]) {
+pub fn run(args: &[String]) -> ActionStatus {
     let command = &args[0];
     let cwd = get_cwd(args);
-    let command_args: Vec<&String> = args
+    // The program is not one of its own arguments.
+    let command_args: Vec<&String> = args[1..]
         .iter()
         .filter(|x| !x.starts_with("--trx8-subprocess"))
         .collect();
@@ -71,41 +88,38 @@ pub fn run(args: &[String]) {
         Ok(output) => output,
         Err(e) => {
             tracing::error!("[run] Failed to execute command: {}", e);
-            return;
+            return ActionStatus::Failed;
         }
     };
 
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        tracing::info!("[run]: {}", stdout);
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        tracing::error!("[run] Command failed: {}", stderr);
-    }
+    handle_output("run", "run", &output)
+}
+
+/// The command line of `ti_run`, the program and its arguments separated by spaces as they
+/// are written in the profile.
+#[cfg(any(target_os = "windows", test))]
+fn command_line(args: &[String]) -> String {
+    args.iter()
+        .filter(|x| !x.starts_with("--trx8-subprocess"))
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .join(" ")
 }
 
 #[cfg(target_os = "windows")]
-pub fn ti_run(args: &[String]) {
+pub fn ti_run(args: &[String]) -> ActionStatus {
     let command = &args[0];
     let cwd = get_cwd(args);
-    let command_args: Vec<&String> = args
-        .iter()
-        .filter(|x| !x.starts_with("--trx8-subprocess"))
-        .collect();
-
-    match utils::nt::launch_as_ti(command.to_string(), command_args.iter().map(|s| s.to_string()).collect(), Some(cwd)) {
-        true => {
-            tracing::info!("[ti_run]: Command executed successfully.");
-            return;
-        }
-        false => {
-            tracing::error!("[ti_run] Failed to execute command as TrustedInstaller.");
-            return;
-        }
-    };
+    if utils::nt::launch_as_ti(command.to_string(), command_line(args), Some(cwd)) {
+        tracing::info!("[ti_run]: Command executed successfully.");
+        ActionStatus::Success
+    } else {
+        tracing::error!("[ti_run] Failed to execute command as TrustedInstaller.");
+        ActionStatus::Failed
+    }
 }
 
-pub fn pwsh(args: &[String]) {
+pub fn pwsh(args: &[String]) -> ActionStatus {
     let command = &args[0];
     let cwd = get_cwd(args);
     let output = match Command::new("powershell")
@@ -117,14 +131,56 @@ pub fn pwsh(args: &[String]) {
         Ok(output) => output,
         Err(e) => {
             tracing::error!("[pwsh] Failed to execute command: {}", e);
-            return;
+            return ActionStatus::Failed;
         }
     };
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        tracing::info!("[pwsh]: {}", stdout);
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        tracing::error!("[pwsh] Command failed: {}", stderr);
+    handle_output("pwsh", "pwsh", &output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn separates_ti_run_arguments() {
+        assert_eq!(
+            command_line(&args(&[
+                "reg.exe",
+                "delete",
+                "--trx8-subprocess-cwd=C:\\",
+                "HKLM\\SOFTWARE\\App",
+                "/f"
+            ])),
+            "reg.exe delete HKLM\\SOFTWARE\\App /f"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_programs_with_their_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("marker"), "").unwrap();
+        let cwd = format!("--trx8-subprocess-cwd={}", dir.path().display());
+        assert_eq!(
+            run(&args(&[
+                "sh",
+                "-c",
+                "test $# -eq 1 && test \"$1\" = a",
+                "sh",
+                "a",
+                &cwd
+            ])),
+            ActionStatus::Success
+        );
+        assert_eq!(
+            run(&args(&["sh", "-c", "test -f marker", &cwd])),
+            ActionStatus::Success
+        );
+        assert_eq!(run(&args(&["sh", "-c", "exit 3"])), ActionStatus::Failed);
+        assert_eq!(run(&args(&["trx8-missing-program"])), ActionStatus::Failed);
     }
 }
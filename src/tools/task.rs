@@ -0,0 +1,110 @@
+/// Scheduled task actions, on the Windows Task Scheduler or systemd timers.
+use tracing::debug;
+
+use super::params::Params;
+use super::{ActionStatus, report_change};
+use crate::task::{self, Backend, Task, Time, Trigger};
+
+fn triggers(params: &Params) -> Result<Vec<Trigger>, String> {
+    let mut triggers = Vec::new();
+    if params.flag("at-boot") {
+        triggers.push(Trigger::Boot);
+    }
+    if params.flag("at-logon") {
+        triggers.push(Trigger::Logon);
+    }
+    for time in params.options("daily") {
+        triggers.push(Trigger::Daily(Time::parse(time)?));
+    }
+    for schedule in params.options("weekly") {
+        triggers.push(Trigger::parse_weekly(schedule)?);
+    }
+    for interval in params.options("every") {
+        triggers.push(Trigger::parse_every(interval)?);
+    }
+    if triggers.is_empty() {
+        return Err(
+            "A task needs a trigger: --daily, --weekly, --every, --at-boot or --at-logon"
+                .to_string(),
+        );
+    }
+    Ok(triggers)
+}
+
+fn with_tasks(
+    operation: impl FnOnce(&mut dyn Backend) -> Result<bool, String>,
+) -> Result<bool, String> {
+    let mut backend = task::open()?;
+    operation(backend.as_mut())
+}
+
+fn task_create_impl(params: &Params) -> Result<bool, String> {
+    let task = Task {
+        name: params.require(0, "name")?.to_string(),
+        command: params.require(1, "command")?.to_string(),
+        arguments: params.positional()[2..]
+            .iter()
+            .map(|a| a.to_string())
+            .collect(),
+        triggers: triggers(params)?,
+        description: params.option("description").map(str::to_string),
+        user: params.option("user").map(str::to_string),
+        working_dir: params.option("cwd").map(str::to_string),
+        enabled: !params.flag("disabled"),
+    };
+    debug!("[task_create] {:?}", task);
+    with_tasks(|backend| task::ensure_task(backend, &task))
+}
+
+fn task_enable_impl(params: &Params) -> Result<bool, String> {
+    let name = params.require(0, "name")?;
+    with_tasks(|backend| task::ensure_enabled(backend, name, true))
+}
+
+fn task_disable_impl(params: &Params) -> Result<bool, String> {
+    let name = params.require(0, "name")?;
+    with_tasks(|backend| {
+        // Tasks to turn off often don't exist on every edition, there is nothing to do then.
+        if !backend.exists(name)? {
+            debug!("[task_disable] {} does not exist", name);
+            return Ok(false);
+        }
+        task::ensure_enabled(backend, name, false)
+    })
+}
+
+fn task_delete_impl(params: &Params) -> Result<bool, String> {
+    let name = params.require(0, "name")?;
+    with_tasks(|backend| {
+        if !backend.exists(name)? {
+            debug!("[task_delete] {} does not exist", name);
+            return Ok(false);
+        }
+        backend.delete(name)?;
+        Ok(true)
+    })
+}
+
+/// `task_create <name> <command> [<argument>...] [--daily=HH:MM] [--weekly=<days>@HH:MM]
+/// [--every=<interval>] [--at-boot] [--at-logon] [--description=<text>] [--user=<user>]
+/// [--cwd=<dir>] [--disabled]`, the task runs as `SYSTEM` or `root` unless a user is given.
+pub fn task_create(args: &[String]) -> ActionStatus {
+    report_change("task_create", task_create_impl(&Params::parse(args)))
+}
+
+/// `task_enable <name>`
+pub fn task_enable(args: &[String]) -> ActionStatus {
+    report_change("task_enable", task_enable_impl(&Params::parse(args)))
+}
+
+/// `task_disable <name>`, a missing task is left alone.
+pub fn task_disable(args: &[String]) -> ActionStatus {
+    report_change("task_disable", task_disable_impl(&Params::parse(args)))
+}
+
+/// `task_delete <name>`
+///
+/// Every task action uses the fake tasks of the `TRX8_FAKE_TASKS` file when it is set.
+pub fn task_delete(args: &[String]) -> ActionStatus {
+    report_change("task_delete", task_delete_impl(&Params::parse(args)))
+}
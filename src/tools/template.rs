@@ -0,0 +1,186 @@
+/// Rendering of Jinja-like templates shipped with the profile into configuration files.
+use minijinja::{Environment, UndefinedBehavior, context};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+use super::params::Params;
+use super::text::{Encoding, TextFile};
+use super::{ActionStatus, Context, report_change};
+use crate::facts;
+
+/// Splits the collected values into the `inputs` and `secrets` maps seen by templates.
+fn values(context: &Context, kind: &str) -> BTreeMap<String, String> {
+    context
+        .values
+        .iter()
+        .filter_map(|(key, value)| {
+            let (prefix, name) = key.split_once('.')?;
+            (prefix == kind).then(|| (name.to_string(), value.clone()))
+        })
+        .collect()
+}
+
+/// Renders the template, errors name the template file and line.
+fn render(context: &Context, template: &Path) -> Result<String, String> {
+    let (Some(dir), Some(name)) = (template.parent(), template.file_name()) else {
+        return Err(format!("Invalid template path: {}", template.display()));
+    };
+    let name = name.to_string_lossy();
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_keep_trailing_newline(true);
+    // Templates can include or extend others next to them.
+    env.set_loader(minijinja::path_loader(dir));
+    let template = env.get_template(&name).map_err(|e| describe(&e))?;
+    template
+        .render(context! {
+            profile => context.config.metadata,
+            inputs => values(context, "inputs"),
+            secrets => values(context, "secrets"),
+            facts => facts::gather(),
+        })
+        .map_err(|e| describe(&e))
+}
+
+fn describe(error: &minijinja::Error) -> String {
+    match (error.name(), error.line()) {
+        (Some(name), Some(line)) => format!(
+            "{}:{}: {}",
+            name,
+            line,
+            error.detail().unwrap_or(&error.kind().to_string())
+        ),
+        _ => error.to_string(),
+    }
+}
+
+fn template_impl(context: &Context, params: &Params) -> Result<bool, String> {
+    let template = Path::new(params.require(0, "template")?);
+    let destination = Path::new(params.require(1, "destination")?);
+    let template = context.profile_dir.join(template);
+    if !template.is_file() {
+        return Err(format!("Template {} not found", template.display()));
+    }
+    let rendered = render(context, &template)?;
+    let mut file = TextFile::decode(rendered.as_bytes())?;
+    file.encoding = match params.option("encoding") {
+        Some(name) => Encoding::parse(name)?,
+        None => Encoding::Utf8,
+    };
+    let bytes = file.encode();
+    if fs::read(destination).is_ok_and(|current| current == bytes) {
+        return Ok(false);
+    }
+    if let Some(backup) = file.write(destination, params.flag("backup"))? {
+        info!(
+            "Backup of {} written to {}",
+            destination.display(),
+            backup.display()
+        );
+    }
+    Ok(true)
+}
+
+/// `template <template> <destination> [--backup] [--encoding=<encoding>]`, the template is
+/// relative to the profile.
+pub fn template(context: &Context, args: &[String]) -> ActionStatus {
+    report_change("template", template_impl(context, &Params::parse(args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::inputs::Values;
+
+    fn config() -> Config {
+        serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "Office", "version": "1.2.0", "trx8_version": "0.1.0"},
+            "scripts": []
+        }))
+        .unwrap()
+    }
+
+    fn values() -> Values {
+        Values::from([
+            ("inputs.host".to_string(), "pc-01".to_string()),
+            ("secrets.token".to_string(), "s3cret".to_string()),
+        ])
+    }
+
+    /// Renders `template` written as `main.j2` in `dir`, next to the other templates.
+    fn render_in(dir: &Path, template: &str) -> Result<String, String> {
+        let (config, values) = (config(), values());
+        let context = Context {
+            config: &config,
+            profile_dir: dir,
+            values: &values,
+        };
+        fs::write(dir.join("main.j2"), template).unwrap();
+        render(&context, &dir.join("main.j2"))
+    }
+
+    #[test]
+    fn renders_profile_inputs_secrets_and_facts() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("footer.j2"), "# {{ profile.version }}").unwrap();
+        let rendered = render_in(
+            dir.path(),
+            "# {{ profile.name }}\nhost={{ inputs.host }}\ntoken={{ secrets.token }}\n\
+             os={{ facts.os }}\n{% include \"footer.j2\" %}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            format!(
+                "# Office\nhost=pc-01\ntoken=s3cret\nos={}\n# 1.2.0\n",
+                std::env::consts::OS
+            )
+        );
+    }
+
+    #[test]
+    fn errors_name_the_template_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = render_in(dir.path(), "a\nb={{ inputs.missing }}\n").unwrap_err();
+        assert!(error.starts_with("main.j2:2: "), "{}", error);
+        let error = render_in(dir.path(), "a\n\n{% if %}\n").unwrap_err();
+        assert!(error.starts_with("main.j2:3: "), "{}", error);
+    }
+
+    #[test]
+    fn writes_only_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("app.conf.j2"), "host={{ inputs.host }}\n").unwrap();
+        let (config, values) = (config(), values());
+        let context = Context {
+            config: &config,
+            profile_dir: dir.path(),
+            values: &values,
+        };
+        let destination = dir.path().join("app.conf");
+        let args = [
+            "app.conf.j2".to_string(),
+            destination.display().to_string(),
+            "--encoding=utf16le".to_string(),
+        ];
+        assert_eq!(template_impl(&context, &Params::parse(&args)), Ok(true));
+        assert_eq!(template_impl(&context, &Params::parse(&args)), Ok(false));
+        assert_eq!(
+            TextFile::read(&destination).unwrap().content,
+            "host=pc-01\n"
+        );
+        assert_eq!(
+            template_impl(
+                &context,
+                &Params::parse(&["missing.j2".to_string(), args[1].clone()])
+            ),
+            Err(format!(
+                "Template {} not found",
+                dir.path().join("missing.j2").display()
+            ))
+        );
+    }
+}
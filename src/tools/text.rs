@@ -0,0 +1,226 @@
+/// Reading and writing text files while keeping their encoding and line endings.
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    /// UTF-16 little endian with a BOM, what most Windows tools write as "Unicode".
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Encoding::Utf8),
+            "utf8-bom" | "utf-8-bom" => Ok(Encoding::Utf8Bom),
+            "utf16" | "utf-16" | "utf16le" | "utf-16le" => Ok(Encoding::Utf16Le),
+            "utf16be" | "utf-16be" => Ok(Encoding::Utf16Be),
+            _ => Err(format!(
+                "Unknown encoding: {} (expected utf8, utf8-bom, utf16le or utf16be)",
+                name
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+}
+
+/// A decoded text file, `content` always uses `\n` and is converted back when written.
+pub struct TextFile {
+    pub content: String,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    /// Whether the file ended with a line ending, kept when splitting into lines.
+    pub trailing_newline: bool,
+}
+
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> Result<String, String> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err("Invalid UTF-16 content (odd number of bytes)".to_string());
+    }
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| {
+            if little_endian {
+                u16::from_le_bytes([pair[0], pair[1]])
+            } else {
+                u16::from_be_bytes([pair[0], pair[1]])
+            }
+        })
+        .collect::<Vec<u16>>();
+    String::from_utf16(&units).map_err(|e| format!("Invalid UTF-16 content: {}", e))
+}
+
+impl TextFile {
+    /// An empty file, used when a missing file has to be created.
+    pub fn new(encoding: Encoding) -> Self {
+        TextFile {
+            content: String::new(),
+            encoding,
+            line_ending: LineEnding::native(),
+            trailing_newline: true,
+        }
+    }
+
+    /// Decodes the bytes, the encoding is detected from the BOM and defaults to UTF-8.
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let (text, encoding) = if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+            (
+                String::from_utf8(rest.to_vec()).map_err(|e| e.to_string())?,
+                Encoding::Utf8Bom,
+            )
+        } else if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+            (decode_utf16(rest, true)?, Encoding::Utf16Le)
+        } else if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+            (decode_utf16(rest, false)?, Encoding::Utf16Be)
+        } else {
+            (
+                String::from_utf8(bytes.to_vec())
+                    .map_err(|_| "Unsupported encoding, the file is not valid UTF-8".to_string())?,
+                Encoding::Utf8,
+            )
+        };
+        let line_ending = match text.find('\n') {
+            Some(i) if i > 0 && text.as_bytes()[i - 1] == b'\r' => LineEnding::CrLf,
+            Some(_) => LineEnding::Lf,
+            None => LineEnding::native(),
+        };
+        let content = text.replace("\r\n", "\n");
+        Ok(TextFile {
+            trailing_newline: content.is_empty() || content.ends_with('\n'),
+            content,
+            encoding,
+            line_ending,
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let text = match self.line_ending {
+            LineEnding::Lf => self.content.clone(),
+            LineEnding::CrLf => self.content.replace('\n', "\r\n"),
+        };
+        match self.encoding {
+            Encoding::Utf8 => text.into_bytes(),
+            Encoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat(),
+            Encoding::Utf16Le => [0xFF, 0xFE]
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(|u| u.to_le_bytes()))
+                .collect(),
+            Encoding::Utf16Be => [0xFE, 0xFF]
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(|u| u.to_be_bytes()))
+                .collect(),
+        }
+    }
+
+    /// The lines of the content, without line endings.
+    pub fn lines(&self) -> Vec<String> {
+        self.content.lines().map(|l| l.to_string()).collect()
+    }
+
+    /// Replaces the content with the lines, keeping whether the file ended with a newline.
+    pub fn set_lines(&mut self, lines: &[String]) {
+        self.content = lines.join("\n");
+        if self.trailing_newline && !lines.is_empty() {
+            self.content.push('\n');
+        }
+    }
+
+    /// Writes the file, first copying the current one to `<path>.<timestamp>.bak` if `backup`.
+    pub fn write(&self, path: &Path, backup: bool) -> Result<Option<PathBuf>, String> {
+        let mut backup_path = None;
+        if backup && path.exists() {
+            let mut name = path.as_os_str().to_owned();
+            name.push(format!(".{}.bak", Local::now().format("%Y%m%d%H%M%S")));
+            let target = PathBuf::from(name);
+            fs::copy(path, &target).map_err(|e| format!("Failed to back up: {}", e))?;
+            backup_path = Some(target);
+        }
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, self.encode())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_encodings() {
+        let bytes = utf16le("[Général]\r\nclé=valeur\r\n");
+        let file = TextFile::decode(&bytes).unwrap();
+        assert_eq!(file.encoding, Encoding::Utf16Le);
+        assert_eq!(file.line_ending, LineEnding::CrLf);
+        assert_eq!(file.content, "[Général]\nclé=valeur\n");
+        assert_eq!(file.encode(), bytes);
+
+        for bytes in [
+            "a\nb".as_bytes().to_vec(),
+            [&[0xEF, 0xBB, 0xBF], "é\r\n".as_bytes()].concat(),
+            [0xFE, 0xFF, 0x00, 0xE9, 0x00, 0x0A].to_vec(),
+        ] {
+            assert_eq!(TextFile::decode(&bytes).unwrap().encode(), bytes);
+        }
+        assert_eq!(
+            TextFile::decode(&[0xFF, 0xFE, 0x41]).err().unwrap(),
+            "Invalid UTF-16 content (odd number of bytes)"
+        );
+        assert_eq!(
+            TextFile::decode(&[0x41, 0xE9]).err().unwrap(),
+            "Unsupported encoding, the file is not valid UTF-8"
+        );
+    }
+
+    #[test]
+    fn keeps_the_trailing_newline() {
+        let mut file = TextFile::decode(b"a\r\nb").unwrap();
+        assert!(!file.trailing_newline);
+        file.set_lines(&["a".to_string(), "c".to_string()]);
+        assert_eq!(file.encode(), b"a\r\nc");
+
+        let mut file = TextFile::decode(b"a\n").unwrap();
+        assert!(file.trailing_newline);
+        assert_eq!(file.line_ending, LineEnding::Lf);
+        file.set_lines(&["a".to_string(), "b".to_string()]);
+        assert_eq!(file.encode(), b"a\nb\n");
+        file.set_lines(&[]);
+        assert_eq!(file.encode(), b"");
+    }
+}
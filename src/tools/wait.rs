@@ -0,0 +1,268 @@
+/// The `wait_until` action, polling a condition until it holds.
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use super::params::Params;
+use super::process::{Target, matching};
+use super::{ActionStatus, report_wait};
+use crate::constants::DEFAULT_ENVS;
+use crate::process;
+use crate::service::{self, State};
+
+/// How often the condition is checked by default.
+const DEFAULT_INTERVAL: u64 = 1;
+/// How long to wait for the condition by default.
+const DEFAULT_TIMEOUT: u64 = 60;
+/// How long a connection to a port may take before the port counts as closed.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often a command is checked for having exited.
+const COMMAND_POLL: Duration = Duration::from_millis(50);
+
+enum Condition<'a> {
+    File(&'a Path),
+    Port(String),
+    Service(&'a str, State),
+    Command(&'a str, &'a [&'a str]),
+    Process(Vec<Target<'a>>),
+}
+
+impl<'a> Condition<'a> {
+    fn parse(params: &'a Params<'a>) -> Result<Self, String> {
+        let kind = params.require(0, "condition")?;
+        let target = params.require(1, "target")?;
+        Ok(match kind {
+            "file" => Condition::File(Path::new(target)),
+            "port" => {
+                // A bare port is on this machine.
+                let address = match target.parse::<u16>() {
+                    Ok(port) => format!("localhost:{}", port),
+                    Err(_) => target.to_string(),
+                };
+                match address
+                    .rsplit_once(':')
+                    .map(|(_, port)| port.parse::<u16>())
+                {
+                    Some(Ok(_)) => Condition::Port(address),
+                    _ => return Err(format!("Invalid address: {} (expected host:port)", target)),
+                }
+            }
+            "service" => Condition::Service(
+                target,
+                State::parse(params.option("state").unwrap_or("running"))?,
+            ),
+            "command" => Condition::Command(target, &params.positional()[2..]),
+            "process" => Condition::Process(
+                params.positional()[1..]
+                    .iter()
+                    .map(|t| Target::parse(t))
+                    .collect(),
+            ),
+            _ => {
+                return Err(format!(
+                    "Unknown condition: {} (expected file, port, service, command or process)",
+                    kind
+                ));
+            }
+        })
+    }
+
+    /// Whether the condition holds now. An error means it is unknown, e.g. for a service that
+    /// isn't installed yet. Commands still running at `deadline` are killed.
+    fn check(&self, deadline: Instant) -> Result<bool, String> {
+        match self {
+            Condition::File(path) => Ok(path.exists()),
+            Condition::Port(address) => {
+                let addresses = address
+                    .to_socket_addrs()
+                    .map_err(|e| format!("Failed to resolve {}: {}", address, e))?;
+                Ok(addresses
+                    .into_iter()
+                    .any(|a| TcpStream::connect_timeout(&a, CONNECT_TIMEOUT).is_ok()))
+            }
+            Condition::Service(name, state) => Ok(service::open()?.state(name)? == *state),
+            Condition::Command(program, args) => {
+                let mut child = Command::new(program)
+                    .args(*args)
+                    .envs(DEFAULT_ENVS.iter().cloned())
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+                loop {
+                    let exited = child
+                        .try_wait()
+                        .map_err(|e| format!("Failed to wait for {}: {}", program, e))?;
+                    if let Some(status) = exited {
+                        return Ok(status.success());
+                    }
+                    if Instant::now() >= deadline {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        return Err(format!("{} was still running, killed it", program));
+                    }
+                    thread::sleep(COMMAND_POLL);
+                }
+            }
+            Condition::Process(targets) => Ok(!matching(&process::list()?, targets).is_empty()),
+        }
+    }
+}
+
+fn wait_until_impl(params: &Params) -> Result<(), String> {
+    let condition = Condition::parse(params)?;
+    let expected = !params.flag("not");
+    let interval = params.seconds("interval", DEFAULT_INTERVAL)?;
+    if interval.is_zero() {
+        return Err("The interval must be at least 1 second".to_string());
+    }
+    let timeout = params.seconds("timeout", DEFAULT_TIMEOUT)?;
+    let described = format!(
+        "{}{}",
+        if expected { "" } else { "not " },
+        params.positional().join(" ")
+    );
+
+    let started = Instant::now();
+    let deadline = started + timeout;
+    // Errors don't tell whether the condition holds, even with `--not`, so polling goes on and
+    // the last one is reported at the timeout.
+    loop {
+        let error = match condition.check(deadline) {
+            Ok(holds) if holds == expected => {
+                info!(
+                    "[wait_until] `{}` held after {} seconds",
+                    described,
+                    started.elapsed().as_secs()
+                );
+                return Ok(());
+            }
+            Ok(_) => None,
+            Err(e) => {
+                debug!("[wait_until] {}", e);
+                Some(e)
+            }
+        };
+        if Instant::now() >= deadline {
+            let timed_out = format!(
+                "Timed out after {} seconds waiting for `{}`",
+                timeout.as_secs(),
+                described
+            );
+            return Err(match error {
+                Some(error) => format!("{}: {}", timed_out, error),
+                None => timed_out,
+            });
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// `wait_until <file|port|service|command|process> <target>... [--not] [--state=<state>]
+/// [--interval=<seconds>] [--timeout=<seconds>]`
+pub fn wait_until(args: &[String]) -> ActionStatus {
+    report_wait("wait_until", wait_until_impl(&Params::parse(args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::net::TcpListener;
+
+    fn wait_until(args: &[&str]) -> Result<(), String> {
+        let args = args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+        wait_until_impl(&Params::parse(&args))
+    }
+
+    #[test]
+    fn waits_for_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ready");
+        let file = path.to_str().unwrap();
+        assert_eq!(
+            wait_until(&["file", file, "--timeout=0"]),
+            Err(format!(
+                "Timed out after 0 seconds waiting for `file {}`",
+                file
+            ))
+        );
+        assert_eq!(wait_until(&["file", file, "--not"]), Ok(()));
+
+        let creating = path.clone();
+        let creator = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            fs::write(creating, "").unwrap();
+        });
+        assert_eq!(wait_until(&["file", file, "--timeout=5"]), Ok(()));
+        creator.join().unwrap();
+        assert_eq!(
+            wait_until(&["file", file, "--not", "--timeout=0"]),
+            Err(format!(
+                "Timed out after 0 seconds waiting for `not file {}`",
+                file
+            ))
+        );
+    }
+
+    #[test]
+    fn waits_for_ports() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        assert_eq!(wait_until(&["port", &address, "--timeout=0"]), Ok(()));
+        drop(listener);
+        assert_eq!(
+            wait_until(&["port", &address, "--not", "--timeout=0"]),
+            Ok(())
+        );
+        assert_eq!(
+            wait_until(&["port", "localhost"]),
+            Err("Invalid address: localhost (expected host:port)".to_string())
+        );
+    }
+
+    #[test]
+    fn errors_are_not_taken_as_an_answer() {
+        // The name can't be resolved, which says nothing about the port being closed.
+        let error = wait_until(&["port", "trx8.invalid:80", "--not", "--timeout=0"]).unwrap_err();
+        assert!(
+            error.starts_with(
+                "Timed out after 0 seconds waiting for `not port trx8.invalid:80`: Failed to resolve"
+            ),
+            "{}",
+            error
+        );
+        let error =
+            wait_until(&["command", "trx8-missing-program", "--not", "--timeout=0"]).unwrap_err();
+        assert!(
+            error.contains(": Failed to run trx8-missing-program"),
+            "{}",
+            error
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn waits_for_commands() {
+        assert_eq!(wait_until(&["command", "true", "--timeout=5"]), Ok(()));
+        assert_eq!(
+            wait_until(&["command", "false", "--not", "--timeout=5"]),
+            Ok(())
+        );
+        // A command still running at the timeout is killed rather than waited for.
+        let started = Instant::now();
+        assert_eq!(
+            wait_until(&["command", "sleep", "30", "--timeout=1"]),
+            Err(
+                "Timed out after 1 seconds waiting for `command sleep 30`: sleep was still \
+                 running, killed it"
+                    .to_string()
+            )
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
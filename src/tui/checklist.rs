@@ -0,0 +1,172 @@
+/// The script checklist shown before applying a configuration.
+use ratatui::Frame;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+
+use crate::config::{Config, Selection};
+
+pub struct Item {
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub checked: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Apply the checked scripts.
+    Apply,
+    /// Save the current selection and keep going.
+    Save,
+    Cancel,
+}
+
+pub struct Checklist {
+    pub title: String,
+    pub header: Vec<String>,
+    pub items: Vec<Item>,
+    pub state: ListState,
+    /// A one-line message shown in the footer, e.g. after saving.
+    pub status: Option<String>,
+}
+
+impl Checklist {
+    /// Creates the checklist, every script is checked unless a selection is given.
+    pub fn new(config: &Config, selection: Option<&Selection>) -> Self {
+        let metadata = &config.metadata;
+        let mut header = vec![format!("Version: {}", metadata.version)];
+        if let Some(authors) = &metadata.author {
+            header.push(format!("Author(s): {}", authors.join(", ")));
+        }
+        if let Some(description) = &metadata.description {
+            header.push(description.clone());
+        }
+        let items = config
+            .scripts
+            .iter()
+            .map(|script| Item {
+                name: script.name.clone(),
+                description: script.description.clone(),
+                tags: script.tags.clone().unwrap_or_default(),
+                checked: selection.is_none_or(|s| s.scripts.contains(&script.name)),
+            })
+            .collect::<Vec<Item>>();
+        let mut state = ListState::default();
+        if !items.is_empty() {
+            state.select(Some(0));
+        }
+        Checklist {
+            title: metadata.name.clone(),
+            header,
+            items,
+            state,
+            status: None,
+        }
+    }
+
+    pub fn selected(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .filter(|item| item.checked)
+            .map(|item| item.name.clone())
+            .collect()
+    }
+
+    /// Updates the state for a key press, returns an outcome when the checklist needs the caller.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Outcome> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+            KeyCode::Char(' ') => {
+                if let Some(item) = self.state.selected().and_then(|i| self.items.get_mut(i)) {
+                    item.checked = !item.checked;
+                }
+            }
+            KeyCode::Char('a') => {
+                let check = !self.items.iter().all(|item| item.checked);
+                for item in self.items.iter_mut() {
+                    item.checked = check;
+                }
+            }
+            KeyCode::Char('s') => return Some(Outcome::Save),
+            KeyCode::Enter => {
+                if self.items.iter().any(|item| item.checked) {
+                    return Some(Outcome::Apply);
+                }
+                self.status = Some("Nothing selected, press q to quit instead.".to_string());
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Outcome::Cancel),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&mut self, frame: &mut Frame) {
+        let [header_area, list_area, details_area, footer_area] = Layout::vertical([
+            Constraint::Length(self.header.len() as u16 + 2),
+            Constraint::Min(3),
+            Constraint::Length(5),
+            Constraint::Length(2),
+        ])
+        .areas(frame.area());
+
+        let header = Paragraph::new(
+            self.header
+                .iter()
+                .map(|line| Line::from(line.as_str()))
+                .collect::<Vec<Line>>(),
+        )
+        .block(Block::bordered().title(format!(" {} ", self.title).bold()));
+        frame.render_widget(header, header_area);
+
+        let checked = self.items.iter().filter(|item| item.checked).count();
+        let list = List::new(self.items.iter().map(|item| {
+            let mut spans = vec![
+                Span::raw(if item.checked { "[x] " } else { "[ ] " }),
+                Span::raw(item.name.as_str()),
+            ];
+            if !item.tags.is_empty() {
+                spans.push(Span::styled(
+                    format!("  [{}]", item.tags.join(", ")),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        }))
+        .block(Block::bordered().title(format!(
+            " Scripts ({}/{} selected) ",
+            checked,
+            self.items.len()
+        )))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let description = self
+            .state
+            .selected()
+            .and_then(|i| self.items.get(i))
+            .and_then(|item| item.description.clone())
+            .unwrap_or_default();
+        let details = Paragraph::new(description)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title(" Description "));
+        frame.render_widget(details, details_area);
+
+        let footer = Paragraph::new(vec![
+            Line::from(
+                "space: toggle  a: toggle all  s: save selection  enter: apply  q: quit".dim(),
+            ),
+            match &self.status {
+                Some(status) => Line::from(status.as_str().yellow()),
+                None => Line::from(
+                    "Only apply trusted configuration files, they can execute arbitrary code."
+                        .yellow(),
+                ),
+            },
+        ]);
+        frame.render_widget(footer, footer_area);
+    }
+}
@@ -0,0 +1,291 @@
+/// Terminal UI (`--tui`) for choosing the scripts to apply and following their progress.
+///
+/// The widgets only depend on `ratatui`'s `Backend` trait and take their input from a closure,
+/// so they can be driven with a `TestBackend` and synthetic key events.
+use ratatui::backend::Backend;
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{DefaultTerminal, Terminal};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::config::{Config, Selection};
+use crate::events;
+
+mod checklist;
+mod progress;
+
+pub use checklist::{Checklist, Outcome};
+pub use progress::Progress;
+
+/// The terminal is kept here between the checklist and the end of the run for the progress view.
+static TERMINAL: Mutex<Option<(DefaultTerminal, Progress)>> = Mutex::new(None);
+
+/// Runs the checklist until the user applies or cancels, `save` is called with the checked
+/// scripts when the user asks to save the selection and returns the message to show.
+pub fn run_checklist<B: Backend>(
+    terminal: &mut Terminal<B>,
+    checklist: &mut Checklist,
+    mut next_key: impl FnMut() -> Option<KeyEvent>,
+    mut save: impl FnMut(Vec<String>) -> String,
+) -> Result<Outcome, B::Error> {
+    loop {
+        terminal.draw(|frame| checklist.render(frame))?;
+        let Some(key) = next_key() else {
+            return Ok(Outcome::Cancel);
+        };
+        checklist.status = None;
+        match checklist.handle_key(key) {
+            Some(Outcome::Save) => checklist.status = Some(save(checklist.selected())),
+            Some(outcome) => return Ok(outcome),
+            None => {}
+        }
+    }
+}
+
+/// Blocks until a key is pressed, a resize is reported as `KeyCode::Null` so the caller redraws.
+fn read_key() -> Option<KeyEvent> {
+    loop {
+        match event::read() {
+            Ok(TermEvent::Key(key)) if key.kind == KeyEventKind::Press => return Some(key),
+            Ok(TermEvent::Resize(_, _)) => return Some(KeyEvent::from(KeyCode::Null)),
+            Ok(_) => continue,
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Shows the checklist, returns the names of the chosen scripts or `None` if cancelled.
+///
+/// When scripts are chosen the terminal stays in the TUI and shows the progress of the run
+/// until [`finish`] is called.
+pub fn choose_scripts(
+    config: &Config,
+    selection: Option<&Selection>,
+    selection_path: &Path,
+) -> Option<Vec<String>> {
+    let mut terminal = ratatui::init();
+    let mut checklist = Checklist::new(config, selection);
+    let outcome = run_checklist(&mut terminal, &mut checklist, read_key, |scripts| {
+        let selection = Selection {
+            profile: config.metadata.name.clone(),
+            scripts,
+        };
+        match selection.save(selection_path) {
+            Ok(_) => format!("Selection saved to {}", selection_path.display()),
+            Err(e) => format!("Failed to save selection: {}", e),
+        }
+    });
+    match outcome {
+        Ok(Outcome::Apply) => {
+            let selected = checklist.selected();
+            *TERMINAL.lock().unwrap() = Some((terminal, Progress::new(config, &selected)));
+            events::set_listener(|event| {
+                if let Some((terminal, progress)) = TERMINAL.lock().unwrap().as_mut() {
+                    progress.handle_event(event);
+                    let _ = terminal.draw(|frame| progress.render(frame));
+                }
+            });
+            Some(selected)
+        }
+        _ => {
+            ratatui::restore();
+            None
+        }
+    }
+}
+
+/// Waits for a key press on the final progress screen and restores the terminal.
+pub fn finish() {
+    if let Some((mut terminal, progress)) = TERMINAL.lock().unwrap().take() {
+        let _ = terminal.draw(|frame| progress.render(frame));
+        while read_key().is_some_and(|key| key.code == KeyCode::Null) {
+            let _ = terminal.draw(|frame| progress.render(frame));
+        }
+        ratatui::restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+    use crate::tools::ActionStatus;
+    use ratatui::backend::TestBackend;
+
+    fn config() -> Config {
+        serde_json::from_str(
+            r#"{
+                "metadata": {"name": "Test", "version": "1.0", "trx8_version": "0.1.0"},
+                "scripts": [
+                    {"name": "One", "description": "The first script", "actions": [
+                        {"name": "echo", "parameters": ["1"]}
+                    ]},
+                    {"name": "Two", "tags": ["dev"], "actions": [
+                        {"name": "echo", "parameters": ["2"]},
+                        {"name": "echo", "parameters": ["2"]}
+                    ]},
+                    {"name": "Three", "actions": []}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                text.push_str(buffer[(x, y)].symbol());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn keys(codes: &[KeyCode]) -> impl FnMut() -> Option<KeyEvent> {
+        let mut keys = codes
+            .iter()
+            .copied()
+            .map(KeyEvent::from)
+            .collect::<Vec<_>>();
+        keys.reverse();
+        move || keys.pop()
+    }
+
+    #[test]
+    fn checklist_toggles_and_applies() {
+        let config = config();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut checklist = Checklist::new(&config, None);
+        let outcome = run_checklist(
+            &mut terminal,
+            &mut checklist,
+            keys(&[KeyCode::Down, KeyCode::Char(' '), KeyCode::Enter]),
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(outcome, Outcome::Apply);
+        assert_eq!(checklist.selected(), ["One", "Three"]);
+        let screen = screen(&terminal);
+        assert!(screen.contains("Scripts (2/3 selected)"));
+        assert!(screen.contains("[ ] Two  [dev]"));
+    }
+
+    #[test]
+    fn checklist_starts_from_a_selection() {
+        let config = config();
+        let selection = Selection {
+            profile: "Test".to_string(),
+            scripts: vec!["Two".to_string()],
+        };
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut checklist = Checklist::new(&config, Some(&selection));
+        assert_eq!(checklist.selected(), ["Two"]);
+        // Toggling all checks everything as not all scripts are checked, then unchecks them.
+        let outcome = run_checklist(
+            &mut terminal,
+            &mut checklist,
+            keys(&[KeyCode::Char('a'), KeyCode::Char('a'), KeyCode::Enter]),
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(outcome, Outcome::Cancel);
+        assert!(checklist.selected().is_empty());
+        assert!(screen(&terminal).contains("Nothing selected"));
+    }
+
+    #[test]
+    fn checklist_saves_the_selection() {
+        let config = config();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.selection.json");
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut checklist = Checklist::new(&config, None);
+        let outcome = run_checklist(
+            &mut terminal,
+            &mut checklist,
+            keys(&[KeyCode::Char(' '), KeyCode::Char('s'), KeyCode::Char('q')]),
+            |scripts| {
+                let selection = Selection {
+                    profile: config.metadata.name.clone(),
+                    scripts,
+                };
+                selection.save(&path).unwrap();
+                "Saved".to_string()
+            },
+        )
+        .unwrap();
+        assert_eq!(outcome, Outcome::Cancel);
+        let saved = Selection::load(&path).unwrap();
+        assert_eq!(saved.profile, "Test");
+        assert_eq!(saved.scripts, ["Two", "Three"]);
+        assert!(screen(&terminal).contains("Saved"));
+    }
+
+    #[test]
+    fn progress_follows_the_events() {
+        let config = config();
+        let selected = vec!["One".to_string(), "Two".to_string()];
+        let mut progress = Progress::new(&config, &selected);
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        let events = [
+            Event::ScriptStarted {
+                index: 0,
+                total: 2,
+                name: "One",
+                description: None,
+            },
+            Event::ActionStarted {
+                script: 0,
+                index: 0,
+                name: "echo",
+                description: None,
+            },
+            Event::ActionOutput {
+                action: "echo",
+                stream: events::Stream::Stdout,
+                text: "1\n",
+            },
+            Event::ActionFinished {
+                script: 0,
+                index: 0,
+                name: "echo",
+                status: ActionStatus::Success,
+            },
+            Event::ScriptStarted {
+                index: 1,
+                total: 2,
+                name: "Two",
+                description: None,
+            },
+            Event::ActionFinished {
+                script: 1,
+                index: 0,
+                name: "echo",
+                status: ActionStatus::Failed,
+            },
+        ];
+        for event in &events {
+            progress.handle_event(event);
+        }
+        terminal.draw(|frame| progress.render(frame)).unwrap();
+        let shown = screen(&terminal);
+        assert!(shown.contains("2/3 actions"));
+        assert!(shown.contains("✓ One (1 actions)"));
+        assert!(shown.contains("✗ Two (2 actions)"));
+        assert!(shown.contains("Action echo failed."));
+        assert_eq!(progress.finished, None);
+
+        progress.handle_event(&Event::RunFinished {
+            success: false,
+            aborted: false,
+            actions: 2,
+            failed: 1,
+            reboot_required: false,
+        });
+        terminal.draw(|frame| progress.render(frame)).unwrap();
+        assert_eq!(progress.finished, Some(false));
+        assert!(screen(&terminal).contains("Configuration applied with errors"));
+    }
+}
@@ -0,0 +1,179 @@
+/// The live progress view shown while applying a configuration, driven by the event stream.
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, ListItem, Paragraph};
+use std::collections::VecDeque;
+
+use crate::config::Config;
+use crate::events::Event;
+use crate::secrets;
+use crate::tools::ActionStatus;
+
+/// How many lines of action output are kept for display.
+const OUTPUT_LINES: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ScriptState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+pub struct Progress {
+    title: String,
+    scripts: Vec<(String, usize, ScriptState)>,
+    current: Option<usize>,
+    action: Option<String>,
+    done_actions: usize,
+    total_actions: usize,
+    output: VecDeque<String>,
+    pub finished: Option<bool>,
+}
+
+impl Progress {
+    /// Creates the progress view for the scripts named in `selected`.
+    pub fn new(config: &Config, selected: &[String]) -> Self {
+        let scripts = config
+            .scripts
+            .iter()
+            .filter(|script| selected.contains(&script.name));
+        Progress {
+            title: config.metadata.name.clone(),
+            scripts: scripts
+                .clone()
+                .map(|script| {
+                    (
+                        script.name.clone(),
+                        script.actions.len(),
+                        ScriptState::Pending,
+                    )
+                })
+                .collect(),
+            current: None,
+            action: None,
+            done_actions: 0,
+            total_actions: scripts.map(|s| s.actions.len()).sum(),
+            output: VecDeque::new(),
+            finished: None,
+        }
+    }
+
+    fn push_output(&mut self, line: String) {
+        if self.output.len() == OUTPUT_LINES {
+            self.output.pop_front();
+        }
+        self.output.push_back(secrets::redact(&line).into_owned());
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::ScriptStarted { index, .. } => {
+                if let Some(previous) = self.current
+                    && let Some(script) = self.scripts.get_mut(previous)
+                    && script.2 == ScriptState::Running
+                {
+                    script.2 = ScriptState::Done;
+                }
+                self.current = Some(*index);
+                if let Some(script) = self.scripts.get_mut(*index) {
+                    script.2 = ScriptState::Running;
+                }
+            }
+            Event::ActionStarted { name, .. } => {
+                self.action = Some(name.to_string());
+            }
+            Event::ActionOutput { text, .. } => {
+                for line in text.lines() {
+                    self.push_output(line.to_string());
+                }
+            }
+            Event::ActionFinished { name, status, .. } => {
+                self.done_actions += 1;
+                self.action = None;
+                if *status == ActionStatus::Failed {
+                    self.push_output(format!("Action {} failed.", name));
+                    if let Some(script) = self.current.and_then(|i| self.scripts.get_mut(i)) {
+                        script.2 = ScriptState::Failed;
+                    }
+                }
+            }
+            Event::RunFinished { success, .. } => {
+                if let Some(script) = self.current.and_then(|i| self.scripts.get_mut(i))
+                    && script.2 == ScriptState::Running
+                {
+                    script.2 = ScriptState::Done;
+                }
+                self.finished = Some(*success);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let [gauge_area, body_area, footer_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [scripts_area, output_area] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(body_area);
+
+        let ratio = if self.total_actions == 0 {
+            1.0
+        } else {
+            self.done_actions as f64 / self.total_actions as f64
+        };
+        let gauge = Gauge::default()
+            .block(Block::bordered().title(format!(" Applying {} ", self.title).bold()))
+            .gauge_style(Style::default().fg(Color::Green))
+            .ratio(ratio.min(1.0))
+            .label(format!(
+                "{}/{} actions",
+                self.done_actions, self.total_actions
+            ));
+        frame.render_widget(gauge, gauge_area);
+
+        let scripts = List::new(self.scripts.iter().map(|(name, actions, state)| {
+            let line = format!("{} ({} actions)", name, actions);
+            ListItem::new(match state {
+                ScriptState::Pending => Line::from(format!("  {}", line).dim()),
+                ScriptState::Running => Line::from(format!("> {}", line).yellow()),
+                ScriptState::Done => Line::from(format!("✓ {}", line).green()),
+                ScriptState::Failed => Line::from(format!("✗ {}", line).red()),
+            })
+        }))
+        .block(Block::bordered().title(" Scripts "));
+        frame.render_widget(scripts, scripts_area);
+
+        let height = output_area.height.saturating_sub(2) as usize;
+        let skip = self.output.len().saturating_sub(height);
+        let output = Paragraph::new(
+            self.output
+                .iter()
+                .skip(skip)
+                .map(|line| Line::from(line.as_str()))
+                .collect::<Vec<Line>>(),
+        )
+        .block(Block::bordered().title(match &self.action {
+            Some(action) => format!(" Output ({}) ", action),
+            None => " Output ".to_string(),
+        }));
+        frame.render_widget(output, output_area);
+
+        let footer = match self.finished {
+            None => Line::from("Applying configuration, please wait...".dim()),
+            Some(true) => {
+                Line::from("Configuration applied successfully, press any key to exit.".green())
+            }
+            Some(false) => Line::from(
+                "Configuration applied with errors, see the log file. Press any key to exit.".red(),
+            ),
+        };
+        frame.render_widget(Paragraph::new(footer), footer_area);
+    }
+}
@@ -1,8 +1,6 @@
 use std::ffi::OsString;
 use std::mem;
 use std::os::windows::ffi::OsStrExt;
-use std::os::windows::process::CommandExt;
-use std::process::Command;
 use std::ptr;
 use std::sync::Mutex;
 use std::sync::OnceLock;
@@ -98,35 +96,12 @@ pub fn enable_privileges() -> bool {
 }
 
 fn find_process_by_name(name: &str) -> Option<u32> {
-    // Using std::process::Command to query system state via `tasklist` avoids the heavy Toolhelp32 API boilerplate.
-    match Command::new("cmd")
-        .args([
-            "/C",
-            "tasklist",
-            "/FI",
-            &format!("IMAGENAME eq {}", name),
-            "/FO",
-            "CSV",
-            "/NH",
-        ])
-        .creation_flags(CREATE_NO_WINDOW.0)
-        .output()
-    {
-        Ok(output) => {
-            let content = String::from_utf8_lossy(&output.stdout);
-            // Parse CSV output: "winlogon.exe","1234","Services","0","24,000 K"
-            if let Some(line) = content.lines().next() {
-                let parts: Vec<&str> = line.split(',').collect();
-                if parts.len() >= 2 {
-                    let pid_str = parts[1].trim_matches('"');
-                    return pid_str.parse::<u32>().ok();
-                }
-            }
-            None
-        }
+    match crate::process::list() {
+        Ok(processes) => crate::process::find_by_name(&processes, name)
+            .first()
+            .map(|process| process.pid),
         Err(e) => {
-            // Fallback to Win32 if tasklist fails (unlikely, but safe)
-            error!("Find process by tasklist failed: {}", e);
+            error!("Find process by name failed: {}", e);
             None
         }
     }